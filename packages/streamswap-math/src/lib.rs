@@ -0,0 +1,317 @@
+//! Stream distribution math of cw-streamswap without any storage access, so the contract,
+//! front-ends and integrators compute exactly the same results.
+
+use cosmwasm_std::{
+    CheckedFromRatioError, ConversionOverflowError, Decimal, Decimal256, Fraction, OverflowError,
+    Timestamp, Uint256, Uint512,
+};
+use thiserror::Error;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MathError {
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    ConversionOverflow(#[from] ConversionOverflowError),
+
+    #[error("{0}")]
+    CheckedFromRatio(#[from] CheckedFromRatioError),
+}
+
+/// Fields of a stream the distribution works on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StreamState {
+    /// Distribution index, out tokens distributed per share.
+    pub dist_index: Decimal256,
+    pub last_updated: Timestamp,
+    /// Out tokens left to distribute.
+    pub out_remaining: Uint256,
+    /// In tokens not spent yet.
+    pub in_supply: Uint256,
+    /// In tokens spent so far.
+    pub spent_in: Uint256,
+    pub shares: Uint256,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    /// Price of the latest distribution.
+    pub current_streamed_price: Decimal256,
+    /// Streamed price accumulated over the streamed seconds, the difference of two points in time
+    /// divided by their distance is the time weighted average price.
+    pub price_cumulative: Decimal256,
+}
+
+/// Fields of a position its purchase is calculated from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PositionState {
+    /// Stream distribution index at the last position update.
+    pub index: Decimal256,
+    pub shares: Uint256,
+    /// In tokens of the position not spent yet.
+    pub in_balance: Uint256,
+    /// Out tokens purchased so far.
+    pub purchased: Uint256,
+    /// Purchase decimals carried to the next update.
+    pub pending_purchase: Decimal256,
+    /// In tokens spent so far.
+    pub spent: Uint256,
+    pub last_updated: Timestamp,
+}
+
+/// Returns the share of the remaining stream duration passed between `last_updated` and `now`.
+pub fn calculate_diff(end_time: Timestamp, last_updated: Timestamp, now: Timestamp) -> Decimal {
+    // diff = (now - last_updated) / (end_time - last_updated)
+    let now = if now > end_time { end_time } else { now };
+    let numerator = now.nanos().saturating_sub(last_updated.nanos());
+    let denominator = end_time.nanos().saturating_sub(last_updated.nanos());
+
+    if denominator == 0 || numerator == 0 {
+        Decimal::zero()
+    } else {
+        Decimal::from_ratio(numerator, denominator)
+    }
+}
+
+/// Distributes out tokens and spends in tokens of the stream up to `now`. Returns the passed
+/// duration share and the distributed out amount.
+pub fn update_stream(
+    now: Timestamp,
+    stream: &mut StreamState,
+) -> Result<(Decimal, Uint256), MathError> {
+    let diff = calculate_diff(stream.end_time, stream.last_updated, now);
+
+    let mut new_distribution_balance = Uint256::zero();
+
+    // if no in balance in the contract, no need to update
+    // if diff not changed this means either stream not started or no in balance so far
+    if !stream.shares.is_zero() && !diff.is_zero() {
+        // new distribution balance is the amount of in tokens that has been distributed since last update
+        // distribution is linear for now.
+        new_distribution_balance = stream
+            .out_remaining
+            .multiply_ratio(diff.numerator(), diff.denominator());
+        // spent in tokens is the amount of in tokens that has been spent since last update
+        // spending is linear and goes to zero at the end of the stream
+        let spent_in = stream
+            .in_supply
+            .multiply_ratio(diff.numerator(), diff.denominator());
+
+        // increase total spent_in of the stream
+        stream.spent_in = stream.spent_in.checked_add(spent_in)?;
+        // decrease in_supply of the steam
+        stream.in_supply = stream.in_supply.checked_sub(spent_in)?;
+
+        // if no new distribution balance, no need to update the price, out_remaining and dist_index
+        if !new_distribution_balance.is_zero() {
+            // decrease amount to be distributed of the stream
+            stream.out_remaining = stream.out_remaining.checked_sub(new_distribution_balance)?;
+            // update distribution index. A positions share of the distribution is calculated by
+            // multiplying the share by the distribution index
+            stream.dist_index = stream
+                .dist_index
+                .checked_add(Decimal256::checked_from_ratio(
+                    new_distribution_balance,
+                    stream.shares,
+                )?)?;
+            stream.current_streamed_price =
+                Decimal256::checked_from_ratio(spent_in, new_distribution_balance)?
+        }
+    }
+
+    // the last streamed price carries over periods without subscriptions
+    if !stream.current_streamed_price.is_zero() {
+        let streamed_nanos = now
+            .min(stream.end_time)
+            .nanos()
+            .saturating_sub(stream.last_updated.nanos());
+        let streamed_seconds = Decimal256::from_ratio(streamed_nanos, NANOS_PER_SECOND);
+        stream.price_cumulative = stream.price_cumulative.checked_add(
+            stream
+                .current_streamed_price
+                .checked_mul(streamed_seconds)?,
+        )?;
+    }
+
+    stream.last_updated = if now < stream.start_time {
+        stream.start_time
+    } else {
+        now
+    };
+
+    Ok((diff, new_distribution_balance))
+}
+
+/// Calculates the position purchase from the position index and the stream index. Returns the
+/// purchased out amount and the spent in amount.
+pub fn update_position(
+    stream_dist_index: Decimal256,
+    stream_shares: Uint256,
+    stream_last_updated: Timestamp,
+    stream_in_supply: Uint256,
+    position: &mut PositionState,
+) -> Result<(Uint256, Uint256), MathError> {
+    // index difference represents the amount of distribution that has been received since last update
+    let index_diff = stream_dist_index.checked_sub(position.index)?;
+
+    let mut spent = Uint256::zero();
+    let mut purchased_uint128 = Uint256::zero();
+
+    // if no shares available, means no distribution and no spent
+    if !stream_shares.is_zero() {
+        // purchased is index_diff * position.shares, shares may not fit a Decimal256 on their own
+        // so the product is taken on the index atomics in 512 bits
+        let purchased = Decimal256::new(Uint256::try_from(
+            position.shares.full_mul(index_diff.atomics()),
+        )?)
+        .checked_add(position.pending_purchase)?;
+        // decimals is the amount of decimals that the out token has to be added to next distribution so that
+        // the data do not get lost due to rounding
+        let decimals = get_decimals(purchased)?;
+
+        // calculates the remaining user balance using position.shares
+        let in_remaining = Uint256::try_from(
+            stream_in_supply.full_mul(position.shares) / Uint512::from(stream_shares),
+        )?;
+
+        // calculates the amount of spent tokens
+        spent = position.in_balance.checked_sub(in_remaining)?;
+        position.spent = position.spent.checked_add(spent)?;
+        position.in_balance = in_remaining;
+        position.pending_purchase = decimals;
+
+        // floors the decimal points
+        purchased_uint128 = purchased * Uint256::one();
+        position.purchased = position.purchased.checked_add(purchased_uint128)?;
+    }
+
+    position.index = stream_dist_index;
+    position.last_updated = stream_last_updated;
+
+    Ok((purchased_uint128, spent))
+}
+
+/// Computes the shares minted for subscribing, or burned for withdrawing, `amount_in`.
+pub fn compute_shares_amount(
+    total_shares: Uint256,
+    in_supply: Uint256,
+    amount_in: Uint256,
+    round_up: bool,
+) -> Result<Uint256, MathError> {
+    if total_shares.is_zero() || amount_in.is_zero() {
+        return Ok(amount_in);
+    }
+    // shares grow faster than in_supply as tokens are spent, keep the product in 512 bits
+    let shares = total_shares.full_mul(amount_in);
+    let in_supply = Uint512::from(in_supply);
+    let shares = if round_up {
+        (shares + in_supply - Uint512::one()) / in_supply
+    } else {
+        shares / in_supply
+    };
+    Ok(Uint256::try_from(shares)?)
+}
+
+/// Returns the fractional part of `value`.
+pub fn get_decimals(value: Decimal256) -> Result<Decimal256, MathError> {
+    Ok(value.checked_sub(value.floor())?)
+}
+
+/// Returns the average price paid per out token, errors if nothing is purchased yet.
+pub fn average_price(spent_in: Uint256, purchased: Uint256) -> Result<Decimal256, MathError> {
+    Ok(Decimal256::checked_from_ratio(spent_in, purchased)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream_state() -> StreamState {
+        StreamState {
+            out_remaining: Uint256::from(1_000u128),
+            start_time: Timestamp::from_seconds(0),
+            end_time: Timestamp::from_seconds(100),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_update_stream_and_position() {
+        let mut stream = stream_state();
+        let shares = compute_shares_amount(
+            stream.shares,
+            stream.in_supply,
+            Uint256::from(500u128),
+            false,
+        )
+        .unwrap();
+        stream.shares = shares;
+        stream.in_supply = Uint256::from(500u128);
+        let mut position = PositionState {
+            shares,
+            in_balance: Uint256::from(500u128),
+            ..Default::default()
+        };
+
+        // half of the duration distributes half of the out tokens
+        let (diff, distributed) = update_stream(Timestamp::from_seconds(50), &mut stream).unwrap();
+        assert_eq!(diff, Decimal::percent(50));
+        assert_eq!(distributed, Uint256::from(500u128));
+        assert_eq!(stream.in_supply, Uint256::from(250u128));
+        assert_eq!(stream.spent_in, Uint256::from(250u128));
+        assert_eq!(stream.current_streamed_price, Decimal256::percent(50));
+        assert_eq!(
+            stream.price_cumulative,
+            Decimal256::from_ratio(25u128, 1u128)
+        );
+
+        let (purchased, spent) = update_position(
+            stream.dist_index,
+            stream.shares,
+            stream.last_updated,
+            stream.in_supply,
+            &mut position,
+        )
+        .unwrap();
+        assert_eq!(purchased, Uint256::from(500u128));
+        assert_eq!(spent, Uint256::from(250u128));
+        assert_eq!(position.in_balance, Uint256::from(250u128));
+
+        // nothing changes after the end
+        update_stream(Timestamp::from_seconds(100), &mut stream).unwrap();
+        let (diff, distributed) = update_stream(Timestamp::from_seconds(200), &mut stream).unwrap();
+        assert_eq!(diff, Decimal::zero());
+        assert_eq!(distributed, Uint256::zero());
+        assert_eq!(stream.out_remaining, Uint256::zero());
+        assert_eq!(
+            stream.price_cumulative,
+            Decimal256::from_ratio(50u128, 1u128)
+        );
+        assert_eq!(
+            average_price(stream.spent_in, Uint256::from(1_000u128)).unwrap(),
+            Decimal256::percent(50)
+        );
+        average_price(stream.spent_in, Uint256::zero()).unwrap_err();
+    }
+
+    #[test]
+    fn test_get_decimals() {
+        assert_eq!(
+            get_decimals(Decimal256::from_ratio(7u128, 2u128)).unwrap(),
+            Decimal256::percent(50)
+        );
+        assert_eq!(
+            get_decimals(Decimal256::from_ratio(3u128, 1u128)).unwrap(),
+            Decimal256::zero()
+        );
+    }
+
+    #[test]
+    fn test_compute_shares_amount_overflow() {
+        let max = Uint256::from(u128::MAX);
+        let shares = compute_shares_amount(max * max, max, max - Uint256::one(), false).unwrap();
+        assert_eq!(shares, max * (max - Uint256::one()));
+        compute_shares_amount(max * max, Uint256::one(), max, false).unwrap_err();
+    }
+}
@@ -0,0 +1,394 @@
+use crate::blocklist::check_not_blocked;
+use crate::compliance::check_compliance;
+use crate::contract::update_stream;
+use crate::helpers::to_uint256;
+use crate::operations::ensure_operations;
+use crate::partial_refund::error_if_not_settled;
+use crate::state::{save_stream, StreamId, STREAMS};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, BankMsg, Coin, Deps, DepsMut, Env, HexBinary, MessageInfo, Order, Response, StdResult,
+    Storage, Uint128, Uint256,
+};
+use cw_storage_plus::Map;
+use cw_utils::must_pay;
+use sha2::{Digest, Sha256};
+
+/// Maximum number of allocation tiers of a stream
+pub const MAX_ALLOCATION_TIERS: usize = 10;
+/// Maximum number of members listed in a tier, larger tiers are set by merkle root
+pub const MAX_TIER_MEMBERS: usize = 100;
+
+/// Out tokens reserved for a set of addresses, split between them pro-rata to their deposits.
+/// Members are either listed or proven against the merkle root of the tier.
+#[cw_serde]
+pub struct AllocationTier {
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// Sha256 merkle root of the member addresses. Leaves are the hashes of the addresses, pairs
+    /// are hashed sorted.
+    pub merkle_root: Option<HexBinary>,
+    /// Maximum in tokens a member can deposit.
+    pub member_cap: Uint256,
+    /// Out tokens reserved for the tier out of the stream out supply.
+    pub out_allocation: Uint256,
+}
+
+/// Proof of membership of a merkle root tier.
+#[cw_serde]
+pub struct TierProof {
+    /// Index of the tier in the stream allocation tiers.
+    pub tier: u32,
+    /// Sibling hashes from the sender's leaf up to the root.
+    pub proof: Vec<HexBinary>,
+}
+
+#[cw_serde]
+pub struct TierDeposit {
+    /// Index of the tier in the stream allocation tiers.
+    pub tier: u32,
+    pub amount: Uint256,
+}
+
+#[cw_serde]
+pub struct TierDepositResponse {
+    pub tier: u32,
+    pub amount: Uint256,
+    /// Out tokens the deposit receives with the current tier deposits.
+    pub allocation: Uint256,
+}
+
+// Stream id -> allocation tiers, only set for streams with guaranteed allocations
+pub const ALLOCATION_TIERS: Map<StreamId, Vec<AllocationTier>> = Map::new("allocation_tiers");
+// (stream id, member) -> index of the member's tier
+pub const TIER_MEMBERS: Map<(StreamId, &str), u32> = Map::new("tier_members");
+// (stream id, member) -> in tokens deposited by the member
+pub const TIER_DEPOSITS: Map<(StreamId, &str), TierDeposit> = Map::new("tier_deposits");
+// (stream id, tier index) -> total in tokens deposited in the tier
+pub const TIER_TOTALS: Map<(StreamId, u32), Uint256> = Map::new("tier_totals");
+
+impl AllocationTier {
+    pub(crate) fn allocation(&self, amount: Uint256, total: Uint256) -> Uint256 {
+        if total.is_zero() {
+            return Uint256::zero();
+        }
+        self.out_allocation.multiply_ratio(amount, total)
+    }
+}
+
+/// Returns true if `proof` leads from the hash of `member` to `root`.
+fn verify_merkle_proof(root: &HexBinary, member: &str, proof: &[HexBinary]) -> bool {
+    let mut hash = Sha256::digest(member.as_bytes()).to_vec();
+    for sibling in proof {
+        let (first, second) = if hash.as_slice() <= sibling.as_slice() {
+            (hash.as_slice(), sibling.as_slice())
+        } else {
+            (sibling.as_slice(), hash.as_slice())
+        };
+        hash = Sha256::new()
+            .chain_update(first)
+            .chain_update(second)
+            .finalize()
+            .to_vec();
+    }
+    hash.as_slice() == root.as_slice()
+}
+
+/// Sets the allocation tiers of the stream, replacing the previous ones. Reserved out tokens are
+/// taken out of the streamed amount. Only the operations role can update, before the stream starts
+/// and before any deposit.
+pub fn execute_update_allocation_tiers(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    tiers: Vec<AllocationTier>,
+) -> Result<Response, ContractError> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if env.block.time >= stream.start_time {
+        return Err(ContractError::StreamIsStarted {});
+    }
+    if TIER_TOTALS
+        .prefix(stream_id)
+        .keys_raw(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+    {
+        return Err(ContractError::AllocationTiersLocked {});
+    }
+    if tiers.len() > MAX_ALLOCATION_TIERS {
+        return Err(ContractError::InvalidAllocationTiers {});
+    }
+
+    let mut reserved = Uint256::zero();
+    let mut validated = Vec::with_capacity(tiers.len());
+    let mut all_members = vec![];
+    for tier in tiers {
+        // members are either listed or set by a sha256 merkle root
+        let members_valid = match &tier.merkle_root {
+            Some(root) => tier.members.is_empty() && root.len() == 32,
+            None => !tier.members.is_empty() && tier.members.len() <= MAX_TIER_MEMBERS,
+        };
+        if !members_valid || tier.member_cap.is_zero() || tier.out_allocation.is_zero() {
+            return Err(ContractError::InvalidAllocationTiers {});
+        }
+        let mut members = Vec::with_capacity(tier.members.len());
+        for member in tier.members {
+            let member = deps.api.addr_validate(&member)?.to_string();
+            // an address can only be in one tier
+            if all_members.contains(&member) {
+                return Err(ContractError::InvalidAllocationTiers {});
+            }
+            all_members.push(member.clone());
+            members.push(member);
+        }
+        reserved = reserved.checked_add(tier.out_allocation)?;
+        validated.push(AllocationTier { members, ..tier });
+    }
+    // the public phase has to stream some of the supply
+    if reserved >= stream.out_supply {
+        return Err(ContractError::InvalidAllocationTiers {});
+    }
+
+    for tier in ALLOCATION_TIERS
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default()
+    {
+        for member in tier.members {
+            TIER_MEMBERS.remove(deps.storage, (stream_id, &member));
+        }
+    }
+    for (index, tier) in validated.iter().enumerate() {
+        for member in &tier.members {
+            TIER_MEMBERS.save(deps.storage, (stream_id, member), &(index as u32))?;
+        }
+    }
+    if validated.is_empty() {
+        ALLOCATION_TIERS.remove(deps.storage, stream_id);
+    } else {
+        ALLOCATION_TIERS.save(deps.storage, stream_id, &validated)?;
+    }
+    // nothing is streamed before start
+    stream.out_remaining = stream.out_supply - reserved;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_allocation_tiers")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("tiers", validated.len().to_string())
+        .add_attribute("reserved", reserved))
+}
+
+/// Deposits in tokens for the tier allocation of the sender, up to the tier member cap. Members of
+/// merkle root tiers prove their membership with their first deposit. Deposits are accepted
+/// before the stream starts and can not be withdrawn.
+pub fn execute_subscribe_tier(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    proof: Option<TierProof>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.is_killswitch_active() {
+        return Err(ContractError::StreamKillswitchActive {});
+    }
+    if env.block.time >= stream.start_time {
+        return Err(ContractError::StreamIsStarted {});
+    }
+    check_not_blocked(deps.storage, stream_id, &[&info.sender])?;
+    check_compliance(deps.as_ref(), stream_id, &[&info.sender])?;
+    let tiers = ALLOCATION_TIERS
+        .may_load(deps.storage, stream_id)?
+        .ok_or(ContractError::NotTierMember {})?;
+    let tier = match TIER_MEMBERS.may_load(deps.storage, (stream_id, info.sender.as_str()))? {
+        Some(tier) => tier,
+        None => {
+            let proof = proof.ok_or(ContractError::NotTierMember {})?;
+            let root = tiers
+                .get(proof.tier as usize)
+                .and_then(|tier| tier.merkle_root.as_ref())
+                .ok_or(ContractError::NotTierMember {})?;
+            if !verify_merkle_proof(root, info.sender.as_str(), &proof.proof) {
+                return Err(ContractError::NotTierMember {});
+            }
+            // proven members stay in the tier, so an address is only ever in one tier
+            TIER_MEMBERS.save(deps.storage, (stream_id, info.sender.as_str()), &proof.tier)?;
+            proof.tier
+        }
+    };
+    let amount = to_uint256(must_pay(&info, &stream.in_denom)?);
+
+    let deposited = TIER_DEPOSITS
+        .may_load(deps.storage, (stream_id, info.sender.as_str()))?
+        .map(|deposit| deposit.amount)
+        .unwrap_or_default();
+    let member_cap = tiers[tier as usize].member_cap;
+    let total = deposited.checked_add(amount)?;
+    if total > member_cap {
+        return Err(ContractError::TierCapExceeded {
+            remaining: member_cap - deposited,
+        });
+    }
+    TIER_DEPOSITS.save(
+        deps.storage,
+        (stream_id, info.sender.as_str()),
+        &TierDeposit {
+            tier,
+            amount: total,
+        },
+    )?;
+    TIER_TOTALS.update(deps.storage, (stream_id, tier), |t| -> StdResult<_> {
+        Ok(t.unwrap_or_default().checked_add(amount)?)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "subscribe_tier")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("tier", tier.to_string())
+        .add_attribute("in_amount", amount))
+}
+
+/// Sends the sender its share of the tier allocation once the stream ends, or refunds the deposit
+/// if the stream is cancelled.
+pub fn execute_exit_tier(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    let deposit = TIER_DEPOSITS
+        .may_load(deps.storage, (stream_id, info.sender.as_str()))?
+        .ok_or(ContractError::NoTierDeposit {})?;
+    TIER_DEPOSITS.remove(deps.storage, (stream_id, info.sender.as_str()));
+
+    let attrs = vec![
+        attr("action", "exit_tier"),
+        attr("stream_id", stream_id.to_string()),
+        attr("owner", info.sender.to_string()),
+        attr("tier", deposit.tier.to_string()),
+    ];
+    if stream.is_cancelled() {
+        return Ok(Response::new()
+            .add_attributes(attrs)
+            .add_attribute("refunded", deposit.amount)
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![Coin {
+                    denom: stream.in_denom,
+                    amount: Uint128::try_from(deposit.amount)?,
+                }],
+            }));
+    }
+    if stream.is_paused() {
+        return Err(ContractError::StreamKillswitchActive {});
+    }
+    if env.block.time <= stream.end_time {
+        return Err(ContractError::StreamNotEnded {});
+    }
+    // the stream is cancelled if the threshold is not reached, partially refunded streams pay
+    // tiers in full once finalized
+    let mut ended = stream.clone();
+    if ended.last_updated < ended.end_time {
+        update_stream(env.block.time, &mut ended)?;
+    }
+    error_if_not_settled(deps.storage, stream_id, &ended)?;
+
+    let tiers = ALLOCATION_TIERS.load(deps.storage, stream_id)?;
+    let total = TIER_TOTALS.load(deps.storage, (stream_id, deposit.tier))?;
+    let allocation = tiers[deposit.tier as usize].allocation(deposit.amount, total);
+
+    Ok(Response::new()
+        .add_attributes(attrs)
+        .add_attribute("purchased", allocation)
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin {
+                denom: stream.out_denom,
+                amount: Uint128::try_from(allocation)?,
+            }],
+        }))
+}
+
+/// Returns the in tokens deposited in tiers, paid to the treasury at finalize, and the out
+/// tokens reserved for tiers without deposits, refunded to the treasury.
+pub fn tier_settlement(
+    storage: &dyn Storage,
+    stream_id: StreamId,
+) -> StdResult<(Uint256, Uint256)> {
+    let tiers = ALLOCATION_TIERS
+        .may_load(storage, stream_id)?
+        .unwrap_or_default();
+    let mut deposited = Uint256::zero();
+    let mut unallocated = Uint256::zero();
+    for (index, tier) in tiers.iter().enumerate() {
+        let total = TIER_TOTALS
+            .may_load(storage, (stream_id, index as u32))?
+            .unwrap_or_default();
+        if total.is_zero() {
+            unallocated = unallocated.checked_add(tier.out_allocation)?;
+        }
+        deposited = deposited.checked_add(total)?;
+    }
+    Ok((deposited, unallocated))
+}
+
+/// Returns the out tokens reserved for tiers, not sold by the public stream.
+pub fn reserved_out(storage: &dyn Storage, stream_id: StreamId) -> StdResult<Uint256> {
+    ALLOCATION_TIERS
+        .may_load(storage, stream_id)?
+        .unwrap_or_default()
+        .iter()
+        .try_fold(Uint256::zero(), |reserved, tier| {
+            Ok(reserved.checked_add(tier.out_allocation)?)
+        })
+}
+
+/// Removes allocation tier state of a pruned stream.
+pub fn remove_allocation_tiers(storage: &mut dyn Storage, stream_id: StreamId) -> StdResult<()> {
+    let tiers = ALLOCATION_TIERS
+        .may_load(storage, stream_id)?
+        .unwrap_or_default();
+    for index in 0..tiers.len() {
+        TIER_TOTALS.remove(storage, (stream_id, index as u32));
+    }
+    // listed members and members proven by their deposits
+    let members = TIER_MEMBERS
+        .prefix(stream_id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for member in members {
+        TIER_MEMBERS.remove(storage, (stream_id, &member));
+    }
+    ALLOCATION_TIERS.remove(storage, stream_id);
+    Ok(())
+}
+
+pub fn query_allocation_tiers(deps: Deps, stream_id: StreamId) -> StdResult<Vec<AllocationTier>> {
+    Ok(ALLOCATION_TIERS
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default())
+}
+
+pub fn query_tier_deposit(
+    deps: Deps,
+    stream_id: StreamId,
+    owner: String,
+) -> StdResult<Option<TierDepositResponse>> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let deposit = match TIER_DEPOSITS.may_load(deps.storage, (stream_id, owner.as_str()))? {
+        Some(deposit) => deposit,
+        None => return Ok(None),
+    };
+    let tiers = ALLOCATION_TIERS.load(deps.storage, stream_id)?;
+    let total = TIER_TOTALS.load(deps.storage, (stream_id, deposit.tier))?;
+    Ok(Some(TierDepositResponse {
+        tier: deposit.tier,
+        amount: deposit.amount,
+        allocation: tiers[deposit.tier as usize].allocation(deposit.amount, total),
+    }))
+}
@@ -0,0 +1,115 @@
+//! Stream logic for contracts embedding StreamSwap instead of calling a deployed instance.
+//! Build with the `library` feature so the entry points are not exported, then call these
+//! handlers from the embedding contract. Replies with the contract reply ids must be routed to
+//! [`reply`] and sudo callbacks to [`sudo`].
+
+// Entry point handlers
+pub use crate::contract::{execute, instantiate, migrate, query, reply, sudo};
+// Reply ids used by the stream submessages
+pub use crate::contract::{CREATION_HOOK_REPLY_ID, FINALIZE_HOOK_REPLY_ID};
+pub use crate::fee_swap::FEE_SWAP_REPLY_ID;
+pub use crate::ibc::{IbcLifecycleComplete, IBC_TRANSFER_REPLY_ID};
+pub use crate::pool::{CREATE_POOL_REPLY_ID, CREATE_POSITION_REPLY_ID, POOL_REPLY_ID_MASK};
+pub use crate::vesting::VESTING_REPLY_ID;
+
+// Streams
+pub use crate::blocklist::{execute_refund_blocked, execute_update_blocklist};
+pub use crate::contract::{
+    execute_accept_treasury, execute_create_stream, execute_exit_stream, execute_finalize_stream,
+    execute_propose_treasury, execute_update_creation_hook, execute_update_finalize_hooks,
+    execute_update_stream,
+};
+pub use crate::crank::execute_crank_exits;
+pub use crate::creator_profiles::{execute_attach_creator_profile, execute_update_creator_profile};
+pub use crate::escheat::execute_claim_escheated;
+pub use crate::hard_cap::execute_update_hard_cap;
+pub use crate::ibc::{execute_claim_ibc_refunds, execute_update_remote_treasury};
+pub use crate::keeper::execute_claim_keeper_bounty;
+pub use crate::migrate_v0_2_1::execute_migrate_step;
+pub use crate::operations::{execute_accept_operations, execute_propose_operations};
+pub use crate::partial_refund::execute_update_partial_refund;
+pub use crate::pool::execute_update_create_pool;
+pub use crate::prune::execute_prune_streams;
+pub use crate::revenue_vesting::execute_claim_vested_revenue;
+pub use crate::subscription_limit::execute_update_subscription_limit;
+pub use crate::tags::execute_update_stream_tags;
+pub use crate::vesting::execute_update_stream_vesting;
+
+pub use crate::allocation_tiers::{
+    execute_exit_tier, execute_subscribe_tier, execute_update_allocation_tiers,
+};
+
+// Positions
+pub use crate::contract::{
+    execute_subscribe, execute_subscribe_pending, execute_update_operator, execute_update_position,
+    execute_withdraw, execute_withdraw_pending,
+};
+
+// Killswitch
+pub use crate::killswitch::{
+    execute_batch_exit_cancelled, execute_cancel_stream, execute_cancel_stream_with_threshold,
+    execute_confirm_cancel_stream, execute_creator_pause_stream, execute_creator_resume_stream,
+    execute_exit_cancelled, execute_exit_paused, execute_pause_stream,
+    execute_resume_expired_pause, execute_resume_stream, execute_withdraw_paused,
+};
+
+// Protocol admin
+pub use crate::compliance::{
+    execute_update_compliance_contract, ComplianceQueryMsg, ComplianceResponse,
+};
+pub use crate::contract::{
+    execute_accept_protocol_admin, execute_apply_config_update, execute_cancel_config_update,
+    execute_freeze_denom, execute_propose_protocol_admin, execute_renounce_protocol_admin,
+    execute_unfreeze_denom, execute_update_config, execute_update_creator_allowlist,
+    execute_update_fee_waiver, execute_update_finalize_hook_limits,
+};
+pub use crate::crank::execute_update_exit_crank;
+pub use crate::escheat::execute_escheat_positions;
+pub use crate::fee_swap::execute_update_fee_swap;
+pub use crate::ibc::execute_update_ibc_transfer;
+pub use crate::keeper::execute_update_keeper_bounty;
+pub use crate::killswitch::{execute_update_guardians, execute_update_remote_controller};
+pub use crate::liquid_staking::execute_update_liquid_staking;
+pub use crate::names::execute_update_name_registry;
+pub use crate::swap_subscribe::execute_subscribe_with_swap;
+pub use crate::vesting::execute_update_vesting_code_id;
+
+// Queries
+pub use crate::allocation_tiers::{query_allocation_tiers, query_tier_deposit};
+pub use crate::audit::{execute_recover_funds, execute_sweep_dust, query_audit_balances};
+pub use crate::blocklist::query_blocklist;
+pub use crate::contract::{
+    list_largest_positions, list_positions, list_positions_by_owner, list_streams,
+    query_average_price, query_config, query_count_positions, query_count_streams,
+    query_creator_allowlist, query_fee_waiver, query_finalize_hooks, query_frozen_denoms,
+    query_last_streamed_price, query_pending_config_update, query_pending_protocol_admin,
+    query_pending_treasury, query_position, query_stream, query_stream_at_height,
+    query_threshold_state, query_validate_denom,
+};
+pub use crate::creator_profiles::{
+    query_creator_profile, query_creator_streams, query_stream_creator,
+};
+pub use crate::escheat::query_escheated_position;
+pub use crate::hard_cap::query_hard_cap;
+pub use crate::ibc::query_ibc_refunds;
+pub use crate::keeper::query_keeper_bounty;
+pub use crate::killswitch::{query_cancel_confirmations, query_guardians};
+pub use crate::lifecycle::query_lifecycle;
+pub use crate::migrate_v0_2_1::query_migrate_dry_run;
+pub use crate::names::query_resolve_name;
+pub use crate::operations::query_stream_operations;
+pub use crate::partial_refund::query_partial_refund;
+pub use crate::pool::query_stream_pool;
+pub use crate::price_history::{query_price_history, query_streamed_price_twap};
+pub use crate::prune::query_pruned_stream;
+pub use crate::revenue_vesting::query_revenue_vesting;
+pub use crate::simulate::{query_simulate_exit, query_simulate_subscribe};
+pub use crate::stats::{query_protocol_stats, query_statistics};
+pub use crate::subscription_limit::query_subscription_limit;
+pub use crate::tags::query_stream_tags;
+pub use crate::vesting::{
+    list_vestings, query_failed_vesting, query_stream_vesting, query_vesting,
+};
+
+// Stream math on the stored types, `streamswap_math` has the storage free versions
+pub use crate::contract::{update_position, update_stream};
@@ -0,0 +1,382 @@
+use crate::allocation_tiers::{tier_settlement, ALLOCATION_TIERS, TIER_DEPOSITS, TIER_TOTALS};
+use crate::contract::{update_position, update_stream};
+use crate::crank::CRANK_RESERVES;
+use crate::escheat::ESCHEATED_POSITIONS;
+use crate::hard_cap::{capped_spent_in, hard_cap_refund};
+use crate::ibc::{IBC_REFUNDS, IBC_REFUND_TOTALS};
+use crate::keeper::KEEPER_BOUNTIES;
+use crate::partial_refund::apply_partial_fill;
+use crate::pool::STREAM_POOLS;
+use crate::revenue_vesting::REVENUE_VESTINGS;
+use crate::simulate::to_std_err;
+use crate::state::{Status, Stream, StreamId, CONFIG, POSITIONS, STREAMS};
+use crate::vesting::FAILED_VESTINGS;
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    BankMsg, Coin, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, Storage, Timestamp,
+    Uint128, Uint256,
+};
+use cw_storage_plus::{Bound, Map};
+use std::collections::BTreeMap;
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Balance of a denom held by the contract against the amount it owes.
+#[cw_serde]
+pub struct DenomAudit {
+    pub denom: String,
+    pub balance: Uint256,
+    pub obligations: Uint256,
+    /// Balance over obligations, rounding dust of distributions accrues here.
+    pub surplus: Uint256,
+    /// Obligations not covered by the balance.
+    pub shortfall: Uint256,
+}
+
+#[cw_serde]
+pub struct AuditBalancesResponse {
+    pub denoms: Vec<DenomAudit>,
+}
+
+/// Compares the bank balances of the contract with the tokens owed to subscribers, treasuries,
+/// the fee collector, keepers, escheated positions, failed vestings and IBC refund claimants.
+/// Iterates all streams and positions, meant for monitoring off chain.
+pub fn query_audit_balances(deps: Deps, env: Env) -> StdResult<AuditBalancesResponse> {
+    let obligations = obligations(deps.storage, env.block.time)?;
+    let mut balances: BTreeMap<String, Uint256> = deps
+        .querier
+        .query_all_balances(env.contract.address)?
+        .into_iter()
+        .map(|coin| (coin.denom, coin.amount.into()))
+        .collect();
+    for denom in obligations.keys() {
+        balances.entry(denom.clone()).or_default();
+    }
+    let denoms = balances
+        .into_iter()
+        .map(|(denom, balance)| {
+            let obligations = obligations.get(&denom).copied().unwrap_or_default();
+            DenomAudit {
+                surplus: balance.saturating_sub(obligations),
+                shortfall: obligations.saturating_sub(balance),
+                denom,
+                balance,
+                obligations,
+            }
+        })
+        .collect();
+    Ok(AuditBalancesResponse { denoms })
+}
+
+/// Obligations of a denom tallied over several sweeps, so no single transaction iterates every
+/// stream.
+#[cw_serde]
+#[derive(Default)]
+pub struct ObligationTally {
+    /// Streams after the id are left to tally.
+    pub start_after: Option<StreamId>,
+    /// Tokens owed in the denom by the streams tallied so far.
+    pub owed: Uint256,
+}
+
+// Denom -> obligations tallied by a sweep in progress
+pub const OBLIGATION_TALLIES: Map<&str, ObligationTally> = Map::new("obligation_tallies");
+
+/// Returns the tokens owed by the contract per denom.
+fn obligations(storage: &dyn Storage, now: Timestamp) -> StdResult<BTreeMap<String, Uint256>> {
+    let mut obligations = BTreeMap::new();
+    let streams = STREAMS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (stream_id, stream) in streams {
+        add_stream_obligations(storage, now, stream_id, stream, &mut obligations)?;
+    }
+    for item in ESCHEATED_POSITIONS.range(storage, None, None, Order::Ascending) {
+        let (_, escheated) = item?;
+        add_obligation(&mut obligations, &escheated.out_denom, escheated.purchased)?;
+        add_obligation(&mut obligations, &escheated.in_denom, escheated.refunded)?;
+    }
+    for item in IBC_REFUNDS.range(storage, None, None, Order::Ascending) {
+        let ((_, denom), amount) = item?;
+        add_obligation(&mut obligations, &denom, amount.into())?;
+    }
+    for item in FAILED_VESTINGS.range(storage, None, None, Order::Ascending) {
+        let (_, escrow) = item?;
+        add_obligation(&mut obligations, &escrow.denom, escrow.amount.into())?;
+    }
+    Ok(obligations)
+}
+
+/// Tallies the obligations in `denom` of the next `limit` streams, continuing the saved tally.
+/// Returns the total owed once every stream is tallied, failing on an open stream using the
+/// denom. Stream ids only grow and closed streams do not reopen, so a tally spread over several
+/// blocks can only overestimate what is owed.
+fn tally_obligations(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    denom: &str,
+    limit: Option<u32>,
+) -> Result<Option<Uint256>, ContractError> {
+    let mut tally = OBLIGATION_TALLIES
+        .may_load(storage, denom)?
+        .unwrap_or_default();
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let streams = STREAMS
+        .range(
+            storage,
+            tally.start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let done = streams.len() < limit;
+    for (stream_id, stream) in streams {
+        ensure_stream_closed(&stream, denom)?;
+        let mut obligations = BTreeMap::new();
+        add_stream_obligations(storage, now, stream_id, stream, &mut obligations)?;
+        for item in
+            ESCHEATED_POSITIONS
+                .prefix(stream_id)
+                .range(storage, None, None, Order::Ascending)
+        {
+            let (_, escheated) = item?;
+            add_obligation(&mut obligations, &escheated.out_denom, escheated.purchased)?;
+            add_obligation(&mut obligations, &escheated.in_denom, escheated.refunded)?;
+        }
+        for item in FAILED_VESTINGS
+            .prefix(stream_id)
+            .range(storage, None, None, Order::Ascending)
+        {
+            let (_, escrow) = item?;
+            add_obligation(&mut obligations, &escrow.denom, escrow.amount.into())?;
+        }
+        if let Some(owed) = obligations.get(denom) {
+            tally.owed = tally.owed.checked_add(*owed)?;
+        }
+        tally.start_after = Some(stream_id);
+    }
+    if !done {
+        OBLIGATION_TALLIES.save(storage, denom, &tally)?;
+        return Ok(None);
+    }
+    OBLIGATION_TALLIES.remove(storage, denom);
+    let refunds = IBC_REFUND_TOTALS
+        .may_load(storage, denom)?
+        .unwrap_or_default();
+    Ok(Some(tally.owed.checked_add(refunds.into())?))
+}
+
+/// Sends the balance of `denom` over its obligations to the fee collector. Only protocol admin
+/// can sweep, once no open stream uses the denom, so the surplus is rounding residue of closed
+/// streams. Obligations are tallied over `limit` streams per call, the dust is sent by the call
+/// completing the tally.
+pub fn execute_sweep_dust(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let Some(owed) = tally_obligations(deps.storage, env.block.time, &denom, limit)? else {
+        return Ok(Response::new()
+            .add_attribute("action", "tally_obligations")
+            .add_attribute("denom", denom));
+    };
+    let balance: Uint256 = deps
+        .querier
+        .query_balance(&env.contract.address, &denom)?
+        .amount
+        .into();
+    let dust = balance.saturating_sub(owed);
+    if dust.is_zero() {
+        return Err(ContractError::NoDust {});
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "sweep_dust")
+        .add_attribute("denom", denom.clone())
+        .add_attribute("swept", dust)
+        .add_message(BankMsg::Send {
+            to_address: config.fee_collector.to_string(),
+            amount: vec![Coin {
+                denom,
+                amount: Uint128::try_from(dust)?,
+            }],
+        }))
+}
+
+/// Sends `amount` of a denom the contract owes nothing in to `recipient`, recovering tokens sent
+/// to the contract by mistake. Only protocol admin can recover. Obligations are tallied like
+/// for `execute_sweep_dust`, the call completing the tally recovers.
+pub fn execute_recover_funds(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    amount: Uint128,
+    recipient: String,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let Some(owed) = tally_obligations(deps.storage, env.block.time, &denom, limit)? else {
+        return Ok(Response::new()
+            .add_attribute("action", "tally_obligations")
+            .add_attribute("denom", denom));
+    };
+    if !owed.is_zero() {
+        return Err(ContractError::DenomInUse { denom });
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "recover_funds")
+        .add_attribute("denom", denom.clone())
+        .add_attribute("amount", amount)
+        .add_attribute("recipient", recipient.to_string())
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin { denom, amount }],
+        }))
+}
+
+/// Fails if the stream is open and uses `denom`.
+fn ensure_stream_closed(stream: &Stream, denom: &str) -> Result<(), ContractError> {
+    let open = !matches!(stream.status, Status::Finalized | Status::Cancelled);
+    let denoms = [
+        &stream.in_denom,
+        &stream.out_denom,
+        &stream.stream_creation_denom,
+    ];
+    if open && denoms.iter().any(|d| *d == denom) {
+        return Err(ContractError::DenomInUse {
+            denom: denom.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn add_obligation(
+    obligations: &mut BTreeMap<String, Uint256>,
+    denom: &str,
+    amount: Uint256,
+) -> StdResult<()> {
+    let owed = obligations.entry(denom.to_string()).or_default();
+    *owed = owed.checked_add(amount)?;
+    Ok(())
+}
+
+fn add_stream_obligations(
+    storage: &dyn Storage,
+    now: Timestamp,
+    stream_id: StreamId,
+    mut stream: Stream,
+    obligations: &mut BTreeMap<String, Uint256>,
+) -> StdResult<()> {
+    let tiers = ALLOCATION_TIERS
+        .may_load(storage, stream_id)?
+        .unwrap_or_default();
+    let mut tier_in = Uint256::zero();
+    let mut tier_out = Uint256::zero();
+    for item in TIER_DEPOSITS
+        .prefix(stream_id)
+        .range(storage, None, None, Order::Ascending)
+    {
+        let (_, deposit) = item?;
+        let total = TIER_TOTALS.load(storage, (stream_id, deposit.tier))?;
+        tier_in = tier_in.checked_add(deposit.amount)?;
+        tier_out =
+            tier_out.checked_add(tiers[deposit.tier as usize].allocation(deposit.amount, total))?;
+    }
+
+    // cancelled streams refund deposits in full, out tokens went back to the treasury
+    if stream.is_cancelled() {
+        let mut in_owed = tier_in;
+        for item in POSITIONS
+            .prefix(stream_id)
+            .range(storage, None, None, Order::Ascending)
+        {
+            let (_, position) = item?;
+            in_owed = in_owed.checked_add(position.in_balance.checked_add(position.spent)?)?;
+        }
+        return add_obligation(obligations, &stream.in_denom, in_owed);
+    }
+
+    let finalized = stream.status == Status::Finalized;
+    if !finalized && !stream.is_paused() {
+        update_stream(now, &mut stream).map_err(to_std_err)?;
+    }
+    let mut in_owed = Uint256::zero();
+    let mut out_owed = tier_out;
+    for item in POSITIONS
+        .prefix(stream_id)
+        .range(storage, None, None, Order::Ascending)
+    {
+        let (_, mut position) = item?;
+        update_position(
+            stream.dist_index,
+            stream.shares,
+            stream.last_updated,
+            stream.in_supply,
+            &mut position,
+        )
+        .map_err(to_std_err)?;
+        let refund = hard_cap_refund(storage, stream_id, stream.spent_in, position.spent)?;
+        position.spent = position.spent.checked_sub(refund)?;
+        position.in_balance = position.in_balance.checked_add(refund)?;
+        apply_partial_fill(storage, stream_id, &mut position)?;
+        in_owed = in_owed.checked_add(position.in_balance)?;
+        out_owed = out_owed.checked_add(position.purchased)?;
+    }
+
+    if finalized {
+        // revenue still vesting, unclaimed keeper bounties and the exit crank reserve stay with
+        // the contract
+        if let Some(vesting) = REVENUE_VESTINGS.may_load(storage, stream_id)? {
+            in_owed = in_owed.checked_add(vesting.total.saturating_sub(vesting.claimed))?;
+        }
+        if let Some(reserve) = CRANK_RESERVES.may_load(storage, stream_id)? {
+            in_owed = in_owed.checked_add(reserve)?;
+        }
+        for item in KEEPER_BOUNTIES
+            .prefix(stream_id)
+            .range(storage, None, None, Order::Ascending)
+        {
+            let (_, bounty) = item?;
+            in_owed = in_owed.checked_add(bounty)?;
+        }
+    } else {
+        // spent tokens, tier deposits and the creation fee are paid out at finalize
+        let (tier_deposits, unallocated_out) = tier_settlement(storage, stream_id)?;
+        in_owed = in_owed
+            .checked_add(capped_spent_in(storage, stream_id, stream.spent_in)?)?
+            .checked_add(tier_deposits)?;
+        out_owed = out_owed
+            .checked_add(stream.out_remaining)?
+            .checked_add(unallocated_out)?;
+        add_obligation(
+            obligations,
+            &stream.stream_creation_denom,
+            stream.stream_creation_fee.into(),
+        )?;
+        // the pool escrow is spent or refunded at finalize
+        if let Some(stream_pool) = STREAM_POOLS.may_load(storage, stream_id)? {
+            out_owed = out_owed.checked_add(stream_pool.create_pool.out_amount_clp)?;
+            for fee in stream_pool.pool_creation_fee {
+                add_obligation(obligations, &fee.denom, fee.amount.into())?;
+            }
+        }
+    }
+    add_obligation(obligations, &stream.in_denom, in_owed)?;
+    add_obligation(obligations, &stream.out_denom, out_owed)
+}
@@ -0,0 +1,154 @@
+use crate::contract::{update_position, update_stream};
+use crate::events::Withdrawn;
+use crate::operations::ensure_operations;
+use crate::price_history::save_price_snapshot;
+use crate::state::{save_stream, Status, StreamId, POSITIONS, STREAMS};
+use crate::stats::record_withdrawal;
+use crate::ContractError;
+use cosmwasm_std::{
+    attr, Addr, BankMsg, Coin, Deps, DepsMut, Empty, Env, MessageInfo, Order, Response, StdResult,
+    Storage, Uint128,
+};
+use cw_storage_plus::{Bound, Map};
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+// (stream id, address) -> (), addresses not allowed to subscribe to the stream
+pub const BLOCKLISTS: Map<(StreamId, &Addr), Empty> = Map::new("blocklists");
+
+/// Fails if any of the addresses is on the blocklist of the stream.
+pub fn check_not_blocked(
+    storage: &dyn Storage,
+    stream_id: StreamId,
+    addrs: &[&Addr],
+) -> Result<(), ContractError> {
+    for addr in addrs {
+        if BLOCKLISTS.has(storage, (stream_id, addr)) {
+            return Err(ContractError::AddressBlocked {
+                address: addr.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Adds and removes addresses of the stream blocklist. Only the operations role can update, until
+/// the stream is finalized or cancelled.
+pub fn execute_update_blocklist(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if matches!(stream.status, Status::Finalized | Status::Cancelled) {
+        return Err(ContractError::StreamEnded {});
+    }
+    for addr in &add {
+        let addr = deps.api.addr_validate(addr)?;
+        BLOCKLISTS.save(deps.storage, (stream_id, &addr), &Empty {})?;
+    }
+    for addr in &remove {
+        let addr = deps.api.addr_validate(addr)?;
+        BLOCKLISTS.remove(deps.storage, (stream_id, &addr));
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_blocklist")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("added", add.join(","))
+        .add_attribute("removed", remove.join(",")))
+}
+
+/// Withdraws the unspent in tokens of a blocked position back to its owner. Tokens purchased so far
+/// stay in the position and are claimed with a regular exit. Only the operations role can refund.
+pub fn execute_refund_blocked(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    owner: String,
+) -> Result<Response, ContractError> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if stream.is_killswitch_active() {
+        return Err(ContractError::StreamKillswitchActive {});
+    }
+    if env.block.time >= stream.end_time {
+        return Err(ContractError::StreamEnded {});
+    }
+    let owner = deps.api.addr_validate(&owner)?;
+    if !BLOCKLISTS.has(deps.storage, (stream_id, &owner)) {
+        return Err(ContractError::AddressNotBlocked {
+            address: owner.to_string(),
+        });
+    }
+    let mut position = POSITIONS.load(deps.storage, (stream_id, &owner))?;
+
+    update_stream(env.block.time, &mut stream)?;
+    update_position(
+        stream.dist_index,
+        stream.shares,
+        stream.last_updated,
+        stream.in_supply,
+        &mut position,
+    )?;
+    let refund_amount = position.in_balance;
+    if refund_amount.is_zero() {
+        return Err(ContractError::InvalidWithdrawAmount {});
+    }
+
+    stream.in_supply = stream.in_supply.checked_sub(refund_amount)?;
+    stream.shares = stream.shares.checked_sub(position.shares)?;
+    position.in_balance = Default::default();
+    position.shares = Default::default();
+    record_withdrawal(deps.storage, stream_id, refund_amount)?;
+
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    save_price_snapshot(deps.storage, stream_id, &stream)?;
+    POSITIONS.save(deps.storage, (stream_id, &owner), &position)?;
+
+    let event = Withdrawn {
+        stream_id,
+        owner: owner.clone(),
+        amount: refund_amount,
+    };
+    let attrs = vec![
+        attr("action", "refund_blocked"),
+        attr("stream_id", stream_id.to_string()),
+        attr("owner", owner.to_string()),
+        attr("refund_amount", refund_amount),
+    ];
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: owner.to_string(),
+            amount: vec![Coin {
+                denom: stream.in_denom,
+                amount: Uint128::try_from(refund_amount)?,
+            }],
+        })
+        .add_attributes(attrs)
+        .add_event(event.into()))
+}
+
+pub fn query_blocklist(
+    deps: Deps,
+    stream_id: StreamId,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Addr>> {
+    let start_after = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    BLOCKLISTS
+        .prefix(stream_id)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
@@ -0,0 +1,89 @@
+use crate::contract::{finalize_stream, update_stream};
+use crate::partial_refund::error_if_not_settleable;
+use crate::price_history::save_price_snapshot;
+use crate::state::{save_stream, Status, Stream, StreamId, STREAMS};
+use crate::ContractError;
+use cosmwasm_std::{DepsMut, Env, Order, Response, StdResult, Storage};
+use cw_storage_plus::{Bound, Item};
+
+// Bounds the number of streams processed by a single clock call
+pub const CLOCK_BATCH_LIMIT: usize = 10;
+
+// Last stream id processed by the clock, the next call continues after it
+pub const CLOCK_CURSOR: Item<StreamId> = Item::new("clock_cursor");
+// Last waiting stream id processed by the clock, waiting streams are scanned separately
+pub const CLOCK_WAITING_CURSOR: Item<StreamId> = Item::new("clock_waiting_cursor");
+
+/// Returns the next batch of streams of the status after the cursor, moving the cursor.
+fn next_batch(
+    storage: &mut dyn Storage,
+    cursor: Item<StreamId>,
+    status: Status,
+) -> StdResult<Vec<(StreamId, Stream)>> {
+    let start_after = cursor.may_load(storage)?;
+    let streams = STREAMS
+        .idx
+        .status
+        .prefix(status.key())
+        .range(
+            storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(CLOCK_BATCH_LIMIT)
+        .collect::<StdResult<Vec<_>>>()?;
+    match streams.last() {
+        Some((stream_id, _)) if streams.len() == CLOCK_BATCH_LIMIT => {
+            cursor.save(storage, stream_id)?
+        }
+        _ => cursor.remove(storage),
+    }
+    Ok(streams)
+}
+
+/// Updates the next batch of active streams and finalizes at most one ended stream per call,
+/// as fee swaps and IBC payouts of a finalize reply through a single pending slot. Streams that
+/// ended without any subscription are still waiting, so a batch of waiting streams is scanned
+/// for ended ones too. Ended streams whose threshold is not reached are left to be cancelled.
+/// The cursors wrap around once all active and waiting streams are processed.
+pub fn sudo_clock(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let active = next_batch(deps.storage, CLOCK_CURSOR, Status::Active)?;
+    let waiting = next_batch(deps.storage, CLOCK_WAITING_CURSOR, Status::Waiting)?;
+
+    let mut updated = 0u32;
+    let mut finalize = None;
+    for (stream_id, mut stream) in active.into_iter().chain(waiting) {
+        if env.block.time > stream.end_time {
+            if finalize.is_some() {
+                continue;
+            }
+            let mut ended = stream.clone();
+            update_stream(env.block.time, &mut ended)?;
+            if error_if_not_settleable(deps.storage, stream_id, &ended).is_ok() {
+                finalize = Some((stream_id, stream));
+            }
+            continue;
+        }
+        // waiting streams have nothing to distribute before they end
+        if stream.status == Status::Waiting || stream.last_updated >= env.block.time {
+            continue;
+        }
+        update_stream(env.block.time, &mut stream)?;
+        save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+        save_price_snapshot(deps.storage, stream_id, &stream)?;
+        updated += 1;
+    }
+
+    let finalized = finalize
+        .as_ref()
+        .map(|(stream_id, _)| stream_id.to_string());
+    let res = match finalize {
+        Some((stream_id, stream)) => finalize_stream(deps, env, stream_id, stream)?,
+        None => Response::new(),
+    };
+    Ok(res
+        .add_attribute("action", "clock")
+        .add_attribute("updated", updated.to_string())
+        .add_attribute("finalized", finalized.unwrap_or_default()))
+}
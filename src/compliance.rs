@@ -0,0 +1,84 @@
+use crate::contract::save_config_update;
+use crate::state::{ConfigUpdate, StreamId, CONFIG};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, MessageInfo, Response};
+use cw_utils::maybe_addr;
+
+/// Query of the compliance contract, asked before subscriptions and exits.
+#[cw_serde]
+pub enum ComplianceQueryMsg {
+    CheckAddress {
+        address: String,
+        stream_id: StreamId,
+    },
+}
+
+#[cw_serde]
+pub struct ComplianceResponse {
+    pub allowed: bool,
+    /// Reason of the rejection, returned to the caller.
+    pub reason: Option<String>,
+}
+
+pub fn execute_update_compliance_contract(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    compliance_contract: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let compliance_contract = maybe_addr(deps.api, compliance_contract)?;
+    let apply_after = save_config_update(
+        deps,
+        &env,
+        config,
+        ConfigUpdate {
+            compliance_contract: Some(compliance_contract.as_ref().map(Addr::to_string)),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_compliance_contract")
+        .add_attribute(
+            "compliance_contract",
+            compliance_contract
+                .map(|a| a.to_string())
+                .unwrap_or_default(),
+        )
+        .add_attributes(apply_after.map(|t| ("apply_after", t.to_string()))))
+}
+
+/// Fails if the compliance contract is set and rejects any of the addresses.
+pub fn check_compliance(
+    deps: Deps,
+    stream_id: StreamId,
+    addrs: &[&Addr],
+) -> Result<(), ContractError> {
+    let Some(compliance_contract) = CONFIG.load(deps.storage)?.compliance_contract else {
+        return Ok(());
+    };
+    for (i, addr) in addrs.iter().enumerate() {
+        if addrs[..i].contains(addr) {
+            continue;
+        }
+        let res: ComplianceResponse = deps.querier.query_wasm_smart(
+            &compliance_contract,
+            &ComplianceQueryMsg::CheckAddress {
+                address: addr.to_string(),
+                stream_id,
+            },
+        )?;
+        if !res.allowed {
+            return Err(ContractError::ComplianceRejected {
+                address: addr.to_string(),
+                reason: res.reason.unwrap_or_default(),
+            });
+        }
+    }
+    Ok(())
+}
@@ -1,30 +1,92 @@
-use crate::killswitch::execute_cancel_stream_with_threshold;
-use crate::migrate_v0_2_1::migrate_v0_2_1;
+use crate::allocation_tiers::{
+    execute_exit_tier, execute_subscribe_tier, execute_update_allocation_tiers,
+    query_allocation_tiers, query_tier_deposit, reserved_out, tier_settlement,
+};
+use crate::audit::query_audit_balances;
+use crate::blocklist::{check_not_blocked, query_blocklist};
+use crate::compliance::check_compliance;
+use crate::crank::reserve_crank_bounties;
+use crate::events::{Exited, Finalized, StreamCreated, Subscribed, Withdrawn};
+use crate::exit_swap::{
+    exit_swap_msg, validate_exit_swap, ExitSwap, EXIT_SWAP_PURCHASED_REPLY_ID,
+    EXIT_SWAP_REFUNDED_REPLY_ID,
+};
+use crate::fee_swap::{fee_collection_msg, FEE_SWAP_REPLY_ID};
+use crate::hard_cap::{
+    capped_spent_in, execute_update_hard_cap, hard_cap_refund, query_hard_cap, HARD_CAPS,
+};
+use crate::ibc::{ibc_transfer_msg, treasury_payout_msg, IBC_TRANSFER_REPLY_ID};
+use crate::keeper::STREAM_KEEPER_BOUNTIES;
+use crate::killswitch::{execute_cancel_stream_with_threshold, PAUSED_EXITS};
+use crate::lifecycle::{effective_end_time, query_lifecycle, stream_phase};
+use crate::liquid_staking::liquid_stake_msg;
+use crate::migrate_v0_2_1::MIGRATION_CURSOR;
 use crate::msg::{
-    AveragePriceResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, LatestStreamedPriceResponse,
-    MigrateMsg, PositionResponse, PositionsResponse, QueryMsg, StreamResponse, StreamsResponse,
-    SudoMsg,
+    AveragePriceResponse, ConfigResponse, CountResponse, ExecuteMsg, FrozenDenomResponse,
+    InstantiateMsg, LatestStreamedPriceResponse, MigrateMsg, OrderBy, PositionResponse,
+    PositionsResponse, QueryMsg, StreamHookMsg, StreamResponse, StreamsResponse, SudoMsg,
+    ValidateDenomResponse,
+};
+use crate::names::{check_stream_name, claim_stream_name, query_resolve_name};
+use crate::operations::ensure_operations;
+use crate::partial_refund::{
+    apply_partial_fill, error_if_not_settled, execute_update_partial_refund, filled,
+    query_partial_refund, settle_threshold, PARTIAL_REFUNDS,
+};
+use crate::pool::{CREATE_POOL_REPLY_ID, CREATE_POSITION_REPLY_ID, POOL_REPLY_ID_MASK};
+use crate::price_history::{query_price_history, query_streamed_price_twap, save_price_snapshot};
+use crate::revenue_vesting::{
+    execute_claim_vested_revenue, query_revenue_vesting, set_revenue_vesting_if_any,
+    start_revenue_vesting,
+};
+use crate::simulate::{query_simulate_exit, query_simulate_subscribe};
+use crate::state::{
+    next_stream_id, save_stream, stream_count, Config, ConfigUpdate, FinalizeHookLimits,
+    FrozenDenom, PendingConfigUpdate, PendingProtocolAdmin, Position, Status, Stream, CONFIG,
+    CREATION_HOOKS, CREATOR_ALLOWLIST, FEE_WAIVERS, FINALIZE_HOOKS, FROZEN_DENOMS,
+    PENDING_CONFIG_UPDATE, PENDING_PROTOCOL_ADMIN, PENDING_TREASURIES, POSITIONS,
+    REMOTE_TREASURIES, STREAMS, STREAM_DONATIONS, STREAM_SAVED_AT, STREAM_SNAPSHOTS,
+};
+use crate::stats::{
+    query_protocol_stats, query_statistics, record_exit, record_fee, record_finalize,
+    record_subscription, record_withdrawal, STREAM_STATS,
 };
-use crate::state::{next_stream_id, Config, Position, Status, Stream, CONFIG, POSITIONS, STREAMS};
+use crate::subscription_limit::{
+    execute_update_subscription_limit, query_subscription_limit, record_limited_subscription,
+};
+use crate::swap_subscribe::SWAP_SUBSCRIBE_REPLY_ID;
+use crate::tags::{execute_update_stream_tags, query_stream_tags, TAGGED_STREAMS};
 use crate::threshold::ThresholdState;
-use crate::{killswitch, ContractError};
+use crate::vesting::VESTING_REPLY_ID;
+use crate::{
+    audit, blocklist, clock, compliance, crank, creator_profiles, escheat, exit_swap, fee_swap,
+    ibc, keeper, killswitch, liquid_staking, migrate_v0_2_1, names, operations, pool, prune,
+    swap_subscribe, vesting, ContractError,
+};
 use cosmwasm_std::{
-    attr, entry_point, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Decimal256,
-    Deps, DepsMut, Env, Fraction, MessageInfo, Order, Response, StdError, StdResult, Timestamp,
-    Uint128, Uint256, Uint64,
+    attr, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Decimal256, Deps,
+    DepsMut, Empty, Env, MessageInfo, Order, Reply, Response, StdError, StdResult, SubMsg,
+    SubMsgResult, Timestamp, Uint128, Uint256, Uint64, WasmMsg,
 };
 use cw2::{get_contract_version, set_contract_version};
 use semver::Version;
 
-use crate::helpers::{check_name_and_url, from_semver, get_decimals, to_uint256};
-use cw_storage_plus::Bound;
-use cw_utils::{maybe_addr, must_pay};
+use crate::helpers::{check_name_and_url, from_semver, to_uint256, validate_ibc_denom};
+use cw_storage_plus::{Bound, PrimaryKey};
+use cw_utils::{maybe_addr, must_pay, Expiration};
+use osmosis_std::types::cosmos::distribution::v1beta1::MsgFundCommunityPool;
+use std::collections::BTreeMap;
 
 // Version and contract info for migration
 const CONTRACT_NAME: &str = "crates.io:cw-streamswap";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+// Reply id of finalize hook messages, hook failures do not revert finalize
+pub const FINALIZE_HOOK_REPLY_ID: u64 = 2;
+// Reply id of creation hook messages, hook failures do not revert stream creation
+pub const CREATION_HOOK_REPLY_ID: u64 = 3;
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
@@ -41,6 +103,19 @@ pub fn instantiate(
         return Err(ContractError::InvalidStreamCreationFee {});
     }
 
+    if msg.max_pause_duration.is_some_and(|d| d.is_zero())
+        || msg.max_creator_pause_duration.is_some_and(|d| d.is_zero())
+    {
+        return Err(ContractError::InvalidMaxPauseDuration {});
+    }
+
+    if msg
+        .creator_cancel_penalty
+        .is_some_and(|p| p > Decimal256::one())
+    {
+        return Err(ContractError::InvalidCreatorCancelPenalty {});
+    }
+
     let config = Config {
         min_stream_seconds: msg.min_stream_seconds,
         min_seconds_until_start_time: msg.min_seconds_until_start_time,
@@ -48,8 +123,25 @@ pub fn instantiate(
         stream_creation_fee: msg.stream_creation_fee,
         exit_fee_percent: msg.exit_fee_percent,
         fee_collector: deps.api.addr_validate(&msg.fee_collector)?,
-        protocol_admin: deps.api.addr_validate(&msg.protocol_admin)?,
+        protocol_admin: Some(deps.api.addr_validate(&msg.protocol_admin)?),
         accepted_in_denom: msg.accepted_in_denom,
+        fee_swap: None,
+        ibc_transfer: None,
+        remote_controller: None,
+        prune_delay_blocks: None,
+        escheat_delay_blocks: None,
+        keeper_bounty: None,
+        liquid_staking: None,
+        name_registry: None,
+        compliance_contract: None,
+        exit_crank: None,
+        vesting_code_id: None,
+        finalize_hook_limits: None,
+        max_pause_duration: msg.max_pause_duration,
+        max_creator_pause_duration: msg.max_creator_pause_duration,
+        creator_cancel_penalty: msg.creator_cancel_penalty,
+        permissioned_creation: msg.permissioned_creation,
+        config_timelock: msg.config_timelock,
     };
     CONFIG.save(deps.storage, &config)?;
 
@@ -69,13 +161,17 @@ pub fn instantiate(
     Ok(Response::default().add_attributes(attrs))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn execute(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    // legacy records are not indexed until the migration completes
+    if MIGRATION_CURSOR.exists(deps.storage) && !matches!(msg, ExecuteMsg::MigrateStep { .. }) {
+        return Err(ContractError::MigrationPending {});
+    }
     match msg {
         ExecuteMsg::CreateStream {
             treasury,
@@ -87,9 +183,25 @@ pub fn execute(
             start_time,
             end_time,
             threshold,
+            revenue_vesting_duration,
+            donation_percent,
+            refund_excess,
         } => execute_create_stream(
-            deps, env, info, treasury, name, url, in_denom, out_denom, out_supply, start_time,
-            end_time, threshold,
+            deps,
+            env,
+            info,
+            treasury,
+            name,
+            url,
+            in_denom,
+            out_denom,
+            out_supply,
+            start_time,
+            end_time,
+            threshold,
+            revenue_vesting_duration,
+            donation_percent,
+            refund_excess,
         ),
         ExecuteMsg::UpdateOperator {
             stream_id,
@@ -99,7 +211,7 @@ pub fn execute(
             stream_id,
             operator_target,
         } => execute_update_position(deps, env, info, stream_id, operator_target),
-        ExecuteMsg::UpdateStream { stream_id } => execute_update_stream(deps, env, stream_id),
+        ExecuteMsg::UpdateStream { stream_id } => execute_update_stream(deps, env, info, stream_id),
         ExecuteMsg::CancelStreamWithThreshold { stream_id } => {
             execute_cancel_stream_with_threshold(deps, env, info, stream_id)
         }
@@ -107,6 +219,7 @@ pub fn execute(
             stream_id,
             operator_target,
             operator,
+            min_shares_out,
         } => {
             let stream = STREAMS.load(deps.storage, stream_id)?;
             if stream.start_time > env.block.time {
@@ -117,6 +230,7 @@ pub fn execute(
                     stream_id,
                     operator,
                     operator_target,
+                    min_shares_out,
                     stream,
                 )?)
             } else {
@@ -127,10 +241,23 @@ pub fn execute(
                     stream_id,
                     operator,
                     operator_target,
+                    min_shares_out,
                     stream,
                 )?)
             }
         }
+        ExecuteMsg::SubscribeWithSwap {
+            stream_id,
+            routes,
+            min_in_amount,
+        } => swap_subscribe::execute_subscribe_with_swap(
+            deps,
+            env,
+            info,
+            stream_id,
+            routes,
+            min_in_amount,
+        ),
         ExecuteMsg::Withdraw {
             stream_id,
             cap,
@@ -159,14 +286,88 @@ pub fn execute(
                 )?)
             }
         }
-        ExecuteMsg::FinalizeStream {
+        ExecuteMsg::FinalizeStream { stream_id } => {
+            execute_finalize_stream(deps, env, info, stream_id)
+        }
+        ExecuteMsg::ProposeTreasury {
             stream_id,
             new_treasury,
-        } => execute_finalize_stream(deps, env, info, stream_id, new_treasury),
+        } => execute_propose_treasury(deps, env, info, stream_id, new_treasury),
+        ExecuteMsg::AcceptTreasury { stream_id } => {
+            execute_accept_treasury(deps, env, info, stream_id)
+        }
+        ExecuteMsg::ProposeOperations {
+            stream_id,
+            new_operations,
+        } => operations::execute_propose_operations(deps, env, info, stream_id, new_operations),
+        ExecuteMsg::AcceptOperations { stream_id } => {
+            operations::execute_accept_operations(deps, env, info, stream_id)
+        }
+        ExecuteMsg::UpdateFinalizeHooks { stream_id, hooks } => {
+            execute_update_finalize_hooks(deps, env, info, stream_id, hooks)
+        }
+        ExecuteMsg::AddCreationHook { addr } => {
+            execute_update_creation_hook(deps, env, info, addr, true)
+        }
+        ExecuteMsg::RemoveCreationHook { addr } => {
+            execute_update_creation_hook(deps, env, info, addr, false)
+        }
+        ExecuteMsg::UpdateRemoteTreasury {
+            stream_id,
+            remote_treasury,
+        } => ibc::execute_update_remote_treasury(deps, env, info, stream_id, remote_treasury),
+        ExecuteMsg::UpdateAllocationTiers { stream_id, tiers } => {
+            execute_update_allocation_tiers(deps, env, info, stream_id, tiers)
+        }
+        ExecuteMsg::SubscribeTier { stream_id, proof } => {
+            execute_subscribe_tier(deps, env, info, stream_id, proof)
+        }
+        ExecuteMsg::ExitTier { stream_id } => execute_exit_tier(deps, env, info, stream_id),
+        ExecuteMsg::UpdateHardCap {
+            stream_id,
+            hard_cap,
+        } => execute_update_hard_cap(deps, env, info, stream_id, hard_cap),
+        ExecuteMsg::UpdatePartialRefund { stream_id, enabled } => {
+            execute_update_partial_refund(deps, env, info, stream_id, enabled)
+        }
+        ExecuteMsg::UpdateSubscriptionLimit { stream_id, limit } => {
+            execute_update_subscription_limit(deps, env, info, stream_id, limit)
+        }
+        ExecuteMsg::PruneStreams { stream_ids } => {
+            prune::execute_prune_streams(deps, env, info, stream_ids)
+        }
+        ExecuteMsg::EscheatPositions { stream_id, limit } => {
+            escheat::execute_escheat_positions(deps, env, info, stream_id, limit)
+        }
+        ExecuteMsg::ClaimEscheated { stream_id } => {
+            escheat::execute_claim_escheated(deps, env, info, stream_id)
+        }
+        ExecuteMsg::ClaimIbcRefunds {} => ibc::execute_claim_ibc_refunds(deps, env, info),
+        ExecuteMsg::CrankExits { stream_id, limit } => {
+            crank::execute_crank_exits(deps, env, info, stream_id, limit)
+        }
+        ExecuteMsg::ClaimKeeperBounty { stream_id } => {
+            keeper::execute_claim_keeper_bounty(deps, env, info, stream_id)
+        }
+        ExecuteMsg::ClaimVestedRevenue { stream_id } => {
+            execute_claim_vested_revenue(deps, env, info, stream_id)
+        }
         ExecuteMsg::ExitStream {
             stream_id,
             operator_target,
-        } => execute_exit_stream(deps, env, info, stream_id, operator_target),
+            remote_recipient,
+            liquid_stake,
+            swap,
+        } => execute_exit_stream(
+            deps,
+            env,
+            info,
+            stream_id,
+            operator_target,
+            remote_recipient,
+            liquid_stake,
+            swap,
+        ),
 
         ExecuteMsg::PauseStream { stream_id } => {
             killswitch::execute_pause_stream(deps, env, info, stream_id)
@@ -177,18 +378,47 @@ pub fn execute(
         ExecuteMsg::CancelStream { stream_id } => {
             killswitch::execute_cancel_stream(deps, env, info, stream_id)
         }
+        ExecuteMsg::ConfirmCancelStream { stream_id } => {
+            killswitch::execute_confirm_cancel_stream(deps, env, info, stream_id)
+        }
+        ExecuteMsg::UpdateGuardians {
+            guardians,
+            cancel_threshold,
+        } => killswitch::execute_update_guardians(deps, env, info, guardians, cancel_threshold),
+        ExecuteMsg::UpdateRemoteController { remote_controller } => {
+            killswitch::execute_update_remote_controller(deps, env, info, remote_controller)
+        }
+        ExecuteMsg::CreatorPauseStream { stream_id } => {
+            killswitch::execute_creator_pause_stream(deps, env, info, stream_id)
+        }
+        ExecuteMsg::CreatorResumeStream { stream_id } => {
+            killswitch::execute_creator_resume_stream(deps, env, info, stream_id)
+        }
+        ExecuteMsg::ResumeExpiredPause { stream_id } => {
+            killswitch::execute_resume_expired_pause(deps, env, info, stream_id)
+        }
         ExecuteMsg::WithdrawPaused {
             stream_id,
             cap,
             operator_target,
         } => killswitch::execute_withdraw_paused(deps, env, info, stream_id, cap, operator_target),
+        ExecuteMsg::ExitPaused {
+            stream_id,
+            operator_target,
+        } => killswitch::execute_exit_paused(deps, env, info, stream_id, operator_target),
         ExecuteMsg::ExitCancelled {
             stream_id,
             operator_target,
         } => killswitch::execute_exit_cancelled(deps, env, info, stream_id, operator_target),
-        ExecuteMsg::UpdateProtocolAdmin {
-            new_protocol_admin: new_admin,
-        } => execute_update_protocol_admin(deps, env, info, new_admin),
+        ExecuteMsg::BatchExitCancelled { stream_id, limit } => {
+            killswitch::execute_batch_exit_cancelled(deps, env, info, stream_id, limit)
+        }
+        ExecuteMsg::ProposeProtocolAdmin {
+            new_protocol_admin,
+            expiry,
+        } => execute_propose_protocol_admin(deps, env, info, new_protocol_admin, expiry),
+        ExecuteMsg::AcceptProtocolAdmin {} => execute_accept_protocol_admin(deps, env, info),
+        ExecuteMsg::RenounceProtocolAdmin {} => execute_renounce_protocol_admin(deps, env, info),
         ExecuteMsg::UpdateConfig {
             min_stream_duration,
             min_duration_until_start_time,
@@ -197,18 +427,113 @@ pub fn execute(
             fee_collector,
             accepted_in_denom,
             exit_fee_percent,
+            max_pause_duration,
+            max_creator_pause_duration,
+            creator_cancel_penalty,
+            permissioned_creation,
+            config_timelock,
+            prune_delay_blocks,
+            escheat_delay_blocks,
         } => execute_update_config(
             deps,
             env,
             info,
-            min_stream_duration,
-            min_duration_until_start_time,
-            stream_creation_denom,
-            stream_creation_fee,
-            fee_collector,
-            accepted_in_denom,
-            exit_fee_percent,
+            ConfigUpdate {
+                min_stream_duration,
+                min_duration_until_start_time,
+                stream_creation_denom,
+                stream_creation_fee,
+                fee_collector,
+                accepted_in_denom,
+                exit_fee_percent,
+                max_pause_duration,
+                max_creator_pause_duration,
+                creator_cancel_penalty,
+                permissioned_creation,
+                config_timelock,
+                prune_delay_blocks,
+                escheat_delay_blocks,
+                ..Default::default()
+            },
         ),
+        ExecuteMsg::ApplyConfigUpdate {} => execute_apply_config_update(deps, env, info),
+        ExecuteMsg::CancelConfigUpdate {} => execute_cancel_config_update(deps, env, info),
+        ExecuteMsg::UpdateFeeWaiver { creator, discount } => {
+            execute_update_fee_waiver(deps, env, info, creator, discount)
+        }
+        ExecuteMsg::UpdateFeeSwap { fee_swap } => {
+            fee_swap::execute_update_fee_swap(deps, env, info, fee_swap)
+        }
+        ExecuteMsg::UpdateIbcTransfer { ibc_transfer } => {
+            ibc::execute_update_ibc_transfer(deps, env, info, ibc_transfer)
+        }
+        ExecuteMsg::UpdateStreamTags { stream_id, tags } => {
+            execute_update_stream_tags(deps, env, info, stream_id, tags)
+        }
+        ExecuteMsg::UpdateCreatorProfile { profile } => {
+            creator_profiles::execute_update_creator_profile(deps, env, info, profile)
+        }
+        ExecuteMsg::AttachCreatorProfile { stream_id, attach } => {
+            creator_profiles::execute_attach_creator_profile(deps, env, info, stream_id, attach)
+        }
+        ExecuteMsg::UpdateBlocklist {
+            stream_id,
+            add,
+            remove,
+        } => blocklist::execute_update_blocklist(deps, env, info, stream_id, add, remove),
+        ExecuteMsg::RefundBlocked { stream_id, owner } => {
+            blocklist::execute_refund_blocked(deps, env, info, stream_id, owner)
+        }
+        ExecuteMsg::SweepDust { denom, limit } => {
+            audit::execute_sweep_dust(deps, env, info, denom, limit)
+        }
+        ExecuteMsg::RecoverFunds {
+            denom,
+            amount,
+            recipient,
+            limit,
+        } => audit::execute_recover_funds(deps, env, info, denom, amount, recipient, limit),
+        ExecuteMsg::UpdateLiquidStaking { liquid_staking } => {
+            liquid_staking::execute_update_liquid_staking(deps, env, info, liquid_staking)
+        }
+        ExecuteMsg::UpdateNameRegistry { name_registry } => {
+            names::execute_update_name_registry(deps, env, info, name_registry)
+        }
+        ExecuteMsg::UpdateComplianceContract {
+            compliance_contract,
+        } => compliance::execute_update_compliance_contract(deps, env, info, compliance_contract),
+        ExecuteMsg::UpdateExitCrank { exit_crank } => {
+            crank::execute_update_exit_crank(deps, env, info, exit_crank)
+        }
+        ExecuteMsg::UpdateVestingCodeId { vesting_code_id } => {
+            vesting::execute_update_vesting_code_id(deps, env, info, vesting_code_id)
+        }
+        ExecuteMsg::UpdateFinalizeHookLimits { limits } => {
+            execute_update_finalize_hook_limits(deps, env, info, limits)
+        }
+        ExecuteMsg::UpdateStreamVesting { stream_id, vesting } => {
+            vesting::execute_update_stream_vesting(deps, env, info, stream_id, vesting)
+        }
+        ExecuteMsg::RetryVesting { stream_id } => {
+            vesting::execute_retry_vesting(deps, env, info, stream_id)
+        }
+        ExecuteMsg::MigrateStep { limit } => {
+            migrate_v0_2_1::execute_migrate_step(deps, env, info, limit)
+        }
+        ExecuteMsg::UpdateCreatePool {
+            stream_id,
+            create_pool,
+        } => pool::execute_update_create_pool(deps, env, info, stream_id, create_pool),
+        ExecuteMsg::UpdateKeeperBounty { keeper_bounty } => {
+            keeper::execute_update_keeper_bounty(deps, env, info, keeper_bounty)
+        }
+        ExecuteMsg::FreezeDenom { denom, expiry } => {
+            execute_freeze_denom(deps, env, info, denom, expiry)
+        }
+        ExecuteMsg::UnfreezeDenom { denom } => execute_unfreeze_denom(deps, env, info, denom),
+        ExecuteMsg::UpdateCreatorAllowlist { add, remove } => {
+            execute_update_creator_allowlist(deps, env, info, add, remove)
+        }
     }
 }
 #[allow(clippy::too_many_arguments)]
@@ -225,8 +550,16 @@ pub fn execute_create_stream(
     start_time: Timestamp,
     end_time: Timestamp,
     threshold: Option<Uint256>,
+    revenue_vesting_duration: Option<Uint64>,
+    donation_percent: Option<Decimal256>,
+    refund_excess: Option<bool>,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
+    if config.permissioned_creation == Some(true)
+        && !CREATOR_ALLOWLIST.has(deps.storage, &info.sender)
+    {
+        return Err(ContractError::CreatorNotAllowed {});
+    }
     if end_time < start_time {
         return Err(ContractError::StreamInvalidEndTime {});
     }
@@ -249,54 +582,44 @@ pub fn execute_create_stream(
         return Err(ContractError::SameDenomOnEachSide {});
     }
 
+    for denom in [&in_denom, &out_denom] {
+        let frozen = FROZEN_DENOMS.may_load(deps.storage, denom)?;
+        if frozen.is_some_and(|f| f.is_frozen(&env.block)) {
+            return Err(ContractError::DenomFrozen {
+                denom: denom.clone(),
+            });
+        }
+    }
+    validate_ibc_denom(deps.as_ref(), &out_denom)?;
+
     if out_supply < Uint256::from(1u128) {
         return Err(ContractError::ZeroOutSupply {});
     }
 
-    if out_denom == config.stream_creation_denom {
-        let total_funds = info
-            .funds
-            .iter()
-            .find(|p| p.denom == config.stream_creation_denom)
-            .ok_or(ContractError::NoFundsSent {})?;
-
-        if to_uint256(total_funds.amount) != to_uint256(config.stream_creation_fee) + out_supply {
-            return Err(ContractError::StreamOutSupplyFundsRequired {});
-        }
-        // check for extra funds sent in msg
-        if info.funds.iter().any(|p| p.denom != out_denom) {
-            return Err(ContractError::InvalidFunds {});
-        }
-    } else {
-        let funds = info
-            .funds
-            .iter()
-            .find(|p| p.denom == out_denom)
-            .ok_or(ContractError::NoFundsSent {})?;
-
-        if to_uint256(funds.amount) != out_supply {
-            return Err(ContractError::StreamOutSupplyFundsRequired {});
-        }
-
-        let creation_fee = info
-            .funds
-            .iter()
-            .find(|p| p.denom == config.stream_creation_denom)
-            .ok_or(ContractError::NoFundsSent {})?;
-        if creation_fee.amount != config.stream_creation_fee {
-            return Err(ContractError::StreamCreationFeeRequired {});
-        }
+    // Partner creators pay a discounted creation fee, or none at all
+    let stream_creation_fee = match FEE_WAIVERS.may_load(deps.storage, &info.sender)? {
+        Some(discount) => Uint128::try_from(
+            (Decimal256::one() - discount) * to_uint256(config.stream_creation_fee),
+        )?,
+        None => config.stream_creation_fee,
+    };
 
-        if info
-            .funds
-            .iter()
-            .any(|p| p.denom != out_denom && p.denom != config.stream_creation_denom)
-        {
-            return Err(ContractError::InvalidFunds {});
-        }
-    }
+    let refunds = check_creation_funds(
+        &info.funds,
+        &out_denom,
+        out_supply,
+        &config.stream_creation_denom,
+        stream_creation_fee,
+        refund_excess.unwrap_or(false),
+    )?;
 
     check_name_and_url(&name, &url)?;
+    check_stream_name(
+        deps.storage,
+        env.block.time,
+        config.name_registry.as_ref(),
+        &name,
+    )?;
 
     let stream = Stream::new(
         name.clone(),
@@ -309,14 +632,22 @@ pub fn execute_create_stream(
         end_time,
         start_time,
         config.stream_creation_denom,
-        config.stream_creation_fee,
+        stream_creation_fee,
         config.exit_fee_percent,
     );
     let id = next_stream_id(deps.storage)?;
-    STREAMS.save(deps.storage, id, &stream)?;
+    claim_stream_name(deps.storage, config.name_registry.as_ref(), &name, id)?;
+    save_stream(deps.storage, id, &stream, env.block.height)?;
 
     let threshold_state = ThresholdState::new();
     threshold_state.set_threshold_if_any(threshold, id, deps.storage)?;
+    set_revenue_vesting_if_any(deps.storage, id, revenue_vesting_duration)?;
+    if let Some(donation_percent) = donation_percent {
+        if donation_percent.is_zero() || donation_percent >= Decimal256::one() {
+            return Err(ContractError::InvalidDonationPercent {});
+        }
+        STREAM_DONATIONS.save(deps.storage, id, &donation_percent)?;
+    }
 
     let attr = vec![
         attr("action", "create_stream"),
@@ -330,48 +661,387 @@ pub fn execute_create_stream(
         attr("start_time", start_time.to_string()),
         attr("end_time", end_time.to_string()),
     ];
-    Ok(Response::default().add_attributes(attr))
+    let hook_msg = to_json_binary(&StreamHookMsg::StreamCreated {
+        stream_id: id,
+        treasury: stream.treasury.to_string(),
+        in_denom: stream.in_denom.clone(),
+        out_denom: stream.out_denom.clone(),
+        out_supply,
+        start_time,
+        end_time,
+    })?;
+    let hook_msgs = CREATION_HOOKS.prepare_hooks(deps.storage, |hook| {
+        Ok(SubMsg::reply_on_error(
+            WasmMsg::Execute {
+                contract_addr: hook.to_string(),
+                msg: hook_msg.clone(),
+                funds: vec![],
+            },
+            CREATION_HOOK_REPLY_ID,
+        ))
+    })?;
+    let event = StreamCreated {
+        stream_id: id,
+        treasury: stream.treasury,
+        name: stream.name,
+        in_denom: stream.in_denom,
+        out_denom: stream.out_denom,
+        out_supply,
+        start_time,
+        end_time,
+    };
+    let mut res = Response::default()
+        .add_submessages(hook_msgs)
+        .add_attributes(attr)
+        .add_event(event.into());
+    if !refunds.is_empty() {
+        res = res.add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: refunds,
+        });
+    }
+    Ok(res)
+}
+
+/// Checks the funds sent to create a stream cover the out supply and the creation fee, coins
+/// of the same denom are summed as wallets may split them. Returns the excess to refund if
+/// `refund_excess` is set, otherwise only exact amounts are accepted.
+fn check_creation_funds(
+    funds: &[Coin],
+    out_denom: &str,
+    out_supply: Uint256,
+    creation_denom: &str,
+    creation_fee: Uint128,
+    refund_excess: bool,
+) -> Result<Vec<Coin>, ContractError> {
+    let mut paid: BTreeMap<&str, Uint256> = BTreeMap::new();
+    for coin in funds {
+        let amount = paid.entry(coin.denom.as_str()).or_default();
+        *amount = amount.checked_add(to_uint256(coin.amount))?;
+    }
+    // out supply is checked first
+    let mut required = vec![(out_denom, out_supply)];
+    if creation_denom == out_denom {
+        required[0].1 = out_supply.checked_add(to_uint256(creation_fee))?;
+    } else if !creation_fee.is_zero() {
+        required.push((creation_denom, to_uint256(creation_fee)));
+    }
+
+    for (denom, required) in &required {
+        let paid = paid.get(denom).ok_or(ContractError::NoFundsSent {})?;
+        if paid < required || (paid > required && !refund_excess) {
+            return Err(if *denom == out_denom {
+                ContractError::StreamOutSupplyFundsRequired {}
+            } else {
+                ContractError::StreamCreationFeeRequired {}
+            });
+        }
+    }
+    if paid
+        .keys()
+        .any(|denom| *denom != out_denom && *denom != creation_denom)
+    {
+        return Err(ContractError::InvalidFunds {});
+    }
+    if !refund_excess {
+        return Ok(vec![]);
+    }
+    paid.into_iter()
+        .filter_map(|(denom, paid)| {
+            let required = required
+                .iter()
+                .find(|(required, _)| *required == denom)
+                .map(|(_, amount)| *amount)
+                .unwrap_or_default();
+            let excess = paid - required;
+            (!excess.is_zero()).then(|| {
+                Ok(Coin {
+                    denom: denom.to_string(),
+                    amount: Uint128::try_from(excess)?,
+                })
+            })
+        })
+        .collect()
+}
+
+pub fn execute_freeze_denom(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    expiry: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if expiry.is_some_and(|e| e.is_expired(&env.block)) {
+        return Err(ContractError::InvalidFreezeExpiry {});
+    }
+    FROZEN_DENOMS.save(deps.storage, &denom, &FrozenDenom { expiry })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "freeze_denom")
+        .add_attribute("denom", denom)
+        .add_attribute("expiry", expiry.map(|e| e.to_string()).unwrap_or_default()))
+}
+
+pub fn execute_unfreeze_denom(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    FROZEN_DENOMS.remove(deps.storage, &denom);
+
+    Ok(Response::new()
+        .add_attribute("action", "unfreeze_denom")
+        .add_attribute("denom", denom))
 }
 
-pub fn execute_update_protocol_admin(
+pub fn execute_update_creator_allowlist(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    for creator in &add {
+        let creator = deps.api.addr_validate(creator)?;
+        CREATOR_ALLOWLIST.save(deps.storage, &creator, &Empty {})?;
+    }
+    for creator in &remove {
+        let creator = deps.api.addr_validate(creator)?;
+        CREATOR_ALLOWLIST.remove(deps.storage, &creator);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_creator_allowlist")
+        .add_attribute("added", add.join(","))
+        .add_attribute("removed", remove.join(",")))
+}
+
+pub fn query_creator_allowlist(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Addr>> {
+    let start_after = maybe_addr(deps.api, start_after)?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    CREATOR_ALLOWLIST
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn query_validate_denom(deps: Deps, denom: String) -> StdResult<ValidateDenomResponse> {
+    let trace = validate_ibc_denom(deps, &denom).map_err(|err| match err {
+        ContractError::Std(err) => err,
+        err => StdError::generic_err(err.to_string()),
+    })?;
+    Ok(ValidateDenomResponse {
+        denom,
+        path: trace.as_ref().map(|t| t.path.clone()),
+        base_denom: trace.map(|t| t.base_denom),
+    })
+}
+
+pub fn query_frozen_denoms(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<FrozenDenomResponse>> {
+    let start = start_after.as_deref().map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    FROZEN_DENOMS
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref()
+                .map_or(true, |(_, frozen)| frozen.is_frozen(&env.block))
+        })
+        .take(limit)
+        .map(|item| {
+            let (denom, frozen) = item?;
+            Ok(FrozenDenomResponse {
+                denom,
+                expiry: frozen.expiry,
+            })
+        })
+        .collect()
+}
+
+pub fn execute_update_fee_waiver(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    creator: String,
+    discount: Option<Decimal256>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let creator = deps.api.addr_validate(&creator)?;
+    match discount {
+        Some(discount) => {
+            if discount.is_zero() || discount > Decimal256::one() {
+                return Err(ContractError::InvalidFeeWaiver {});
+            }
+            FEE_WAIVERS.save(deps.storage, &creator, &discount)?;
+        }
+        None => FEE_WAIVERS.remove(deps.storage, &creator),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_fee_waiver")
+        .add_attribute("creator", creator)
+        .add_attribute(
+            "discount",
+            discount.map(|d| d.to_string()).unwrap_or_default(),
+        ))
+}
+
+pub fn execute_propose_protocol_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
     new_admin: String,
+    expiry: Option<Expiration>,
 ) -> Result<Response, ContractError> {
-    let mut config = CONFIG.load(deps.storage)?;
-    if info.sender != config.protocol_admin {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
-    config.protocol_admin = deps.api.addr_validate(&new_admin)?;
-    CONFIG.save(deps.storage, &config)?;
+    if expiry.is_some_and(|e| e.is_expired(&env.block)) {
+        return Err(ContractError::ProtocolAdminProposalExpired {});
+    }
+    let new_protocol_admin = deps.api.addr_validate(&new_admin)?;
+    PENDING_PROTOCOL_ADMIN.save(
+        deps.storage,
+        &PendingProtocolAdmin {
+            new_protocol_admin,
+            expiry,
+        },
+    )?;
 
     let attrs = vec![
-        attr("action", "update_protocol_admin"),
+        attr("action", "propose_protocol_admin"),
         attr("new_admin", new_admin),
+        attr("expiry", expiry.map(|e| e.to_string()).unwrap_or_default()),
+    ];
+
+    Ok(Response::default().add_attributes(attrs))
+}
+
+pub fn execute_accept_protocol_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let pending = PENDING_PROTOCOL_ADMIN
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingProtocolAdmin {})?;
+    if info.sender != pending.new_protocol_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if pending.expiry.is_some_and(|e| e.is_expired(&env.block)) {
+        return Err(ContractError::ProtocolAdminProposalExpired {});
+    }
+    let mut config = CONFIG.load(deps.storage)?;
+    config.protocol_admin = Some(pending.new_protocol_admin);
+    CONFIG.save(deps.storage, &config)?;
+    PENDING_PROTOCOL_ADMIN.remove(deps.storage);
+
+    let attrs = vec![
+        attr("action", "accept_protocol_admin"),
+        attr("new_admin", info.sender),
     ];
 
     Ok(Response::default().add_attributes(attrs))
 }
 
+pub fn execute_renounce_protocol_admin(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    config.protocol_admin = None;
+    CONFIG.save(deps.storage, &config)?;
+    PENDING_PROTOCOL_ADMIN.remove(deps.storage);
+
+    Ok(Response::default().add_attribute("action", "renounce_protocol_admin"))
+}
+
+pub fn query_pending_config_update(deps: Deps) -> StdResult<Option<PendingConfigUpdate>> {
+    PENDING_CONFIG_UPDATE.may_load(deps.storage)
+}
+
+pub fn query_pending_protocol_admin(deps: Deps) -> StdResult<Option<PendingProtocolAdmin>> {
+    PENDING_PROTOCOL_ADMIN.may_load(deps.storage)
+}
+
 /// Updates stream to calculate released distribution and spent amount
 pub fn execute_update_stream(
     deps: DepsMut,
     env: Env,
+    info: MessageInfo,
     stream_id: u64,
 ) -> Result<Response, ContractError> {
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
     if stream.is_paused() {
         return Err(ContractError::StreamPaused {});
     }
+    let last_saved_height = STREAM_SAVED_AT.may_load(deps.storage, stream_id)?;
+    let spent_in = stream.spent_in;
     let (_, dist_amount) = update_stream(env.block.time, &mut stream)?;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    save_price_snapshot(deps.storage, stream_id, &stream)?;
+
+    // Swap fee accrued by this update, keepers of stale streams earn a share of it. Swap fee of
+    // hard capped and partially refunding streams is only known once they end, so they pay no
+    // bounties.
+    let keeper_bounty = match CONFIG.load(deps.storage)?.keeper_bounty {
+        Some(keeper_bounty)
+            if !HARD_CAPS.has(deps.storage, stream_id)
+                && !PARTIAL_REFUNDS.has(deps.storage, stream_id) =>
+        {
+            let accrued_fee =
+                Decimal256::from_ratio(stream.spent_in.checked_sub(spent_in)?, Uint256::one())
+                    .checked_mul(stream.stream_exit_fee_percent)?
+                    * Uint256::one();
+            keeper::accrue_keeper_bounty(
+                deps.storage,
+                &keeper_bounty,
+                stream_id,
+                &info.sender,
+                last_saved_height,
+                env.block.height,
+                accrued_fee,
+            )?
+        }
+        _ => Uint256::zero(),
+    };
 
     let attrs = vec![
         attr("action", "update_stream"),
         attr("stream_id", stream_id.to_string()),
         attr("new_distribution_amount", dist_amount),
         attr("dist_index", stream.dist_index.to_string()),
+        attr("keeper_bounty", keeper_bounty),
     ];
     let res = Response::new().add_attributes(attrs);
     Ok(res)
@@ -381,64 +1051,10 @@ pub fn update_stream(
     now: Timestamp,
     stream: &mut Stream,
 ) -> Result<(Decimal, Uint256), ContractError> {
-    let diff = calculate_diff(stream.end_time, stream.last_updated, now);
-
-    let mut new_distribution_balance = Uint256::zero();
-
-    // if no in balance in the contract, no need to update
-    // if diff not changed this means either stream not started or no in balance so far
-    if !stream.shares.is_zero() && !diff.is_zero() {
-        // new distribution balance is the amount of in tokens that has been distributed since last update
-        // distribution is linear for now.
-        new_distribution_balance = stream
-            .out_remaining
-            .multiply_ratio(diff.numerator(), diff.denominator());
-        // spent in tokens is the amount of in tokens that has been spent since last update
-        // spending is linear and goes to zero at the end of the stream
-        let spent_in = stream
-            .in_supply
-            .multiply_ratio(diff.numerator(), diff.denominator());
-
-        // increase total spent_in of the stream
-        stream.spent_in = stream.spent_in.checked_add(spent_in)?;
-        // decrease in_supply of the steam
-        stream.in_supply = stream.in_supply.checked_sub(spent_in)?;
-
-        // if no new distribution balance, no need to update the price, out_remaining and dist_index
-        if !new_distribution_balance.is_zero() {
-            // decrease amount to be distributed of the stream
-            stream.out_remaining = stream.out_remaining.checked_sub(new_distribution_balance)?;
-            // update distribution index. A positions share of the distribution is calculated by
-            // multiplying the share by the distribution index
-            stream.dist_index = stream.dist_index.checked_add(Decimal256::from_ratio(
-                new_distribution_balance,
-                stream.shares,
-            ))?;
-            stream.current_streamed_price =
-                Decimal256::from_ratio(spent_in, new_distribution_balance)
-        }
-    }
-
-    stream.last_updated = if now < stream.start_time {
-        stream.start_time
-    } else {
-        now
-    };
-
-    Ok((diff, new_distribution_balance))
-}
-
-fn calculate_diff(end_time: Timestamp, last_updated: Timestamp, now: Timestamp) -> Decimal {
-    // diff = (now - last_updated) / (end_time - last_updated)
-    let now = if now > end_time { end_time } else { now };
-    let numerator = now.nanos().saturating_sub(last_updated.nanos());
-    let denominator = end_time.nanos().saturating_sub(last_updated.nanos());
-
-    if denominator == 0 || numerator == 0 {
-        Decimal::zero()
-    } else {
-        Decimal::from_ratio(numerator, denominator)
-    }
+    let mut state = stream.math_state();
+    let res = streamswap_math::update_stream(now, &mut state)?;
+    stream.apply_math_state(state);
+    Ok(res)
 }
 
 pub fn execute_update_position(
@@ -461,7 +1077,8 @@ pub fn execute_update_position(
 
     // sync stream
     update_stream(env.block.time, &mut stream)?;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    save_price_snapshot(deps.storage, stream_id, &stream)?;
 
     // updates position to latest distribution. Returns the amount of out tokens that has been purchased
     // and in tokens that has been spent.
@@ -491,44 +1108,19 @@ pub fn update_position(
     stream_in_supply: Uint256,
     position: &mut Position,
 ) -> Result<(Uint256, Uint256), ContractError> {
-    // index difference represents the amount of distribution that has been received since last update
-    let index_diff = stream_dist_index.checked_sub(position.index)?;
-
-    let mut spent = Uint256::zero();
-    let mut purchased_uint128 = Uint256::zero();
-
-    // if no shares available, means no distribution and no spent
-    if !stream_shares.is_zero() {
-        // purchased is index_diff * position.shares
-        let purchased = Decimal256::from_ratio(position.shares, Uint256::one())
-            .checked_mul(index_diff)?
-            .checked_add(position.pending_purchase)?;
-        // decimals is the amount of decimals that the out token has to be added to next distribution so that
-        // the data do not get lost due to rounding
-        let decimals = get_decimals(purchased)?;
-
-        // calculates the remaining user balance using position.shares
-        let in_remaining = stream_in_supply
-            .checked_mul(position.shares)?
-            .checked_div(stream_shares)?;
-
-        // calculates the amount of spent tokens
-        spent = position.in_balance.checked_sub(in_remaining)?;
-        position.spent = position.spent.checked_add(spent)?;
-        position.in_balance = in_remaining;
-        position.pending_purchase = decimals;
-
-        // floors the decimal points
-        purchased_uint128 = (purchased * Uint256::one()).try_into()?;
-        position.purchased = position.purchased.checked_add(purchased_uint128)?;
-    }
-
-    position.index = stream_dist_index;
-    position.last_updated = stream_last_updated;
-
-    Ok((purchased_uint128, spent))
+    let mut state = position.math_state();
+    let res = streamswap_math::update_position(
+        stream_dist_index,
+        stream_shares,
+        stream_last_updated,
+        stream_in_supply,
+        &mut state,
+    )?;
+    position.apply_math_state(state);
+    Ok(res)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_subscribe(
     deps: DepsMut,
     env: Env,
@@ -536,6 +1128,7 @@ pub fn execute_subscribe(
     stream_id: u64,
     operator: Option<String>,
     operator_target: Option<String>,
+    min_shares_out: Option<Uint256>,
     mut stream: Stream,
 ) -> Result<Response, ContractError> {
     // check if stream is paused
@@ -553,11 +1146,14 @@ pub fn execute_subscribe(
 
     let in_amount = must_pay(&info, &stream.in_denom)?;
     let in_amount_uint256 = to_uint256(in_amount);
+    record_limited_subscription(deps.storage, stream_id, env.block.height, in_amount_uint256)?;
     let new_shares;
 
     let operator = maybe_addr(deps.api, operator)?;
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
+    check_not_blocked(deps.storage, stream_id, &[&info.sender, &operator_target])?;
+    check_compliance(deps.as_ref(), stream_id, &[&info.sender, &operator_target])?;
     let position = POSITIONS.may_load(deps.storage, (stream_id, &operator_target))?;
     match position {
         None => {
@@ -566,7 +1162,8 @@ pub fn execute_subscribe(
                 return Err(ContractError::Unauthorized {});
             }
             update_stream(env.block.time, &mut stream)?;
-            new_shares = stream.compute_shares_amount(in_amount_uint256, false);
+            new_shares = stream.compute_shares_amount(in_amount_uint256, false)?;
+            check_min_shares(new_shares, min_shares_out)?;
             // new positions do not update purchase as it has no effect on distribution
             let new_position = Position::new(
                 info.sender,
@@ -577,13 +1174,15 @@ pub fn execute_subscribe(
                 operator,
             );
             POSITIONS.save(deps.storage, (stream_id, &operator_target), &new_position)?;
+            record_subscription(deps.storage, stream_id, in_amount_uint256, true)?;
         }
         Some(mut position) => {
             check_access(&info, &position.owner, &position.operator)?;
 
             // incoming tokens should not participate in prev distribution
             update_stream(env.block.time, &mut stream)?;
-            new_shares = stream.compute_shares_amount(in_amount_uint256, false);
+            new_shares = stream.compute_shares_amount(in_amount_uint256, false)?;
+            check_min_shares(new_shares, min_shares_out)?;
             update_position(
                 stream.dist_index,
                 stream.shares,
@@ -595,24 +1194,43 @@ pub fn execute_subscribe(
             position.in_balance = position.in_balance.checked_add(in_amount_uint256)?;
             position.shares = position.shares.checked_add(new_shares)?;
             POSITIONS.save(deps.storage, (stream_id, &operator_target), &position)?;
+            record_subscription(deps.storage, stream_id, in_amount_uint256, false)?;
         }
     }
 
     // increase in supply and shares
     stream.in_supply = stream.in_supply.checked_add(in_amount_uint256)?;
     stream.shares = stream.shares.checked_add(new_shares)?;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    save_price_snapshot(deps.storage, stream_id, &stream)?;
 
+    let event = Subscribed {
+        stream_id,
+        owner: operator_target.clone(),
+        amount: in_amount_uint256,
+        in_supply: stream.in_supply,
+    };
     let res = Response::new()
         .add_attribute("action", "subscribe")
         .add_attribute("stream_id", stream_id.to_string())
         .add_attribute("owner", operator_target)
         .add_attribute("in_supply", stream.in_supply)
-        .add_attribute("in_amount", in_amount);
+        .add_attribute("in_amount", in_amount)
+        .add_event(event.into());
 
     Ok(res)
 }
 
+fn check_min_shares(shares: Uint256, min_shares_out: Option<Uint256>) -> Result<(), ContractError> {
+    match min_shares_out {
+        Some(min_shares_out) if shares < min_shares_out => {
+            Err(ContractError::SharesBelowMinimum { shares })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn execute_subscribe_pending(
     deps: DepsMut,
     env: Env,
@@ -620,6 +1238,7 @@ pub fn execute_subscribe_pending(
     stream_id: u64,
     operator: Option<String>,
     operator_target: Option<String>,
+    min_shares_out: Option<Uint256>,
     mut stream: Stream,
 ) -> Result<Response, ContractError> {
     // check if stream is paused
@@ -628,11 +1247,15 @@ pub fn execute_subscribe_pending(
     }
     let in_amount = must_pay(&info, &stream.in_denom)?;
     let in_amount_uint256 = to_uint256(in_amount);
-    let new_shares = stream.compute_shares_amount(in_amount_uint256, false);
+    record_limited_subscription(deps.storage, stream_id, env.block.height, in_amount_uint256)?;
+    let new_shares = stream.compute_shares_amount(in_amount_uint256, false)?;
+    check_min_shares(new_shares, min_shares_out)?;
 
     let operator = maybe_addr(deps.api, operator)?;
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
+    check_not_blocked(deps.storage, stream_id, &[&info.sender, &operator_target])?;
+    check_compliance(deps.as_ref(), stream_id, &[&info.sender, &operator_target])?;
     let position = POSITIONS.may_load(deps.storage, (stream_id, &operator_target))?;
     match position {
         None => {
@@ -649,6 +1272,7 @@ pub fn execute_subscribe_pending(
                 operator,
             );
             POSITIONS.save(deps.storage, (stream_id, &operator_target), &new_position)?;
+            record_subscription(deps.storage, stream_id, in_amount_uint256, true)?;
         }
         Some(mut position) => {
             check_access(&info, &position.owner, &position.operator)?;
@@ -656,18 +1280,26 @@ pub fn execute_subscribe_pending(
             position.in_balance = position.in_balance.checked_add(in_amount_uint256)?;
             position.shares = position.shares.checked_add(new_shares)?;
             POSITIONS.save(deps.storage, (stream_id, &operator_target), &position)?;
+            record_subscription(deps.storage, stream_id, in_amount_uint256, false)?;
         }
     }
     stream.in_supply = stream.in_supply.checked_add(in_amount_uint256)?;
     stream.shares = stream.shares.checked_add(new_shares)?;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
 
+    let event = Subscribed {
+        stream_id,
+        owner: operator_target.clone(),
+        amount: in_amount_uint256,
+        in_supply: stream.in_supply,
+    };
     Ok(Response::new()
         .add_attribute("action", "subscribe_pending")
         .add_attribute("stream_id", stream_id.to_string())
         .add_attribute("owner", operator_target)
         .add_attribute("in_supply", stream.in_supply)
-        .add_attribute("in_amount", in_amount))
+        .add_attribute("in_amount", in_amount)
+        .add_event(event.into()))
 }
 
 pub fn execute_update_operator(
@@ -737,15 +1369,17 @@ pub fn execute_withdraw(
     let shares_amount = if withdraw_amount == position.in_balance {
         position.shares
     } else {
-        stream.compute_shares_amount(withdraw_amount, true)
+        stream.compute_shares_amount(withdraw_amount, true)?
     };
 
     stream.in_supply = stream.in_supply.checked_sub(withdraw_amount)?;
     stream.shares = stream.shares.checked_sub(shares_amount)?;
     position.in_balance = position.in_balance.checked_sub(withdraw_amount)?;
     position.shares = position.shares.checked_sub(shares_amount)?;
+    record_withdrawal(deps.storage, stream_id, withdraw_amount)?;
 
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    save_price_snapshot(deps.storage, stream_id, &stream)?;
     POSITIONS.save(deps.storage, (stream_id, &position.owner), &position)?;
 
     let attributes = vec![
@@ -754,6 +1388,11 @@ pub fn execute_withdraw(
         attr("operator_target", operator_target.clone()),
         attr("withdraw_amount", withdraw_amount),
     ];
+    let event = Withdrawn {
+        stream_id,
+        owner: position.owner.clone(),
+        amount: withdraw_amount,
+    };
     // TODO: This might be a problem if the withdraw amount is too large but unlikely
     let withdraw_amount: Uint128 = Uint128::try_from(withdraw_amount)?;
 
@@ -763,17 +1402,18 @@ pub fn execute_withdraw(
             to_address: operator_target.to_string(),
             amount: vec![Coin {
                 denom: stream.in_denom,
-                amount: Uint128::from(withdraw_amount),
+                amount: withdraw_amount,
             }],
         }))
-        .add_attributes(attributes);
+        .add_attributes(attributes)
+        .add_event(event.into());
 
     Ok(res)
 }
 
 pub fn execute_withdraw_pending(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     stream_id: u64,
     mut stream: Stream,
@@ -800,15 +1440,16 @@ pub fn execute_withdraw_pending(
     let shares_amount = if withdraw_amount == position.in_balance {
         position.shares
     } else {
-        stream.compute_shares_amount(withdraw_amount, true)
+        stream.compute_shares_amount(withdraw_amount, true)?
     };
 
     stream.in_supply = stream.in_supply.checked_sub(withdraw_amount)?;
     stream.shares = stream.shares.checked_sub(shares_amount)?;
     position.in_balance = position.in_balance.checked_sub(withdraw_amount)?;
     position.shares = position.shares.checked_sub(shares_amount)?;
+    record_withdrawal(deps.storage, stream_id, withdraw_amount)?;
 
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
     POSITIONS.save(deps.storage, (stream_id, &position.owner), &position)?;
 
     let attributes = vec![
@@ -817,6 +1458,11 @@ pub fn execute_withdraw_pending(
         attr("operator_target", operator_target.clone()),
         attr("withdraw_amount", withdraw_amount),
     ];
+    let event = Withdrawn {
+        stream_id,
+        owner: position.owner.clone(),
+        amount: withdraw_amount,
+    };
 
     let withdraw_amount: Uint128 = Uint128::try_from(withdraw_amount)?;
 
@@ -829,7 +1475,8 @@ pub fn execute_withdraw_pending(
                 amount: withdraw_amount,
             }],
         }))
-        .add_attributes(attributes);
+        .add_attributes(attributes)
+        .add_event(event.into());
 
     Ok(res)
 }
@@ -839,9 +1486,8 @@ pub fn execute_finalize_stream(
     env: Env,
     info: MessageInfo,
     stream_id: u64,
-    new_treasury: Option<String>,
 ) -> Result<Response, ContractError> {
-    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    let stream = STREAMS.load(deps.storage, stream_id)?;
     // check if the stream is already finalized
     if stream.status == Status::Finalized {
         return Err(ContractError::StreamAlreadyFinalized {});
@@ -856,39 +1502,157 @@ pub fn execute_finalize_stream(
     if env.block.time <= stream.end_time {
         return Err(ContractError::StreamNotEnded {});
     }
+    finalize_stream(deps, env, stream_id, stream)
+}
+
+/// Finalizes an ended stream, sending the revenue to the treasury and the fees to the fee
+/// collector.
+pub(crate) fn finalize_stream(
+    mut deps: DepsMut,
+    env: Env,
+    stream_id: u64,
+    mut stream: Stream,
+) -> Result<Response, ContractError> {
     if stream.last_updated < stream.end_time {
         update_stream(env.block.time, &mut stream)?;
     }
 
-    if stream.status == Status::Active {
+    // streams ending without any subscription are still waiting
+    if matches!(stream.status, Status::Active | Status::Waiting) {
         stream.status = Status::Finalized
     }
     // If threshold is set and not reached, finalize will fail
     // Creator should execute cancel_stream_with_threshold to cancel the stream
-    // Only returns error if threshold is set and not reached
-    let thresholds_state = ThresholdState::new();
-    thresholds_state.error_if_not_reached(stream_id, deps.storage, &stream)?;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    // Only returns error if threshold is set and not reached, streams refunding partially are
+    // settled with a partial fill instead
+    let fill = settle_threshold(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    save_price_snapshot(deps.storage, stream_id, &stream)?;
+    // Tokens spent by positions exited while paused are still in `spent_in` and paid out below
+    PAUSED_EXITS.remove(deps.storage, stream_id);
 
     let config = CONFIG.load(deps.storage)?;
-    let treasury = maybe_addr(deps.api, new_treasury)?.unwrap_or_else(|| stream.treasury.clone());
-
+    let treasury = stream.treasury.clone();
+
+    // Spent tokens over the hard cap are refunded to subscribers on exit. Tier deposits are paid
+    // alongside the streamed tokens, reserved out tokens of tiers without deposits are refunded.
+    let (tier_deposits, unallocated_out) = tier_settlement(deps.storage, stream_id)?;
+    // Partially filled streams keep the filled share of the spent tokens and purchases, the
+    // unfilled purchases are refunded to the treasury.
+    let mut spent_in = capped_spent_in(deps.storage, stream_id, stream.spent_in)?;
+    let mut unfilled_out = Uint256::zero();
+    if let Some(fill) = fill {
+        spent_in = filled(spent_in, fill)?;
+        let distributed = stream
+            .out_supply
+            .checked_sub(reserved_out(deps.storage, stream_id)?)?
+            .checked_sub(stream.out_remaining)?;
+        unfilled_out = distributed.checked_sub(filled(distributed, fill)?)?;
+    }
+    let spent_in = spent_in.checked_add(tier_deposits)?;
     //Stream's swap fee collected at fixed rate from accumulated spent_in of positions(ie stream.spent_in)
-    let swap_fee = Decimal256::from_ratio(stream.spent_in, Uint256::one())
+    let total_swap_fee = Decimal256::from_ratio(spent_in, Uint256::one())
         .checked_mul(stream.stream_exit_fee_percent)?
         * Uint256::one();
 
-    let creator_revenue = stream.spent_in.checked_sub(swap_fee)?;
+    let creator_revenue = spent_in.checked_sub(total_swap_fee)?;
+    // Keeper bounties are paid out of the swap fee, the rest goes to the fee collector
+    let keeper_bounties = STREAM_KEEPER_BOUNTIES
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default();
+    let swap_fee = total_swap_fee.checked_sub(keeper_bounties)?;
+    // Exit crank bounties are held back from the fee until the positions are exited
+    let crank_reserve = reserve_crank_bounties(deps.storage, &config, stream_id, swap_fee)?;
+    let swap_fee = swap_fee.checked_sub(crank_reserve)?;
+    // Donated share of creator revenue goes to the community pool
+    let donation = match STREAM_DONATIONS.may_load(deps.storage, stream_id)? {
+        Some(donation_percent) => {
+            Decimal256::from_ratio(creator_revenue, Uint256::one()).checked_mul(donation_percent)?
+                * Uint256::one()
+        }
+        None => Uint256::zero(),
+    };
+    let creator_revenue = creator_revenue.checked_sub(donation)?;
+    let refunded_out = stream
+        .out_remaining
+        .checked_add(unallocated_out)?
+        .checked_add(unfilled_out)?;
+    let total_sold = stream.out_supply.checked_sub(refunded_out)?;
+    // Liquidity of the post sale pool is paid out of creator revenue
+    let (pool_msgs, in_amount_clp) = pool::create_pool_msgs(
+        deps.branch(),
+        &env,
+        stream_id,
+        &stream,
+        spent_in,
+        total_sold,
+        creator_revenue,
+    )?;
+    let creator_revenue = creator_revenue.checked_sub(in_amount_clp)?;
     let creator_revenue_u128: Uint128 = Uint128::try_from(creator_revenue)?;
 
+    // Hooks are notified after funds are sent, a failing hook does not revert finalize
+    let hooks = FINALIZE_HOOKS
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default();
+    let hook_msg = to_json_binary(&StreamHookMsg::StreamFinalized {
+        stream_id,
+        treasury: treasury.to_string(),
+        in_denom: stream.in_denom.clone(),
+        out_denom: stream.out_denom.clone(),
+        spent_in,
+        total_sold,
+        creators_revenue: creator_revenue,
+        swap_fee,
+    })?;
+    let gas_limit = config.finalize_hook_limits().gas_limit.u64();
+    let hook_msgs: Vec<SubMsg> = hooks
+        .into_iter()
+        .map(|hook| {
+            SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: hook.to_string(),
+                    msg: hook_msg.clone(),
+                    funds: vec![],
+                },
+                FINALIZE_HOOK_REPLY_ID,
+            )
+            .with_gas_limit(gas_limit)
+        })
+        .collect();
+
+    // If the stream vests revenue, treasury claims it over time instead of at finalize
+    let revenue_vested =
+        start_revenue_vesting(deps.storage, stream_id, env.block.time, creator_revenue)?;
     //Creator's revenue claimed at finalize
-    let revenue_msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: treasury.to_string(),
-        amount: vec![Coin {
+    let revenue_msg = if spent_in != Uint256::zero() && !revenue_vested {
+        let revenue = Coin {
             denom: stream.in_denom.clone(),
             amount: creator_revenue_u128,
-        }],
-    });
+        };
+        Some(treasury_payout_msg(
+            deps.storage,
+            &env,
+            &config,
+            stream_id,
+            &treasury,
+            revenue,
+        )?)
+    } else {
+        None
+    };
+    record_finalize(
+        deps.storage,
+        stream_id,
+        &stream.in_denom,
+        creator_revenue,
+        swap_fee,
+    )?;
+    record_fee(
+        deps.storage,
+        &stream.stream_creation_denom,
+        to_uint256(stream.stream_creation_fee),
+    )?;
     //Exact fee for stream creation charged at creation but claimed at finalize
     let creation_fee_msg = CosmosMsg::Bank(BankMsg::Send {
         to_address: config.fee_collector.to_string(),
@@ -897,63 +1661,236 @@ pub fn execute_finalize_stream(
             amount: stream.stream_creation_fee,
         }],
     });
+    let creation_fee = stream.stream_creation_fee;
 
     let swap_fee_128: Uint128 = Uint128::try_from(swap_fee)?;
-    let swap_fee_msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: config.fee_collector.to_string(),
-        amount: vec![Coin {
-            denom: stream.in_denom,
+    let swap_fee_msg = fee_collection_msg(
+        deps,
+        &env,
+        &config,
+        Coin {
+            denom: stream.in_denom.clone(),
             amount: swap_fee_128,
-        }],
-    });
+        },
+    )?;
+
+    let mut messages = vec![];
+    if let Some(revenue_msg) = revenue_msg {
+        messages.push(revenue_msg);
+    }
+    messages.extend(pool_msgs);
+    if !donation.is_zero() {
+        let donation_msg = MsgFundCommunityPool {
+            amount: vec![Coin {
+                denom: stream.in_denom.clone(),
+                amount: Uint128::try_from(donation)?,
+            }
+            .into()],
+            depositor: env.contract.address.to_string(),
+        };
+        messages.push(SubMsg::new(donation_msg));
+    }
+    // Creation fee is waived for partner creators
+    if !creation_fee.is_zero() {
+        messages.push(SubMsg::new(creation_fee_msg));
+    }
+    if spent_in != Uint256::zero() && !swap_fee.is_zero() {
+        messages.push(swap_fee_msg);
+    }
+
+    // In case the stream is ended without any shares in it. We need to refund the remaining out tokens although that is unlikely to happen
+    if refunded_out > Uint256::zero() {
+        let remaining_out: Uint128 = Uint128::try_from(refunded_out)?;
+        let remaining_msg = CosmosMsg::Bank(BankMsg::Send {
+            to_address: treasury.to_string(),
+            amount: vec![Coin {
+                denom: stream.out_denom,
+                amount: remaining_out,
+            }],
+        });
+        messages.push(SubMsg::new(remaining_msg));
+    }
+    messages.extend(hook_msgs);
+
+    let event = Finalized {
+        stream_id,
+        treasury: treasury.clone(),
+        creators_revenue: creator_revenue,
+        swap_fee,
+        refunded_out_remaining: refunded_out,
+    };
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_event(event.into())
+        .add_attributes(vec![
+            attr("action", "finalize_stream"),
+            attr("stream_id", stream_id.to_string()),
+            attr("treasury", treasury.as_str()),
+            attr("fee_collector", config.fee_collector.to_string()),
+            attr("creators_revenue", creator_revenue),
+            attr("refunded_out_remaining", refunded_out.to_string()),
+            attr("total_sold", total_sold.to_string()),
+            attr("swap_fee", swap_fee),
+            attr("keeper_bounties", keeper_bounties),
+            attr("creation_fee", creation_fee.to_string()),
+            attr("revenue_vested", revenue_vested.to_string()),
+            attr("donated", donation),
+            attr("in_amount_clp", in_amount_clp),
+        ]))
+}
+
+/// Proposes a new treasury for the stream. The proposed address has to accept the
+/// role before it receives any funds.
+pub fn execute_propose_treasury(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+    new_treasury: String,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.treasury != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let new_treasury = deps.api.addr_validate(&new_treasury)?;
+    PENDING_TREASURIES.save(deps.storage, stream_id, &new_treasury)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_treasury")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("treasury", stream.treasury)
+        .add_attribute("pending_treasury", new_treasury))
+}
+
+pub fn execute_accept_treasury(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    let pending_treasury = PENDING_TREASURIES
+        .may_load(deps.storage, stream_id)?
+        .ok_or(ContractError::NoPendingTreasury {})?;
+    if pending_treasury != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let previous_treasury = stream.treasury;
+    stream.treasury = pending_treasury;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    PENDING_TREASURIES.remove(deps.storage, stream_id);
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_treasury")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("previous_treasury", previous_treasury)
+        .add_attribute("treasury", stream.treasury))
+}
+
+pub fn execute_update_finalize_hooks(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+    hooks: Vec<String>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if stream.status == Status::Finalized {
+        return Err(ContractError::StreamAlreadyFinalized {});
+    }
+    let max = CONFIG.load(deps.storage)?.finalize_hook_limits().max_hooks;
+    if hooks.len() > max as usize {
+        return Err(ContractError::TooManyFinalizeHooks { max });
+    }
+    let hooks = hooks
+        .iter()
+        .map(|hook| deps.api.addr_validate(hook))
+        .collect::<StdResult<Vec<Addr>>>()?;
+    FINALIZE_HOOKS.save(deps.storage, stream_id, &hooks)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_finalize_hooks")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("hooks_count", hooks.len().to_string()))
+}
+
+/// Updates the bounds of the finalize hooks. Streams already holding more hooks than the new
+/// maximum keep them. Only protocol admin can update.
+pub fn execute_update_finalize_hook_limits(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limits: Option<FinalizeHookLimits>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if limits.as_ref().is_some_and(|l| l.gas_limit.is_zero()) {
+        return Err(ContractError::InvalidFinalizeHookLimits {});
+    }
+    let effective = limits.clone().unwrap_or_default();
+    let apply_after = save_config_update(
+        deps,
+        &env,
+        config,
+        ConfigUpdate {
+            finalize_hook_limits: Some(limits),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_finalize_hook_limits")
+        .add_attribute("max_hooks", effective.max_hooks.to_string())
+        .add_attribute("gas_limit", effective.gas_limit.to_string())
+        .add_attributes(apply_after.map(|t| ("apply_after", t.to_string()))))
+}
 
-    let mut messages = if stream.spent_in != Uint256::zero() {
-        vec![revenue_msg, creation_fee_msg, swap_fee_msg]
+pub fn execute_update_creation_hook(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    addr: String,
+    add: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let hook = deps.api.addr_validate(&addr)?;
+    if add {
+        CREATION_HOOKS.add_hook(deps.storage, hook)?;
     } else {
-        vec![creation_fee_msg]
-    };
-
-    // In case the stream is ended without any shares in it. We need to refund the remaining out tokens although that is unlikely to happen
-    if stream.out_remaining > Uint256::zero() {
-        let remaining_out: Uint128 = Uint128::try_from(stream.out_remaining)?;
-        let remaining_msg = CosmosMsg::Bank(BankMsg::Send {
-            to_address: treasury.to_string(),
-            amount: vec![Coin {
-                denom: stream.out_denom,
-                amount: remaining_out,
-            }],
-        });
-        messages.push(remaining_msg);
+        CREATION_HOOKS.remove_hook(deps.storage, hook)?;
     }
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "finalize_stream"),
-        attr("stream_id", stream_id.to_string()),
-        attr("treasury", treasury.as_str()),
-        attr("fee_collector", config.fee_collector.to_string()),
-        attr("creators_revenue", creator_revenue),
-        attr("refunded_out_remaining", stream.out_remaining.to_string()),
-        attr(
-            "total_sold",
-            stream
-                .out_supply
-                .checked_sub(stream.out_remaining)?
-                .to_string(),
-        ),
-        attr("swap_fee", swap_fee),
-        attr("creation_fee", config.stream_creation_fee.to_string()),
-    ]))
+    Ok(Response::new()
+        .add_attribute(
+            "action",
+            if add {
+                "add_creation_hook"
+            } else {
+                "remove_creation_hook"
+            },
+        )
+        .add_attribute("hook", addr))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_exit_stream(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     stream_id: u64,
     operator_target: Option<String>,
+    remote_recipient: Option<String>,
+    liquid_stake: bool,
+    swap: Option<ExitSwap>,
 ) -> Result<Response, ContractError> {
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
-    let _config = CONFIG.load(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
     // check if stream is paused
     if stream.is_killswitch_active() {
         return Err(ContractError::StreamKillswitchActive {});
@@ -964,13 +1901,12 @@ pub fn execute_exit_stream(
     if stream.last_updated < stream.end_time {
         update_stream(env.block.time, &mut stream)?;
     }
-    let threshold_state = ThresholdState::new();
-
-    threshold_state.error_if_not_reached(stream_id, deps.storage, &stream)?;
+    error_if_not_settled(deps.storage, stream_id, &stream)?;
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
     let mut position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
     check_access(&info, &position.owner, &position.operator)?;
+    check_compliance(deps.as_ref(), stream_id, &[&info.sender, &position.owner])?;
 
     // update position before exit
     update_position(
@@ -980,25 +1916,67 @@ pub fn execute_exit_stream(
         stream.in_supply,
         &mut position,
     )?;
+    let excess_refund = hard_cap_refund(deps.storage, stream_id, stream.spent_in, position.spent)?;
+    position.spent = position.spent.checked_sub(excess_refund)?;
+    position.in_balance = position.in_balance.checked_add(excess_refund)?;
+    apply_partial_fill(deps.storage, stream_id, &mut position)?;
     // Swap fee = fixed_rate*position.spent_in this calculation is only for execution reply attributes
     let swap_fee = Decimal256::from_ratio(position.spent, Uint256::one())
         .checked_mul(stream.stream_exit_fee_percent)?
         * Uint256::one();
 
-    let purchased = Uint128::try_from(position.purchased)?;
-
-    let send_msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: operator_target.to_string(),
-        amount: vec![Coin {
-            denom: stream.out_denom.to_string(),
-            amount: purchased,
-        }],
+    let purchased = Coin {
+        denom: stream.out_denom.to_string(),
+        amount: Uint128::try_from(position.purchased)?,
+    };
+    let swap = swap.unwrap_or(ExitSwap {
+        purchased: None,
+        refunded: None,
     });
+    validate_exit_swap(&swap)?;
+    // swapped purchases are delivered to the position owner on this chain
+    if swap.purchased.is_some() && (liquid_stake || remote_recipient.is_some()) {
+        return Err(ContractError::ConflictingExitDelivery {});
+    }
+    // vested purchases are delivered to a vesting contract of the position owner
+    let vested = vesting::STREAM_VESTINGS.has(deps.storage, stream_id);
+    if vested && (liquid_stake || remote_recipient.is_some() || swap.purchased.is_some()) {
+        return Err(ContractError::ConflictingExitDelivery {});
+    }
+    let send_msg = match remote_recipient {
+        Some(_) if liquid_stake => return Err(ContractError::ConflictingExitDelivery {}),
+        None if vested && !purchased.amount.is_zero() => {
+            vesting::vesting_msg(deps.branch(), &env, stream_id, &operator_target, purchased)?
+        }
+        None if liquid_stake => liquid_stake_msg(&config, &operator_target, purchased)?,
+        None if swap.purchased.is_some() => exit_swap_msg(
+            deps.branch(),
+            &env,
+            swap.purchased.as_ref(),
+            EXIT_SWAP_PURCHASED_REPLY_ID,
+            &operator_target,
+            purchased,
+        )?,
+        Some(receiver) => ibc_transfer_msg(
+            deps.storage,
+            &env,
+            &config,
+            receiver,
+            purchased,
+            operator_target.clone(),
+        )?,
+        None => SubMsg::new(BankMsg::Send {
+            to_address: operator_target.to_string(),
+            amount: vec![purchased],
+        }),
+    };
 
     stream.shares = stream.shares.checked_sub(position.shares)?;
 
-    STREAMS.save(deps.storage, stream_id, &stream)?;
-    POSITIONS.remove(deps.storage, (stream_id, &position.owner));
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    save_price_snapshot(deps.storage, stream_id, &stream)?;
+    POSITIONS.remove(deps.storage, (stream_id, &position.owner))?;
+    record_exit(deps.storage, stream_id)?;
 
     let attributes = vec![
         attr("action", "exit_stream"),
@@ -1007,58 +1985,194 @@ pub fn execute_exit_stream(
         attr("purchased", position.purchased),
         attr("swap_fee_paid", swap_fee),
     ];
+    let event = Exited {
+        stream_id,
+        owner: position.owner.clone(),
+        purchased: position.purchased,
+        refunded: position.in_balance,
+        swap_fee,
+    };
     if !position.in_balance.is_zero() {
         let unspent: Uint128 = Uint128::try_from(position.in_balance)?;
-        let unspent_msg = CosmosMsg::Bank(BankMsg::Send {
-            to_address: operator_target.to_string(),
-            amount: vec![Coin {
+        let unspent_msg = exit_swap_msg(
+            deps,
+            &env,
+            swap.refunded.as_ref(),
+            EXIT_SWAP_REFUNDED_REPLY_ID,
+            &operator_target,
+            Coin {
                 denom: stream.in_denom,
                 amount: unspent,
-            }],
-        });
+            },
+        )?;
 
         Ok(Response::new()
-            .add_message(send_msg)
-            .add_message(unspent_msg)
-            .add_attributes(attributes))
+            .add_submessage(send_msg)
+            .add_submessage(unspent_msg)
+            .add_attributes(attributes)
+            .add_event(event.into()))
     } else {
         Ok(Response::new()
-            .add_message(send_msg)
-            .add_attributes(attributes))
+            .add_submessage(send_msg)
+            .add_attributes(attributes)
+            .add_event(event.into()))
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Updates the config, or proposes the update if a config timelock is set.
 pub fn execute_update_config(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    update: ConfigUpdate,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+
+    if !cfg.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    validate_config_update(deps.as_ref(), &update)?;
+
+    match save_config_update(deps.branch(), &env, cfg, update)? {
+        Some(apply_after) => Ok(Response::default()
+            .add_attribute("action", "propose_config_update")
+            .add_attribute("apply_after", apply_after.to_string())),
+        None => Ok(update_config_response(&CONFIG.load(deps.storage)?)),
+    }
+}
+
+/// Applies the config update, or queues it as the pending config update if a config timelock is
+/// set, replacing the update already pending. Returns the time a queued update can be applied
+/// from. Protocol admin config setters all go through here, so none of them skips the timelock.
+pub fn save_config_update(
+    deps: DepsMut,
+    env: &Env,
+    cfg: Config,
+    update: ConfigUpdate,
+) -> Result<Option<Timestamp>, ContractError> {
+    match cfg.config_timelock.filter(|t| !t.is_zero()) {
+        Some(timelock) => {
+            let apply_after = env.block.time.plus_seconds(timelock.u64());
+            PENDING_CONFIG_UPDATE.save(
+                deps.storage,
+                &PendingConfigUpdate {
+                    update,
+                    apply_after,
+                },
+            )?;
+            Ok(Some(apply_after))
+        }
+        None => {
+            apply_config_update(deps, cfg, update)?;
+            Ok(None)
+        }
+    }
+}
+
+pub fn execute_apply_config_update(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let pending = PENDING_CONFIG_UPDATE
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingConfigUpdate {})?;
+    if env.block.time < pending.apply_after {
+        return Err(ContractError::ConfigUpdateTimelocked {
+            apply_after: pending.apply_after,
+        });
+    }
+    PENDING_CONFIG_UPDATE.remove(deps.storage);
+    let cfg = CONFIG.load(deps.storage)?;
+    let cfg = apply_config_update(deps, cfg, pending.update)?;
+
+    Ok(update_config_response(&cfg))
+}
+
+pub fn execute_cancel_config_update(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    min_stream_duration: Option<Uint64>,
-    min_duration_until_start_time: Option<Uint64>,
-    stream_creation_denom: Option<String>,
-    stream_creation_fee: Option<Uint128>,
-    fee_collector: Option<String>,
-    accepted_in_denom: Option<String>,
-    exit_fee_percent: Option<Decimal256>,
 ) -> Result<Response, ContractError> {
-    let mut cfg = CONFIG.load(deps.storage)?;
-
-    if info.sender != cfg.protocol_admin {
+    let cfg = CONFIG.load(deps.storage)?;
+    if !cfg.is_protocol_admin(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
+    if !PENDING_CONFIG_UPDATE.exists(deps.storage) {
+        return Err(ContractError::NoPendingConfigUpdate {});
+    }
+    PENDING_CONFIG_UPDATE.remove(deps.storage);
 
-    if let Some(stream_creation_fee) = stream_creation_fee {
+    Ok(Response::default().add_attribute("action", "cancel_config_update"))
+}
+
+fn validate_config_update(deps: Deps, update: &ConfigUpdate) -> Result<(), ContractError> {
+    if let Some(stream_creation_fee) = update.stream_creation_fee {
         if stream_creation_fee.is_zero() {
             return Err(ContractError::InvalidStreamCreationFee {});
         }
     }
     // exit fee percent can not be equal to or greater than 1, or smaller than 0
-    if let Some(exit_fee_percent) = exit_fee_percent {
+    if let Some(exit_fee_percent) = update.exit_fee_percent {
         if exit_fee_percent >= Decimal256::one() || exit_fee_percent < Decimal256::zero() {
             return Err(ContractError::InvalidExitFeePercent {});
         }
     }
+    if update
+        .max_pause_duration
+        .flatten()
+        .is_some_and(|d| d.is_zero())
+        || update
+            .max_creator_pause_duration
+            .flatten()
+            .is_some_and(|d| d.is_zero())
+    {
+        return Err(ContractError::InvalidMaxPauseDuration {});
+    }
+    if update
+        .creator_cancel_penalty
+        .flatten()
+        .is_some_and(|p| p > Decimal256::one())
+    {
+        return Err(ContractError::InvalidCreatorCancelPenalty {});
+    }
+    if let Some(fee_collector) = &update.fee_collector {
+        deps.api.addr_validate(fee_collector)?;
+    }
+    Ok(())
+}
 
+fn apply_config_update(
+    deps: DepsMut,
+    mut cfg: Config,
+    update: ConfigUpdate,
+) -> Result<Config, ContractError> {
+    let ConfigUpdate {
+        min_stream_duration,
+        min_duration_until_start_time,
+        stream_creation_denom,
+        stream_creation_fee,
+        fee_collector,
+        accepted_in_denom,
+        exit_fee_percent,
+        max_pause_duration,
+        max_creator_pause_duration,
+        creator_cancel_penalty,
+        permissioned_creation,
+        config_timelock,
+        prune_delay_blocks,
+        escheat_delay_blocks,
+        fee_swap,
+        ibc_transfer,
+        remote_controller,
+        keeper_bounty,
+        liquid_staking,
+        name_registry,
+        compliance_contract,
+        exit_crank,
+        vesting_code_id,
+        finalize_hook_limits,
+    } = update;
     cfg.min_stream_seconds = min_stream_duration.unwrap_or(cfg.min_stream_seconds);
     cfg.min_seconds_until_start_time =
         min_duration_until_start_time.unwrap_or(cfg.min_seconds_until_start_time);
@@ -1068,9 +2182,36 @@ pub fn execute_update_config(
     let collector = maybe_addr(deps.api, fee_collector)?.unwrap_or(cfg.fee_collector);
     cfg.fee_collector = collector;
     cfg.exit_fee_percent = exit_fee_percent.unwrap_or(cfg.exit_fee_percent);
+    // optional fields are replaced, cleared ones included
+    cfg.max_pause_duration = max_pause_duration.unwrap_or(cfg.max_pause_duration);
+    cfg.max_creator_pause_duration =
+        max_creator_pause_duration.unwrap_or(cfg.max_creator_pause_duration);
+    cfg.creator_cancel_penalty = creator_cancel_penalty.unwrap_or(cfg.creator_cancel_penalty);
+    cfg.permissioned_creation = permissioned_creation.unwrap_or(cfg.permissioned_creation);
+    cfg.config_timelock = config_timelock.unwrap_or(cfg.config_timelock);
+    cfg.prune_delay_blocks = prune_delay_blocks.unwrap_or(cfg.prune_delay_blocks);
+    cfg.escheat_delay_blocks = escheat_delay_blocks.unwrap_or(cfg.escheat_delay_blocks);
+    cfg.fee_swap = fee_swap.unwrap_or(cfg.fee_swap);
+    cfg.ibc_transfer = ibc_transfer.unwrap_or(cfg.ibc_transfer);
+    if let Some(remote_controller) = remote_controller {
+        cfg.remote_controller = maybe_addr(deps.api, remote_controller)?;
+    }
+    cfg.keeper_bounty = keeper_bounty.unwrap_or(cfg.keeper_bounty);
+    cfg.liquid_staking = liquid_staking.unwrap_or(cfg.liquid_staking);
+    cfg.name_registry = name_registry.unwrap_or(cfg.name_registry);
+    if let Some(compliance_contract) = compliance_contract {
+        cfg.compliance_contract = maybe_addr(deps.api, compliance_contract)?;
+    }
+    cfg.exit_crank = exit_crank.unwrap_or(cfg.exit_crank);
+    cfg.vesting_code_id = vesting_code_id.unwrap_or(cfg.vesting_code_id);
+    cfg.finalize_hook_limits = finalize_hook_limits.unwrap_or(cfg.finalize_hook_limits);
 
     CONFIG.save(deps.storage, &cfg)?;
 
+    Ok(cfg)
+}
+
+fn update_config_response(cfg: &Config) -> Response {
     let attributes = vec![
         attr("action", "update_config"),
         attr("min_stream_duration", cfg.min_stream_seconds),
@@ -1078,12 +2219,12 @@ pub fn execute_update_config(
             "min_duration_until_start_time",
             cfg.min_seconds_until_start_time,
         ),
-        attr("stream_creation_denom", cfg.stream_creation_denom),
+        attr("stream_creation_denom", &cfg.stream_creation_denom),
         attr("stream_creation_fee", cfg.stream_creation_fee),
-        attr("fee_collector", cfg.fee_collector),
+        attr("fee_collector", &cfg.fee_collector),
     ];
 
-    Ok(Response::default().add_attributes(attributes))
+    Response::default().add_attributes(attributes)
 }
 
 fn check_access(
@@ -1092,26 +2233,71 @@ fn check_access(
     position_operator: &Option<Addr>,
 ) -> Result<(), ContractError> {
     if position_owner.as_ref() != info.sender
-        && position_operator
-            .as_ref()
-            .map_or(true, |o| o != &info.sender)
+        && position_operator.as_ref().is_none_or(|o| o != info.sender)
     {
         return Err(ContractError::Unauthorized {});
     }
     Ok(())
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
     match msg {
         SudoMsg::PauseStream { stream_id } => killswitch::sudo_pause_stream(deps, env, stream_id),
         SudoMsg::CancelStream { stream_id } => killswitch::sudo_cancel_stream(deps, env, stream_id),
         SudoMsg::ResumeStream { stream_id } => killswitch::sudo_resume_stream(deps, env, stream_id),
+        SudoMsg::PauseAllStreams { start_after, limit } => {
+            killswitch::sudo_pause_all_streams(deps, env, start_after, limit)
+        }
+        SudoMsg::ResumeAllStreams { start_after, limit } => {
+            killswitch::sudo_resume_all_streams(deps, env, start_after, limit)
+        }
+        SudoMsg::UpdateStreamParams {
+            stream_id,
+            end_time,
+            threshold,
+        } => killswitch::sudo_update_stream_params(deps, env, stream_id, end_time, threshold),
+        SudoMsg::IbcLifecycleComplete(msg) => ibc::sudo_ibc_lifecycle_complete(deps, env, msg),
+        SudoMsg::Clock {} => clock::sudo_clock(deps, env),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        FEE_SWAP_REPLY_ID => fee_swap::reply_fee_swap(deps, env, msg),
+        IBC_TRANSFER_REPLY_ID => ibc::reply_ibc_transfer(deps, env, msg),
+        SWAP_SUBSCRIBE_REPLY_ID => swap_subscribe::reply_swap_subscribe(deps, env, msg),
+        id if id & POOL_REPLY_ID_MASK == CREATE_POOL_REPLY_ID => {
+            pool::reply_create_pool(deps, env, msg)
+        }
+        id if id & POOL_REPLY_ID_MASK == CREATE_POSITION_REPLY_ID => {
+            pool::reply_create_position(deps, env, msg)
+        }
+        EXIT_SWAP_PURCHASED_REPLY_ID | EXIT_SWAP_REFUNDED_REPLY_ID => {
+            exit_swap::reply_exit_swap(deps, env, msg)
+        }
+        VESTING_REPLY_ID => vesting::reply_vesting(deps, env, msg),
+        FINALIZE_HOOK_REPLY_ID | CREATION_HOOK_REPLY_ID => {
+            let action = if msg.id == FINALIZE_HOOK_REPLY_ID {
+                "finalize_hook_failed"
+            } else {
+                "creation_hook_failed"
+            };
+            let error = match msg.result {
+                SubMsgResult::Err(err) => err,
+                SubMsgResult::Ok(_) => String::new(),
+            };
+            Ok(Response::new()
+                .add_attribute("action", action)
+                .add_attribute("error", error))
+        }
+        id => Err(ContractError::UnknownReplyId { id }),
     }
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
     let contract_info = get_contract_version(deps.storage)?;
     let storage_contract_name: String = contract_info.contract;
     let storage_version: Version = contract_info.version.parse().map_err(from_semver)?;
@@ -1124,29 +2310,91 @@ pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, C
     }
     if storage_version < version {
         set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-        // migrate v0.2.0 -> v0.2.1
-        migrate_v0_2_1(deps.storage)?;
+        // migrate v0.2.0 -> v0.2.1, large state is continued with `MigrateStep`
+        let batch = migrate_v0_2_1::migrate_v0_2_1_step(deps.storage, env.block.height, msg.limit)?;
+        return Ok(migrate_v0_2_1::migration_response("migrate", &batch));
     }
 
     Ok(Response::default())
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
         QueryMsg::Stream { stream_id } => to_json_binary(&query_stream(deps, env, stream_id)?),
+        QueryMsg::StreamAtHeight { stream_id, height } => {
+            to_json_binary(&query_stream_at_height(deps, env, stream_id, height)?)
+        }
         QueryMsg::Position { stream_id, owner } => {
             to_json_binary(&query_position(deps, env, stream_id, owner)?)
         }
-        QueryMsg::ListStreams { start_after, limit } => {
-            to_json_binary(&list_streams(deps, start_after, limit)?)
+        QueryMsg::ListStreams {
+            start_after,
+            limit,
+            status,
+            order,
+            tag,
+        } => to_json_binary(&list_streams(
+            deps,
+            env,
+            start_after,
+            limit,
+            status,
+            order,
+            tag,
+        )?),
+        QueryMsg::Blocklist {
+            stream_id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_blocklist(deps, stream_id, start_after, limit)?),
+        QueryMsg::Lifecycle { stream_id } => {
+            to_json_binary(&query_lifecycle(deps, env, stream_id)?)
+        }
+        QueryMsg::StreamTags { stream_id } => to_json_binary(&query_stream_tags(deps, stream_id)?),
+        QueryMsg::CreatorProfile { creator } => {
+            to_json_binary(&creator_profiles::query_creator_profile(deps, creator)?)
         }
+        QueryMsg::StreamCreator { stream_id } => {
+            to_json_binary(&creator_profiles::query_stream_creator(deps, stream_id)?)
+        }
+        QueryMsg::CreatorStreams {
+            creator,
+            start_after,
+            limit,
+        } => to_json_binary(&creator_profiles::query_creator_streams(
+            deps,
+            creator,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::ResolveName { name } => to_json_binary(&query_resolve_name(deps, env, name)?),
+        QueryMsg::CountStreams {} => to_json_binary(&query_count_streams(deps)?),
         QueryMsg::ListPositions {
             stream_id,
             start_after,
             limit,
-        } => to_json_binary(&list_positions(deps, stream_id, start_after, limit)?),
+            order,
+        } => to_json_binary(&list_positions(deps, stream_id, start_after, limit, order)?),
+        QueryMsg::CountPositions { stream_id } => {
+            to_json_binary(&query_count_positions(deps, stream_id)?)
+        }
+        QueryMsg::LargestPositions { stream_id, limit } => {
+            to_json_binary(&list_largest_positions(deps, stream_id, limit)?)
+        }
+        QueryMsg::PositionsByOwner {
+            owner,
+            start_after,
+            limit,
+            order,
+        } => to_json_binary(&list_positions_by_owner(
+            deps,
+            owner,
+            start_after,
+            limit,
+            order,
+        )?),
         QueryMsg::AveragePrice { stream_id } => {
             to_json_binary(&query_average_price(deps, env, stream_id)?)
         }
@@ -1156,8 +2404,113 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::Threshold { stream_id } => {
             to_json_binary(&query_threshold_state(deps, env, stream_id)?)
         }
+        QueryMsg::PendingTreasury { stream_id } => {
+            to_json_binary(&query_pending_treasury(deps, stream_id)?)
+        }
+        QueryMsg::StreamOperations { stream_id } => {
+            to_json_binary(&operations::query_stream_operations(deps, stream_id)?)
+        }
+        QueryMsg::ValidateDenom { denom } => to_json_binary(&query_validate_denom(deps, denom)?),
+        QueryMsg::AllocationTiers { stream_id } => {
+            to_json_binary(&query_allocation_tiers(deps, stream_id)?)
+        }
+        QueryMsg::TierDeposit { stream_id, owner } => {
+            to_json_binary(&query_tier_deposit(deps, stream_id, owner)?)
+        }
+        QueryMsg::HardCap { stream_id } => to_json_binary(&query_hard_cap(deps, stream_id)?),
+        QueryMsg::PartialRefund { stream_id } => {
+            to_json_binary(&query_partial_refund(deps, stream_id)?)
+        }
+        QueryMsg::SubscriptionLimit { stream_id } => {
+            to_json_binary(&query_subscription_limit(deps, env, stream_id)?)
+        }
+        QueryMsg::RemoteTreasury { stream_id } => {
+            to_json_binary(&REMOTE_TREASURIES.may_load(deps.storage, stream_id)?)
+        }
+        QueryMsg::PrunedStream { stream_id } => {
+            to_json_binary(&prune::query_pruned_stream(deps, stream_id)?)
+        }
+        QueryMsg::EscheatedPosition { stream_id, owner } => {
+            to_json_binary(&escheat::query_escheated_position(deps, stream_id, owner)?)
+        }
+        QueryMsg::StreamVesting { stream_id } => {
+            to_json_binary(&vesting::query_stream_vesting(deps, stream_id)?)
+        }
+        QueryMsg::Vesting { stream_id, owner } => {
+            to_json_binary(&vesting::query_vesting(deps, stream_id, owner)?)
+        }
+        QueryMsg::FailedVesting { stream_id, owner } => {
+            to_json_binary(&vesting::query_failed_vesting(deps, stream_id, owner)?)
+        }
+        QueryMsg::ListVestings {
+            stream_id,
+            start_after,
+            limit,
+        } => to_json_binary(&vesting::list_vestings(
+            deps,
+            stream_id,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::MigrateDryRun { limit } => {
+            to_json_binary(&migrate_v0_2_1::query_migrate_dry_run(deps, limit)?)
+        }
+        QueryMsg::StreamPool { stream_id } => {
+            to_json_binary(&pool::query_stream_pool(deps, stream_id)?)
+        }
+        QueryMsg::IbcRefunds { address } => to_json_binary(&ibc::query_ibc_refunds(deps, address)?),
+        QueryMsg::KeeperBounty { stream_id, keeper } => {
+            to_json_binary(&keeper::query_keeper_bounty(deps, stream_id, keeper)?)
+        }
+        QueryMsg::CreationHooks {} => to_json_binary(&CREATION_HOOKS.query_hooks(deps)?),
+        QueryMsg::FinalizeHooks { stream_id } => {
+            to_json_binary(&query_finalize_hooks(deps, stream_id)?)
+        }
+        QueryMsg::PriceHistory {
+            stream_id,
+            start,
+            end,
+            limit,
+        } => to_json_binary(&query_price_history(deps, stream_id, start, end, limit)?),
+        QueryMsg::StreamedPriceTwap { stream_id, window } => to_json_binary(
+            &query_streamed_price_twap(deps, env, stream_id, window.u64())?,
+        ),
+        QueryMsg::Guardians {} => to_json_binary(&killswitch::query_guardians(deps)?),
+        QueryMsg::CancelConfirmations { stream_id } => {
+            to_json_binary(&killswitch::query_cancel_confirmations(deps, stream_id)?)
+        }
+        QueryMsg::FeeWaiver { creator } => to_json_binary(&query_fee_waiver(deps, creator)?),
+        QueryMsg::RevenueVesting { stream_id } => {
+            to_json_binary(&query_revenue_vesting(deps, stream_id)?)
+        }
+        QueryMsg::ProtocolStats {} => to_json_binary(&query_protocol_stats(deps)?),
+        QueryMsg::Statistics { stream_id } => to_json_binary(&query_statistics(deps, stream_id)?),
+        QueryMsg::SimulateSubscribe {
+            stream_id,
+            amount,
+            at_time,
+        } => to_json_binary(&query_simulate_subscribe(
+            deps, env, stream_id, amount, at_time,
+        )?),
+        QueryMsg::PendingConfigUpdate {} => to_json_binary(&query_pending_config_update(deps)?),
+        QueryMsg::PendingProtocolAdmin {} => to_json_binary(&query_pending_protocol_admin(deps)?),
+        QueryMsg::CreatorAllowlist { start_after, limit } => {
+            to_json_binary(&query_creator_allowlist(deps, start_after, limit)?)
+        }
+        QueryMsg::FrozenDenoms { start_after, limit } => {
+            to_json_binary(&query_frozen_denoms(deps, env, start_after, limit)?)
+        }
+        QueryMsg::SimulateExit { stream_id, owner } => {
+            to_json_binary(&query_simulate_exit(deps, env, stream_id, owner)?)
+        }
+        QueryMsg::AuditBalances {} => to_json_binary(&query_audit_balances(deps, env)?),
     }
 }
+pub fn query_fee_waiver(deps: Deps, creator: String) -> StdResult<Option<Decimal256>> {
+    let creator = deps.api.addr_validate(&creator)?;
+    FEE_WAIVERS.may_load(deps.storage, &creator)
+}
+
 pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let cfg = CONFIG.load(deps.storage)?;
     Ok(ConfigResponse {
@@ -1167,15 +2520,71 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         stream_creation_fee: cfg.stream_creation_fee,
         exit_fee_percent: cfg.exit_fee_percent,
         fee_collector: cfg.fee_collector.to_string(),
-        protocol_admin: cfg.protocol_admin.to_string(),
+        protocol_admin: cfg.protocol_admin.map(|a| a.to_string()),
         accepted_in_denom: cfg.accepted_in_denom,
+        fee_swap: cfg.fee_swap,
+        ibc_transfer: cfg.ibc_transfer,
+        remote_controller: cfg.remote_controller.map(|a| a.to_string()),
+        prune_delay_blocks: cfg.prune_delay_blocks,
+        escheat_delay_blocks: cfg.escheat_delay_blocks,
+        keeper_bounty: cfg.keeper_bounty,
+        liquid_staking: cfg.liquid_staking,
+        name_registry: cfg.name_registry,
+        compliance_contract: cfg.compliance_contract.map(|a| a.to_string()),
+        exit_crank: cfg.exit_crank,
+        vesting_code_id: cfg.vesting_code_id,
+        finalize_hook_limits: cfg.finalize_hook_limits.unwrap_or_default(),
+        max_pause_duration: cfg.max_pause_duration,
+        max_creator_pause_duration: cfg.max_creator_pause_duration,
+        creator_cancel_penalty: cfg.creator_cancel_penalty,
+        permissioned_creation: cfg.permissioned_creation.unwrap_or_default(),
+        config_timelock: cfg.config_timelock,
     })
 }
 
-pub fn query_stream(deps: Deps, _env: Env, stream_id: u64) -> StdResult<StreamResponse> {
+pub fn query_stream(deps: Deps, env: Env, stream_id: u64) -> StdResult<StreamResponse> {
     let stream = STREAMS.load(deps.storage, stream_id)?;
     let stream = StreamResponse {
         id: stream_id,
+        phase: stream_phase(&stream, env.block.time),
+        effective_end_time: effective_end_time(&stream, env.block.time),
+        treasury: stream.treasury.to_string(),
+        in_denom: stream.in_denom,
+        out_denom: stream.out_denom,
+        out_supply: stream.out_supply,
+        start_time: stream.start_time,
+        end_time: stream.end_time,
+        spent_in: stream.spent_in,
+        dist_index: stream.dist_index,
+        out_remaining: stream.out_remaining,
+        in_supply: stream.in_supply,
+        shares: stream.shares,
+        last_updated: stream.last_updated,
+        status: stream.status,
+        pause_date: stream.pause_date,
+        url: stream.url,
+        current_streamed_price: stream.current_streamed_price,
+        exit_fee_percent: stream.stream_exit_fee_percent,
+        stream_creation_fee: stream.stream_creation_fee,
+    };
+    Ok(stream)
+}
+
+pub fn query_stream_at_height(
+    deps: Deps,
+    env: Env,
+    stream_id: u64,
+    height: u64,
+) -> StdResult<StreamResponse> {
+    // streams are only snapshotted from their first update after the upgrade introducing history
+    let stream = STREAM_SNAPSHOTS
+        .may_load_at_height(deps.storage, stream_id, height)?
+        .ok_or_else(|| StdError::not_found("stream at height"))?
+        .apply(STREAMS.load(deps.storage, stream_id)?);
+    let stream = StreamResponse {
+        id: stream_id,
+        phase: stream_phase(&stream, env.block.time),
+        effective_end_time: effective_end_time(&stream, env.block.time),
         treasury: stream.treasury.to_string(),
         in_denom: stream.in_denom,
         out_denom: stream.out_denom,
@@ -1202,20 +2611,75 @@ pub fn query_stream(deps: Deps, _env: Env, stream_id: u64) -> StdResult<StreamRe
 const MAX_LIMIT: u32 = 30;
 const DEFAULT_LIMIT: u32 = 10;
 
+// start_after bounds the range from below when ascending and from above when descending
+fn range_bounds<'a, K: PrimaryKey<'a>>(
+    start_after: Option<Bound<'a, K>>,
+    order: Order,
+) -> (Option<Bound<'a, K>>, Option<Bound<'a, K>>) {
+    match order {
+        Order::Ascending => (start_after, None),
+        Order::Descending => (None, start_after),
+    }
+}
+
+pub fn query_count_streams(deps: Deps) -> StdResult<CountResponse> {
+    let count = stream_count(deps.storage)?;
+    Ok(CountResponse { count })
+}
+
+pub fn query_count_positions(deps: Deps, stream_id: u64) -> StdResult<CountResponse> {
+    let stats = STREAM_STATS
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default();
+    Ok(CountResponse {
+        count: stats.subscribers.saturating_sub(stats.exits),
+    })
+}
+
 pub fn list_streams(
     deps: Deps,
+    env: Env,
     start_after: Option<u64>,
     limit: Option<u32>,
+    status: Option<Status>,
+    order: Option<OrderBy>,
+    tag: Option<String>,
 ) -> StdResult<StreamsResponse> {
-    let start = start_after.map(Bound::exclusive);
+    let order = order.map_or(Order::Ascending, Order::from);
+    let (min, max) = range_bounds(start_after.map(Bound::exclusive), order);
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let streams: StdResult<Vec<StreamResponse>> = STREAMS
-        .range(deps.storage, start, None, Order::Ascending)
+    let iter = match (tag, status) {
+        // tagged streams are listed from the tag index, filtered by status if given
+        (Some(tag), status) => Box::new(
+            TAGGED_STREAMS
+                .prefix(&tag)
+                .keys(deps.storage, min, max, order)
+                .map(|stream_id| {
+                    let stream_id = stream_id?;
+                    Ok((stream_id, STREAMS.load(deps.storage, stream_id)?))
+                })
+                .filter(move |item| match (item, &status) {
+                    (Ok((_, stream)), Some(status)) => stream.status == *status,
+                    _ => true,
+                }),
+        ),
+        (None, Some(status)) => {
+            STREAMS
+                .idx
+                .status
+                .prefix(status.key())
+                .range(deps.storage, min, max, order)
+        }
+        (None, None) => STREAMS.range(deps.storage, min, max, order),
+    };
+    let streams: StdResult<Vec<StreamResponse>> = iter
         .take(limit)
         .map(|item| {
             let (stream_id, stream) = item?;
             let stream = StreamResponse {
                 id: stream_id,
+                phase: stream_phase(&stream, env.block.time),
+                effective_end_time: effective_end_time(&stream, env.block.time),
                 treasury: stream.treasury.to_string(),
                 in_denom: stream.in_denom,
                 out_denom: stream.out_denom,
@@ -1250,7 +2714,33 @@ pub fn query_position(
 ) -> StdResult<PositionResponse> {
     let owner = deps.api.addr_validate(&owner)?;
     let position = POSITIONS.load(deps.storage, (stream_id, &owner))?;
-    let res = PositionResponse {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    Ok(position_response(
+        stream_id,
+        &owner,
+        position,
+        stream.current_streamed_price,
+    ))
+}
+
+fn position_response(
+    stream_id: u64,
+    owner: &Addr,
+    position: Position,
+    current_streamed_price: Decimal256,
+) -> PositionResponse {
+    let average_price = streamswap_math::average_price(position.spent, position.purchased).ok();
+    // the unspent balance buys at the current streamed price for the rest of the stream
+    let projected_purchased = Decimal256::from_ratio(position.in_balance, 1u128)
+        .checked_div(current_streamed_price)
+        .map(|p| p.to_uint_floor())
+        .unwrap_or_default();
+    let projected_average_price = streamswap_math::average_price(
+        position.spent + position.in_balance,
+        position.purchased + projected_purchased,
+    )
+    .ok();
+    PositionResponse {
         stream_id,
         owner: owner.to_string(),
         in_balance: position.in_balance,
@@ -1261,8 +2751,10 @@ pub fn query_position(
         operator: position.operator,
         last_updated: position.last_updated,
         pending_purchase: position.pending_purchase,
-    };
-    Ok(res)
+        average_price,
+        projected_purchased,
+        projected_average_price,
+    }
 }
 
 pub fn list_positions(
@@ -1270,30 +2762,94 @@ pub fn list_positions(
     stream_id: u64,
     start_after: Option<String>,
     limit: Option<u32>,
+    order: Option<OrderBy>,
 ) -> StdResult<PositionsResponse> {
     let addr = maybe_addr(deps.api, start_after)?;
-    let start = addr.as_ref().map(Bound::exclusive);
+    let order = order.map_or(Order::Ascending, Order::from);
+    let (min, max) = range_bounds(addr.as_ref().map(Bound::exclusive), order);
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let current_streamed_price = STREAMS
+        .may_load(deps.storage, stream_id)?
+        .map(|stream| stream.current_streamed_price)
+        .unwrap_or_default();
 
     let positions: StdResult<Vec<PositionResponse>> = POSITIONS
         .prefix(stream_id)
-        .range(deps.storage, start, None, Order::Ascending)
+        .range(deps.storage, min, max, order)
         .take(limit)
         .map(|item| {
             let (owner, position) = item?;
-            let position = PositionResponse {
+            Ok(position_response(
                 stream_id,
-                owner: owner.to_string(),
-                index: position.index,
-                last_updated: position.last_updated,
-                purchased: position.purchased,
-                pending_purchase: position.pending_purchase,
-                spent: position.spent,
-                in_balance: position.in_balance,
-                shares: position.shares,
-                operator: position.operator,
-            };
-            Ok(position)
+                &owner,
+                position,
+                current_streamed_price,
+            ))
+        })
+        .collect();
+    let positions = positions?;
+    Ok(PositionsResponse { positions })
+}
+
+pub fn list_positions_by_owner(
+    deps: Deps,
+    owner: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    order: Option<OrderBy>,
+) -> StdResult<PositionsResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let order = order.map_or(Order::Ascending, Order::from);
+    let start = start_after.map(|stream_id| Bound::exclusive((stream_id, owner.clone())));
+    let (min, max) = range_bounds(start, order);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let positions: StdResult<Vec<PositionResponse>> = POSITIONS
+        .idx
+        .owner
+        .prefix(owner)
+        .range(deps.storage, min, max, order)
+        .take(limit)
+        .map(|item| {
+            let ((stream_id, owner), position) = item?;
+            let stream = STREAMS.load(deps.storage, stream_id)?;
+            Ok(position_response(
+                stream_id,
+                &owner,
+                position,
+                stream.current_streamed_price,
+            ))
+        })
+        .collect();
+    let positions = positions?;
+    Ok(PositionsResponse { positions })
+}
+
+pub fn list_largest_positions(
+    deps: Deps,
+    stream_id: u64,
+    limit: Option<u32>,
+) -> StdResult<PositionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let current_streamed_price = STREAMS
+        .may_load(deps.storage, stream_id)?
+        .map(|stream| stream.current_streamed_price)
+        .unwrap_or_default();
+
+    let positions: StdResult<Vec<PositionResponse>> = POSITIONS
+        .idx
+        .shares
+        .sub_prefix(stream_id)
+        .range(deps.storage, None, None, Order::Descending)
+        .take(limit)
+        .map(|item| {
+            let ((stream_id, owner), position) = item?;
+            Ok(position_response(
+                stream_id,
+                &owner,
+                position,
+                current_streamed_price,
+            ))
         })
         .collect();
     let positions = positions?;
@@ -1306,8 +2862,10 @@ pub fn query_average_price(
     stream_id: u64,
 ) -> StdResult<AveragePriceResponse> {
     let stream = STREAMS.load(deps.storage, stream_id)?;
-    let total_purchased = stream.out_supply - stream.out_remaining;
-    let average_price = Decimal256::from_ratio(stream.spent_in, total_purchased);
+    let total_purchased =
+        stream.out_supply - reserved_out(deps.storage, stream_id)? - stream.out_remaining;
+    let average_price = streamswap_math::average_price(stream.spent_in, total_purchased)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
     Ok(AveragePriceResponse { average_price })
 }
 
@@ -1331,3 +2889,13 @@ pub fn query_threshold_state(
     let threshold = threshold_state.get_threshold(stream_id, deps.storage)?;
     Ok(threshold)
 }
+
+pub fn query_pending_treasury(deps: Deps, stream_id: u64) -> StdResult<Option<Addr>> {
+    PENDING_TREASURIES.may_load(deps.storage, stream_id)
+}
+
+pub fn query_finalize_hooks(deps: Deps, stream_id: u64) -> StdResult<Vec<Addr>> {
+    Ok(FINALIZE_HOOKS
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default())
+}
@@ -0,0 +1,212 @@
+use crate::compliance::check_compliance;
+use crate::contract::save_config_update;
+use crate::contract::{execute_exit_stream, update_position};
+use crate::hard_cap::hard_cap_refund;
+use crate::partial_refund::apply_partial_fill;
+use crate::state::{Config, ConfigUpdate, ExitCrank, Status, StreamId, CONFIG, POSITIONS, STREAMS};
+use crate::stats::record_fee;
+use crate::ContractError;
+use cosmwasm_std::{
+    attr, Addr, BankMsg, Coin, Decimal256, DepsMut, Env, MessageInfo, Order, Response, StdResult,
+    Storage, Uint128, Uint256,
+};
+use cw_storage_plus::Map;
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+// Stream id -> share of the swap fee held back at finalize to pay `CrankExits` callers
+pub const CRANK_RESERVES: Map<StreamId, Uint256> = Map::new("crank_reserves");
+
+pub fn validate_exit_crank(exit_crank: &ExitCrank) -> Result<(), ContractError> {
+    if exit_crank.fee_share.is_zero() || exit_crank.fee_share > Decimal256::one() {
+        return Err(ContractError::InvalidExitCrank {});
+    }
+    Ok(())
+}
+
+pub fn execute_update_exit_crank(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    exit_crank: Option<ExitCrank>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(exit_crank) = &exit_crank {
+        validate_exit_crank(exit_crank)?;
+    }
+    let (grace_period, fee_share) = exit_crank
+        .as_ref()
+        .map(|c| (c.grace_period.to_string(), c.fee_share.to_string()))
+        .unwrap_or_default();
+    let apply_after = save_config_update(
+        deps,
+        &env,
+        config,
+        ConfigUpdate {
+            exit_crank: Some(exit_crank),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_exit_crank")
+        .add_attribute("grace_period", grace_period)
+        .add_attribute("fee_share", fee_share)
+        .add_attributes(apply_after.map(|t| ("apply_after", t.to_string()))))
+}
+
+/// Holds back the crank share of the swap fee of a finalized stream if the crank is enabled.
+/// Returns the reserved amount.
+pub fn reserve_crank_bounties(
+    storage: &mut dyn Storage,
+    config: &Config,
+    stream_id: StreamId,
+    swap_fee: Uint256,
+) -> StdResult<Uint256> {
+    let Some(exit_crank) = &config.exit_crank else {
+        return Ok(Uint256::zero());
+    };
+    let reserve = Decimal256::from_ratio(swap_fee, Uint256::one())
+        .checked_mul(exit_crank.fee_share)?
+        * Uint256::one();
+    if !reserve.is_zero() {
+        CRANK_RESERVES.save(storage, stream_id, &reserve)?;
+    }
+    Ok(reserve)
+}
+
+/// Exits up to `limit` positions of a finalized stream on behalf of their owners once the grace
+/// period after the end has passed. Purchased and unspent tokens are sent to the owners, the
+/// caller is paid the crank share of the swap fee of each exited position. Once no positions
+/// are left, the rest of the reserve goes to the fee collector.
+pub fn execute_crank_exits(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let exit_crank = config
+        .exit_crank
+        .clone()
+        .ok_or(ContractError::ExitCrankDisabled {})?;
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    // swap fees are settled at finalize, the reserve is funded from them
+    if stream.status != Status::Finalized {
+        return Err(ContractError::StreamNotFinalized {});
+    }
+    let grace_period_end = stream.end_time.plus_seconds(exit_crank.grace_period.u64());
+    if env.block.time <= grace_period_end {
+        return Err(ContractError::ExitGracePeriodActive {
+            ends_at: grace_period_end,
+        });
+    }
+
+    // positions rejected by the compliance contract are skipped, they would fail the exit
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let owners = POSITIONS
+        .prefix(stream_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .filter(|owner| {
+            owner.as_ref().map_or(true, |owner| {
+                !matches!(
+                    check_compliance(deps.as_ref(), stream_id, &[owner]),
+                    Err(ContractError::ComplianceRejected { .. })
+                )
+            })
+        })
+        .take(limit)
+        .collect::<StdResult<Vec<Addr>>>()?;
+
+    let mut reserve = CRANK_RESERVES
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default();
+    let mut bounty = Uint256::zero();
+    let mut res = Response::new();
+    for owner in &owners {
+        let mut position = POSITIONS.load(deps.storage, (stream_id, owner))?;
+        update_position(
+            stream.dist_index,
+            stream.shares,
+            stream.last_updated,
+            stream.in_supply,
+            &mut position,
+        )?;
+        let excess_refund =
+            hard_cap_refund(deps.storage, stream_id, stream.spent_in, position.spent)?;
+        position.spent = position.spent.checked_sub(excess_refund)?;
+        apply_partial_fill(deps.storage, stream_id, &mut position)?;
+        let swap_fee = Decimal256::from_ratio(position.spent, Uint256::one())
+            .checked_mul(stream.stream_exit_fee_percent)?
+            * Uint256::one();
+        let position_bounty = (Decimal256::from_ratio(swap_fee, Uint256::one())
+            .checked_mul(exit_crank.fee_share)?
+            * Uint256::one())
+        .min(reserve);
+        reserve = reserve.checked_sub(position_bounty)?;
+        bounty = bounty.checked_add(position_bounty)?;
+
+        let exit_info = MessageInfo {
+            sender: owner.clone(),
+            funds: vec![],
+        };
+        let exit_res = execute_exit_stream(
+            deps.branch(),
+            env.clone(),
+            exit_info,
+            stream_id,
+            None,
+            None,
+            false,
+            None,
+        )?;
+        res = res
+            .add_submessages(exit_res.messages)
+            .add_events(exit_res.events);
+    }
+
+    if !bounty.is_zero() {
+        res = res.add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin {
+                denom: stream.in_denom.clone(),
+                amount: Uint128::try_from(bounty)?,
+            }],
+        });
+    }
+    let positions_left = POSITIONS
+        .prefix(stream_id)
+        .keys_raw(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    let mut released = Uint256::zero();
+    if positions_left {
+        CRANK_RESERVES.save(deps.storage, stream_id, &reserve)?;
+    } else {
+        CRANK_RESERVES.remove(deps.storage, stream_id);
+        released = reserve;
+        record_fee(deps.storage, &stream.in_denom, released)?;
+        if !released.is_zero() {
+            res = res.add_message(BankMsg::Send {
+                to_address: config.fee_collector.to_string(),
+                amount: vec![Coin {
+                    denom: stream.in_denom,
+                    amount: Uint128::try_from(released)?,
+                }],
+            });
+        }
+    }
+
+    Ok(res.add_attributes(vec![
+        attr("action", "crank_exits"),
+        attr("stream_id", stream_id.to_string()),
+        attr("exited", owners.len().to_string()),
+        attr("bounty", bounty),
+        attr("released_reserve", released),
+    ]))
+}
@@ -0,0 +1,158 @@
+use crate::helpers::check_name_and_url;
+use crate::names::name_key;
+use crate::operations::ensure_operations;
+use crate::state::{Status, StreamId, STREAMS};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, Deps, DepsMut, Empty, Env, MessageInfo, Order, Response, StdResult, Storage,
+};
+use cw_storage_plus::{Bound, Map};
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+// Hex encoded sha256 hash
+const LOGO_HASH_LENGTH: usize = 64;
+
+/// Profile a creator registers to be recognized across its streams.
+#[cw_serde]
+pub struct CreatorProfile {
+    pub display_name: String,
+    pub url: Option<String>,
+    /// Hex encoded sha256 hash of the logo, so frontends can verify the served image.
+    pub logo_hash: Option<String>,
+}
+
+#[cw_serde]
+pub struct StreamCreatorResponse {
+    pub creator: Addr,
+    pub profile: CreatorProfile,
+}
+
+// Creator -> registered profile
+pub const CREATOR_PROFILES: Map<&Addr, CreatorProfile> = Map::new("creator_profiles");
+// Stream id -> creator whose profile is attached to the stream
+pub const STREAM_CREATORS: Map<StreamId, Addr> = Map::new("stream_creators");
+// (creator, stream id) -> (), lists the streams of a creator
+pub const CREATOR_STREAMS: Map<(&Addr, StreamId), Empty> = Map::new("creator_streams");
+// Lowercased display name -> creator holding the name, so no creator can impersonate another
+pub const CREATOR_NAMES: Map<&str, Addr> = Map::new("creator_names");
+
+fn validate_creator_profile(profile: &CreatorProfile) -> Result<(), ContractError> {
+    check_name_and_url(&profile.display_name, &profile.url)?;
+    if let Some(logo_hash) = &profile.logo_hash {
+        if logo_hash.len() != LOGO_HASH_LENGTH
+            || !logo_hash
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        {
+            return Err(ContractError::InvalidCreatorProfile {});
+        }
+    }
+    Ok(())
+}
+
+/// Registers the profile of the sender, replacing the previous one. Display names are unique
+/// regardless of case and surrounding spaces. `None` removes the profile and releases its name,
+/// streams it is attached to no longer resolve to it.
+pub fn execute_update_creator_profile(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    profile: Option<CreatorProfile>,
+) -> Result<Response, ContractError> {
+    let res = Response::new()
+        .add_attribute("action", "update_creator_profile")
+        .add_attribute("creator", info.sender.to_string());
+    if let Some(previous) = CREATOR_PROFILES.may_load(deps.storage, &info.sender)? {
+        CREATOR_NAMES.remove(deps.storage, &name_key(&previous.display_name));
+    }
+    let Some(profile) = profile else {
+        CREATOR_PROFILES.remove(deps.storage, &info.sender);
+        return Ok(res);
+    };
+    validate_creator_profile(&profile)?;
+    let key = name_key(&profile.display_name);
+    if CREATOR_NAMES.has(deps.storage, &key) {
+        return Err(ContractError::DisplayNameTaken {
+            name: profile.display_name,
+        });
+    }
+    CREATOR_NAMES.save(deps.storage, &key, &info.sender)?;
+    CREATOR_PROFILES.save(deps.storage, &info.sender, &profile)?;
+
+    Ok(res.add_attribute("display_name", profile.display_name))
+}
+
+/// Attaches the profile of the sender to the stream, or detaches the attached profile. Only the
+/// operations role can update with its own profile, until the stream is finalized or cancelled.
+pub fn execute_attach_creator_profile(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    attach: bool,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if matches!(stream.status, Status::Finalized | Status::Cancelled) {
+        return Err(ContractError::StreamEnded {});
+    }
+    remove_stream_creator(deps.storage, stream_id)?;
+    if attach {
+        if !CREATOR_PROFILES.has(deps.storage, &info.sender) {
+            return Err(ContractError::NoCreatorProfile {});
+        }
+        STREAM_CREATORS.save(deps.storage, stream_id, &info.sender)?;
+        CREATOR_STREAMS.save(deps.storage, (&info.sender, stream_id), &Empty {})?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "attach_creator_profile")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("creator", info.sender.to_string())
+        .add_attribute("attached", attach.to_string()))
+}
+
+/// Detaches the creator profile of the stream and removes it from the creator listing.
+pub fn remove_stream_creator(storage: &mut dyn Storage, stream_id: StreamId) -> StdResult<()> {
+    if let Some(creator) = STREAM_CREATORS.may_load(storage, stream_id)? {
+        CREATOR_STREAMS.remove(storage, (&creator, stream_id));
+        STREAM_CREATORS.remove(storage, stream_id);
+    }
+    Ok(())
+}
+
+pub fn query_creator_profile(deps: Deps, creator: String) -> StdResult<Option<CreatorProfile>> {
+    let creator = deps.api.addr_validate(&creator)?;
+    CREATOR_PROFILES.may_load(deps.storage, &creator)
+}
+
+/// Returns the creator attached to the stream with its current profile.
+pub fn query_stream_creator(
+    deps: Deps,
+    stream_id: StreamId,
+) -> StdResult<Option<StreamCreatorResponse>> {
+    let Some(creator) = STREAM_CREATORS.may_load(deps.storage, stream_id)? else {
+        return Ok(None);
+    };
+    Ok(CREATOR_PROFILES
+        .may_load(deps.storage, &creator)?
+        .map(|profile| StreamCreatorResponse { creator, profile }))
+}
+
+pub fn query_creator_streams(
+    deps: Deps,
+    creator: String,
+    start_after: Option<StreamId>,
+    limit: Option<u32>,
+) -> StdResult<Vec<StreamId>> {
+    let creator = deps.api.addr_validate(&creator)?;
+    let start = start_after.map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    CREATOR_STREAMS
+        .prefix(&creator)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
@@ -1,9 +1,12 @@
 use crate::threshold::ThresholdError;
 use cosmwasm_std::{
-    ConversionOverflowError, DivideByZeroError, OverflowError, StdError, Uint128, Uint256,
+    CheckedFromRatioError, ConversionOverflowError, DivideByZeroError, Instantiate2AddressError,
+    OverflowError, StdError, Timestamp, Uint128, Uint256,
 };
+use cw_controllers::HookError;
 use cw_utils::PaymentError;
 use std::convert::Infallible;
+use streamswap_math::MathError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -26,12 +29,27 @@ pub enum ContractError {
     #[error("{0}")]
     ThresholdError(#[from] ThresholdError),
 
+    #[error("{0}")]
+    Hook(#[from] HookError),
+
     #[error("{0}")]
     ConversionOverflowError(#[from] ConversionOverflowError),
 
+    #[error("{0}")]
+    CheckedFromRatioError(#[from] CheckedFromRatioError),
+
+    #[error("{0}")]
+    Instantiate2Address(#[from] Instantiate2AddressError),
+
     #[error("Cannot migrate from different contract type: {previous_contract}")]
     CannotMigrate { previous_contract: String },
 
+    #[error("Migration pending, continue it with MigrateStep")]
+    MigrationPending {},
+
+    #[error("No pending migration")]
+    NoPendingMigration {},
+
     #[error("No rewards accrued")]
     NoDistribution {},
 
@@ -154,4 +172,251 @@ pub enum ContractError {
 
     #[error("Invalid exit fee")]
     InvalidStreamExitFee {},
+
+    #[error("Invalid fee swap config")]
+    InvalidFeeSwap {},
+
+    #[error("Fee swap reply is missing swap result")]
+    InvalidFeeSwapReply {},
+
+    #[error("Invalid stream tags")]
+    InvalidTags {},
+
+    #[error("Invalid creator profile")]
+    InvalidCreatorProfile {},
+
+    #[error("Sender has no creator profile")]
+    NoCreatorProfile {},
+
+    #[error("Display name {name} is taken")]
+    DisplayNameTaken { name: String },
+
+    #[error("Subscription would mint {shares} shares, below the minimum")]
+    SharesBelowMinimum { shares: Uint256 },
+
+    #[error("Address {address} rejected by compliance: {reason}")]
+    ComplianceRejected { address: String, reason: String },
+
+    #[error("Address {address} is blocked from the stream")]
+    AddressBlocked { address: String },
+
+    #[error("Address {address} is not blocked from the stream")]
+    AddressNotBlocked { address: String },
+
+    #[error("Denom {denom} is used by an open stream")]
+    DenomInUse { denom: String },
+
+    #[error("No dust to sweep")]
+    NoDust {},
+
+    #[error("Invalid exit swap")]
+    InvalidExitSwap {},
+
+    #[error("Exit swap reply is missing swap result")]
+    InvalidExitSwapReply {},
+
+    #[error("Invalid swap subscription")]
+    InvalidSwapSubscription {},
+
+    #[error("Swap subscription reply is missing swap result")]
+    InvalidSwapSubscriptionReply {},
+
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("No pending treasury")]
+    NoPendingTreasury {},
+
+    #[error("No pending operations")]
+    NoPendingOperations {},
+
+    #[error("Too many finalize hooks, max: {max}")]
+    TooManyFinalizeHooks { max: u32 },
+
+    #[error("Finalize hook gas limit can not be zero")]
+    InvalidFinalizeHookLimits {},
+
+    #[error("Revenue vesting duration can not be zero")]
+    InvalidRevenueVestingDuration {},
+
+    #[error("No vested revenue to claim")]
+    NoVestedRevenue {},
+
+    #[error("Fee waiver discount must be greater than zero and at most one")]
+    InvalidFeeWaiver {},
+
+    #[error("Donation percent must be greater than zero and less than one")]
+    InvalidDonationPercent {},
+
+    #[error("Max pause duration can not be zero")]
+    InvalidMaxPauseDuration {},
+
+    #[error("Stream pause has not expired")]
+    PauseNotExpired {},
+
+    #[error("Streams with a threshold can not be exited while paused")]
+    ThresholdExitPaused {},
+
+    #[error("Streams vesting purchases can not be exited while paused")]
+    VestingExitPaused {},
+
+    #[error("Invalid guardian set")]
+    InvalidGuardianSet {},
+
+    #[error("Cancel already confirmed by guardian")]
+    CancelAlreadyConfirmed {},
+
+    #[error("No cancel proposal for stream")]
+    NoCancelProposal {},
+
+    #[error("Creator pause is disabled")]
+    CreatorPauseDisabled {},
+
+    #[error("Stream was already paused by its creator")]
+    CreatorPauseUsed {},
+
+    #[error("Stream is not paused by its creator")]
+    NotCreatorPaused {},
+
+    #[error("Creator cancel penalty can not be greater than one")]
+    InvalidCreatorCancelPenalty {},
+
+    #[error("No stream params to update")]
+    EmptyStreamParamsUpdate {},
+
+    #[error("Denom {denom} is frozen")]
+    DenomFrozen { denom: String },
+
+    #[error("Creator is not allowed to create streams")]
+    CreatorNotAllowed {},
+
+    #[error("No pending protocol admin")]
+    NoPendingProtocolAdmin {},
+
+    #[error("Protocol admin proposal expired")]
+    ProtocolAdminProposalExpired {},
+
+    #[error("No pending config update")]
+    NoPendingConfigUpdate {},
+
+    #[error("Config update is timelocked until {apply_after}")]
+    ConfigUpdateTimelocked { apply_after: Timestamp },
+
+    #[error("Freeze expiry is already expired")]
+    InvalidFreezeExpiry {},
+
+    #[error("Invalid IBC transfer config")]
+    InvalidIbcTransfer {},
+
+    #[error("IBC transfers are disabled")]
+    IbcTransferDisabled {},
+
+    #[error("Invalid IBC transfer reply")]
+    InvalidIbcTransferReply {},
+
+    #[error("IBC denom {denom} has no known trace or supply")]
+    InvalidIbcDenom { denom: String },
+
+    #[error("No IBC refunds to claim")]
+    NoIbcRefunds {},
+
+    #[error("Stream pruning is disabled")]
+    PruneDisabled {},
+
+    #[error("Stream {stream_id} can not be pruned yet")]
+    StreamNotPrunable { stream_id: u64 },
+
+    #[error("Stream settles a missed threshold with partial refunds at finalize")]
+    PartialRefundEnabled {},
+
+    #[error("Escheating is disabled")]
+    EscheatDisabled {},
+
+    #[error("Positions of stream {stream_id} can not be escheated yet")]
+    EscheatNotAvailable { stream_id: u64 },
+
+    #[error("No escheated position to claim")]
+    NoEscheatedPosition {},
+
+    #[error("Invalid keeper bounty config")]
+    InvalidKeeperBounty {},
+
+    #[error("No keeper bounty to claim")]
+    NoKeeperBounty {},
+
+    #[error("Stream is not finalized")]
+    StreamNotFinalized {},
+
+    #[error("Invalid exit crank config")]
+    InvalidExitCrank {},
+
+    #[error("Exit crank is disabled")]
+    ExitCrankDisabled {},
+
+    #[error("Positions can exit by themselves until {ends_at}")]
+    ExitGracePeriodActive { ends_at: Timestamp },
+
+    #[error("Invalid liquid staking config")]
+    InvalidLiquidStaking {},
+
+    #[error("Liquid staking is disabled")]
+    LiquidStakingDisabled {},
+
+    #[error("Liquid staking hub does not accept {denom}")]
+    LiquidStakingDenomMismatch { denom: String },
+
+    #[error("Stream name {name} is taken")]
+    StreamNameTaken { name: String },
+
+    #[error("Purchased tokens can be delivered only one way: swapped, liquid staked, sent over IBC or vested")]
+    ConflictingExitDelivery {},
+
+    #[error("Vesting is disabled")]
+    VestingDisabled {},
+
+    #[error("No failed vesting to retry")]
+    NoFailedVesting {},
+
+    #[error("Invalid vesting config")]
+    InvalidVestingConfig {},
+
+    #[error("Invalid create pool config")]
+    InvalidCreatePool {},
+
+    #[error("Pool creation reply is missing the created pool or position")]
+    InvalidPoolReply {},
+
+    #[error("Invalid allocation tiers")]
+    InvalidAllocationTiers {},
+
+    #[error("Allocation tiers can not be changed after deposits")]
+    AllocationTiersLocked {},
+
+    #[error("Sender is not in an allocation tier")]
+    NotTierMember {},
+
+    #[error("Tier member cap exceeded, {remaining} can be deposited")]
+    TierCapExceeded { remaining: Uint256 },
+
+    #[error("No tier deposit")]
+    NoTierDeposit {},
+
+    #[error("Hard cap can not be zero")]
+    InvalidHardCap {},
+
+    #[error("Invalid subscription limit")]
+    InvalidSubscriptionLimit {},
+
+    #[error("Subscription limit exceeded, {remaining} can be subscribed in this window")]
+    SubscriptionLimitExceeded { remaining: Uint256 },
+}
+
+impl From<MathError> for ContractError {
+    fn from(err: MathError) -> Self {
+        match err {
+            MathError::Overflow(err) => ContractError::Overflow(err),
+            MathError::ConversionOverflow(err) => ContractError::ConversionOverflowError(err),
+            MathError::CheckedFromRatio(err) => ContractError::CheckedFromRatioError(err),
+        }
+    }
 }
@@ -0,0 +1,168 @@
+use crate::blocklist::check_not_blocked;
+use crate::compliance::check_compliance;
+use crate::contract::update_position;
+use crate::hard_cap::hard_cap_refund;
+use crate::partial_refund::apply_partial_fill;
+use crate::state::{save_stream, Status, StreamId, CONFIG, POSITIONS, STREAMS, STREAM_CLOSED_AT};
+use crate::stats::record_exit;
+use crate::vesting::{vesting_msg, STREAM_VESTINGS};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, Addr, BankMsg, Coin, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult,
+    Uint128, Uint256,
+};
+use cw_storage_plus::Map;
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Tokens of a never exited position moved out of the stream, claimable by the owner.
+#[cw_serde]
+pub struct EscheatedPosition {
+    pub out_denom: String,
+    pub purchased: Uint256,
+    pub in_denom: String,
+    /// Unspent in tokens, including the hard cap refund.
+    pub refunded: Uint256,
+}
+
+// (stream id, owner) -> tokens of the escheated position
+pub const ESCHEATED_POSITIONS: Map<(StreamId, &Addr), EscheatedPosition> =
+    Map::new("escheated_positions");
+
+/// Moves up to `limit` positions of a stream finalized more than `escheat_delay_blocks` ago into
+/// escrow, removing them from the stream. Owners claim the tokens with `ClaimEscheated`. Only
+/// protocol admin can escheat.
+pub fn execute_escheat_positions(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let escheat_delay = config
+        .escheat_delay_blocks
+        .ok_or(ContractError::EscheatDisabled {})?;
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.status != Status::Finalized {
+        return Err(ContractError::StreamNotFinalized {});
+    }
+    // streams closed before closing heights were recorded can not be escheated
+    let closed_at_height = STREAM_CLOSED_AT
+        .may_load(deps.storage, stream_id)?
+        .ok_or(ContractError::EscheatNotAvailable { stream_id })?;
+    if env.block.height <= closed_at_height + escheat_delay.u64() {
+        return Err(ContractError::EscheatNotAvailable { stream_id });
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let positions = POSITIONS
+        .prefix(stream_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let escheated = positions.len();
+    for (owner, mut position) in positions {
+        update_position(
+            stream.dist_index,
+            stream.shares,
+            stream.last_updated,
+            stream.in_supply,
+            &mut position,
+        )?;
+        let excess_refund =
+            hard_cap_refund(deps.storage, stream_id, stream.spent_in, position.spent)?;
+        position.spent = position.spent.checked_sub(excess_refund)?;
+        position.in_balance = position.in_balance.checked_add(excess_refund)?;
+        apply_partial_fill(deps.storage, stream_id, &mut position)?;
+        stream.shares = stream.shares.checked_sub(position.shares)?;
+        ESCHEATED_POSITIONS.save(
+            deps.storage,
+            (stream_id, &owner),
+            &EscheatedPosition {
+                out_denom: stream.out_denom.clone(),
+                purchased: position.purchased,
+                in_denom: stream.in_denom.clone(),
+                refunded: position.in_balance,
+            },
+        )?;
+        POSITIONS.remove(deps.storage, (stream_id, &owner))?;
+        record_exit(deps.storage, stream_id)?;
+    }
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "escheat_positions"),
+        attr("stream_id", stream_id.to_string()),
+        attr("escheated", escheated.to_string()),
+    ]))
+}
+
+/// Sends the sender the tokens of its escheated position. Purchases of vesting streams fund a
+/// vesting contract of the sender instead. Blocked and non compliant senders can not claim.
+pub fn execute_claim_escheated(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+) -> Result<Response, ContractError> {
+    let escheated = ESCHEATED_POSITIONS
+        .may_load(deps.storage, (stream_id, &info.sender))?
+        .ok_or(ContractError::NoEscheatedPosition {})?;
+    check_not_blocked(deps.storage, stream_id, &[&info.sender])?;
+    check_compliance(deps.as_ref(), stream_id, &[&info.sender])?;
+    ESCHEATED_POSITIONS.remove(deps.storage, (stream_id, &info.sender));
+
+    let mut res = Response::new();
+    if !escheated.purchased.is_zero() {
+        let purchased = Coin {
+            denom: escheated.out_denom,
+            amount: Uint128::try_from(escheated.purchased)?,
+        };
+        res = if STREAM_VESTINGS.has(deps.storage, stream_id) {
+            res.add_submessage(vesting_msg(
+                deps.branch(),
+                &env,
+                stream_id,
+                &info.sender,
+                purchased,
+            )?)
+        } else {
+            res.add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![purchased],
+            })
+        };
+    }
+    if !escheated.refunded.is_zero() {
+        res = res.add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin {
+                denom: escheated.in_denom,
+                amount: Uint128::try_from(escheated.refunded)?,
+            }],
+        });
+    }
+
+    Ok(res.add_attributes(vec![
+        attr("action", "claim_escheated"),
+        attr("stream_id", stream_id.to_string()),
+        attr("owner", info.sender.to_string()),
+        attr("purchased", escheated.purchased),
+        attr("refunded", escheated.refunded),
+    ]))
+}
+
+pub fn query_escheated_position(
+    deps: Deps,
+    stream_id: StreamId,
+    owner: String,
+) -> StdResult<Option<EscheatedPosition>> {
+    let owner = deps.api.addr_validate(&owner)?;
+    ESCHEATED_POSITIONS.may_load(deps.storage, (stream_id, &owner))
+}
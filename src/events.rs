@@ -0,0 +1,144 @@
+// Typed events emitted next to the `wasm` attributes, so indexers can rely on a stable event
+// type and attribute set for each stream lifecycle step.
+use crate::state::StreamId;
+use cosmwasm_std::{attr, Addr, Event, Timestamp, Uint256};
+
+pub struct StreamCreated {
+    pub stream_id: StreamId,
+    pub treasury: Addr,
+    pub name: String,
+    pub in_denom: String,
+    pub out_denom: String,
+    pub out_supply: Uint256,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+}
+
+impl From<StreamCreated> for Event {
+    fn from(e: StreamCreated) -> Self {
+        Event::new("stream_created").add_attributes(vec![
+            attr("stream_id", e.stream_id.to_string()),
+            attr("treasury", e.treasury),
+            attr("name", e.name),
+            attr("in_denom", e.in_denom),
+            attr("out_denom", e.out_denom),
+            attr("out_supply", e.out_supply),
+            attr("start_time", e.start_time.to_string()),
+            attr("end_time", e.end_time.to_string()),
+        ])
+    }
+}
+
+pub struct Subscribed {
+    pub stream_id: StreamId,
+    pub owner: Addr,
+    pub amount: Uint256,
+    pub in_supply: Uint256,
+}
+
+impl From<Subscribed> for Event {
+    fn from(e: Subscribed) -> Self {
+        Event::new("stream_subscribed").add_attributes(vec![
+            attr("stream_id", e.stream_id.to_string()),
+            attr("owner", e.owner),
+            attr("amount", e.amount),
+            attr("in_supply", e.in_supply),
+        ])
+    }
+}
+
+pub struct Withdrawn {
+    pub stream_id: StreamId,
+    pub owner: Addr,
+    pub amount: Uint256,
+}
+
+impl From<Withdrawn> for Event {
+    fn from(e: Withdrawn) -> Self {
+        Event::new("stream_withdrawn").add_attributes(vec![
+            attr("stream_id", e.stream_id.to_string()),
+            attr("owner", e.owner),
+            attr("amount", e.amount),
+        ])
+    }
+}
+
+pub struct Exited {
+    pub stream_id: StreamId,
+    pub owner: Addr,
+    /// Out tokens sent to the owner.
+    pub purchased: Uint256,
+    /// Unspent in tokens sent to the owner.
+    pub refunded: Uint256,
+    pub swap_fee: Uint256,
+}
+
+impl From<Exited> for Event {
+    fn from(e: Exited) -> Self {
+        Event::new("stream_exited").add_attributes(vec![
+            attr("stream_id", e.stream_id.to_string()),
+            attr("owner", e.owner),
+            attr("purchased", e.purchased),
+            attr("refunded", e.refunded),
+            attr("swap_fee", e.swap_fee),
+        ])
+    }
+}
+
+pub struct Finalized {
+    pub stream_id: StreamId,
+    pub treasury: Addr,
+    pub creators_revenue: Uint256,
+    pub swap_fee: Uint256,
+    pub refunded_out_remaining: Uint256,
+}
+
+impl From<Finalized> for Event {
+    fn from(e: Finalized) -> Self {
+        Event::new("stream_finalized").add_attributes(vec![
+            attr("stream_id", e.stream_id.to_string()),
+            attr("treasury", e.treasury),
+            attr("creators_revenue", e.creators_revenue),
+            attr("swap_fee", e.swap_fee),
+            attr("refunded_out_remaining", e.refunded_out_remaining),
+        ])
+    }
+}
+
+pub struct Paused {
+    pub stream_id: StreamId,
+    pub pause_date: Timestamp,
+}
+
+impl From<Paused> for Event {
+    fn from(e: Paused) -> Self {
+        Event::new("stream_paused").add_attributes(vec![
+            attr("stream_id", e.stream_id.to_string()),
+            attr("pause_date", e.pause_date.to_string()),
+        ])
+    }
+}
+
+pub struct Resumed {
+    pub stream_id: StreamId,
+    pub end_time: Timestamp,
+}
+
+impl From<Resumed> for Event {
+    fn from(e: Resumed) -> Self {
+        Event::new("stream_resumed").add_attributes(vec![
+            attr("stream_id", e.stream_id.to_string()),
+            attr("end_time", e.end_time.to_string()),
+        ])
+    }
+}
+
+pub struct Cancelled {
+    pub stream_id: StreamId,
+}
+
+impl From<Cancelled> for Event {
+    fn from(e: Cancelled) -> Self {
+        Event::new("stream_cancelled").add_attribute("stream_id", e.stream_id.to_string())
+    }
+}
@@ -0,0 +1,123 @@
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, Addr, BankMsg, Coin, DepsMut, Env, Reply, Response, SubMsg, SubMsgResult, Uint128,
+};
+use cw_storage_plus::Map;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, SwapAmountInRoute,
+};
+use std::str::FromStr;
+
+pub const EXIT_SWAP_PURCHASED_REPLY_ID: u64 = 6;
+pub const EXIT_SWAP_REFUNDED_REPLY_ID: u64 = 7;
+
+/// Swap of exit proceeds to the denom the last route ends in.
+#[cw_serde]
+pub struct SwapRoute {
+    pub routes: Vec<SwapAmountInRoute>,
+    /// Swaps returning less than `min_out_amount` fall back to sending the raw tokens.
+    pub min_out_amount: Uint128,
+}
+
+/// Swaps applied to the purchased out tokens and the refunded in tokens of an exit.
+#[cw_serde]
+pub struct ExitSwap {
+    pub purchased: Option<SwapRoute>,
+    pub refunded: Option<SwapRoute>,
+}
+
+/// Exit proceeds waiting for the result of their swap.
+#[cw_serde]
+pub struct PendingExitSwap {
+    pub recipient: Addr,
+    /// Raw proceeds, sent as is if the swap fails.
+    pub token_in: Coin,
+    pub token_out_denom: String,
+}
+
+// Reply id -> pending swap, purchased and refunded tokens of an exit swap in the same response
+pub const PENDING_EXIT_SWAPS: Map<u64, PendingExitSwap> = Map::new("pending_exit_swaps");
+
+pub fn validate_exit_swap(exit_swap: &ExitSwap) -> Result<(), ContractError> {
+    for swap in [&exit_swap.purchased, &exit_swap.refunded]
+        .into_iter()
+        .flatten()
+    {
+        if swap.routes.is_empty() || swap.min_out_amount.is_zero() {
+            return Err(ContractError::InvalidExitSwap {});
+        }
+    }
+    Ok(())
+}
+
+/// Builds the message delivering `amount` to `recipient`, through a poolmanager swap if a route
+/// is given.
+pub fn exit_swap_msg(
+    deps: DepsMut,
+    env: &Env,
+    swap: Option<&SwapRoute>,
+    reply_id: u64,
+    recipient: &Addr,
+    amount: Coin,
+) -> Result<SubMsg, ContractError> {
+    let swap = match swap {
+        Some(swap) if !amount.amount.is_zero() => swap,
+        _ => {
+            return Ok(SubMsg::new(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![amount],
+            }))
+        }
+    };
+    let token_out_denom = swap
+        .routes
+        .last()
+        .ok_or(ContractError::InvalidExitSwap {})?
+        .token_out_denom
+        .clone();
+    PENDING_EXIT_SWAPS.save(
+        deps.storage,
+        reply_id,
+        &PendingExitSwap {
+            recipient: recipient.clone(),
+            token_in: amount.clone(),
+            token_out_denom,
+        },
+    )?;
+    let swap_msg = MsgSwapExactAmountIn {
+        sender: env.contract.address.to_string(),
+        routes: swap.routes.clone(),
+        token_in: Some(amount.into()),
+        token_out_min_amount: swap.min_out_amount.to_string(),
+    };
+    Ok(SubMsg::reply_always(swap_msg, reply_id))
+}
+
+pub fn reply_exit_swap(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let pending = PENDING_EXIT_SWAPS.load(deps.storage, msg.id)?;
+    PENDING_EXIT_SWAPS.remove(deps.storage, msg.id);
+
+    let (sent, swapped) = match msg.result {
+        SubMsgResult::Ok(res) => {
+            let data = res.data.ok_or(ContractError::InvalidExitSwapReply {})?;
+            let res: MsgSwapExactAmountInResponse = data.try_into()?;
+            let amount = Uint128::from_str(&res.token_out_amount)?;
+            (Coin::new(amount.u128(), pending.token_out_denom), true)
+        }
+        SubMsgResult::Err(_) => (pending.token_in, false),
+    };
+
+    let attrs = vec![
+        attr("action", "exit_swap"),
+        attr("swapped", swapped.to_string()),
+        attr("recipient", pending.recipient.to_string()),
+        attr("sent", sent.to_string()),
+    ];
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: pending.recipient.to_string(),
+            amount: vec![sent],
+        })
+        .add_attributes(attrs))
+}
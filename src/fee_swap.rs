@@ -0,0 +1,173 @@
+use crate::contract::save_config_update;
+use crate::state::{Config, ConfigUpdate, FeeSwap, CONFIG};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, BankMsg, Coin, Decimal256, Deps, DepsMut, Env, MessageInfo, Reply, Response, SubMsg,
+    SubMsgResult, Uint128, Uint256,
+};
+use cw_storage_plus::Item;
+use osmosis_std::shim::Timestamp;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse,
+};
+use osmosis_std::types::osmosis::twap::v1beta1::TwapQuerier;
+use std::str::FromStr;
+
+pub const FEE_SWAP_REPLY_ID: u64 = 1;
+
+/// Swap fee waiting for the result of its poolmanager swap.
+#[cw_serde]
+pub struct PendingFeeSwap {
+    /// Original swap fee, sent to the fee collector as is if the swap fails.
+    pub fee: Coin,
+    pub target_denom: String,
+}
+
+pub const PENDING_FEE_SWAP: Item<PendingFeeSwap> = Item::new("pending_fee_swap");
+
+pub fn validate_fee_swap(fee_swap: &FeeSwap) -> Result<(), ContractError> {
+    // slippage can not be equal to or greater than 1
+    if fee_swap.max_slippage >= Decimal256::one() || fee_swap.twap_window.is_zero() {
+        return Err(ContractError::InvalidFeeSwap {});
+    }
+    // last route must end in the target denom
+    match fee_swap.routes.last() {
+        Some(route) if route.token_out_denom == fee_swap.target_denom => Ok(()),
+        _ => Err(ContractError::InvalidFeeSwap {}),
+    }
+}
+
+pub fn execute_update_fee_swap(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    fee_swap: Option<FeeSwap>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(fee_swap) = &fee_swap {
+        validate_fee_swap(fee_swap)?;
+    }
+    let target_denom = fee_swap
+        .as_ref()
+        .map(|f| f.target_denom.clone())
+        .unwrap_or_default();
+    let apply_after = save_config_update(
+        deps,
+        &env,
+        config,
+        ConfigUpdate {
+            fee_swap: Some(fee_swap),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_fee_swap")
+        .add_attribute("target_denom", target_denom)
+        .add_attributes(apply_after.map(|t| ("apply_after", t.to_string()))))
+}
+
+/// Builds the message delivering the swap fee to the fee collector.
+/// If a fee swap is configured, the fee is routed through poolmanager with a reply so the fee
+/// collector receives the raw fee in case the swap fails. The minimum output is bounded by the
+/// pool TWAPs, a price moved within the finalize block does not lower it.
+pub fn fee_collection_msg(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    fee: Coin,
+) -> Result<SubMsg, ContractError> {
+    let fallback = SubMsg::new(BankMsg::Send {
+        to_address: config.fee_collector.to_string(),
+        amount: vec![fee.clone()],
+    });
+    let fee_swap = match &config.fee_swap {
+        Some(fee_swap) if fee_swap.target_denom != fee.denom && !fee.amount.is_zero() => fee_swap,
+        _ => return Ok(fallback),
+    };
+
+    let token_out_min = match twap_amount_out(deps.as_ref(), env, fee_swap, &fee) {
+        Some(twap_out) => (Decimal256::one() - fee_swap.max_slippage) * twap_out,
+        // Swapping without a TWAP would leave the fee unprotected against slippage
+        None => return Ok(fallback),
+    };
+    if token_out_min.is_zero() {
+        return Ok(fallback);
+    }
+
+    PENDING_FEE_SWAP.save(
+        deps.storage,
+        &PendingFeeSwap {
+            fee: fee.clone(),
+            target_denom: fee_swap.target_denom.clone(),
+        },
+    )?;
+    let swap_msg = MsgSwapExactAmountIn {
+        sender: env.contract.address.to_string(),
+        routes: fee_swap.routes.clone(),
+        token_in: Some(fee.into()),
+        token_out_min_amount: token_out_min.to_string(),
+    };
+    Ok(SubMsg::reply_always(swap_msg, FEE_SWAP_REPLY_ID))
+}
+
+/// Converts the fee through the routes at the arithmetic TWAP of each pool over `twap_window`.
+fn twap_amount_out(deps: Deps, env: &Env, fee_swap: &FeeSwap, fee: &Coin) -> Option<Uint256> {
+    let start_time = env.block.time.minus_seconds(fee_swap.twap_window.u64());
+    let start_time = Timestamp {
+        seconds: start_time.seconds() as i64,
+        nanos: start_time.subsec_nanos() as i32,
+    };
+    let querier = TwapQuerier::new(&deps.querier);
+    let mut denom = fee.denom.clone();
+    let mut amount = Decimal256::from_ratio(fee.amount, 1u128);
+    for route in &fee_swap.routes {
+        // price of the base asset in the quote asset
+        let twap = querier
+            .arithmetic_twap_to_now(
+                route.pool_id,
+                denom,
+                route.token_out_denom.clone(),
+                Some(start_time.clone()),
+            )
+            .ok()?;
+        amount = amount
+            .checked_mul(Decimal256::from_str(&twap.arithmetic_twap).ok()?)
+            .ok()?;
+        denom = route.token_out_denom.clone();
+    }
+    Some(amount * Uint256::one())
+}
+
+pub fn reply_fee_swap(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let pending = PENDING_FEE_SWAP.load(deps.storage)?;
+    PENDING_FEE_SWAP.remove(deps.storage);
+    let config = CONFIG.load(deps.storage)?;
+
+    let (collected, swapped) = match msg.result {
+        SubMsgResult::Ok(res) => {
+            let data = res.data.ok_or(ContractError::InvalidFeeSwapReply {})?;
+            let res: MsgSwapExactAmountInResponse = data.try_into()?;
+            let amount = Uint128::from_str(&res.token_out_amount)?;
+            (Coin::new(amount.u128(), pending.target_denom), true)
+        }
+        SubMsgResult::Err(_) => (pending.fee, false),
+    };
+
+    let attrs = vec![
+        attr("action", "fee_swap"),
+        attr("swapped", swapped.to_string()),
+        attr("fee_collector", config.fee_collector.to_string()),
+        attr("collected", collected.to_string()),
+    ];
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: config.fee_collector.to_string(),
+            amount: vec![collected],
+        })
+        .add_attributes(attrs))
+}
@@ -0,0 +1,73 @@
+use crate::operations::ensure_operations;
+use crate::state::{StreamId, STREAMS};
+use crate::ContractError;
+use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Uint256};
+use cw_storage_plus::Map;
+
+// Stream id -> maximum in tokens the treasury receives. Spent tokens over the cap are refunded
+// to subscribers pro-rata to their spent amount on exit.
+pub const HARD_CAPS: Map<StreamId, Uint256> = Map::new("hard_caps");
+
+/// Returns the spent in tokens the treasury and fee collector are paid from.
+pub fn capped_spent_in(
+    storage: &dyn Storage,
+    stream_id: StreamId,
+    spent_in: Uint256,
+) -> StdResult<Uint256> {
+    Ok(match HARD_CAPS.may_load(storage, stream_id)? {
+        Some(hard_cap) => spent_in.min(hard_cap),
+        None => spent_in,
+    })
+}
+
+/// Returns the part of `position_spent` refunded because the stream spent more than its hard cap.
+pub fn hard_cap_refund(
+    storage: &dyn Storage,
+    stream_id: StreamId,
+    spent_in: Uint256,
+    position_spent: Uint256,
+) -> StdResult<Uint256> {
+    let capped = capped_spent_in(storage, stream_id, spent_in)?;
+    if capped == spent_in {
+        return Ok(Uint256::zero());
+    }
+    // refunds are rounded down so their sum never exceeds the excess
+    Ok(position_spent.multiply_ratio(spent_in - capped, spent_in))
+}
+
+/// Sets the hard cap of the stream, `None` removes it. Only the operations role can update, before
+/// the stream starts.
+pub fn execute_update_hard_cap(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    hard_cap: Option<Uint256>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if env.block.time >= stream.start_time {
+        return Err(ContractError::StreamIsStarted {});
+    }
+    match hard_cap {
+        Some(hard_cap) => {
+            if hard_cap.is_zero() {
+                return Err(ContractError::InvalidHardCap {});
+            }
+            HARD_CAPS.save(deps.storage, stream_id, &hard_cap)?;
+        }
+        None => HARD_CAPS.remove(deps.storage, stream_id),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_hard_cap")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute(
+            "hard_cap",
+            hard_cap.map(|c| c.to_string()).unwrap_or_default(),
+        ))
+}
+
+pub fn query_hard_cap(deps: Deps, stream_id: StreamId) -> StdResult<Option<Uint256>> {
+    HARD_CAPS.may_load(deps.storage, stream_id)
+}
@@ -1,5 +1,12 @@
+use crate::msg::{ConfigResponse, ExecuteMsg, PositionResponse, QueryMsg, StreamResponse};
 use crate::ContractError;
-use cosmwasm_std::{Decimal256, StdError, Uint128, Uint256};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Coin, CosmosMsg, Deps, QuerierWrapper, StdError, StdResult, Uint128,
+    Uint256, WasmMsg,
+};
+use osmosis_std::types::cosmos::bank::v1beta1::BankQuerier;
+use osmosis_std::types::ibc::applications::transfer::v1::{DenomTrace, TransferQuerier};
 use std::str::FromStr;
 
 /// Stream validation related constants
@@ -12,21 +19,96 @@ const MAX_URL_LENGTH: usize = 128;
 const SAFE_TEXT_CHARS: &str = "<>$!&?#()*+'-./\"";
 const SAFE_URL_CHARS: &str = "-_:/?#@!$&()*+,;=.~[]'%";
 
-// calculate the reward with decimal
-pub fn get_decimals(value: Decimal256) -> Result<Decimal256, ContractError> {
-    let stringed: &str = &value.to_string();
-    let parts: &[&str] = &stringed.split('.').collect::<Vec<&str>>();
-    match parts.len() {
-        1 => Ok(Decimal256::zero()),
-        2 => {
-            let decimals = Decimal256::from_str(&("0.".to_owned() + parts[1]))?;
-            Ok(decimals)
+/// Wrapper around a stream contract address building its messages and queries, so integrating
+/// contracts and multi-test suites do not hand-roll the JSON.
+#[cw_serde]
+pub struct StreamContract(pub Addr);
+
+impl StreamContract {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    pub fn call(&self, msg: ExecuteMsg, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg: to_json_binary(&msg)?,
+            funds,
         }
-        _ => Err(ContractError::InvalidDecimals {}),
+        .into())
+    }
+
+    /// Subscribes `funds` in tokens to the stream.
+    pub fn subscribe(&self, stream_id: u64, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::Subscribe {
+                stream_id,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            },
+            funds,
+        )
+    }
+
+    /// Withdraws unspent in tokens, all of them if `cap` is not set.
+    pub fn withdraw(&self, stream_id: u64, cap: Option<Uint256>) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::Withdraw {
+                stream_id,
+                cap,
+                operator_target: None,
+            },
+            vec![],
+        )
+    }
+
+    pub fn exit(&self, stream_id: u64) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::ExitStream {
+                stream_id,
+                operator_target: None,
+                remote_recipient: None,
+                liquid_stake: false,
+                swap: None,
+            },
+            vec![],
+        )
+    }
+
+    pub fn finalize(&self, stream_id: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::FinalizeStream { stream_id }, vec![])
+    }
+
+    pub fn query_config(&self, querier: &QuerierWrapper) -> StdResult<ConfigResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::Config {})
+    }
+
+    pub fn query_stream(
+        &self,
+        querier: &QuerierWrapper,
+        stream_id: u64,
+    ) -> StdResult<StreamResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::Stream { stream_id })
+    }
+
+    pub fn query_position(
+        &self,
+        querier: &QuerierWrapper,
+        stream_id: u64,
+        owner: impl Into<String>,
+    ) -> StdResult<PositionResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::Position {
+                stream_id,
+                owner: owner.into(),
+            },
+        )
     }
 }
 
-pub fn check_name_and_url(name: &String, url: &Option<String>) -> Result<(), ContractError> {
+pub fn check_name_and_url(name: &str, url: &Option<String>) -> Result<(), ContractError> {
     if name.len() < MIN_NAME_LENGTH {
         return Err(ContractError::StreamNameTooShort {});
     }
@@ -56,6 +138,33 @@ pub fn check_name_and_url(name: &String, url: &Option<String>) -> Result<(), Con
     Ok(())
 }
 
+/// Resolves an `ibc/{hash}` denom through the transfer module denom traces. Fails if the trace
+/// is unknown or the denom has no supply on chain. Returns None for non IBC denoms.
+pub fn validate_ibc_denom(deps: Deps, denom: &str) -> Result<Option<DenomTrace>, ContractError> {
+    let hash = match denom.strip_prefix("ibc/") {
+        Some(hash) => hash,
+        None => return Ok(None),
+    };
+    let invalid = || ContractError::InvalidIbcDenom {
+        denom: denom.to_string(),
+    };
+    let trace = TransferQuerier::new(&deps.querier)
+        .denom_trace(hash.to_string())
+        .ok()
+        .and_then(|res| res.denom_trace)
+        .ok_or_else(invalid)?;
+    let supply = BankQuerier::new(&deps.querier)
+        .supply_of(denom.to_string())?
+        .amount
+        .map(|coin| Uint128::from_str(&coin.amount))
+        .transpose()?
+        .unwrap_or_default();
+    if supply.is_zero() {
+        return Err(invalid());
+    }
+    Ok(Some(trace))
+}
+
 pub fn from_semver(err: semver::Error) -> ContractError {
     ContractError::from(StdError::generic_err(format!("Semver: {}", err)))
 }
@@ -63,3 +172,13 @@ pub fn from_semver(err: semver::Error) -> ContractError {
 pub fn to_uint256(value: Uint128) -> Uint256 {
     Uint256::from(value.u128())
 }
+
+/// Deserializes a present field into `Some`, so an explicit `null` unsets a config field while a
+/// missing field, defaulting to `None`, keeps it as is.
+pub fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    serde::Deserialize::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,307 @@
+use crate::contract::save_config_update;
+use crate::state::{
+    Config, ConfigUpdate, IbcTransfer, Status, StreamId, CONFIG, REMOTE_TREASURIES, STREAMS,
+};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, Addr, BankMsg, Coin, Deps, DepsMut, Env, MessageInfo, Order, Reply, Response, StdResult,
+    Storage, SubMsg, SubMsgResult, Uint128,
+};
+use cw_storage_plus::{Item, Map};
+use osmosis_std::types::ibc::applications::transfer::v1::{MsgTransfer, MsgTransferResponse};
+
+pub const IBC_TRANSFER_REPLY_ID: u64 = 4;
+const TRANSFER_PORT: &str = "transfer";
+
+/// Outgoing ICS-20 transfer, credited back locally if the packet fails or times out.
+#[cw_serde]
+pub struct OutgoingTransfer {
+    pub channel_id: String,
+    /// Local address the tokens are credited to if the transfer fails.
+    pub refund_address: Addr,
+    pub amount: Coin,
+}
+
+// Transfer waiting for its sequence number from the transfer reply
+pub const PENDING_IBC_TRANSFER: Item<OutgoingTransfer> = Item::new("pending_ibc_transfer");
+// (channel id, packet sequence) -> transfer waiting for its ack or timeout
+pub const IBC_TRANSFERS: Map<(&str, u64), OutgoingTransfer> = Map::new("ibc_transfers");
+// (refund address, denom) -> amount of failed transfers claimable by the address
+pub const IBC_REFUNDS: Map<(&Addr, &str), Uint128> = Map::new("ibc_refunds");
+// Denom -> total of the claimable refunds, so audits do not iterate every refund address
+pub const IBC_REFUND_TOTALS: Map<&str, Uint128> = Map::new("ibc_refund_totals");
+
+/// Lifecycle callback sent by the ibc-hooks middleware for transfers with an `ibc_callback` memo.
+#[cw_serde]
+pub enum IbcLifecycleComplete {
+    IbcAck {
+        channel: String,
+        sequence: u64,
+        ack: String,
+        success: bool,
+    },
+    IbcTimeout {
+        channel: String,
+        sequence: u64,
+    },
+}
+
+pub fn validate_ibc_transfer(ibc_transfer: &IbcTransfer) -> Result<(), ContractError> {
+    if ibc_transfer.channel_id.is_empty() || ibc_transfer.timeout_seconds.is_zero() {
+        return Err(ContractError::InvalidIbcTransfer {});
+    }
+    Ok(())
+}
+
+pub fn execute_update_ibc_transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ibc_transfer: Option<IbcTransfer>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(ibc_transfer) = &ibc_transfer {
+        validate_ibc_transfer(ibc_transfer)?;
+    }
+    let channel_id = ibc_transfer
+        .as_ref()
+        .map(|t| t.channel_id.clone())
+        .unwrap_or_default();
+    let apply_after = save_config_update(
+        deps,
+        &env,
+        config,
+        ConfigUpdate {
+            ibc_transfer: Some(ibc_transfer),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_ibc_transfer")
+        .add_attribute("channel_id", channel_id)
+        .add_attributes(apply_after.map(|t| ("apply_after", t.to_string()))))
+}
+
+/// Builds the transfer of `amount` to `receiver` over the configured channel. The ibc-hooks
+/// callback memo makes the chain report the packet outcome back through sudo, so a failed
+/// transfer can be claimed back by `refund_address`. Only one transfer can be sent per response.
+pub fn ibc_transfer_msg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &Config,
+    receiver: String,
+    amount: Coin,
+    refund_address: Addr,
+) -> Result<SubMsg, ContractError> {
+    let ibc_transfer = config
+        .ibc_transfer
+        .as_ref()
+        .ok_or(ContractError::IbcTransferDisabled {})?;
+    PENDING_IBC_TRANSFER.save(
+        storage,
+        &OutgoingTransfer {
+            channel_id: ibc_transfer.channel_id.clone(),
+            refund_address,
+            amount: amount.clone(),
+        },
+    )?;
+    let timeout = env
+        .block
+        .time
+        .plus_seconds(ibc_transfer.timeout_seconds.u64());
+    let transfer_msg = MsgTransfer {
+        source_port: TRANSFER_PORT.to_string(),
+        source_channel: ibc_transfer.channel_id.clone(),
+        token: Some(amount.into()),
+        sender: env.contract.address.to_string(),
+        receiver,
+        timeout_height: None,
+        timeout_timestamp: timeout.nanos(),
+        memo: format!(r#"{{"ibc_callback":"{}"}}"#, env.contract.address),
+    };
+    Ok(SubMsg::reply_on_success(
+        transfer_msg,
+        IBC_TRANSFER_REPLY_ID,
+    ))
+}
+
+/// Sends stream revenue to the treasury, over IBC if the stream has a remote treasury. Failed
+/// transfers are claimable by the local treasury.
+pub fn treasury_payout_msg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &Config,
+    stream_id: StreamId,
+    treasury: &Addr,
+    amount: Coin,
+) -> Result<SubMsg, ContractError> {
+    match REMOTE_TREASURIES.may_load(storage, stream_id)? {
+        Some(remote_treasury) => ibc_transfer_msg(
+            storage,
+            env,
+            config,
+            remote_treasury,
+            amount,
+            treasury.clone(),
+        ),
+        None => Ok(SubMsg::new(BankMsg::Send {
+            to_address: treasury.to_string(),
+            amount: vec![amount],
+        })),
+    }
+}
+
+/// Sets the remote chain address receiving revenue of the stream. Only treasury can update,
+/// before the stream is finalized.
+pub fn execute_update_remote_treasury(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    remote_treasury: Option<String>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.treasury != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if stream.status == Status::Finalized {
+        return Err(ContractError::StreamAlreadyFinalized {});
+    }
+    match &remote_treasury {
+        Some(remote_treasury) => {
+            if CONFIG.load(deps.storage)?.ibc_transfer.is_none() {
+                return Err(ContractError::IbcTransferDisabled {});
+            }
+            REMOTE_TREASURIES.save(deps.storage, stream_id, remote_treasury)?;
+        }
+        None => REMOTE_TREASURIES.remove(deps.storage, stream_id),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_remote_treasury")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("remote_treasury", remote_treasury.unwrap_or_default()))
+}
+
+pub fn reply_ibc_transfer(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let transfer = PENDING_IBC_TRANSFER.load(deps.storage)?;
+    PENDING_IBC_TRANSFER.remove(deps.storage);
+    let sequence = match msg.result {
+        SubMsgResult::Ok(res) => {
+            let data = res.data.ok_or(ContractError::InvalidIbcTransferReply {})?;
+            let res: MsgTransferResponse = data.try_into()?;
+            res.sequence
+        }
+        SubMsgResult::Err(_) => return Err(ContractError::InvalidIbcTransferReply {}),
+    };
+    IBC_TRANSFERS.save(deps.storage, (&transfer.channel_id, sequence), &transfer)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "ibc_transfer"),
+        attr("channel_id", transfer.channel_id),
+        attr("sequence", sequence.to_string()),
+        attr("amount", transfer.amount.to_string()),
+    ]))
+}
+
+pub fn sudo_ibc_lifecycle_complete(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcLifecycleComplete,
+) -> Result<Response, ContractError> {
+    let (channel, sequence, success) = match msg {
+        IbcLifecycleComplete::IbcAck {
+            channel,
+            sequence,
+            success,
+            ..
+        } => (channel, sequence, success),
+        IbcLifecycleComplete::IbcTimeout { channel, sequence } => (channel, sequence, false),
+    };
+    let transfer = match IBC_TRANSFERS.may_load(deps.storage, (&channel, sequence))? {
+        Some(transfer) => transfer,
+        // not a transfer of this contract
+        None => return Ok(Response::new()),
+    };
+    IBC_TRANSFERS.remove(deps.storage, (&channel, sequence));
+
+    let attrs = vec![
+        attr("action", "ibc_lifecycle_complete"),
+        attr("channel_id", channel),
+        attr("sequence", sequence.to_string()),
+        attr("success", success.to_string()),
+    ];
+    if success {
+        return Ok(Response::new().add_attributes(attrs));
+    }
+    // Sending the refund right away would fail the callback if the refund address can not
+    // receive, so the tokens are kept claimable instead.
+    IBC_REFUNDS.update(
+        deps.storage,
+        (&transfer.refund_address, &transfer.amount.denom),
+        |refund| -> StdResult<_> {
+            Ok(refund
+                .unwrap_or_default()
+                .checked_add(transfer.amount.amount)?)
+        },
+    )?;
+    IBC_REFUND_TOTALS.update(
+        deps.storage,
+        &transfer.amount.denom,
+        |total| -> StdResult<_> {
+            Ok(total
+                .unwrap_or_default()
+                .checked_add(transfer.amount.amount)?)
+        },
+    )?;
+    Ok(Response::new().add_attributes(attrs).add_attributes(vec![
+        attr("refund_address", transfer.refund_address),
+        attr("refund", transfer.amount.to_string()),
+    ]))
+}
+
+pub fn execute_claim_ibc_refunds(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let refunds = IBC_REFUNDS
+        .prefix(&info.sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin::new(amount.u128(), denom)))
+        .collect::<StdResult<Vec<_>>>()?;
+    if refunds.is_empty() {
+        return Err(ContractError::NoIbcRefunds {});
+    }
+    for refund in &refunds {
+        IBC_REFUNDS.remove(deps.storage, (&info.sender, &refund.denom));
+        let total = IBC_REFUND_TOTALS.load(deps.storage, &refund.denom)?;
+        IBC_REFUND_TOTALS.save(
+            deps.storage,
+            &refund.denom,
+            &total.checked_sub(refund.amount)?,
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "claim_ibc_refunds")
+        .add_attribute("owner", info.sender.to_string())
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: refunds,
+        }))
+}
+
+pub fn query_ibc_refunds(deps: Deps, address: String) -> StdResult<Vec<Coin>> {
+    let address = deps.api.addr_validate(&address)?;
+    IBC_REFUNDS
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin::new(amount.u128(), denom)))
+        .collect()
+}
@@ -0,0 +1,124 @@
+use crate::contract::save_config_update;
+use crate::state::{ConfigUpdate, KeeperBounty, Status, StreamId, CONFIG, STREAMS};
+use crate::ContractError;
+use cosmwasm_std::{
+    Addr, BankMsg, Coin, Decimal256, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage,
+    Uint128, Uint256,
+};
+use cw_storage_plus::Map;
+
+// (stream id, keeper) -> bounty earned by the keeper, claimable once the stream is finalized
+pub const KEEPER_BOUNTIES: Map<(StreamId, &Addr), Uint256> = Map::new("keeper_bounties");
+// Stream id -> total bounty credited to keepers, deducted from the swap fee at finalize
+pub const STREAM_KEEPER_BOUNTIES: Map<StreamId, Uint256> = Map::new("stream_keeper_bounties");
+
+pub fn validate_keeper_bounty(keeper_bounty: &KeeperBounty) -> Result<(), ContractError> {
+    if keeper_bounty.fee_share.is_zero()
+        || keeper_bounty.fee_share > Decimal256::one()
+        || keeper_bounty.min_blocks.is_zero()
+    {
+        return Err(ContractError::InvalidKeeperBounty {});
+    }
+    Ok(())
+}
+
+pub fn execute_update_keeper_bounty(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    keeper_bounty: Option<KeeperBounty>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(keeper_bounty) = &keeper_bounty {
+        validate_keeper_bounty(keeper_bounty)?;
+    }
+    let fee_share = keeper_bounty
+        .as_ref()
+        .map(|b| b.fee_share.to_string())
+        .unwrap_or_default();
+    let apply_after = save_config_update(
+        deps,
+        &env,
+        config,
+        ConfigUpdate {
+            keeper_bounty: Some(keeper_bounty),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_keeper_bounty")
+        .add_attribute("fee_share", fee_share)
+        .add_attributes(apply_after.map(|t| ("apply_after", t.to_string()))))
+}
+
+/// Credits `keeper` with its share of the swap fee accrued by an update, if the stream was not
+/// saved within the last `min_blocks`. Returns the credited bounty.
+pub fn accrue_keeper_bounty(
+    storage: &mut dyn Storage,
+    keeper_bounty: &KeeperBounty,
+    stream_id: StreamId,
+    keeper: &Addr,
+    last_saved_height: Option<u64>,
+    height: u64,
+    accrued_fee: Uint256,
+) -> StdResult<Uint256> {
+    // streams saved before update heights were recorded are eligible
+    if last_saved_height.is_some_and(|last| height <= last + keeper_bounty.min_blocks.u64()) {
+        return Ok(Uint256::zero());
+    }
+    let bounty = Decimal256::from_ratio(accrued_fee, Uint256::one())
+        .checked_mul(keeper_bounty.fee_share)?
+        * Uint256::one();
+    if bounty.is_zero() {
+        return Ok(bounty);
+    }
+    KEEPER_BOUNTIES.update(storage, (stream_id, keeper), |b| -> StdResult<_> {
+        Ok(b.unwrap_or_default().checked_add(bounty)?)
+    })?;
+    STREAM_KEEPER_BOUNTIES.update(storage, stream_id, |b| -> StdResult<_> {
+        Ok(b.unwrap_or_default().checked_add(bounty)?)
+    })?;
+    Ok(bounty)
+}
+
+/// Sends the sender its bounty earned on a finalized stream. Bounties of cancelled streams are
+/// forfeited, as the spent tokens are refunded to the subscribers.
+pub fn execute_claim_keeper_bounty(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.status != Status::Finalized {
+        return Err(ContractError::StreamNotFinalized {});
+    }
+    let bounty = KEEPER_BOUNTIES
+        .may_load(deps.storage, (stream_id, &info.sender))?
+        .ok_or(ContractError::NoKeeperBounty {})?;
+    KEEPER_BOUNTIES.remove(deps.storage, (stream_id, &info.sender));
+
+    Ok(Response::new()
+        .add_attribute("action", "claim_keeper_bounty")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("keeper", info.sender.to_string())
+        .add_attribute("bounty", bounty)
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin {
+                denom: stream.in_denom,
+                amount: Uint128::try_from(bounty)?,
+            }],
+        }))
+}
+
+pub fn query_keeper_bounty(deps: Deps, stream_id: StreamId, keeper: String) -> StdResult<Uint256> {
+    let keeper = deps.api.addr_validate(&keeper)?;
+    Ok(KEEPER_BOUNTIES
+        .may_load(deps.storage, (stream_id, &keeper))?
+        .unwrap_or_default())
+}
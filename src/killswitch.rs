@@ -1,16 +1,46 @@
-use crate::contract::{update_position, update_stream};
-use crate::state::{Status, Stream, CONFIG, POSITIONS, STREAMS};
+use crate::contract::{save_config_update, update_position, update_stream};
+use crate::events::{Cancelled, Exited, Paused, Resumed, Withdrawn};
+use crate::operations::{ensure_operations, stream_operations};
+use crate::partial_refund::PARTIAL_REFUNDS;
+use crate::pool::release_pool_escrow;
+use crate::price_history::save_price_snapshot;
+use crate::state::{
+    save_stream, ConfigUpdate, GuardianSet, Status, Stream, StreamId, CANCEL_CONFIRMATIONS, CONFIG,
+    CREATOR_PAUSES, GUARDIANS, POSITIONS, STREAMS,
+};
+use crate::stats::{record_exit, record_fee, record_withdrawal};
 use crate::threshold::{ThresholdError, ThresholdState};
+use crate::vesting::STREAM_VESTINGS;
 use crate::ContractError;
+use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    attr, BankMsg, Coin, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdResult, Timestamp,
-    Uint128, Uint256,
+    attr, Addr, BankMsg, Coin, CosmosMsg, Decimal256, Deps, DepsMut, Env, MessageInfo, Order,
+    Response, StdResult, Storage, Timestamp, Uint128, Uint256,
 };
+use cw_storage_plus::{Bound, Map};
 use cw_utils::maybe_addr;
 
+// Bounds the number of streams scanned by batch sudo calls and positions refunded per batch exit
+const MAX_BATCH_LIMIT: u32 = 100;
+const DEFAULT_BATCH_LIMIT: u32 = 30;
+
+/// Totals of positions exited while their stream was paused.
+#[cw_serde]
+#[derive(Default)]
+pub struct PausedExits {
+    /// Out tokens sent to the exited positions.
+    pub purchased: Uint256,
+    /// In tokens spent by the exited positions, still counted in the stream `spent_in`.
+    pub spent: Uint256,
+}
+
+// Stream id -> totals of positions exited while paused, settled if the stream is cancelled and
+// cleared at finalize
+pub const PAUSED_EXITS: Map<StreamId, PausedExits> = Map::new("paused_exits");
+
 pub fn execute_withdraw_paused(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     stream_id: u64,
     cap: Option<Uint256>,
@@ -26,11 +56,7 @@ pub fn execute_withdraw_paused(
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
     let mut position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
-    if position.owner != info.sender
-        && position
-            .operator
-            .as_ref()
-            .map_or(true, |o| o != &info.sender)
+    if position.owner != info.sender && position.operator.as_ref().is_none_or(|o| o != info.sender)
     {
         return Err(ContractError::Unauthorized {});
     }
@@ -58,15 +84,16 @@ pub fn execute_withdraw_paused(
     let shares_amount = if withdraw_amount == position.in_balance {
         position.shares
     } else {
-        stream.compute_shares_amount(withdraw_amount, true)
+        stream.compute_shares_amount(withdraw_amount, true)?
     };
 
     stream.in_supply = stream.in_supply.checked_sub(withdraw_amount)?;
     stream.shares = stream.shares.checked_sub(shares_amount)?;
     position.in_balance = position.in_balance.checked_sub(withdraw_amount)?;
     position.shares = position.shares.checked_sub(shares_amount)?;
+    record_withdrawal(deps.storage, stream_id, withdraw_amount)?;
 
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
     POSITIONS.save(deps.storage, (stream_id, &position.owner), &position)?;
 
     let attributes = vec![
@@ -75,6 +102,11 @@ pub fn execute_withdraw_paused(
         attr("operator_target", operator_target.clone()),
         attr("withdraw_amount", withdraw_amount),
     ];
+    let event = Withdrawn {
+        stream_id,
+        owner: position.owner.clone(),
+        amount: withdraw_amount,
+    };
     let withdraw_amount_u128: Uint128 = withdraw_amount.to_string().parse().unwrap();
     // send funds to withdraw address or to the sender
     let res = Response::new()
@@ -85,12 +117,15 @@ pub fn execute_withdraw_paused(
                 amount: withdraw_amount_u128,
             }],
         }))
-        .add_attributes(attributes);
+        .add_attributes(attributes)
+        .add_event(event.into());
 
     Ok(res)
 }
 
-pub fn execute_exit_cancelled(
+/// Exits a position of a stream paused for longer than `max_pause_duration`. Purchased tokens are
+/// calculated at the pause date.
+pub fn execute_exit_paused(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
@@ -98,49 +133,123 @@ pub fn execute_exit_cancelled(
     operator_target: Option<String>,
 ) -> Result<Response, ContractError> {
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    let cfg = CONFIG.load(deps.storage)?;
+    if !stream.is_paused() {
+        return Err(ContractError::StreamNotPaused {});
+    }
+    // threshold streams refund spent tokens if cancelled, purchases can only be paid at finalize
+    if ThresholdState::new().check_if_threshold_set(stream_id, deps.storage)? {
+        return Err(ContractError::ThresholdExitPaused {});
+    }
+    // vesting contracts are derived from the stream and owner, purchases vest with a single exit
+    if STREAM_VESTINGS.has(deps.storage, stream_id) {
+        return Err(ContractError::VestingExitPaused {});
+    }
+    // ok to use unwrap here
+    let pause_date = stream.pause_date.unwrap();
+    match cfg.max_pause_duration {
+        Some(max_pause_duration)
+            if env.block.time >= pause_date.plus_seconds(max_pause_duration.u64()) => {}
+        _ => return Err(ContractError::PauseNotExpired {}),
+    }
 
-    // This execution requires the stream to be cancelled or
-    // the stream to be ended and the threshold not reached.
+    let operator_target =
+        maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
+    let mut position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
+    if position.owner != info.sender && position.operator.as_ref().is_none_or(|o| o != info.sender)
+    {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    // check if stream is cancelled
-    if !stream.is_cancelled() {
-        let threshold_state = ThresholdState::new();
-        // Threshold should be set
-        let is_set = threshold_state.check_if_threshold_set(stream_id, deps.storage)?;
-        if !is_set {
-            return Err(ContractError::StreamNotCancelled {});
-        }
+    // stream is updated at pause, position is synced to the pause state
+    update_position(
+        stream.dist_index,
+        stream.shares,
+        stream.last_updated,
+        stream.in_supply,
+        &mut position,
+    )?;
 
-        // Stream should not be paused
-        // If stream paused now_block can exceed end_block
-        // Stream being appeared as ended only happens when its paused or cancelled
-        if stream.is_paused() == true {
-            return Err(ContractError::StreamNotCancelled {});
-        }
-        // Stream should be ended
-        if stream.end_time > env.block.time {
-            return Err(ContractError::StreamNotCancelled {});
-        }
-        // Update stream before checking threshold
-        update_stream(env.block.time, &mut stream)?;
-        threshold_state.error_if_reached(stream_id, deps.storage, &stream)?;
+    // stream might be resumed later, remove the position from its supply. Spent tokens stay in
+    // `spent_in`, the swap fee is taken from them at finalize or cancel.
+    stream.in_supply = stream.in_supply.checked_sub(position.in_balance)?;
+    stream.shares = stream.shares.checked_sub(position.shares)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    POSITIONS.remove(deps.storage, (stream_id, &position.owner))?;
+    record_exit(deps.storage, stream_id)?;
+    let mut paused_exits = PAUSED_EXITS
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default();
+    paused_exits.purchased = paused_exits.purchased.checked_add(position.purchased)?;
+    paused_exits.spent = paused_exits.spent.checked_add(position.spent)?;
+    PAUSED_EXITS.save(deps.storage, stream_id, &paused_exits)?;
+    let swap_fee = Decimal256::from_ratio(position.spent, Uint256::one())
+        .checked_mul(stream.stream_exit_fee_percent)?
+        * Uint256::one();
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !position.purchased.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: operator_target.to_string(),
+            amount: vec![Coin {
+                denom: stream.out_denom,
+                amount: Uint128::try_from(position.purchased)?,
+            }],
+        }));
     }
+    if !position.in_balance.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: operator_target.to_string(),
+            amount: vec![Coin {
+                denom: stream.in_denom,
+                amount: Uint128::try_from(position.in_balance)?,
+            }],
+        }));
+    }
+
+    let attributes = vec![
+        attr("action", "exit_paused"),
+        attr("stream_id", stream_id.to_string()),
+        attr("operator_target", operator_target),
+        attr("purchased", position.purchased),
+        attr("unspent", position.in_balance),
+        attr("swap_fee", swap_fee),
+    ];
+    let event = Exited {
+        stream_id,
+        owner: position.owner,
+        purchased: position.purchased,
+        refunded: position.in_balance,
+        swap_fee,
+    };
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(attributes)
+        .add_event(event.into()))
+}
+
+pub fn execute_exit_cancelled(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+    operator_target: Option<String>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_exitable_cancelled(deps.storage, &env, stream_id, stream.clone())?;
 
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
     let position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
-    if position.owner != info.sender
-        && position
-            .operator
-            .as_ref()
-            .map_or(true, |o| o != &info.sender)
+    if position.owner != info.sender && position.operator.as_ref().is_none_or(|o| o != info.sender)
     {
         return Err(ContractError::Unauthorized {});
     }
 
     // no need to update position here, we just need to return total balance
     let total_balance = position.in_balance + position.spent;
-    POSITIONS.remove(deps.storage, (stream_id, &position.owner));
+    POSITIONS.remove(deps.storage, (stream_id, &position.owner))?;
+    record_exit(deps.storage, stream_id)?;
 
     let attributes = vec![
         attr("action", "withdraw_cancelled"),
@@ -148,6 +257,13 @@ pub fn execute_exit_cancelled(
         attr("operator_target", operator_target.clone()),
         attr("total_balance", total_balance),
     ];
+    let event = Exited {
+        stream_id,
+        owner: position.owner,
+        purchased: Uint256::zero(),
+        refunded: total_balance,
+        swap_fee: Uint256::zero(),
+    };
     let total_balance_u128: Uint128 = total_balance.to_string().parse().unwrap();
     // send funds to withdraw address or to the sender
     let res = Response::new()
@@ -158,19 +274,110 @@ pub fn execute_exit_cancelled(
                 amount: total_balance_u128,
             }],
         }))
-        .add_attributes(attributes);
+        .add_attributes(attributes)
+        .add_event(event.into());
 
     Ok(res)
 }
 
+/// Refunds up to `limit` positions of a cancelled stream, or an ended stream that missed its
+/// threshold, to their owners. Anyone can call, to unwind streams with many positions.
+pub fn execute_batch_exit_cancelled(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    stream_id: u64,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_exitable_cancelled(deps.storage, &env, stream_id, stream.clone())?;
+
+    let limit = limit.unwrap_or(DEFAULT_BATCH_LIMIT).min(MAX_BATCH_LIMIT) as usize;
+    let positions = POSITIONS
+        .prefix(stream_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut res = Response::new();
+    let mut refunded = Uint256::zero();
+    for (owner, position) in &positions {
+        let total_balance = position.in_balance.checked_add(position.spent)?;
+        POSITIONS.remove(deps.storage, (stream_id, owner))?;
+        record_exit(deps.storage, stream_id)?;
+        refunded = refunded.checked_add(total_balance)?;
+
+        let event = Exited {
+            stream_id,
+            owner: owner.clone(),
+            purchased: Uint256::zero(),
+            refunded: total_balance,
+            swap_fee: Uint256::zero(),
+        };
+        res = res.add_event(event.into());
+        if !total_balance.is_zero() {
+            res = res.add_message(BankMsg::Send {
+                to_address: owner.to_string(),
+                amount: vec![Coin {
+                    denom: stream.in_denom.clone(),
+                    amount: Uint128::try_from(total_balance)?,
+                }],
+            });
+        }
+    }
+
+    Ok(res.add_attributes(vec![
+        attr("action", "batch_exit_cancelled"),
+        attr("stream_id", stream_id.to_string()),
+        attr("exited", positions.len().to_string()),
+        attr("refunded", refunded),
+    ]))
+}
+
+/// Fails unless the stream is cancelled, or ended with its threshold not reached.
+fn ensure_exitable_cancelled(
+    storage: &dyn Storage,
+    env: &Env,
+    stream_id: u64,
+    mut stream: Stream,
+) -> Result<(), ContractError> {
+    // This execution requires the stream to be cancelled or
+    // the stream to be ended and the threshold not reached.
+
+    // check if stream is cancelled
+    if !stream.is_cancelled() {
+        let threshold_state = ThresholdState::new();
+        // Threshold should be set
+        let is_set = threshold_state.check_if_threshold_set(stream_id, storage)?;
+        // Streams refunding partially exit with the partial fill after finalize
+        if !is_set || PARTIAL_REFUNDS.has(storage, stream_id) {
+            return Err(ContractError::StreamNotCancelled {});
+        }
+
+        // Stream should not be paused
+        // If stream paused now_block can exceed end_block
+        // Stream being appeared as ended only happens when its paused or cancelled
+        if stream.is_paused() {
+            return Err(ContractError::StreamNotCancelled {});
+        }
+        // Stream should be ended
+        if stream.end_time > env.block.time {
+            return Err(ContractError::StreamNotCancelled {});
+        }
+        // Update stream before checking threshold
+        update_stream(env.block.time, &mut stream)?;
+        threshold_state.error_if_reached(stream_id, storage, &stream)?;
+    }
+    Ok(())
+}
+
 pub fn execute_pause_stream(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     stream_id: u64,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    if info.sender != config.protocol_admin {
+    // Any guardian can pause, protocol admin pauses if no guardian set is configured
+    if !is_killswitch_authority(deps.as_ref(), &info.sender)? {
         return Err(ContractError::Unauthorized {});
     }
     //check if stream is ended
@@ -190,13 +397,117 @@ pub fn execute_pause_stream(
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
     update_stream(env.block.time, &mut stream)?;
     pause_stream(env.block.time, &mut stream)?;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    save_price_snapshot(deps.storage, stream_id, &stream)?;
 
     Ok(Response::default()
         .add_attribute("action", "pause_stream")
         .add_attribute("stream_id", stream_id.to_string())
         .add_attribute("is_paused", "true")
-        .add_attribute("pause_date", env.block.time.to_string()))
+        .add_attribute("pause_date", env.block.time.to_string())
+        .add_event(
+            Paused {
+                stream_id,
+                pause_date: env.block.time,
+            }
+            .into(),
+        ))
+}
+
+/// Pauses the stream on behalf of its operations role. Kept apart from protocol admin pause, a creator
+/// pause can be used once per stream and is bounded by `max_creator_pause_duration`.
+pub fn execute_creator_pause_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.max_creator_pause_duration.is_none() {
+        return Err(ContractError::CreatorPauseDisabled {});
+    }
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if CREATOR_PAUSES.has(deps.storage, stream_id) {
+        return Err(ContractError::CreatorPauseUsed {});
+    }
+    if env.block.time >= stream.end_time {
+        return Err(ContractError::StreamEnded {});
+    }
+    // check if stream is not started
+    if env.block.time < stream.start_time {
+        return Err(ContractError::StreamNotStarted {});
+    }
+    // paused or cancelled can not be paused
+    if stream.is_killswitch_active() {
+        return Err(ContractError::StreamKillswitchActive {});
+    }
+    update_stream(env.block.time, &mut stream)?;
+    pause_stream(env.block.time, &mut stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    save_price_snapshot(deps.storage, stream_id, &stream)?;
+    CREATOR_PAUSES.save(deps.storage, stream_id, &env.block.time)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "creator_pause_stream")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("is_paused", "true")
+        .add_attribute("pause_date", env.block.time.to_string())
+        .add_event(
+            Paused {
+                stream_id,
+                pause_date: env.block.time,
+            }
+            .into(),
+        ))
+}
+
+pub fn execute_creator_resume_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    //Cancelled can't be resumed
+    if stream.is_cancelled() {
+        return Err(ContractError::StreamIsCancelled {});
+    }
+    if !stream.is_paused() {
+        return Err(ContractError::StreamNotPaused {});
+    }
+    // Only the creator pause can be resumed here, protocol admin pause stays untouched
+    let creator_pause_date = CREATOR_PAUSES.may_load(deps.storage, stream_id)?;
+    if creator_pause_date != stream.pause_date {
+        return Err(ContractError::NotCreatorPaused {});
+    }
+    if stream_operations(deps.storage, stream_id, &stream)? != info.sender {
+        // ok to use unwrap here
+        let pause_date = stream.pause_date.unwrap();
+        let expired = config
+            .max_creator_pause_duration
+            .is_none_or(|max| env.block.time >= pause_date.plus_seconds(max.u64()));
+        if !expired {
+            return Err(ContractError::Unauthorized {});
+        }
+    }
+    resume_stream(env.block.time, &mut stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    CANCEL_CONFIRMATIONS.remove(deps.storage, stream_id);
+
+    Ok(Response::default()
+        .add_attribute("action", "creator_resume_stream")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("new_end_date", stream.end_time.to_string())
+        .add_attribute("status", "active")
+        .add_event(
+            Resumed {
+                stream_id,
+                end_time: stream.end_time,
+            }
+            .into(),
+        ))
 }
 
 pub fn pause_stream(now: Timestamp, stream: &mut Stream) -> StdResult<()> {
@@ -220,7 +531,7 @@ pub fn execute_resume_stream(
     if stream.status != Status::Paused {
         return Err(ContractError::StreamNotPaused {});
     }
-    if cfg.protocol_admin != info.sender {
+    if !cfg.is_killswitch_controller(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
 
@@ -234,61 +545,329 @@ pub fn execute_resume_stream(
         .plus_nanos(env.block.time.nanos() - pause_date.nanos());
 
     stream.status = Status::Active;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    CANCEL_CONFIRMATIONS.remove(deps.storage, stream_id);
 
     let attributes = vec![
         attr("action", "resume_stream"),
         attr("stream_id", stream_id.to_string()),
     ];
-    Ok(Response::default().add_attributes(attributes))
+    let event = Resumed {
+        stream_id,
+        end_time: stream.end_time,
+    };
+    Ok(Response::default()
+        .add_attributes(attributes)
+        .add_event(event.into()))
+}
+
+/// Resumes a stream paused for longer than the configured `max_pause_duration`, so funds can not be
+/// frozen indefinitely by an inactive admin.
+pub fn execute_resume_expired_pause(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    stream_id: u64,
+) -> Result<Response, ContractError> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    let cfg = CONFIG.load(deps.storage)?;
+    //Cancelled can't be resumed
+    if stream.is_cancelled() {
+        return Err(ContractError::StreamIsCancelled {});
+    }
+    if !stream.is_paused() {
+        return Err(ContractError::StreamNotPaused {});
+    }
+    // ok to use unwrap here
+    let pause_date = stream.pause_date.unwrap();
+    match cfg.max_pause_duration {
+        Some(max_pause_duration)
+            if env.block.time >= pause_date.plus_seconds(max_pause_duration.u64()) => {}
+        _ => return Err(ContractError::PauseNotExpired {}),
+    }
+    resume_stream(env.block.time, &mut stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    CANCEL_CONFIRMATIONS.remove(deps.storage, stream_id);
+
+    Ok(Response::default()
+        .add_attribute("action", "resume_expired_pause")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("new_end_date", stream.end_time.to_string())
+        .add_attribute("status", "active")
+        .add_event(
+            Resumed {
+                stream_id,
+                end_time: stream.end_time,
+            }
+            .into(),
+        ))
+}
+
+pub fn resume_stream(now: Timestamp, stream: &mut Stream) -> StdResult<()> {
+    // ok to use unwrap here
+    let pause_date = stream.pause_date.unwrap();
+    //postpone stream times with respect to pause duration
+    stream.end_time = stream.end_time.plus_nanos(now.nanos() - pause_date.nanos());
+    stream.last_updated = stream
+        .last_updated
+        .plus_nanos(now.nanos() - pause_date.nanos());
+
+    stream.status = Status::Active;
+    stream.pause_date = None;
+    Ok(())
 }
 
 pub fn execute_cancel_stream(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     stream_id: u64,
 ) -> Result<Response, ContractError> {
-    let cfg = CONFIG.load(deps.storage)?;
-    if cfg.protocol_admin != info.sender {
+    let guardian_set = GUARDIANS.may_load(deps.storage)?;
+    match &guardian_set {
+        Some(guardian_set) if !guardian_set.is_guardian(&info.sender) => {
+            return Err(ContractError::Unauthorized {});
+        }
+        Some(_) => {}
+        None => {
+            let cfg = CONFIG.load(deps.storage)?;
+            if !cfg.is_killswitch_controller(&info.sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+        }
+    }
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.is_cancelled() {
+        return Err(ContractError::StreamIsCancelled {});
+    }
+    if !stream.is_paused() {
+        return Err(ContractError::StreamNotPaused {});
+    }
+    match guardian_set {
+        Some(guardian_set) => {
+            confirm_cancel(deps, env, stream_id, stream, guardian_set, info.sender)
+        }
+        None => cancel_stream(deps, env, stream_id, stream),
+    }
+}
+
+/// Adds a guardian confirmation to an open cancel proposal, cancelling the stream once
+/// `cancel_threshold` is reached.
+pub fn execute_confirm_cancel_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+) -> Result<Response, ContractError> {
+    let guardian_set = GUARDIANS
+        .may_load(deps.storage)?
+        .ok_or(ContractError::Unauthorized {})?;
+    if !guardian_set.is_guardian(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
-    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    let stream = STREAMS.load(deps.storage, stream_id)?;
     if stream.is_cancelled() {
         return Err(ContractError::StreamIsCancelled {});
     }
     if !stream.is_paused() {
         return Err(ContractError::StreamNotPaused {});
     }
-    stream.status = Status::Cancelled;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    if !CANCEL_CONFIRMATIONS.has(deps.storage, stream_id) {
+        return Err(ContractError::NoCancelProposal {});
+    }
+    confirm_cancel(deps, env, stream_id, stream, guardian_set, info.sender)
+}
 
-    let out_supply_u128: Uint128 = stream.out_supply.to_string().parse().unwrap();
+fn confirm_cancel(
+    deps: DepsMut,
+    env: Env,
+    stream_id: u64,
+    stream: Stream,
+    guardian_set: GuardianSet,
+    guardian: Addr,
+) -> Result<Response, ContractError> {
+    let mut confirmations = CANCEL_CONFIRMATIONS
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default();
+    if confirmations.contains(&guardian) {
+        return Err(ContractError::CancelAlreadyConfirmed {});
+    }
+    confirmations.push(guardian.clone());
+    // Only confirmations of current guardians count, the set might have changed since the proposal
+    let confirmed = confirmations
+        .iter()
+        .filter(|g| guardian_set.is_guardian(g))
+        .count();
+    if confirmed < guardian_set.cancel_threshold as usize {
+        CANCEL_CONFIRMATIONS.save(deps.storage, stream_id, &confirmations)?;
+        return Ok(Response::new()
+            .add_attribute("action", "confirm_cancel_stream")
+            .add_attribute("stream_id", stream_id.to_string())
+            .add_attribute("guardian", guardian)
+            .add_attribute("confirmations", confirmed.to_string()));
+    }
+    CANCEL_CONFIRMATIONS.remove(deps.storage, stream_id);
+    cancel_stream(deps, env, stream_id, stream)
+}
 
-    //Refund all out tokens to stream creator(treasury)
-    let messages: Vec<CosmosMsg> = vec![
-        CosmosMsg::Bank(BankMsg::Send {
-            to_address: stream.treasury.to_string(),
-            amount: vec![Coin {
-                denom: stream.out_denom,
-                amount: out_supply_u128,
-            }],
-        }),
-        //Refund stream creation fee to stream creator
-        CosmosMsg::Bank(BankMsg::Send {
+fn cancel_stream(
+    deps: DepsMut,
+    env: Env,
+    stream_id: u64,
+    mut stream: Stream,
+) -> Result<Response, ContractError> {
+    stream.status = Status::Cancelled;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    let pool_escrow_msgs = release_pool_escrow(deps.storage, stream_id, &stream)?;
+
+    //Refund out tokens to stream creator(treasury)
+    let mut messages = cancel_refund_msgs(deps.storage, stream_id, &stream)?;
+    //Refund stream creation fee to stream creator, unless it was waived
+    if !stream.stream_creation_fee.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
             to_address: stream.treasury.to_string(),
             amount: vec![Coin {
                 denom: stream.stream_creation_denom,
                 amount: stream.stream_creation_fee,
             }],
-        }),
-    ];
+        }));
+    }
 
     Ok(Response::new()
         .add_attribute("action", "cancel_stream")
         .add_messages(messages)
+        .add_messages(pool_escrow_msgs)
         .add_attribute("stream_id", stream_id.to_string())
-        .add_attribute("status", "cancelled"))
+        .add_attribute("status", "cancelled")
+        .add_event(Cancelled { stream_id }.into()))
+}
+
+/// Refunds the out supply of a cancelled stream to the treasury, less the tokens purchased by
+/// positions exited while paused. Their spent tokens are paid out as at finalize, the swap fee
+/// to the fee collector and the rest to the treasury.
+fn cancel_refund_msgs(
+    storage: &mut dyn Storage,
+    stream_id: StreamId,
+    stream: &Stream,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let paused_exits = PAUSED_EXITS
+        .may_load(storage, stream_id)?
+        .unwrap_or_default();
+    PAUSED_EXITS.remove(storage, stream_id);
+    let out_refund = stream.out_supply.checked_sub(paused_exits.purchased)?;
+    let swap_fee = Decimal256::from_ratio(paused_exits.spent, Uint256::one())
+        .checked_mul(stream.stream_exit_fee_percent)?
+        * Uint256::one();
+    let revenue = paused_exits.spent.checked_sub(swap_fee)?;
+    record_fee(storage, &stream.in_denom, swap_fee)?;
+
+    let fee_collector = CONFIG.load(storage)?.fee_collector;
+    let mut messages = vec![];
+    for (to_address, denom, amount) in [
+        (&stream.treasury, &stream.out_denom, out_refund),
+        (&stream.treasury, &stream.in_denom, revenue),
+        (&fee_collector, &stream.in_denom, swap_fee),
+    ] {
+        if !amount.is_zero() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: to_address.to_string(),
+                amount: vec![Coin {
+                    denom: denom.clone(),
+                    amount: Uint128::try_from(amount)?,
+                }],
+            }));
+        }
+    }
+    Ok(messages)
+}
+
+pub fn execute_update_guardians(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    guardians: Vec<String>,
+    cancel_threshold: u32,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if !cfg.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if guardians.is_empty() {
+        GUARDIANS.remove(deps.storage);
+        return Ok(Response::new()
+            .add_attribute("action", "update_guardians")
+            .add_attribute("guardians", "0"));
+    }
+
+    let mut guardian_addrs = guardians
+        .iter()
+        .map(|g| deps.api.addr_validate(g))
+        .collect::<StdResult<Vec<_>>>()?;
+    guardian_addrs.sort();
+    guardian_addrs.dedup();
+    if cancel_threshold == 0 || cancel_threshold as usize > guardian_addrs.len() {
+        return Err(ContractError::InvalidGuardianSet {});
+    }
+    let guardian_set = GuardianSet {
+        guardians: guardian_addrs,
+        cancel_threshold,
+    };
+    GUARDIANS.save(deps.storage, &guardian_set)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_guardians")
+        .add_attribute("guardians", guardian_set.guardians.len().to_string())
+        .add_attribute("cancel_threshold", cancel_threshold.to_string()))
+}
+
+/// Sets the address a remote chain governance controls the killswitch through. Polytone proxies
+/// and interchain accounts execute as local addresses, so no packet handling is needed here.
+pub fn execute_update_remote_controller(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    remote_controller: Option<String>,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if !cfg.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let remote_controller = maybe_addr(deps.api, remote_controller)?;
+    let apply_after = save_config_update(
+        deps,
+        &env,
+        cfg,
+        ConfigUpdate {
+            remote_controller: Some(remote_controller.as_ref().map(Addr::to_string)),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_remote_controller")
+        .add_attribute(
+            "remote_controller",
+            remote_controller.map(|a| a.to_string()).unwrap_or_default(),
+        )
+        .add_attributes(apply_after.map(|t| ("apply_after", t.to_string()))))
+}
+
+/// Returns true if `sender` can pause streams.
+fn is_killswitch_authority(deps: Deps, sender: &Addr) -> StdResult<bool> {
+    match GUARDIANS.may_load(deps.storage)? {
+        Some(guardian_set) => Ok(guardian_set.is_guardian(sender)),
+        None => Ok(CONFIG.load(deps.storage)?.is_killswitch_controller(sender)),
+    }
+}
+
+pub fn query_guardians(deps: Deps) -> StdResult<Option<GuardianSet>> {
+    GUARDIANS.may_load(deps.storage)
+}
+
+pub fn query_cancel_confirmations(deps: Deps, stream_id: u64) -> StdResult<Vec<Addr>> {
+    Ok(CANCEL_CONFIRMATIONS
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default())
 }
 
 pub fn execute_cancel_stream_with_threshold(
@@ -329,26 +908,57 @@ pub fn execute_cancel_stream_with_threshold(
     }
     // Threshold should not be reached
     threshold_state.error_if_reached(stream_id, deps.storage, &stream)?;
+    // Streams refunding partially are finalized instead
+    if PARTIAL_REFUNDS.has(deps.storage, stream_id) {
+        return Err(ContractError::PartialRefundEnabled {});
+    }
 
     stream.status = Status::Cancelled;
 
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    let pool_escrow_msgs = release_pool_escrow(deps.storage, stream_id, &stream)?;
 
-    //Refund all out tokens to stream creator(treasury)
-    let out_supply_u128: Uint128 = stream.out_supply.to_string().parse().unwrap();
-    let messages: Vec<CosmosMsg> = vec![CosmosMsg::Bank(BankMsg::Send {
-        to_address: stream.treasury.to_string(),
-        amount: vec![Coin {
-            denom: stream.out_denom,
-            amount: out_supply_u128,
-        }],
-    })];
+    //Refund out tokens to stream creator(treasury)
+    let mut messages = cancel_refund_msgs(deps.storage, stream_id, &stream)?;
+
+    // The penalty share of the creation fee goes to the fee collector and the rest is refunded.
+    // Without a penalty the creation fee is refunded in full, a penalty of one forfeits it.
+    let config = CONFIG.load(deps.storage)?;
+    let penalty = Uint128::try_from(
+        config.creator_cancel_penalty.unwrap_or_default()
+            * Uint256::from(stream.stream_creation_fee.u128()),
+    )?;
+    let refund = stream.stream_creation_fee.checked_sub(penalty)?;
+    record_fee(
+        deps.storage,
+        &stream.stream_creation_denom,
+        Uint256::from(penalty.u128()),
+    )?;
+    for (to_address, amount) in [
+        (config.fee_collector.to_string(), penalty),
+        (stream.treasury.to_string(), refund),
+    ] {
+        if !amount.is_zero() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address,
+                amount: vec![Coin {
+                    denom: stream.stream_creation_denom.clone(),
+                    amount,
+                }],
+            }));
+        }
+    }
 
     Ok(Response::new()
         .add_attribute("action", "cancel_stream")
         .add_messages(messages)
+        .add_messages(pool_escrow_msgs)
         .add_attribute("stream_id", stream_id.to_string())
-        .add_attribute("status", "cancelled"))
+        .add_attribute("status", "cancelled")
+        .add_attribute("creation_fee_denom", stream.stream_creation_denom)
+        .add_attribute("penalty", penalty)
+        .add_attribute("creation_fee_refunded", refund)
+        .add_event(Cancelled { stream_id }.into()))
 }
 
 pub fn sudo_pause_stream(
@@ -371,13 +981,21 @@ pub fn sudo_pause_stream(
     }
     update_stream(env.block.time, &mut stream)?;
     pause_stream(env.block.time, &mut stream)?;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    save_price_snapshot(deps.storage, stream_id, &stream)?;
 
     Ok(Response::default()
         .add_attribute("action", "sudo_pause_stream")
         .add_attribute("stream_id", stream_id.to_string())
         .add_attribute("is_paused", "true")
-        .add_attribute("pause_date", env.block.time.to_string()))
+        .add_attribute("pause_date", env.block.time.to_string())
+        .add_event(
+            Paused {
+                stream_id,
+                pause_date: env.block.time,
+            }
+            .into(),
+        ))
 }
 
 pub fn sudo_resume_stream(
@@ -394,30 +1012,179 @@ pub fn sudo_resume_stream(
     if !stream.is_paused() {
         return Err(ContractError::StreamNotPaused {});
     }
-    // ok to use unwrap here
-    let pause_date = stream.pause_date.unwrap();
-    //postpone stream times with respect to pause duration
-    stream.end_time = stream
-        .end_time
-        .plus_nanos(env.block.time.nanos() - pause_date.nanos());
-    stream.last_updated = stream
-        .last_updated
-        .plus_nanos(env.block.time.nanos() - pause_date.nanos());
-
-    stream.status = Status::Active;
-    stream.pause_date = None;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
+    resume_stream(env.block.time, &mut stream)?;
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    CANCEL_CONFIRMATIONS.remove(deps.storage, stream_id);
 
     Ok(Response::default()
         .add_attribute("action", "resume_stream")
         .add_attribute("stream_id", stream_id.to_string())
         .add_attribute("new_end_date", stream.end_time.to_string())
-        .add_attribute("status", "active"))
+        .add_attribute("status", "active")
+        .add_event(
+            Resumed {
+                stream_id,
+                end_time: stream.end_time,
+            }
+            .into(),
+        ))
+}
+
+/// Pauses every running stream in the scanned range, for chain-wide emergencies.
+pub fn sudo_pause_all_streams(
+    deps: DepsMut,
+    env: Env,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let streams = batch_streams(deps.as_ref(), start_after, limit)?;
+    let last_stream_id = streams.last().map(|(id, _)| *id);
+
+    let mut events = vec![];
+    for (stream_id, mut stream) in streams {
+        // Same conditions as sudo_pause_stream, other streams are skipped
+        if env.block.time >= stream.end_time
+            || env.block.time < stream.start_time
+            || stream.is_killswitch_active()
+        {
+            continue;
+        }
+        update_stream(env.block.time, &mut stream)?;
+        pause_stream(env.block.time, &mut stream)?;
+        save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+        save_price_snapshot(deps.storage, stream_id, &stream)?;
+        events.push(
+            Paused {
+                stream_id,
+                pause_date: env.block.time,
+            }
+            .into(),
+        );
+    }
+
+    Ok(Response::default()
+        .add_attribute("action", "sudo_pause_all_streams")
+        .add_attribute("paused", events.len().to_string())
+        .add_attribute("pause_date", env.block.time.to_string())
+        .add_attribute(
+            "last_stream_id",
+            last_stream_id.map(|id| id.to_string()).unwrap_or_default(),
+        )
+        .add_events(events))
+}
+
+/// Resumes every paused stream in the scanned range.
+pub fn sudo_resume_all_streams(
+    deps: DepsMut,
+    env: Env,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let streams = batch_streams(deps.as_ref(), start_after, limit)?;
+    let last_stream_id = streams.last().map(|(id, _)| *id);
+
+    let mut events = vec![];
+    for (stream_id, mut stream) in streams {
+        if !stream.is_paused() {
+            continue;
+        }
+        resume_stream(env.block.time, &mut stream)?;
+        save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+        CANCEL_CONFIRMATIONS.remove(deps.storage, stream_id);
+        events.push(
+            Resumed {
+                stream_id,
+                end_time: stream.end_time,
+            }
+            .into(),
+        );
+    }
+
+    Ok(Response::default()
+        .add_attribute("action", "sudo_resume_all_streams")
+        .add_attribute("resumed", events.len().to_string())
+        .add_attribute(
+            "last_stream_id",
+            last_stream_id.map(|id| id.to_string()).unwrap_or_default(),
+        )
+        .add_events(events))
+}
+
+fn batch_streams(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, Stream)>> {
+    let limit = limit.unwrap_or(DEFAULT_BATCH_LIMIT).min(MAX_BATCH_LIMIT) as usize;
+    STREAMS
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect()
+}
+
+/// Lets chain governance remediate a misconfigured stream without cancelling it.
+pub fn sudo_update_stream_params(
+    deps: DepsMut,
+    env: Env,
+    stream_id: u64,
+    end_time: Option<Timestamp>,
+    threshold: Option<Uint256>,
+) -> Result<Response, ContractError> {
+    if end_time.is_none() && threshold.is_none() {
+        return Err(ContractError::EmptyStreamParamsUpdate {});
+    }
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.is_cancelled() {
+        return Err(ContractError::StreamIsCancelled {});
+    }
+    if stream.status == Status::Finalized {
+        return Err(ContractError::StreamAlreadyFinalized {});
+    }
+    if env.block.time >= stream.end_time {
+        return Err(ContractError::StreamEnded {});
+    }
+
+    let threshold_state = ThresholdState::new();
+    let old_end_time = stream.end_time;
+    let old_threshold = threshold_state.get_threshold(stream_id, deps.storage)?;
+
+    if let Some(end_time) = end_time {
+        if end_time <= stream.end_time {
+            return Err(ContractError::StreamInvalidEndTime {});
+        }
+        // distribute with the old end time up to now, paused streams are already updated
+        if !stream.is_paused() {
+            update_stream(env.block.time, &mut stream)?;
+        }
+        stream.end_time = end_time;
+        save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+        save_price_snapshot(deps.storage, stream_id, &stream)?;
+    }
+    threshold_state.set_threshold_if_any(threshold, stream_id, deps.storage)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "sudo_update_stream_params")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("old_end_time", old_end_time.to_string())
+        .add_attribute("new_end_time", stream.end_time.to_string())
+        .add_attribute(
+            "old_threshold",
+            old_threshold.unwrap_or_default().to_string(),
+        )
+        .add_attribute(
+            "new_threshold",
+            threshold.or(old_threshold).unwrap_or_default().to_string(),
+        ))
 }
 
 pub fn sudo_cancel_stream(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     stream_id: u64,
 ) -> Result<Response, ContractError> {
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
@@ -428,30 +1195,27 @@ pub fn sudo_cancel_stream(
         return Err(ContractError::StreamNotPaused {});
     }
     stream.status = Status::Cancelled;
-    STREAMS.save(deps.storage, stream_id, &stream)?;
-    let out_supply_u128: Uint128 = stream.out_supply.to_string().parse().unwrap();
-    //Refund all out tokens to stream creator(treasury)
-    let messages: Vec<CosmosMsg> = vec![
-        CosmosMsg::Bank(BankMsg::Send {
-            to_address: stream.treasury.to_string(),
-            amount: vec![Coin {
-                denom: stream.out_denom,
-                amount: out_supply_u128,
-            }],
-        }),
-        //Refund stream creation fee to stream creator
-        CosmosMsg::Bank(BankMsg::Send {
+    save_stream(deps.storage, stream_id, &stream, env.block.height)?;
+    CANCEL_CONFIRMATIONS.remove(deps.storage, stream_id);
+    let pool_escrow_msgs = release_pool_escrow(deps.storage, stream_id, &stream)?;
+    //Refund out tokens to stream creator(treasury)
+    let mut messages = cancel_refund_msgs(deps.storage, stream_id, &stream)?;
+    //Refund stream creation fee to stream creator, unless it was waived
+    if !stream.stream_creation_fee.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
             to_address: stream.treasury.to_string(),
             amount: vec![Coin {
                 denom: stream.stream_creation_denom,
                 amount: stream.stream_creation_fee,
             }],
-        }),
-    ];
+        }));
+    }
 
     Ok(Response::new()
         .add_attribute("action", "cancel_stream")
         .add_messages(messages)
+        .add_messages(pool_escrow_msgs)
         .add_attribute("stream_id", stream_id.to_string())
-        .add_attribute("status", "cancelled"))
+        .add_attribute("status", "cancelled")
+        .add_event(Cancelled { stream_id }.into()))
 }
@@ -1,13 +1,43 @@
 extern crate core;
 
 pub use crate::error::ContractError;
+pub mod allocation_tiers;
+pub mod api;
+pub mod audit;
+pub mod blocklist;
+mod clock;
+mod compliance;
 pub mod contract;
+mod crank;
+pub mod creator_profiles;
 mod error;
-mod helpers;
+pub mod escheat;
+pub mod events;
+pub mod exit_swap;
+mod fee_swap;
+pub mod hard_cap;
+pub mod helpers;
+mod ibc;
+mod keeper;
 mod killswitch;
-mod migrate_v0_2_1;
+pub mod lifecycle;
+mod liquid_staking;
+pub mod migrate_v0_2_1;
 pub mod msg;
+pub mod names;
+pub mod operations;
+pub mod partial_refund;
+pub mod pool;
+pub mod price_history;
+pub mod prune;
+pub mod revenue_vesting;
+pub mod simulate;
 pub mod state;
+pub mod stats;
+pub mod subscription_limit;
+mod swap_subscribe;
+pub mod tags;
 #[cfg(test)]
 mod tests;
 pub mod threshold;
+pub mod vesting;
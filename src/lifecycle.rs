@@ -0,0 +1,92 @@
+use crate::contract::update_stream;
+use crate::partial_refund::{error_if_not_settleable, error_if_not_settled};
+use crate::simulate::to_std_err;
+use crate::state::{Status, Stream, StreamId, STREAMS};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Deps, Env, StdResult, Timestamp};
+
+/// Phase of a stream derived from its status and the block time.
+#[cw_serde]
+pub enum Phase {
+    /// Before the start time, subscriptions are accepted but nothing is streamed yet.
+    Waiting,
+    /// Between the start and end times, tokens are streamed.
+    Active,
+    /// Paused by the killswitch or the creator, the end time moves with the pause duration.
+    Paused,
+    /// Past the end time, waiting to be finalized.
+    Ended,
+    Finalized,
+    Cancelled,
+}
+
+#[cw_serde]
+pub struct LifecycleResponse {
+    pub stream_id: StreamId,
+    pub phase: Phase,
+    /// Seconds until the stream moves to the next phase, none if the move depends on an action.
+    pub seconds_until_next_phase: Option<u64>,
+    /// End time of the stream, including the pause extension if it were resumed now.
+    pub effective_end_time: Timestamp,
+    /// Whether the treasury can finalize the stream now.
+    pub finalize_callable: bool,
+    /// Whether positions can exit now, with `ExitStream` or `ExitCancelled` for cancelled streams.
+    pub exit_callable: bool,
+}
+
+pub fn stream_phase(stream: &Stream, now: Timestamp) -> Phase {
+    match stream.status {
+        Status::Finalized => Phase::Finalized,
+        Status::Cancelled => Phase::Cancelled,
+        Status::Paused => Phase::Paused,
+        Status::Waiting | Status::Active if now < stream.start_time => Phase::Waiting,
+        Status::Waiting | Status::Active if now <= stream.end_time => Phase::Active,
+        Status::Waiting | Status::Active => Phase::Ended,
+    }
+}
+
+/// Returns the end time of the stream, postponed by the current pause duration if paused.
+pub fn effective_end_time(stream: &Stream, now: Timestamp) -> Timestamp {
+    match stream.pause_date {
+        Some(pause_date) if stream.is_paused() => stream
+            .end_time
+            .plus_nanos(now.nanos().saturating_sub(pause_date.nanos())),
+        _ => stream.end_time,
+    }
+}
+
+pub fn query_lifecycle(deps: Deps, env: Env, stream_id: StreamId) -> StdResult<LifecycleResponse> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    let now = env.block.time;
+    let phase = stream_phase(&stream, now);
+    let seconds_until_next_phase = match phase {
+        Phase::Waiting => Some(stream.start_time.seconds() - now.seconds()),
+        Phase::Active => Some(stream.end_time.seconds() - now.seconds()),
+        _ => None,
+    };
+    let effective_end_time = effective_end_time(&stream, now);
+
+    // unreached thresholds block finalize and exits, the stream has to be cancelled instead
+    // unless it refunds partially, then exits wait for finalize
+    if stream.last_updated < stream.end_time {
+        update_stream(now, &mut stream).map_err(to_std_err)?;
+    }
+    let finalize_callable =
+        phase == Phase::Ended && error_if_not_settleable(deps.storage, stream_id, &stream).is_ok();
+    let exit_callable = match phase {
+        Phase::Ended | Phase::Finalized => {
+            error_if_not_settled(deps.storage, stream_id, &stream).is_ok()
+        }
+        Phase::Cancelled => true,
+        _ => false,
+    };
+
+    Ok(LifecycleResponse {
+        stream_id,
+        phase,
+        seconds_until_next_phase,
+        effective_end_time,
+        finalize_callable,
+        exit_callable,
+    })
+}
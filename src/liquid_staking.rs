@@ -0,0 +1,74 @@
+use crate::contract::save_config_update;
+use crate::state::{Config, ConfigUpdate, LiquidStaking, CONFIG};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Coin, DepsMut, Env, MessageInfo, Response, SubMsg, WasmMsg,
+};
+
+/// Bond message of the liquid staking hub, staked tokens are minted to `receiver`.
+#[cw_serde]
+pub enum HubExecuteMsg {
+    Bond { receiver: Option<String> },
+}
+
+pub fn execute_update_liquid_staking(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    liquid_staking: Option<LiquidStaking>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(liquid_staking) = &liquid_staking {
+        deps.api.addr_validate(&liquid_staking.hub)?;
+        if liquid_staking.denom.is_empty() {
+            return Err(ContractError::InvalidLiquidStaking {});
+        }
+    }
+    let hub = liquid_staking
+        .as_ref()
+        .map(|l| l.hub.clone())
+        .unwrap_or_default();
+    let apply_after = save_config_update(
+        deps,
+        &env,
+        config,
+        ConfigUpdate {
+            liquid_staking: Some(liquid_staking),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_liquid_staking")
+        .add_attribute("hub", hub)
+        .add_attributes(apply_after.map(|t| ("apply_after", t.to_string()))))
+}
+
+/// Bonds `amount` with the configured liquid staking hub on behalf of `receiver`. Only the
+/// staking denom of the hub can be bonded.
+pub fn liquid_stake_msg(
+    config: &Config,
+    receiver: &Addr,
+    amount: Coin,
+) -> Result<SubMsg, ContractError> {
+    let liquid_staking = config
+        .liquid_staking
+        .as_ref()
+        .ok_or(ContractError::LiquidStakingDisabled {})?;
+    if amount.denom != liquid_staking.denom {
+        return Err(ContractError::LiquidStakingDenomMismatch {
+            denom: amount.denom,
+        });
+    }
+    Ok(SubMsg::new(WasmMsg::Execute {
+        contract_addr: liquid_staking.hub.clone(),
+        msg: to_json_binary(&HubExecuteMsg::Bond {
+            receiver: Some(receiver.to_string()),
+        })?,
+        funds: vec![amount],
+    }))
+}
@@ -1,10 +1,13 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    Addr, Decimal, Decimal256, Fraction, StdResult, Storage, Timestamp, Uint128, Uint256,
+    attr, Addr, Decimal, Decimal256, Deps, DepsMut, Env, Fraction, MessageInfo, Order, Response,
+    StdResult, Storage, Timestamp, Uint128, Uint256,
 };
-use cw_storage_plus::Map;
+use cw_storage_plus::{Bound, Item, Map};
 
-use crate::state::{Position, Status, Stream, StreamId, POSITIONS, STREAMS};
+use crate::state::{save_stream, Position, Status, Stream, StreamId, POSITIONS};
+use crate::stats::record_subscription;
+use crate::ContractError;
 
 #[cw_serde]
 pub struct StreamV0_2_0 {
@@ -73,75 +76,212 @@ pub struct PositionV0_2_0 {
 pub const OLD_STREAMS: Map<StreamId, StreamV0_2_0> = Map::new("stream");
 pub const OLD_POSITIONS: Map<(StreamId, &Addr), PositionV0_2_0> = Map::new("positions");
 
-pub fn migrate_v0_2_1(storage: &mut dyn Storage) -> StdResult<()> {
-    // Migrate the state from v0.2.0 to v0.2.1
-    let old_streams = OLD_STREAMS
-        .range(storage, None, None, cosmwasm_std::Order::Ascending)
-        .collect::<StdResult<Vec<_>>>()?;
-
-    // migrate streams
-    for (id, stream) in old_streams {
-        let new_stream = Stream {
-            name: stream.name,
-            treasury: stream.treasury,
-            url: stream.url,
-            dist_index: stream.dist_index,
-            last_updated: stream.last_updated,
-            out_denom: stream.out_denom,
-            out_supply: Uint256::from_uint128(stream.out_supply),
-            out_remaining: Uint256::from_uint128(stream.out_remaining),
-            in_denom: stream.in_denom,
-            in_supply: Uint256::from_uint128(stream.in_supply),
-            spent_in: Uint256::from_uint128(stream.spent_in),
-            shares: Uint256::from_uint128(stream.shares),
-            start_time: stream.start_time,
-            end_time: stream.end_time,
-            current_streamed_price: Decimal256::from_ratio(
-                stream.current_streamed_price.numerator(),
-                stream.current_streamed_price.denominator(),
-            ),
-            status: stream.status,
-            pause_date: stream.pause_date,
-            stream_creation_denom: stream.stream_creation_denom,
-            stream_creation_fee: stream.stream_creation_fee,
-            stream_exit_fee_percent: Decimal256::from_ratio(
-                stream.stream_exit_fee_percent.numerator(),
-                stream.stream_exit_fee_percent.denominator(),
-            ),
-        };
-        STREAMS.save(storage, id, &new_stream)?;
+const MAX_LIMIT: u32 = 100;
+const DEFAULT_LIMIT: u32 = 30;
+
+/// Progress of the v0.2.0 migration, streams are converted before positions.
+#[cw_serde]
+pub enum MigrationCursor {
+    /// Streams after the id are left to convert.
+    Streams { start_after: Option<StreamId> },
+    /// Streams are converted, positions after the key are left.
+    Positions {
+        start_after: Option<(StreamId, Addr)>,
+    },
+}
+
+// Set while legacy records are left to convert, execute messages other than `MigrateStep` fail
+pub const MIGRATION_CURSOR: Item<MigrationCursor> = Item::new("migration_cursor");
+
+/// Legacy records converted to the current schema by a migration step.
+#[cw_serde]
+pub struct MigrationBatch {
+    pub streams: Vec<(StreamId, Stream)>,
+    pub positions: Vec<((StreamId, Addr), Position)>,
+    /// Cursor of the following step, `None` once all records are converted.
+    pub next: Option<MigrationCursor>,
+}
+
+fn convert_stream(stream: StreamV0_2_0) -> Stream {
+    Stream {
+        name: stream.name,
+        treasury: stream.treasury,
+        url: stream.url,
+        dist_index: stream.dist_index,
+        last_updated: stream.last_updated,
+        out_denom: stream.out_denom,
+        out_supply: Uint256::from_uint128(stream.out_supply),
+        out_remaining: Uint256::from_uint128(stream.out_remaining),
+        in_denom: stream.in_denom,
+        in_supply: Uint256::from_uint128(stream.in_supply),
+        spent_in: Uint256::from_uint128(stream.spent_in),
+        shares: Uint256::from_uint128(stream.shares),
+        start_time: stream.start_time,
+        end_time: stream.end_time,
+        current_streamed_price: Decimal256::from_ratio(
+            stream.current_streamed_price.numerator(),
+            stream.current_streamed_price.denominator(),
+        ),
+        price_cumulative: Decimal256::zero(),
+        status: stream.status,
+        pause_date: stream.pause_date,
+        stream_creation_denom: stream.stream_creation_denom,
+        stream_creation_fee: stream.stream_creation_fee,
+        stream_exit_fee_percent: Decimal256::from_ratio(
+            stream.stream_exit_fee_percent.numerator(),
+            stream.stream_exit_fee_percent.denominator(),
+        ),
+    }
+}
+
+fn convert_position(position: PositionV0_2_0) -> Position {
+    Position {
+        owner: position.owner,
+        in_balance: Uint256::from_uint128(position.in_balance),
+        shares: Uint256::from_uint128(position.shares),
+        index: position.index,
+        last_updated: position.last_updated,
+        purchased: Uint256::from_uint128(position.purchased),
+        pending_purchase: position.pending_purchase,
+        spent: Uint256::from_uint128(position.spent),
+        operator: position.operator,
+    }
+}
+
+/// Converts up to `limit` legacy records after the cursor without saving them. Streams are
+/// converted first, the rest of the limit goes to positions once no streams are left.
+pub fn next_migration_batch(
+    storage: &dyn Storage,
+    cursor: MigrationCursor,
+    limit: Option<u32>,
+) -> StdResult<MigrationBatch> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+    let mut batch = MigrationBatch {
+        streams: vec![],
+        positions: vec![],
+        next: None,
+    };
+    let positions_after = match cursor {
+        MigrationCursor::Streams { start_after } => {
+            batch.streams = OLD_STREAMS
+                .range(
+                    storage,
+                    start_after.map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(limit)
+                .map(|item| item.map(|(id, stream)| (id, convert_stream(stream))))
+                .collect::<StdResult<_>>()?;
+            if batch.streams.len() == limit {
+                batch.next = Some(MigrationCursor::Streams {
+                    start_after: batch.streams.last().map(|(id, _)| *id),
+                });
+                return Ok(batch);
+            }
+            None
+        }
+        MigrationCursor::Positions { start_after } => start_after,
+    };
+
+    let limit = limit - batch.streams.len();
+    batch.positions = OLD_POSITIONS
+        .range(
+            storage,
+            positions_after
+                .as_ref()
+                .map(|(stream_id, owner)| Bound::exclusive((*stream_id, owner))),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(key, position)| (key, convert_position(position))))
+        .collect::<StdResult<_>>()?;
+    if batch.positions.len() == limit {
+        batch.next = Some(MigrationCursor::Positions {
+            start_after: batch.positions.last().map(|(key, _)| key.clone()),
+        });
+    }
+    Ok(batch)
+}
+
+/// Converts and saves the next batch of legacy records, continuing from the saved cursor. The
+/// cursor is removed once all records are converted.
+pub fn migrate_v0_2_1_step(
+    storage: &mut dyn Storage,
+    height: u64,
+    limit: Option<u32>,
+) -> StdResult<MigrationBatch> {
+    let cursor = MIGRATION_CURSOR
+        .may_load(storage)?
+        .unwrap_or(MigrationCursor::Streams { start_after: None });
+    let batch = next_migration_batch(storage, cursor, limit)?;
+    for (id, stream) in &batch.streams {
+        save_stream(storage, *id, stream, height)?;
+    }
+    for ((stream_id, owner), position) in &batch.positions {
+        POSITIONS.save(storage, (*stream_id, owner), position)?;
+        // legacy positions never went through stats, exited ones are already removed and
+        // withdrawals are lost, so the volume is the spent and the remaining balance
+        record_subscription(
+            storage,
+            *stream_id,
+            position.in_balance.checked_add(position.spent)?,
+            true,
+        )?;
     }
+    match &batch.next {
+        Some(cursor) => MIGRATION_CURSOR.save(storage, cursor)?,
+        None => MIGRATION_CURSOR.remove(storage),
+    }
+    Ok(batch)
+}
+
+pub fn migration_response(action: &str, batch: &MigrationBatch) -> Response {
+    Response::new().add_attributes(vec![
+        attr("action", action),
+        attr("streams", batch.streams.len().to_string()),
+        attr("positions", batch.positions.len().to_string()),
+        attr("done", batch.next.is_none().to_string()),
+    ])
+}
 
-    // migrate positions
-    let old_positions = OLD_POSITIONS
-        .range(storage, None, None, cosmwasm_std::Order::Ascending)
-        .collect::<StdResult<Vec<_>>>()?;
-
-    for ((stream_id, owner), position) in old_positions {
-        let new_position = Position {
-            owner: position.owner,
-            in_balance: Uint256::from_uint128(position.in_balance),
-            shares: Uint256::from_uint128(position.shares),
-            index: position.index,
-            last_updated: position.last_updated,
-            purchased: Uint256::from_uint128(position.purchased),
-            pending_purchase: position.pending_purchase,
-            spent: Uint256::from_uint128(position.spent),
-            operator: position.operator,
-        };
-        POSITIONS.save(storage, (stream_id, &owner), &new_position)?;
+/// Converts the next `limit` legacy records of a migration too large for a single transaction.
+/// Anyone can continue a pending migration.
+pub fn execute_migrate_step(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    if !MIGRATION_CURSOR.exists(deps.storage) {
+        return Err(ContractError::NoPendingMigration {});
     }
+    let batch = migrate_v0_2_1_step(deps.storage, env.block.height, limit)?;
+    Ok(migration_response("migrate_step", &batch))
+}
 
-    Ok(())
+/// Returns the records the next `MigrateStep` would convert, without saving them. Empty if no
+/// migration is pending.
+pub fn query_migrate_dry_run(deps: Deps, limit: Option<u32>) -> StdResult<MigrationBatch> {
+    match MIGRATION_CURSOR.may_load(deps.storage)? {
+        Some(cursor) => next_migration_batch(deps.storage, cursor, limit),
+        None => Ok(MigrationBatch {
+            streams: vec![],
+            positions: vec![],
+            next: None,
+        }),
+    }
 }
 
 #[cfg(test)]
 mod test_migrate {
     use crate::migrate_v0_2_1::{
-        migrate_v0_2_1, PositionV0_2_0, StreamV0_2_0, OLD_POSITIONS, OLD_STREAMS,
+        migrate_v0_2_1_step, MigrationBatch, MigrationCursor, PositionV0_2_0, StreamV0_2_0,
+        MIGRATION_CURSOR, OLD_POSITIONS, OLD_STREAMS,
     };
     use crate::state::{Status, POSITIONS, STREAMS};
-    use cosmwasm_std::{Addr, Decimal, Decimal256, StdResult, Timestamp, Uint128};
+    use cosmwasm_std::{attr, Addr, Decimal, Decimal256, StdResult, Timestamp, Uint128, Uint256};
 
     #[test]
     fn test_migrate_v0_2_1() {
@@ -213,8 +353,29 @@ mod test_migrate {
                 .unwrap();
         }
 
-        // Call the migrate_v0_2_1 function
-        migrate_v0_2_1(&mut deps.storage).unwrap();
+        // Migrate one record per step, the cursor is kept until all records are converted
+        let batch = migrate_v0_2_1_step(&mut deps.storage, 12_345, Some(1)).unwrap();
+        assert_eq!(batch.streams.len(), 1);
+        assert_eq!(
+            batch.next,
+            Some(MigrationCursor::Streams {
+                start_after: Some(1)
+            })
+        );
+        // no streams are left, the limit goes to positions
+        let batch = migrate_v0_2_1_step(&mut deps.storage, 12_345, Some(1)).unwrap();
+        assert_eq!(batch.streams.len(), 0);
+        assert_eq!(batch.positions.len(), 1);
+        assert_eq!(
+            batch.next,
+            Some(MigrationCursor::Positions {
+                start_after: Some((1, Addr::unchecked("owner1")))
+            })
+        );
+        let batch = migrate_v0_2_1_step(&mut deps.storage, 12_345, Some(1)).unwrap();
+        assert_eq!(batch.positions.len(), 0);
+        assert_eq!(batch.next, None);
+        assert!(!MIGRATION_CURSOR.exists(&deps.storage));
 
         // Assert that the old streams and positions have been migrated to the new format
         let new_streams: StdResult<Vec<_>> = STREAMS
@@ -236,5 +397,116 @@ mod test_migrate {
             )
             .collect();
         assert_eq!(new_positions.unwrap().len(), old_positions.len());
+
+        // migrated positions are counted
+        let count = crate::contract::query_count_positions(deps.as_ref(), 1).unwrap();
+        assert_eq!(count.count, 1);
+        let stats = crate::stats::query_statistics(deps.as_ref(), 1).unwrap();
+        assert_eq!(stats.subscribers, 1);
+        assert_eq!(
+            stats.subscription_volume,
+            Uint256::from(old_positions[0].1.in_balance + old_positions[0].1.spent)
+        );
+        assert_eq!(stats.exits, 0);
+    }
+
+    fn old_stream(name: &str) -> StreamV0_2_0 {
+        StreamV0_2_0 {
+            name: name.to_string(),
+            treasury: Addr::unchecked("treasury"),
+            url: None,
+            dist_index: Decimal256::zero(),
+            last_updated: Timestamp::from_seconds(1_000),
+            out_denom: "token_out".to_string(),
+            out_supply: Uint128::new(1_000_000),
+            out_remaining: Uint128::new(1_000_000),
+            in_denom: "token_in".to_string(),
+            in_supply: Uint128::zero(),
+            spent_in: Uint128::zero(),
+            shares: Uint128::zero(),
+            start_time: Timestamp::from_seconds(1_000),
+            end_time: Timestamp::from_seconds(2_000),
+            current_streamed_price: Decimal::zero(),
+            status: Status::Waiting,
+            pause_date: None,
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_exit_fee_percent: Decimal::percent(1),
+        }
+    }
+
+    #[test]
+    fn test_migrate_step() {
+        use crate::contract::{execute, migrate, query};
+        use crate::msg::{ExecuteMsg, MigrateMsg, QueryMsg};
+        use crate::ContractError;
+        use cosmwasm_std::from_json;
+        use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+        use cw2::set_contract_version;
+
+        let mut deps = mock_dependencies();
+        set_contract_version(deps.as_mut().storage, "crates.io:cw-streamswap", "0.2.0").unwrap();
+        for id in 1..=3 {
+            OLD_STREAMS
+                .save(
+                    deps.as_mut().storage,
+                    id,
+                    &old_stream(&format!("Stream {id}")),
+                )
+                .unwrap();
+        }
+
+        // the migration converts the first batch only
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg { limit: Some(2) }).unwrap();
+        assert!(res.attributes.contains(&attr("streams", "2")));
+        assert!(res.attributes.contains(&attr("done", "false")));
+
+        // other messages fail until the migration completes
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            ExecuteMsg::ProposeTreasury {
+                stream_id: 1,
+                new_treasury: "treasury2".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::MigrationPending {});
+
+        // dry run converts the next batch without saving it
+        let batch: MigrationBatch = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::MigrateDryRun { limit: None },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(batch.streams.len(), 1);
+        assert_eq!(batch.streams[0].0, 3);
+        assert_eq!(batch.next, None);
+        assert!(MIGRATION_CURSOR.exists(&deps.storage));
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::MigrateStep { limit: None },
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("streams", "1")));
+        assert!(res.attributes.contains(&attr("done", "true")));
+        assert_eq!(STREAMS.load(&deps.storage, 3).unwrap(), batch.streams[0].1);
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::MigrateStep { limit: None },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoPendingMigration {});
     }
 }
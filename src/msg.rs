@@ -1,6 +1,28 @@
-use crate::state::Status;
+use crate::allocation_tiers::{AllocationTier, TierDepositResponse, TierProof};
+use crate::audit::AuditBalancesResponse;
+use crate::creator_profiles::{CreatorProfile, StreamCreatorResponse};
+use crate::escheat::EscheatedPosition;
+use crate::exit_swap::ExitSwap;
+use crate::ibc::IbcLifecycleComplete;
+use crate::lifecycle::{LifecycleResponse, Phase};
+use crate::operations::StreamOperationsResponse;
+use crate::partial_refund::PartialRefundResponse;
+use crate::pool::{CreatePool, StreamPool};
+use crate::price_history::{PriceHistoryResponse, StreamedPriceTwapResponse};
+use crate::revenue_vesting::RevenueVesting;
+use crate::simulate::{SimulateExitResponse, SimulateSubscribeResponse};
+use crate::state::{
+    ExitCrank, FeeSwap, FinalizeHookLimits, GuardianSet, IbcTransfer, KeeperBounty, LiquidStaking,
+    NameRegistry, PendingConfigUpdate, PendingProtocolAdmin, Status,
+};
+use crate::stats::{ProtocolStatsResponse, StatisticsResponse};
+use crate::subscription_limit::{SubscriptionLimit, SubscriptionLimitResponse};
+use crate::vesting::{StreamVesting, VestingConfig, VestingResponse};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Decimal256, Timestamp, Uint128, Uint256, Uint64};
+use cosmwasm_std::{Addr, Decimal256, Order, Timestamp, Uint128, Uint256, Uint64};
+use cw_controllers::HooksResponse;
+use cw_utils::Expiration;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -20,6 +42,17 @@ pub struct InstantiateMsg {
     pub protocol_admin: String,
     /// Accepted in_denom to buy out_tokens
     pub accepted_in_denom: String,
+    /// Maximum pause duration in seconds, after which anyone can resume a paused stream
+    pub max_pause_duration: Option<Uint64>,
+    /// Maximum duration in seconds a treasury can pause its own stream
+    pub max_creator_pause_duration: Option<Uint64>,
+    /// Share of the creation fee sent to the fee collector when the treasury cancels a stream,
+    /// the rest is refunded
+    pub creator_cancel_penalty: Option<Decimal256>,
+    /// Only allowlisted creators can create streams when true
+    pub permissioned_creation: Option<bool>,
+    /// Delay in seconds before a config update can be applied
+    pub config_timelock: Option<Uint64>,
 }
 
 #[cw_serde]
@@ -47,15 +80,33 @@ pub enum ExecuteMsg {
         end_time: Timestamp,
         /// Minimum amount of `spent_in` for a stream to be finalized.
         threshold: Option<Uint256>,
+        /// If set, creator revenue is released to the treasury over this many seconds after
+        /// finalize instead of at once.
+        revenue_vesting_duration: Option<Uint64>,
+        /// Share of creator revenue donated to the community pool at finalize.
+        donation_percent: Option<Decimal256>,
+        /// If true, funds sent over the out supply and creation fee are refunded instead of
+        /// failing the creation.
+        refund_excess: Option<bool>,
     },
-    /// Update stream and calculates distribution state.
+    /// Update stream and calculates distribution state. If a keeper bounty is configured and the
+    /// stream was not updated for its `min_blocks`, the sender earns a share of the accrued swap
+    /// fee, claimable once the stream is finalized.
     UpdateStream {
         stream_id: u64,
     },
-    // Update protocol admin, only authorized admin can update.
-    UpdateProtocolAdmin {
+    /// ProposeProtocolAdmin proposes a new protocol admin, only authorized admin can propose.
+    /// A new proposal replaces the previous one. The transfer completes once the new admin
+    /// accepts it.
+    ProposeProtocolAdmin {
         new_protocol_admin: String,
+        expiry: Option<Expiration>,
     },
+    /// AcceptProtocolAdmin accepts a pending protocol admin proposal, only the proposed admin can
+    /// accept.
+    AcceptProtocolAdmin {},
+    /// RenounceProtocolAdmin removes the protocol admin and any pending proposal permanently.
+    RenounceProtocolAdmin {},
     /// UpdateOperator updates the operator of the position.
     UpdateOperator {
         stream_id: u64,
@@ -71,6 +122,16 @@ pub enum ExecuteMsg {
         operator_target: Option<String>,
         /// operator can subscribe/withdraw/update position.
         operator: Option<String>,
+        /// Fails the subscription if it mints fewer shares, protecting against dilution by
+        /// subscriptions executed before it.
+        min_shares_out: Option<Uint256>,
+    },
+    /// Swaps the attached funds to the in denom of the stream through `routes` and subscribes
+    /// with the output. Funds are refunded if the swap returns less than `min_in_amount`.
+    SubscribeWithSwap {
+        stream_id: u64,
+        routes: Vec<SwapAmountInRoute>,
+        min_in_amount: Uint128,
     },
     /// Withdraw unspent tokens in balance.
     Withdraw {
@@ -91,7 +152,135 @@ pub enum ExecuteMsg {
     /// call this method.
     FinalizeStream {
         stream_id: u64,
-        new_treasury: Option<String>,
+    },
+    /// ProposeTreasury proposes a new address to receive the stream earnings.
+    /// Only current treasury can propose, the change is applied once the new address accepts it.
+    ProposeTreasury {
+        stream_id: u64,
+        new_treasury: String,
+    },
+    /// AcceptTreasury is executed by the proposed treasury to take over the treasury role.
+    AcceptTreasury {
+        stream_id: u64,
+    },
+    /// ProposeOperations proposes a new holder of the operations role, which configures the sale
+    /// terms, blocklist, tags and creator profile of the stream, creator pauses and resumes and
+    /// sets finalize hooks. The treasury holds the role until it is handed over, and keeps the
+    /// pool escrow and the remote treasury as they move its funds. Only current holder can
+    /// propose, the change is applied once accepted.
+    ProposeOperations {
+        stream_id: u64,
+        new_operations: String,
+    },
+    /// AcceptOperations is executed by the proposed address to take over the operations role.
+    AcceptOperations {
+        stream_id: u64,
+    },
+    /// UpdateFinalizeHooks replaces the contracts notified when the stream is finalized.
+    /// Only the operations role can update, before the stream is finalized.
+    UpdateFinalizeHooks {
+        stream_id: u64,
+        hooks: Vec<String>,
+    },
+    /// AddCreationHook registers a contract notified when any stream is created.
+    /// Only protocol admin can add.
+    AddCreationHook {
+        addr: String,
+    },
+    /// RemoveCreationHook unregisters a creation hook. Only protocol admin can remove.
+    RemoveCreationHook {
+        addr: String,
+    },
+    /// UpdateRemoteTreasury sets a remote chain address receiving the stream revenue over the
+    /// configured IBC channel, `None` pays the treasury locally. Only treasury can update, before
+    /// the stream is finalized.
+    UpdateRemoteTreasury {
+        stream_id: u64,
+        remote_treasury: Option<String>,
+    },
+    /// UpdateAllocationTiers reserves out tokens for tiers of addresses, replacing the previous
+    /// tiers. Members deposit in tokens before start and share the tier allocation pro-rata to
+    /// their deposits, the public stream sells the rest of the supply. Only the operations role can
+    /// update, before the stream starts and before any deposit.
+    UpdateAllocationTiers {
+        stream_id: u64,
+        tiers: Vec<AllocationTier>,
+    },
+    /// SubscribeTier deposits in tokens for the sender's tier allocation, up to the tier member
+    /// cap. Members of merkle root tiers send a `proof` with their first deposit. Only before the
+    /// stream starts, deposits can not be withdrawn.
+    SubscribeTier {
+        stream_id: u64,
+        proof: Option<TierProof>,
+    },
+    /// ExitTier sends the sender its share of the tier allocation once the stream ends, or
+    /// refunds the deposit if the stream is cancelled.
+    ExitTier {
+        stream_id: u64,
+    },
+    /// UpdateHardCap sets the maximum in tokens the treasury receives, `None` removes it. If the
+    /// stream spends more, the excess is refunded to subscribers pro-rata on exit. Only the
+    /// operations role can update, before the stream starts.
+    UpdateHardCap {
+        stream_id: u64,
+        hard_cap: Option<Uint256>,
+    },
+    /// UpdatePartialRefund enables settling a missed threshold at finalize instead of cancelling.
+    /// Positions keep `spent_in / threshold` of their purchase and spent tokens, the rest of the
+    /// spent tokens is refunded on exit and the treasury receives the proportional revenue. Only
+    /// the operations role can update, before the stream starts.
+    UpdatePartialRefund {
+        stream_id: u64,
+        enabled: bool,
+    },
+    /// UpdateSubscriptionLimit caps in tokens the stream accepts from subscriptions within a window
+    /// of blocks, `None` removes the cap. Only the operations role can update, before the stream
+    /// ends. Pending subscriptions before start are not limited.
+    UpdateSubscriptionLimit {
+        stream_id: u64,
+        limit: Option<SubscriptionLimit>,
+    },
+    /// PruneStreams removes state of streams finalized or cancelled more than
+    /// `prune_delay_blocks` ago whose positions are all exited or claimed from escrow, keeping a
+    /// summary of each. Anyone can prune.
+    PruneStreams {
+        stream_ids: Vec<u64>,
+    },
+    /// CrankExits exits up to `limit` positions of a finalized stream on behalf of their owners,
+    /// once the exit crank grace period after the end has passed. The caller is paid a share of
+    /// the swap fee of each exited position.
+    CrankExits {
+        stream_id: u64,
+        limit: Option<u32>,
+    },
+    /// ClaimKeeperBounty sends the sender its keeper bounty earned on a finalized stream.
+    ClaimKeeperBounty {
+        stream_id: u64,
+    },
+    /// MigrateStep converts the next `limit` legacy records left by a migration. Other messages
+    /// fail until the migration completes. Anyone can continue the migration.
+    MigrateStep {
+        limit: Option<u32>,
+    },
+    /// EscheatPositions moves up to `limit` positions never exited of a stream finalized more
+    /// than `escheat_delay_blocks` ago into escrow, claimable by their owners with
+    /// `ClaimEscheated`. Only protocol admin can escheat.
+    EscheatPositions {
+        stream_id: u64,
+        limit: Option<u32>,
+    },
+    /// ClaimEscheated sends the sender the tokens of its escheated position. Purchases of vesting
+    /// streams fund a vesting contract of the sender. Blocked and non compliant senders can not
+    /// claim.
+    ClaimEscheated {
+        stream_id: u64,
+    },
+    /// ClaimIbcRefunds sends the sender tokens of its failed or timed out IBC transfers.
+    ClaimIbcRefunds {},
+    /// ClaimVestedRevenue sends the released part of the creator revenue to the treasury.
+    /// Only for streams created with `revenue_vesting_duration`.
+    ClaimVestedRevenue {
+        stream_id: u64,
     },
     /// ExitStream withdraws (by a user who subscribed to the stream) purchased
     /// tokens_out from the pool and remained tokens_in. Must be called after
@@ -100,6 +289,17 @@ pub enum ExecuteMsg {
         stream_id: u64,
         /// operator_target is the address of operator targets to execute on behalf of the user.
         operator_target: Option<String>,
+        /// If set, purchased tokens are sent to this remote chain address over the configured
+        /// IBC channel. The position owner can claim them back if the transfer fails.
+        remote_recipient: Option<String>,
+        /// If true, purchased tokens are bonded with the configured liquid staking hub and the
+        /// staked tokens minted to the position owner. Purchases are not delegated to a validator
+        /// directly, as the contract would be the delegator and hold the stake of every owner.
+        #[serde(default)]
+        liquid_stake: bool,
+        /// Swaps purchased and/or refunded tokens before sending them to the position owner.
+        /// Failed swaps send the raw tokens instead.
+        swap: Option<ExitSwap>,
     },
     //
     // Killswitch features
@@ -108,6 +308,21 @@ pub enum ExecuteMsg {
     PauseStream {
         stream_id: u64,
     },
+    /// CreatorPauseStream pauses the stream on behalf of its operations role, e.g. in response to a token
+    /// bug. A stream can be creator paused only once, for at most `max_creator_pause_duration`.
+    CreatorPauseStream {
+        stream_id: u64,
+    },
+    /// CreatorResumeStream resumes a creator paused stream. The operations role can resume any
+    /// time, anyone can resume once `max_creator_pause_duration` has passed.
+    CreatorResumeStream {
+        stream_id: u64,
+    },
+    /// ResumeExpiredPause resumes a stream paused for longer than `max_pause_duration`.
+    /// Anyone can call this method.
+    ResumeExpiredPause {
+        stream_id: u64,
+    },
     /// WithdrawPaused is used to withdraw unspent position funds during pause.
     WithdrawPaused {
         stream_id: u64,
@@ -115,16 +330,35 @@ pub enum ExecuteMsg {
         // operator_target is the address of operator targets to execute on behalf of the user.
         operator_target: Option<String>,
     },
+    /// ExitPaused exits a stream paused for longer than `max_pause_duration` with the tokens
+    /// purchased until the pause and the unspent balance. Spent tokens are settled with the
+    /// stream, as revenue and swap fee at finalize or cancel. Not available for streams with a
+    /// threshold or vesting.
+    ExitPaused {
+        stream_id: u64,
+        /// operator_target is the address of operator targets to execute on behalf of the user.
+        operator_target: Option<String>,
+    },
     /// ExitCancelled returns the whole balance user put in the stream, both spent and unspent.
     ExitCancelled {
         stream_id: u64,
         /// operator_target is the address of operator targets to execute on behalf of the user.
         operator_target: Option<String>,
     },
+    /// BatchExitCancelled refunds up to `limit` positions of a cancelled stream, or an ended
+    /// stream that missed its threshold, to their owners. Callable by anyone.
+    BatchExitCancelled {
+        stream_id: u64,
+        limit: Option<u32>,
+    },
+    /// CancelStreamWithThreshold cancels an ended stream that missed its threshold. The creation
+    /// fee is refunded to the treasury, minus the `creator_cancel_penalty` share if configured.
     CancelStreamWithThreshold {
         stream_id: u64,
     },
 
+    /// UpdateConfig updates the config, or proposes the update if a config timelock is set.
+    /// Unset fields are kept as is, optional fields are cleared by an explicit `null`.
     UpdateConfig {
         min_stream_duration: Option<Uint64>,
         min_duration_until_start_time: Option<Uint64>,
@@ -133,13 +367,219 @@ pub enum ExecuteMsg {
         fee_collector: Option<String>,
         accepted_in_denom: Option<String>,
         exit_fee_percent: Option<Decimal256>,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "crate::helpers::double_option"
+        )]
+        max_pause_duration: Option<Option<Uint64>>,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "crate::helpers::double_option"
+        )]
+        max_creator_pause_duration: Option<Option<Uint64>>,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "crate::helpers::double_option"
+        )]
+        creator_cancel_penalty: Option<Option<Decimal256>>,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "crate::helpers::double_option"
+        )]
+        permissioned_creation: Option<Option<bool>>,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "crate::helpers::double_option"
+        )]
+        config_timelock: Option<Option<Uint64>>,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "crate::helpers::double_option"
+        )]
+        prune_delay_blocks: Option<Option<Uint64>>,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "crate::helpers::double_option"
+        )]
+        escheat_delay_blocks: Option<Option<Uint64>>,
     },
+    /// ApplyConfigUpdate applies the pending config update once the config timelock has passed.
+    /// Anyone can apply.
+    ApplyConfigUpdate {},
+    /// CancelConfigUpdate drops the pending config update. Only protocol admin can cancel.
+    CancelConfigUpdate {},
     ResumeStream {
         stream_id: u64,
     },
+    /// CancelStream cancels a paused stream. If a guardian set is configured, it confirms the
+    /// cancel on behalf of the sending guardian instead, and the stream is cancelled once enough
+    /// guardians confirm.
     CancelStream {
         stream_id: u64,
     },
+    /// ConfirmCancelStream adds a guardian confirmation to an open cancel proposal.
+    ConfirmCancelStream {
+        stream_id: u64,
+    },
+    /// UpdateGuardians replaces the killswitch guardian set. Empty `guardians` removes the set,
+    /// giving killswitch control back to protocol admin. Only protocol admin can update.
+    UpdateGuardians {
+        guardians: Vec<String>,
+        cancel_threshold: u32,
+    },
+    /// UpdateFeeSwap sets the route swap fees are swapped through before reaching the fee
+    /// collector. `None` disables the swap. Only protocol admin can update.
+    UpdateFeeSwap {
+        fee_swap: Option<FeeSwap>,
+    },
+    /// UpdateIbcTransfer sets the channel used for cross-chain exits. `None` disables them.
+    /// Only protocol admin can update.
+    UpdateIbcTransfer {
+        ibc_transfer: Option<IbcTransfer>,
+    },
+    /// UpdateStreamTags replaces the tags of a stream, at most 5 lowercase alphanumeric tags with
+    /// dashes. Only the operations role can update, until the stream is finalized or cancelled.
+    UpdateStreamTags {
+        stream_id: u64,
+        tags: Vec<String>,
+    },
+    /// UpdateCreatorProfile registers the profile of the sender, a display name with an optional
+    /// url and hex encoded sha256 logo hash. `None` removes the profile.
+    UpdateCreatorProfile {
+        profile: Option<CreatorProfile>,
+    },
+    /// AttachCreatorProfile attaches the profile of the sender to a stream, `false` detaches the
+    /// attached profile. Only the operations role can update, until the stream is finalized or
+    /// cancelled.
+    AttachCreatorProfile {
+        stream_id: u64,
+        attach: bool,
+    },
+    /// UpdateBlocklist adds and removes addresses not allowed to subscribe to a stream. Blocked
+    /// positions can still withdraw and exit. Only the operations role can update, until the stream
+    /// is finalized or cancelled.
+    UpdateBlocklist {
+        stream_id: u64,
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// RefundBlocked withdraws the unspent in tokens of a blocked position back to its owner.
+    /// Purchased tokens are kept by the position. Only the operations role can refund.
+    RefundBlocked {
+        stream_id: u64,
+        owner: String,
+    },
+    /// SweepDust sends the balance of `denom` over what the contract owes to the fee collector,
+    /// once no open stream uses the denom. What is owed is tallied over up to `limit` streams
+    /// per call, the call completing the tally sweeps. Only protocol admin can sweep.
+    SweepDust {
+        denom: String,
+        limit: Option<u32>,
+    },
+    /// RecoverFunds sends tokens of a denom the contract owes nothing in, such as tokens sent by
+    /// mistake, to `recipient`. Denoms of open streams can not be recovered. What is owed is
+    /// tallied over up to `limit` streams per call, the call completing the tally recovers. Only
+    /// protocol admin can recover.
+    RecoverFunds {
+        denom: String,
+        amount: Uint128,
+        recipient: String,
+        limit: Option<u32>,
+    },
+    /// UpdateLiquidStaking sets the hub exits can bond purchased tokens with. `None` disables
+    /// liquid staking. Only protocol admin can update.
+    UpdateLiquidStaking {
+        liquid_staking: Option<LiquidStaking>,
+    },
+    /// UpdateNameRegistry enables unique stream names, released `release_after` seconds after
+    /// the stream ends. `None` disables it. Only protocol admin can update.
+    UpdateNameRegistry {
+        name_registry: Option<NameRegistry>,
+    },
+    /// UpdateComplianceContract sets the contract screening subscribers and exiting positions of
+    /// all streams. `None` disables screening. Only protocol admin can update.
+    UpdateComplianceContract {
+        compliance_contract: Option<String>,
+    },
+    /// UpdateExitCrank enables exiting positions left behind after the grace period for a share
+    /// of their swap fee, reserved at finalize. `None` disables it. Only protocol admin can update.
+    UpdateExitCrank {
+        exit_crank: Option<ExitCrank>,
+    },
+    /// UpdateVestingCodeId sets the cw-vesting code instantiated at exit for streams vesting
+    /// purchases. `None` disables setting vesting on streams, streams already vesting keep their
+    /// code. Only protocol admin can update.
+    UpdateVestingCodeId {
+        vesting_code_id: Option<u64>,
+    },
+    /// UpdateFinalizeHookLimits sets the maximum number of finalize hooks of a stream and the gas
+    /// each hook can use. `None` restores the defaults. Only protocol admin can update.
+    UpdateFinalizeHookLimits {
+        limits: Option<FinalizeHookLimits>,
+    },
+    /// UpdateStreamVesting vests purchased tokens in a cw-vesting contract instantiated for each
+    /// position at exit. `None` sends them at exit. Only the operations role can update, before the
+    /// stream starts.
+    UpdateStreamVesting {
+        stream_id: u64,
+        vesting: Option<VestingConfig>,
+    },
+    /// RetryVesting instantiates the vesting contract of the sender again with the purchased
+    /// tokens escrowed by a failed instantiation at exit.
+    RetryVesting {
+        stream_id: u64,
+    },
+    /// UpdateCreatePool creates a concentrated liquidity pool at finalize, seeded with
+    /// `out_amount_clp` out tokens and in tokens at the average streamed price out of the creator
+    /// revenue. The out tokens and the pool creation fee are escrowed with this message and
+    /// refunded if the stream is cancelled. The position, full range unless ticks are set, is
+    /// transferred to the treasury once created. `None` refunds the escrow. Only treasury can
+    /// update, before the stream starts.
+    UpdateCreatePool {
+        stream_id: u64,
+        create_pool: Option<CreatePool>,
+    },
+    /// UpdateKeeperBounty sets the bounty paid for updating stale streams. `None` disables it.
+    /// Only protocol admin can update.
+    UpdateKeeperBounty {
+        keeper_bounty: Option<KeeperBounty>,
+    },
+    /// UpdateRemoteController sets the Polytone proxy or interchain account through which a
+    /// remote chain governance can pause, resume and cancel streams. `None` removes it.
+    /// Only protocol admin can update.
+    UpdateRemoteController {
+        remote_controller: Option<String>,
+    },
+    /// UpdateFeeWaiver sets the stream creation fee discount of a creator, `1` waives the
+    /// fee entirely. `None` removes the waiver. Only protocol admin can update.
+    UpdateFeeWaiver {
+        creator: String,
+        discount: Option<Decimal256>,
+    },
+    /// FreezeDenom blocks creating streams selling or accepting `denom`. Existing streams are not
+    /// affected. Only protocol admin can freeze.
+    /// If `expiry` is set, the denom is unfrozen automatically once it expires.
+    FreezeDenom {
+        denom: String,
+        expiry: Option<Expiration>,
+    },
+    /// UnfreezeDenom allows creating streams with `denom` again. Only protocol admin can unfreeze.
+    UnfreezeDenom {
+        denom: String,
+    },
+    /// UpdateCreatorAllowlist adds and removes creators allowed to create streams while creation
+    /// is permissioned. Only protocol admin can update.
+    UpdateCreatorAllowlist {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
 }
 
 #[cw_serde]
@@ -151,22 +591,79 @@ pub enum QueryMsg {
     /// Returns a stream's current state.
     #[returns(StreamResponse)]
     Stream { stream_id: u64 },
-    /// Returns list of streams paginated by `start_after` and `limit`.
+    /// Returns a stream's state at the beginning of block `height`.
+    #[returns(StreamResponse)]
+    StreamAtHeight { stream_id: u64, height: u64 },
+    /// Returns list of streams paginated by `start_after` and `limit`, optionally only the ones
+    /// with the given `status` and `tag`. Ascending by stream id unless `order` is set.
     #[returns(StreamsResponse)]
     ListStreams {
         start_after: Option<u64>,
         limit: Option<u32>,
+        status: Option<Status>,
+        order: Option<OrderBy>,
+        tag: Option<String>,
+    },
+    /// Returns the addresses blocked from subscribing to a stream.
+    #[returns(Vec<Addr>)]
+    Blocklist {
+        stream_id: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the phase of a stream and the actions currently callable on it.
+    #[returns(LifecycleResponse)]
+    Lifecycle { stream_id: u64 },
+    /// Returns the tags of a stream.
+    #[returns(Vec<String>)]
+    StreamTags { stream_id: u64 },
+    /// Returns the registered profile of a creator.
+    #[returns(Option<CreatorProfile>)]
+    CreatorProfile { creator: String },
+    /// Returns the creator attached to a stream with its profile.
+    #[returns(Option<StreamCreatorResponse>)]
+    StreamCreator { stream_id: u64 },
+    /// Returns the ids of the streams a creator attached its profile to, paginated by
+    /// `start_after` and `limit`.
+    #[returns(Vec<u64>)]
+    CreatorStreams {
+        creator: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
     },
+    /// Returns the id of the stream holding a name in the name registry.
+    #[returns(Option<u64>)]
+    ResolveName { name: String },
+    /// Returns number of streams in state, pruned streams are not counted.
+    #[returns(CountResponse)]
+    CountStreams {},
     /// Returns current state of a position.
     #[returns(PositionResponse)]
     Position { stream_id: u64, owner: String },
-    /// Returns list of positions paginated by `start_after` and `limit`.
+    /// Returns list of positions paginated by `start_after` and `limit`. Ascending by owner unless
+    /// `order` is set.
     #[returns(PositionsResponse)]
     ListPositions {
         stream_id: u64,
         start_after: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     },
+    /// Returns number of open positions of a stream.
+    #[returns(CountResponse)]
+    CountPositions { stream_id: u64 },
+    /// Returns positions of `owner` across all streams paginated by `start_after` stream id and
+    /// `limit`.
+    #[returns(PositionsResponse)]
+    PositionsByOwner {
+        owner: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        order: Option<OrderBy>,
+    },
+    /// Returns the largest positions of a stream by shares, descending.
+    #[returns(PositionsResponse)]
+    LargestPositions { stream_id: u64, limit: Option<u32> },
     /// Returns average price of a stream sale.
     #[returns(AveragePriceResponse)]
     AveragePrice { stream_id: u64 },
@@ -175,6 +672,136 @@ pub enum QueryMsg {
     LastStreamedPrice { stream_id: u64 },
     #[returns(Uint128)]
     Threshold { stream_id: u64 },
+    /// Returns the treasury proposed for a stream, waiting for acceptance.
+    #[returns(Option<Addr>)]
+    PendingTreasury { stream_id: u64 },
+    /// Returns the operations role of a stream and the address proposed to take it over.
+    #[returns(StreamOperationsResponse)]
+    StreamOperations { stream_id: u64 },
+    /// Returns contracts notified when the stream is finalized.
+    #[returns(Vec<Addr>)]
+    FinalizeHooks { stream_id: u64 },
+    /// Resolves the trace of an IBC denom, failing for unknown or zero supply IBC denoms.
+    /// Non IBC denoms are returned without trace.
+    #[returns(ValidateDenomResponse)]
+    ValidateDenom { denom: String },
+    /// Returns the remote chain address receiving revenue of the stream, if any.
+    #[returns(Option<String>)]
+    RemoteTreasury { stream_id: u64 },
+    /// Returns the allocation tiers of a stream.
+    #[returns(Vec<AllocationTier>)]
+    AllocationTiers { stream_id: u64 },
+    /// Returns the tier deposit of `owner` and the allocation it currently receives.
+    #[returns(Option<TierDepositResponse>)]
+    TierDeposit { stream_id: u64, owner: String },
+    /// Returns the hard cap of a stream, if any.
+    #[returns(Option<Uint256>)]
+    HardCap { stream_id: u64 },
+    /// Returns whether a stream refunds partially and its fill once settled.
+    #[returns(PartialRefundResponse)]
+    PartialRefund { stream_id: u64 },
+    /// Returns the subscription limit of a stream and the amount it still accepts in the
+    /// current window.
+    #[returns(Option<SubscriptionLimitResponse>)]
+    SubscriptionLimit { stream_id: u64 },
+    /// Returns the summary of a pruned stream.
+    #[returns(Option<crate::prune::PrunedStream>)]
+    PrunedStream { stream_id: u64 },
+    /// Returns the tokens of an escheated position claimable by the owner.
+    #[returns(Option<EscheatedPosition>)]
+    EscheatedPosition { stream_id: u64, owner: String },
+    /// Returns the vesting of purchased tokens of a stream.
+    #[returns(Option<StreamVesting>)]
+    StreamVesting { stream_id: u64 },
+    /// Returns the vesting contract instantiated at exit for the owner.
+    #[returns(Option<Addr>)]
+    Vesting { stream_id: u64, owner: String },
+    /// Returns the purchased tokens escrowed for the owner by a failed vesting instantiation.
+    #[returns(Option<cosmwasm_std::Coin>)]
+    FailedVesting { stream_id: u64, owner: String },
+    /// Lists the vesting contracts instantiated at exits of the stream, ordered by owner.
+    #[returns(Vec<VestingResponse>)]
+    ListVestings {
+        stream_id: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the legacy records the next `MigrateStep` would convert, without saving them.
+    #[returns(crate::migrate_v0_2_1::MigrationBatch)]
+    MigrateDryRun { limit: Option<u32> },
+    /// Returns the pool created at finalize of a stream, with its id once created.
+    #[returns(Option<StreamPool>)]
+    StreamPool { stream_id: u64 },
+    /// Returns tokens of failed IBC transfers claimable by the address.
+    #[returns(Vec<cosmwasm_std::Coin>)]
+    IbcRefunds { address: String },
+    /// Returns the keeper bounty earned by `keeper` on a stream.
+    #[returns(Uint256)]
+    KeeperBounty { stream_id: u64, keeper: String },
+    /// Returns contracts notified when a stream is created.
+    #[returns(HooksResponse)]
+    CreationHooks {},
+    /// Returns release state of creator revenue, if the stream vests it.
+    #[returns(Option<RevenueVesting>)]
+    RevenueVesting { stream_id: u64 },
+    /// Returns streamed price snapshots between `start` and `end`, paginated by `limit`.
+    #[returns(PriceHistoryResponse)]
+    PriceHistory {
+        stream_id: u64,
+        start: Option<Timestamp>,
+        end: Option<Timestamp>,
+        limit: Option<u32>,
+    },
+    /// Returns the time weighted average streamed price over the last `window` seconds.
+    #[returns(StreamedPriceTwapResponse)]
+    StreamedPriceTwap { stream_id: u64, window: Uint64 },
+    /// Returns the killswitch guardian set, if configured.
+    #[returns(Option<GuardianSet>)]
+    Guardians {},
+    /// Returns guardians confirmed cancelling a stream.
+    #[returns(Vec<Addr>)]
+    CancelConfirmations { stream_id: u64 },
+    /// Returns the stream creation fee discount of a creator, if any.
+    #[returns(Option<Decimal256>)]
+    FeeWaiver { creator: String },
+    /// Returns subscription, withdrawal and exit statistics of a stream.
+    #[returns(StatisticsResponse)]
+    Statistics { stream_id: u64 },
+    /// Returns protocol wide subscription, revenue and fee totals per denom.
+    #[returns(ProtocolStatsResponse)]
+    ProtocolStats {},
+    /// Estimates the outcome of subscribing `amount` at `at_time`, current block time if not set.
+    #[returns(SimulateSubscribeResponse)]
+    SimulateSubscribe {
+        stream_id: u64,
+        amount: Uint256,
+        at_time: Option<Timestamp>,
+    },
+    /// Returns what exiting the position of `owner` would pay out at the current block time.
+    #[returns(SimulateExitResponse)]
+    SimulateExit { stream_id: u64, owner: String },
+    /// Compares the bank balances of the contract with the tokens it owes, per denom.
+    #[returns(AuditBalancesResponse)]
+    AuditBalances {},
+    /// Returns denoms streams can not be created with, paginated by `start_after` and `limit`.
+    /// Expired freezes are skipped.
+    #[returns(Vec<FrozenDenomResponse>)]
+    FrozenDenoms {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the config update waiting for the config timelock.
+    #[returns(Option<PendingConfigUpdate>)]
+    PendingConfigUpdate {},
+    /// Returns the protocol admin transfer waiting to be accepted.
+    #[returns(Option<PendingProtocolAdmin>)]
+    PendingProtocolAdmin {},
+    /// Returns creators allowed to create streams while creation is permissioned.
+    #[returns(Vec<Addr>)]
+    CreatorAllowlist {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
@@ -194,7 +821,56 @@ pub struct ConfigResponse {
     /// Address of the fee collector.
     pub fee_collector: String,
     /// Address of the protocol admin.
-    pub protocol_admin: String,
+    pub protocol_admin: Option<String>,
+    /// Swap applied to swap fees before they are sent to the fee collector.
+    pub fee_swap: Option<FeeSwap>,
+    /// Channel used to deliver exits to remote chain addresses.
+    pub ibc_transfer: Option<IbcTransfer>,
+    /// Remote chain governance address accepted by the killswitch.
+    pub remote_controller: Option<String>,
+    /// Blocks after finalize or cancel from which stream state can be pruned.
+    pub prune_delay_blocks: Option<Uint64>,
+    /// Blocks after finalize from which positions never exited can be escheated.
+    pub escheat_delay_blocks: Option<Uint64>,
+    /// Bounty paid for updating stale streams.
+    pub keeper_bounty: Option<KeeperBounty>,
+    /// Liquid staking hub exits can bond purchased tokens with.
+    pub liquid_staking: Option<LiquidStaking>,
+    /// Release delay of stream names, names are not unique if not set.
+    pub name_registry: Option<NameRegistry>,
+    /// Contract screening subscribers and exiting positions.
+    pub compliance_contract: Option<String>,
+    /// Grace period and bounty of the exit crank.
+    pub exit_crank: Option<ExitCrank>,
+    /// Code id of the cw-vesting contract instantiated at exit.
+    pub vesting_code_id: Option<u64>,
+    /// Maximum number of finalize hooks of a stream and the gas each hook can use.
+    pub finalize_hook_limits: FinalizeHookLimits,
+    /// Maximum pause duration in seconds, after which anyone can resume a paused stream.
+    pub max_pause_duration: Option<Uint64>,
+    /// Maximum duration in seconds a treasury can pause its own stream.
+    pub max_creator_pause_duration: Option<Uint64>,
+    /// Share of the creation fee kept as penalty when the treasury cancels a stream.
+    pub creator_cancel_penalty: Option<Decimal256>,
+    /// Only allowlisted creators can create streams when true.
+    pub permissioned_creation: bool,
+    /// Delay in seconds before a config update can be applied.
+    pub config_timelock: Option<Uint64>,
+}
+
+#[cw_serde]
+pub struct ValidateDenomResponse {
+    pub denom: String,
+    /// Port and channel path of the IBC denom.
+    pub path: Option<String>,
+    /// Denom on the origin chain.
+    pub base_denom: Option<String>,
+}
+
+#[cw_serde]
+pub struct FrozenDenomResponse {
+    pub denom: String,
+    pub expiry: Option<Expiration>,
 }
 
 #[cw_serde]
@@ -230,6 +906,10 @@ pub struct StreamResponse {
     pub current_streamed_price: Decimal256,
     /// Status of the stream. Can be `Waiting`, `Active`, `Finalzed`, `Paused` or `Canceled` for kill switch.
     pub status: Status,
+    /// Phase of the stream derived from its status and the block time.
+    pub phase: Phase,
+    /// End time including the current pause duration if paused.
+    pub effective_end_time: Timestamp,
     /// Date when the stream was paused.
     pub pause_date: Option<Timestamp>,
     /// Exit fee percent.
@@ -262,6 +942,13 @@ pub struct PositionResponse {
     pub spent: Uint256,
     // operator can update position
     pub operator: Option<Addr>,
+    /// Average price paid per out token, none until something is purchased.
+    pub average_price: Option<Decimal256>,
+    /// Out tokens the unspent balance would buy if the stream continued at the current streamed
+    /// price.
+    pub projected_purchased: Uint256,
+    /// Average price including the projected purchase.
+    pub projected_average_price: Option<Decimal256>,
 }
 
 #[cw_serde]
@@ -269,6 +956,26 @@ pub struct PositionsResponse {
     pub positions: Vec<PositionResponse>,
 }
 
+#[cw_serde]
+pub enum OrderBy {
+    Ascending,
+    Descending,
+}
+
+impl From<OrderBy> for Order {
+    fn from(order: OrderBy) -> Self {
+        match order {
+            OrderBy::Ascending => Order::Ascending,
+            OrderBy::Descending => Order::Descending,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct CountResponse {
+    pub count: u64,
+}
+
 #[cw_serde]
 pub struct AveragePriceResponse {
     pub average_price: Decimal256,
@@ -279,12 +986,72 @@ pub struct LatestStreamedPriceResponse {
     pub current_streamed_price: Decimal256,
 }
 
+/// Messages sent to hook contracts on stream lifecycle events.
+#[cw_serde]
+pub enum StreamHookMsg {
+    StreamCreated {
+        stream_id: u64,
+        treasury: String,
+        in_denom: String,
+        out_denom: String,
+        out_supply: Uint256,
+        start_time: Timestamp,
+        end_time: Timestamp,
+    },
+    StreamFinalized {
+        stream_id: u64,
+        treasury: String,
+        in_denom: String,
+        out_denom: String,
+        /// total amount of `token_in` spent by subscribers.
+        spent_in: Uint256,
+        /// total amount of `token_out` sold.
+        total_sold: Uint256,
+        /// `token_in` sent to treasury after swap fee.
+        creators_revenue: Uint256,
+        swap_fee: Uint256,
+    },
+}
+
 #[cw_serde]
 pub enum SudoMsg {
-    PauseStream { stream_id: u64 },
-    CancelStream { stream_id: u64 },
-    ResumeStream { stream_id: u64 },
+    PauseStream {
+        stream_id: u64,
+    },
+    CancelStream {
+        stream_id: u64,
+    },
+    ResumeStream {
+        stream_id: u64,
+    },
+    /// Pauses all running streams in id order, scanning at most `limit` streams per call.
+    /// Continue with `start_after` set to the returned `last_stream_id`.
+    PauseAllStreams {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Resumes all paused streams in id order, scanning at most `limit` streams per call.
+    ResumeAllStreams {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Adjusts parameters of a live stream. `end_time` can only be extended and the stream must
+    /// not have ended yet.
+    UpdateStreamParams {
+        stream_id: u64,
+        end_time: Option<Timestamp>,
+        threshold: Option<Uint256>,
+    },
+    /// Packet outcome of a transfer sent with an ibc-hooks callback memo.
+    IbcLifecycleComplete(IbcLifecycleComplete),
+    /// End-blocker tick of clock or cron modules. Updates a batch of active streams, continuing
+    /// from the previous call, and finalizes an ended stream, including streams that ended
+    /// without any subscription.
+    Clock {},
 }
 
 #[cw_serde]
-pub struct MigrateMsg {}
+pub struct MigrateMsg {
+    /// Legacy records converted by the migration, the rest is converted with `MigrateStep`.
+    pub limit: Option<u32>,
+}
@@ -0,0 +1,114 @@
+use crate::contract::save_config_update;
+use crate::state::{ConfigUpdate, NameRegistry, StreamId, CONFIG, STREAMS};
+use crate::ContractError;
+use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Timestamp};
+use cw_storage_plus::Map;
+
+// Lowercased stream name -> stream id holding the name
+pub const STREAM_NAMES: Map<&str, StreamId> = Map::new("stream_names");
+
+pub(crate) fn name_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Returns the stream holding `name`, names are released `release_after` seconds after the
+/// stream ends or once it is pruned.
+fn name_holder(
+    storage: &dyn Storage,
+    now: Timestamp,
+    name: &str,
+    release_after: u64,
+) -> StdResult<Option<StreamId>> {
+    let Some(stream_id) = STREAM_NAMES.may_load(storage, &name_key(name))? else {
+        return Ok(None);
+    };
+    Ok(STREAMS
+        .may_load(storage, stream_id)?
+        .filter(|stream| now < stream.end_time.plus_seconds(release_after))
+        .map(|_| stream_id))
+}
+
+/// Fails if the registry is enabled and another stream holds the name.
+pub fn check_stream_name(
+    storage: &dyn Storage,
+    now: Timestamp,
+    registry: Option<&NameRegistry>,
+    name: &str,
+) -> Result<(), ContractError> {
+    let Some(registry) = registry else {
+        return Ok(());
+    };
+    if name_holder(storage, now, name, registry.release_after.u64())?.is_some() {
+        return Err(ContractError::StreamNameTaken {
+            name: name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Claims the name for the stream if the registry is enabled, checked by `check_stream_name`.
+pub fn claim_stream_name(
+    storage: &mut dyn Storage,
+    registry: Option<&NameRegistry>,
+    name: &str,
+    stream_id: StreamId,
+) -> StdResult<()> {
+    if registry.is_some() {
+        STREAM_NAMES.save(storage, &name_key(name), &stream_id)?;
+    }
+    Ok(())
+}
+
+/// Removes the name entry if it still points to the stream.
+pub fn remove_stream_name(
+    storage: &mut dyn Storage,
+    name: &str,
+    stream_id: StreamId,
+) -> StdResult<()> {
+    let key = name_key(name);
+    if STREAM_NAMES.may_load(storage, &key)? == Some(stream_id) {
+        STREAM_NAMES.remove(storage, &key);
+    }
+    Ok(())
+}
+
+pub fn execute_update_name_registry(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name_registry: Option<NameRegistry>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let release_after = name_registry
+        .as_ref()
+        .map(|r| r.release_after.to_string())
+        .unwrap_or_default();
+    let apply_after = save_config_update(
+        deps,
+        &env,
+        config,
+        ConfigUpdate {
+            name_registry: Some(name_registry),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_name_registry")
+        .add_attribute("release_after", release_after)
+        .add_attributes(apply_after.map(|t| ("apply_after", t.to_string()))))
+}
+
+/// Returns the stream holding the name. Names of streams ended longer than the release delay
+/// ago resolve to none.
+pub fn query_resolve_name(deps: Deps, env: Env, name: String) -> StdResult<Option<StreamId>> {
+    let release_after = CONFIG
+        .load(deps.storage)?
+        .name_registry
+        .map(|r| r.release_after.u64())
+        .unwrap_or_default();
+    name_holder(deps.storage, env.block.time, &name, release_after)
+}
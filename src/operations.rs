@@ -0,0 +1,102 @@
+use crate::state::{Stream, StreamId, STREAMS};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage};
+use cw_storage_plus::Map;
+
+// Stream id -> operations role of the stream, the treasury holds the role until handed over.
+// Actions moving treasury funds stay treasury-only: the pool escrow and the remote treasury.
+pub const STREAM_OPERATIONS: Map<StreamId, Addr> = Map::new("stream_operations");
+// Operations role proposed by the current holder, waiting to be accepted
+pub const PENDING_OPERATIONS: Map<StreamId, Addr> = Map::new("pending_operations");
+
+#[cw_serde]
+pub struct StreamOperationsResponse {
+    pub operations: Addr,
+    pub pending_operations: Option<Addr>,
+}
+
+/// Returns the holder of the operations role of the stream.
+pub fn stream_operations(
+    storage: &dyn Storage,
+    stream_id: StreamId,
+    stream: &Stream,
+) -> StdResult<Addr> {
+    Ok(STREAM_OPERATIONS
+        .may_load(storage, stream_id)?
+        .unwrap_or_else(|| stream.treasury.clone()))
+}
+
+/// Fails unless the sender holds the operations role of the stream.
+pub fn ensure_operations(
+    storage: &dyn Storage,
+    stream_id: StreamId,
+    stream: &Stream,
+    sender: &Addr,
+) -> Result<(), ContractError> {
+    if stream_operations(storage, stream_id, stream)? != sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+/// Proposes a new holder of the operations role, which configures the sale terms, blocklist, tags
+/// and creator profile of the stream, pauses within the creator pause limits and sets finalize
+/// hooks. Only the current holder can propose, the change is applied once the new address accepts
+/// it.
+pub fn execute_propose_operations(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    new_operations: String,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    let operations = stream_operations(deps.storage, stream_id, &stream)?;
+    if operations != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let new_operations = deps.api.addr_validate(&new_operations)?;
+    PENDING_OPERATIONS.save(deps.storage, stream_id, &new_operations)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_operations")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("operations", operations)
+        .add_attribute("pending_operations", new_operations))
+}
+
+pub fn execute_accept_operations(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    let pending_operations = PENDING_OPERATIONS
+        .may_load(deps.storage, stream_id)?
+        .ok_or(ContractError::NoPendingOperations {})?;
+    if pending_operations != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let previous_operations = stream_operations(deps.storage, stream_id, &stream)?;
+    STREAM_OPERATIONS.save(deps.storage, stream_id, &pending_operations)?;
+    PENDING_OPERATIONS.remove(deps.storage, stream_id);
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_operations")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("previous_operations", previous_operations)
+        .add_attribute("operations", pending_operations))
+}
+
+pub fn query_stream_operations(
+    deps: Deps,
+    stream_id: StreamId,
+) -> StdResult<StreamOperationsResponse> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    Ok(StreamOperationsResponse {
+        operations: stream_operations(deps.storage, stream_id, &stream)?,
+        pending_operations: PENDING_OPERATIONS.may_load(deps.storage, stream_id)?,
+    })
+}
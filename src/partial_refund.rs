@@ -0,0 +1,128 @@
+use crate::operations::ensure_operations;
+use crate::state::{Position, Stream, StreamId, STREAMS};
+use crate::threshold::{ThresholdError, ThresholdState};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Decimal256, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdResult, Storage, Uint256,
+};
+use cw_storage_plus::Map;
+
+// Stream id -> (), streams settling a missed threshold with partial refunds instead of cancelling
+pub const PARTIAL_REFUNDS: Map<StreamId, Empty> = Map::new("partial_refunds");
+// Stream id -> share of the spent tokens and purchases kept at finalize, spent over threshold
+pub const PARTIAL_FILLS: Map<StreamId, Decimal256> = Map::new("partial_fills");
+
+#[cw_serde]
+pub struct PartialRefundResponse {
+    pub enabled: bool,
+    /// Share kept by positions, set at finalize if the threshold was missed.
+    pub fill: Option<Decimal256>,
+}
+
+/// Enables or disables partial refunds for a stream with a threshold. If the threshold is missed,
+/// the stream is finalized with each position keeping `spent_in / threshold` of its purchase and
+/// spent tokens, the rest of the spent tokens is refunded. Only the operations role can update,
+/// before the stream starts.
+pub fn execute_update_partial_refund(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if env.block.time >= stream.start_time {
+        return Err(ContractError::StreamIsStarted {});
+    }
+    if enabled {
+        if !ThresholdState::new().check_if_threshold_set(stream_id, deps.storage)? {
+            return Err(ThresholdError::ThresholdNotSet {}.into());
+        }
+        PARTIAL_REFUNDS.save(deps.storage, stream_id, &Empty {})?;
+    } else {
+        PARTIAL_REFUNDS.remove(deps.storage, stream_id);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_partial_refund")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+/// Fails if the threshold is not reached, unless the stream refunds partially and can be
+/// finalized anyway.
+pub fn error_if_not_settleable(
+    storage: &dyn Storage,
+    stream_id: StreamId,
+    stream: &Stream,
+) -> Result<(), ThresholdError> {
+    if PARTIAL_REFUNDS.has(storage, stream_id) {
+        return Ok(());
+    }
+    ThresholdState::new().error_if_not_reached(stream_id, storage, stream)
+}
+
+/// Fails if the threshold is not reached, unless the stream was finalized with a partial fill.
+pub fn error_if_not_settled(
+    storage: &dyn Storage,
+    stream_id: StreamId,
+    stream: &Stream,
+) -> Result<(), ThresholdError> {
+    if PARTIAL_FILLS.has(storage, stream_id) {
+        return Ok(());
+    }
+    ThresholdState::new().error_if_not_reached(stream_id, storage, stream)
+}
+
+/// Checks the threshold at finalize. Streams refunding partially that missed it are settled with
+/// a fill of `spent_in / threshold`, returned if set.
+pub fn settle_threshold(
+    storage: &mut dyn Storage,
+    stream_id: StreamId,
+    stream: &Stream,
+) -> Result<Option<Decimal256>, ThresholdError> {
+    let threshold_state = ThresholdState::new();
+    match threshold_state.error_if_not_reached(stream_id, storage, stream) {
+        Err(ThresholdError::ThresholdNotReached {}) if PARTIAL_REFUNDS.has(storage, stream_id) => {
+            let threshold = threshold_state
+                .get_threshold(stream_id, storage)?
+                .unwrap_or_default();
+            let fill = Decimal256::from_ratio(stream.spent_in, threshold);
+            PARTIAL_FILLS.save(storage, stream_id, &fill)?;
+            Ok(Some(fill))
+        }
+        res => res.map(|_| None),
+    }
+}
+
+/// Returns the kept share of `amount`, rounded down.
+pub fn filled(amount: Uint256, fill: Decimal256) -> StdResult<Uint256> {
+    Ok(Decimal256::from_ratio(amount, Uint256::one()).checked_mul(fill)? * Uint256::one())
+}
+
+/// Applies the fill of a partially refunded stream to an updated position. The unfilled share of
+/// the spent tokens moves back to the balance, rounded down so refunds never exceed the unfilled
+/// spent tokens, and the unfilled share of the purchase is dropped.
+pub fn apply_partial_fill(
+    storage: &dyn Storage,
+    stream_id: StreamId,
+    position: &mut Position,
+) -> StdResult<()> {
+    let Some(fill) = PARTIAL_FILLS.may_load(storage, stream_id)? else {
+        return Ok(());
+    };
+    let refund = filled(position.spent, Decimal256::one() - fill)?;
+    position.spent = position.spent.checked_sub(refund)?;
+    position.in_balance = position.in_balance.checked_add(refund)?;
+    position.purchased = filled(position.purchased, fill)?;
+    Ok(())
+}
+
+pub fn query_partial_refund(deps: Deps, stream_id: StreamId) -> StdResult<PartialRefundResponse> {
+    Ok(PartialRefundResponse {
+        enabled: PARTIAL_REFUNDS.has(deps.storage, stream_id),
+        fill: PARTIAL_FILLS.may_load(deps.storage, stream_id)?,
+    })
+}
@@ -0,0 +1,417 @@
+use crate::state::{Stream, StreamId, STREAMS};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, Addr, BankMsg, Coin, CosmosMsg, Decimal256, Deps, DepsMut, Env, MessageInfo,
+    QuerierWrapper, Reply, Response, StdResult, Storage, SubMsg, SubMsgResult, Uint128, Uint256,
+};
+use cw_storage_plus::Map;
+use cw_utils::nonpayable;
+use osmosis_std::types::osmosis::concentratedliquidity::poolmodel::concentrated::v1beta1::{
+    MsgCreateConcentratedPool, MsgCreateConcentratedPoolResponse,
+};
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    MsgCreatePosition, MsgCreatePositionResponse, MsgTransferPositions,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::PoolmanagerQuerier;
+use std::str::FromStr;
+
+// Pool replies carry the stream id in the low bits of the reply id, as several streams can
+// create their pools in one transaction
+pub const CREATE_POOL_REPLY_ID: u64 = 1 << 62;
+pub const CREATE_POSITION_REPLY_ID: u64 = 2 << 62;
+pub const POOL_REPLY_ID_MASK: u64 = 3 << 62;
+
+// Full range ticks of concentrated liquidity pools
+const MIN_TICK: i64 = -108_000_000;
+const MAX_TICK: i64 = 342_000_000;
+
+/// Concentrated liquidity pool created at finalize, seeded with out tokens and the matching in
+/// tokens at the average streamed price.
+#[cw_serde]
+pub struct CreatePool {
+    /// Out tokens provided as liquidity, escrowed until finalize.
+    pub out_amount_clp: Uint256,
+    pub tick_spacing: u64,
+    pub spread_factor: Decimal256,
+    /// Ticks of the position, full range if not set. The range has to contain the average
+    /// streamed price the pool starts at, otherwise the position tokens are refunded.
+    pub lower_tick: Option<i64>,
+    pub upper_tick: Option<i64>,
+}
+
+#[cw_serde]
+pub struct StreamPool {
+    pub create_pool: CreatePool,
+    /// Poolmanager pool creation fee, escrowed until finalize.
+    pub pool_creation_fee: Vec<Coin>,
+    /// Id of the pool, set once created at finalize.
+    pub pool_id: Option<u64>,
+    /// Id of the position, transferred to the treasury once created.
+    pub position_id: Option<u64>,
+}
+
+// Stream id -> pool created at finalize, the escrow is held until the stream closes
+pub const STREAM_POOLS: Map<StreamId, StreamPool> = Map::new("stream_pools");
+// Stream id -> tokens provided to the position once the pool of the finalized stream is created
+pub const PENDING_POOL_POSITIONS: Map<StreamId, Vec<Coin>> = Map::new("pending_pool_positions");
+
+fn validate_create_pool(create_pool: &CreatePool) -> Result<(), ContractError> {
+    let tick_spacing = create_pool.tick_spacing as i64;
+    let lower_tick = create_pool.lower_tick.unwrap_or(MIN_TICK);
+    let upper_tick = create_pool.upper_tick.unwrap_or(MAX_TICK);
+    if create_pool.out_amount_clp.is_zero()
+        || tick_spacing <= 0
+        || MIN_TICK % tick_spacing != 0
+        || MAX_TICK % tick_spacing != 0
+        || lower_tick % tick_spacing != 0
+        || upper_tick % tick_spacing != 0
+        || lower_tick < MIN_TICK
+        || upper_tick > MAX_TICK
+        || lower_tick >= upper_tick
+        || create_pool.spread_factor >= Decimal256::one()
+    {
+        return Err(ContractError::InvalidCreatePool {});
+    }
+    Ok(())
+}
+
+/// Sets the pool created with the stream revenue at finalize, replacing the previous one. The
+/// sender escrows `out_amount_clp` out tokens and the pool creation fee, the previous escrow is
+/// refunded. Only treasury can update, before the stream starts.
+pub fn execute_update_create_pool(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    create_pool: Option<CreatePool>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.treasury != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if env.block.time >= stream.start_time {
+        return Err(ContractError::StreamIsStarted {});
+    }
+    let mut res = Response::new()
+        .add_messages(release_pool_escrow(deps.storage, stream_id, &stream)?)
+        .add_attribute("action", "update_create_pool")
+        .add_attribute("stream_id", stream_id.to_string());
+
+    let Some(create_pool) = create_pool else {
+        nonpayable(&info)?;
+        return Ok(res);
+    };
+    validate_create_pool(&create_pool)?;
+    let pool_creation_fee = query_pool_creation_fee(deps.querier)?;
+    let mut escrow = pool_creation_fee.clone();
+    escrow.push(Coin {
+        denom: stream.out_denom.clone(),
+        amount: Uint128::try_from(create_pool.out_amount_clp)?,
+    });
+    if !same_coins(escrow, info.funds.clone()) {
+        return Err(ContractError::InvalidFunds {});
+    }
+    res = res.add_attribute("out_amount_clp", create_pool.out_amount_clp);
+    STREAM_POOLS.save(
+        deps.storage,
+        stream_id,
+        &StreamPool {
+            create_pool,
+            pool_creation_fee,
+            pool_id: None,
+            position_id: None,
+        },
+    )?;
+
+    Ok(res)
+}
+
+fn query_pool_creation_fee(querier: QuerierWrapper) -> StdResult<Vec<Coin>> {
+    PoolmanagerQuerier::new(&querier)
+        .params()?
+        .params
+        .map(|params| params.pool_creation_fee)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|coin| {
+            Ok(Coin {
+                amount: Uint128::from_str(&coin.amount)?,
+                denom: coin.denom,
+            })
+        })
+        .collect()
+}
+
+/// Sums amounts of the same denom and sorts by denom, dropping zero amounts.
+fn merge_coins(coins: Vec<Coin>) -> Vec<(String, u128)> {
+    let mut merged: Vec<(String, u128)> = vec![];
+    for coin in coins.into_iter().filter(|coin| !coin.amount.is_zero()) {
+        match merged.iter_mut().find(|(denom, _)| *denom == coin.denom) {
+            Some((_, amount)) => *amount += coin.amount.u128(),
+            None => merged.push((coin.denom, coin.amount.u128())),
+        }
+    }
+    merged.sort();
+    merged
+}
+
+/// Compares coin lists regardless of their order, amounts of the same denom are summed.
+fn same_coins(a: Vec<Coin>, b: Vec<Coin>) -> bool {
+    merge_coins(a) == merge_coins(b)
+}
+
+/// Returns what is left of the escrow once the fee is paid, `None` if the escrow is short.
+fn escrow_excess(escrow: Vec<Coin>, fee: Vec<Coin>) -> Option<Vec<Coin>> {
+    let mut excess = merge_coins(escrow);
+    for (denom, amount) in merge_coins(fee) {
+        let (_, escrowed) = excess.iter_mut().find(|(d, _)| *d == denom)?;
+        *escrowed = escrowed.checked_sub(amount)?;
+    }
+    Some(
+        excess
+            .into_iter()
+            .filter(|(_, amount)| *amount > 0)
+            .map(|(denom, amount)| Coin::new(amount, denom))
+            .collect(),
+    )
+}
+
+fn pool_reply_stream_id(reply_id: u64) -> StreamId {
+    reply_id & !POOL_REPLY_ID_MASK
+}
+
+/// Refunds the pool escrow of a stream not creating its pool to the treasury.
+pub fn release_pool_escrow(
+    storage: &mut dyn Storage,
+    stream_id: StreamId,
+    stream: &Stream,
+) -> StdResult<Vec<CosmosMsg>> {
+    let Some(stream_pool) = STREAM_POOLS.may_load(storage, stream_id)? else {
+        return Ok(vec![]);
+    };
+    STREAM_POOLS.remove(storage, stream_id);
+    let mut escrow = stream_pool.pool_creation_fee;
+    escrow.push(Coin {
+        denom: stream.out_denom.clone(),
+        amount: Uint128::try_from(stream_pool.create_pool.out_amount_clp)?,
+    });
+    Ok(escrow
+        .into_iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .map(|coin| {
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: stream.treasury.to_string(),
+                amount: vec![coin],
+            })
+        })
+        .collect())
+}
+
+/// Creates the pool of a finalized stream, the position is created in the reply with the escrowed
+/// out tokens and in tokens at the average price, `spent_in / total_sold`, taken from the
+/// creator revenue. If the revenue is short, fewer out tokens are provided and the rest
+/// is refunded. The pool creation fee is paid at its current amount, the excess of the escrow is
+/// refunded. Streams that sold nothing, or whose escrow no longer covers the fee, refund the
+/// escrow. Returns the in tokens provided.
+pub fn create_pool_msgs(
+    deps: DepsMut,
+    env: &Env,
+    stream_id: StreamId,
+    stream: &Stream,
+    spent_in: Uint256,
+    total_sold: Uint256,
+    creator_revenue: Uint256,
+) -> Result<(Vec<SubMsg>, Uint256), ContractError> {
+    let Some(mut stream_pool) = STREAM_POOLS.may_load(deps.storage, stream_id)? else {
+        return Ok((vec![], Uint256::zero()));
+    };
+    let out_amount_clp = stream_pool.create_pool.out_amount_clp;
+    let in_needed = if total_sold.is_zero() {
+        Uint256::zero()
+    } else {
+        out_amount_clp.multiply_ratio(spent_in, total_sold)
+    };
+    let in_clp = in_needed.min(creator_revenue);
+    let out_clp = if in_clp < in_needed {
+        in_clp.multiply_ratio(total_sold, spent_in)
+    } else {
+        out_amount_clp
+    };
+    let pool_creation_fee = query_pool_creation_fee(deps.querier)?;
+    let fee_excess = escrow_excess(
+        stream_pool.pool_creation_fee.clone(),
+        pool_creation_fee.clone(),
+    );
+    let (Some(fee_excess), false) = (fee_excess, in_clp.is_zero() || out_clp.is_zero()) else {
+        let msgs = release_pool_escrow(deps.storage, stream_id, stream)?;
+        return Ok((msgs.into_iter().map(SubMsg::new).collect(), Uint256::zero()));
+    };
+    stream_pool.pool_creation_fee = pool_creation_fee;
+    STREAM_POOLS.save(deps.storage, stream_id, &stream_pool)?;
+
+    let mut tokens_provided = vec![
+        Coin {
+            denom: stream.out_denom.clone(),
+            amount: Uint128::try_from(out_clp)?,
+        },
+        Coin {
+            denom: stream.in_denom.clone(),
+            amount: Uint128::try_from(in_clp)?,
+        },
+    ];
+    tokens_provided.sort_by(|a, b| a.denom.cmp(&b.denom));
+    PENDING_POOL_POSITIONS.save(deps.storage, stream_id, &tokens_provided)?;
+    let create_pool_msg = MsgCreateConcentratedPool {
+        sender: env.contract.address.to_string(),
+        denom0: stream.out_denom.clone(),
+        denom1: stream.in_denom.clone(),
+        tick_spacing: stream_pool.create_pool.tick_spacing,
+        spread_factor: stream_pool.create_pool.spread_factor.atomics().to_string(),
+    };
+    let mut msgs = vec![SubMsg::reply_always(
+        create_pool_msg,
+        CREATE_POOL_REPLY_ID | stream_id,
+    )];
+    let mut refund = fee_excess;
+    let unused_out = out_amount_clp.checked_sub(out_clp)?;
+    if !unused_out.is_zero() {
+        refund.push(Coin {
+            denom: stream.out_denom.clone(),
+            amount: Uint128::try_from(unused_out)?,
+        });
+    }
+    for coin in refund {
+        msgs.push(SubMsg::new(BankMsg::Send {
+            to_address: stream.treasury.to_string(),
+            amount: vec![coin],
+        }));
+    }
+    Ok((msgs, in_clp))
+}
+
+/// Sends the tokens of a position that could not be provided to the treasury.
+fn refund_pool_tokens(treasury: &Addr, tokens: Vec<Coin>) -> Vec<BankMsg> {
+    tokens
+        .into_iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .map(|coin| BankMsg::Send {
+            to_address: treasury.to_string(),
+            amount: vec![coin],
+        })
+        .collect()
+}
+
+/// Saves the id of the created pool and provides the position to it. If the pool
+/// could not be created, the pool creation fee and the position tokens are refunded to the
+/// treasury and the stream stays finalized.
+pub fn reply_create_pool(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let stream_id = pool_reply_stream_id(msg.id);
+    let tokens_provided = PENDING_POOL_POSITIONS.load(deps.storage, stream_id)?;
+    let treasury = STREAMS.load(deps.storage, stream_id)?.treasury;
+    let data = match msg.result {
+        SubMsgResult::Ok(res) => res.data.ok_or(ContractError::InvalidPoolReply {})?,
+        SubMsgResult::Err(err) => {
+            PENDING_POOL_POSITIONS.remove(deps.storage, stream_id);
+            let stream_pool = STREAM_POOLS.load(deps.storage, stream_id)?;
+            STREAM_POOLS.remove(deps.storage, stream_id);
+            let mut refund = stream_pool.pool_creation_fee;
+            refund.extend(tokens_provided);
+            return Ok(Response::new()
+                .add_messages(refund_pool_tokens(&treasury, refund))
+                .add_attributes(vec![
+                    attr("action", "create_pool_failed"),
+                    attr("stream_id", stream_id.to_string()),
+                    attr("error", err),
+                ]));
+        }
+    };
+    let res: MsgCreateConcentratedPoolResponse = data.try_into()?;
+    let mut stream_pool = STREAM_POOLS.load(deps.storage, stream_id)?;
+    stream_pool.pool_id = Some(res.pool_id);
+    STREAM_POOLS.save(deps.storage, stream_id, &stream_pool)?;
+
+    let position_msg = MsgCreatePosition {
+        pool_id: res.pool_id,
+        sender: env.contract.address.to_string(),
+        lower_tick: stream_pool.create_pool.lower_tick.unwrap_or(MIN_TICK),
+        upper_tick: stream_pool.create_pool.upper_tick.unwrap_or(MAX_TICK),
+        tokens_provided: tokens_provided.into_iter().map(Into::into).collect(),
+        token_min_amount0: "0".to_string(),
+        token_min_amount1: "0".to_string(),
+    };
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_always(
+            position_msg,
+            CREATE_POSITION_REPLY_ID | stream_id,
+        ))
+        .add_attributes(vec![
+            attr("action", "create_pool"),
+            attr("stream_id", stream_id.to_string()),
+            attr("pool_id", res.pool_id.to_string()),
+        ]))
+}
+
+/// Saves the id of the created position and transfers it to the treasury of the stream, which
+/// can withdraw the liquidity from the pool. Tokens a ranged position did not take, or all of
+/// them if the position could not be created, are refunded to the treasury.
+pub fn reply_create_position(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let stream_id = pool_reply_stream_id(msg.id);
+    let tokens_provided = PENDING_POOL_POSITIONS.load(deps.storage, stream_id)?;
+    PENDING_POOL_POSITIONS.remove(deps.storage, stream_id);
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    let treasury = stream.treasury;
+    let data = match msg.result {
+        SubMsgResult::Ok(res) => res.data.ok_or(ContractError::InvalidPoolReply {})?,
+        SubMsgResult::Err(err) => {
+            return Ok(Response::new()
+                .add_messages(refund_pool_tokens(&treasury, tokens_provided))
+                .add_attributes(vec![
+                    attr("action", "create_pool_position_failed"),
+                    attr("stream_id", stream_id.to_string()),
+                    attr("error", err),
+                ]));
+        }
+    };
+    let res: MsgCreatePositionResponse = data.try_into()?;
+    let mut stream_pool = STREAM_POOLS.load(deps.storage, stream_id)?;
+    stream_pool.position_id = Some(res.position_id);
+    STREAM_POOLS.save(deps.storage, stream_id, &stream_pool)?;
+    // out tokens are the pool denom0, in tokens denom1
+    let mut unused = vec![];
+    for coin in tokens_provided {
+        let used = if coin.denom == stream.out_denom {
+            &res.amount0
+        } else {
+            &res.amount1
+        };
+        let used = Uint128::from_str(used).map_err(|_| ContractError::InvalidPoolReply {})?;
+        unused.push(Coin {
+            amount: coin.amount.saturating_sub(used),
+            denom: coin.denom,
+        });
+    }
+
+    let transfer_msg = MsgTransferPositions {
+        position_ids: vec![res.position_id],
+        sender: env.contract.address.to_string(),
+        new_owner: treasury.to_string(),
+    };
+    Ok(Response::new()
+        .add_message(transfer_msg)
+        .add_messages(refund_pool_tokens(&treasury, unused))
+        .add_attributes(vec![
+            attr("action", "create_pool_position"),
+            attr("stream_id", stream_id.to_string()),
+            attr("position_id", res.position_id.to_string()),
+            attr("owner", treasury.to_string()),
+        ]))
+}
+
+pub fn query_stream_pool(deps: Deps, stream_id: StreamId) -> StdResult<Option<StreamPool>> {
+    STREAM_POOLS.may_load(deps.storage, stream_id)
+}
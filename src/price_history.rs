@@ -0,0 +1,224 @@
+use crate::contract::update_stream;
+use crate::simulate::to_std_err;
+use crate::state::{Stream, StreamId, STREAMS};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal256, Deps, Env, Order, StdError, StdResult, Storage, Timestamp};
+use cw_storage_plus::{Bound, Map};
+
+/// Maximum number of price snapshots kept per stream, oldest ones are dropped first
+pub const MAX_PRICE_HISTORY: u32 = 500;
+const MAX_LIMIT: u32 = 100;
+const DEFAULT_LIMIT: u32 = 30;
+
+#[cw_serde]
+pub struct PricePoint {
+    pub time: Timestamp,
+    pub price: Decimal256,
+}
+
+#[cw_serde]
+pub struct PriceHistoryResponse {
+    pub prices: Vec<PricePoint>,
+}
+
+#[cw_serde]
+pub struct StreamedPriceTwapResponse {
+    /// Time weighted average of the streamed price between `start` and `end`.
+    pub twap: Decimal256,
+    pub start: Timestamp,
+    /// Block time, or the end of the stream once it ended.
+    pub end: Timestamp,
+}
+
+// (stream id, update time in nanos) -> streamed price at that update
+pub const PRICE_HISTORY: Map<(StreamId, u64), Decimal256> = Map::new("price_history");
+// Stream id -> number of stored price snapshots
+pub const PRICE_HISTORY_LEN: Map<StreamId, u32> = Map::new("price_history_len");
+// (stream id, update time in nanos) -> price accumulator at that update, kept with the snapshots
+pub const PRICE_CUMULATIVES: Map<(StreamId, u64), Decimal256> = Map::new("price_cumulatives");
+
+/// Records `current_streamed_price` of an updated stream.
+pub fn save_price_snapshot(
+    storage: &mut dyn Storage,
+    stream_id: StreamId,
+    stream: &Stream,
+) -> StdResult<()> {
+    // nothing streamed yet
+    if stream.current_streamed_price.is_zero() {
+        return Ok(());
+    }
+    let key = (stream_id, stream.last_updated.nanos());
+    // multiple updates in the same block overwrite the snapshot
+    let is_new = !PRICE_HISTORY.has(storage, key);
+    PRICE_HISTORY.save(storage, key, &stream.current_streamed_price)?;
+    PRICE_CUMULATIVES.save(storage, key, &stream.price_cumulative)?;
+    if !is_new {
+        return Ok(());
+    }
+
+    let len = PRICE_HISTORY_LEN
+        .may_load(storage, stream_id)?
+        .unwrap_or_default();
+    if len < MAX_PRICE_HISTORY {
+        return PRICE_HISTORY_LEN.save(storage, stream_id, &(len + 1));
+    }
+    // buffer is full, drop the oldest snapshot
+    let oldest = PRICE_HISTORY
+        .prefix(stream_id)
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .transpose()?;
+    if let Some(oldest) = oldest {
+        PRICE_HISTORY.remove(storage, (stream_id, oldest));
+        PRICE_CUMULATIVES.remove(storage, (stream_id, oldest));
+    }
+    Ok(())
+}
+
+/// Returns price snapshots between `start` and `end` (both inclusive) in ascending time.
+pub fn query_price_history(
+    deps: Deps,
+    stream_id: StreamId,
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+    limit: Option<u32>,
+) -> StdResult<PriceHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let min = start.map(|t| Bound::inclusive(t.nanos()));
+    let max = end.map(|t| Bound::inclusive(t.nanos()));
+    let prices = PRICE_HISTORY
+        .prefix(stream_id)
+        .range(deps.storage, min, max, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (time, price) = item?;
+            Ok(PricePoint {
+                time: Timestamp::from_nanos(time),
+                price,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(PriceHistoryResponse { prices })
+}
+
+/// Returns the time weighted average streamed price over the last `window` seconds of the
+/// stream. The accumulator between stored snapshots is interpolated linearly, windows starting
+/// before the oldest snapshot fail.
+pub fn query_streamed_price_twap(
+    deps: Deps,
+    env: Env,
+    stream_id: StreamId,
+    window: u64,
+) -> StdResult<StreamedPriceTwapResponse> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.last_updated < stream.end_time && !stream.is_killswitch_active() {
+        update_stream(env.block.time, &mut stream).map_err(to_std_err)?;
+    }
+    let end = env.block.time.min(stream.end_time).min(stream.last_updated);
+    if window == 0 || window > end.seconds() {
+        return Err(StdError::generic_err("Invalid twap window"));
+    }
+    let start = end.minus_seconds(window);
+
+    let (before_time, before) = PRICE_CUMULATIVES
+        .prefix(stream_id)
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive(start.nanos())),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?
+        .ok_or_else(|| StdError::generic_err("Not enough price history for the twap window"))?;
+    let (after_time, after) = PRICE_CUMULATIVES
+        .prefix(stream_id)
+        .range(
+            deps.storage,
+            Some(Bound::exclusive(start.nanos())),
+            None,
+            Order::Ascending,
+        )
+        .next()
+        .transpose()?
+        .filter(|(time, _)| *time < end.nanos())
+        .unwrap_or((end.nanos(), stream.price_cumulative));
+    let start_cumulative = if after_time == before_time {
+        before
+    } else {
+        before
+            + (after - before)
+                * Decimal256::from_ratio(start.nanos() - before_time, after_time - before_time)
+    };
+
+    let twap = (stream.price_cumulative - start_cumulative) / Decimal256::from_ratio(window, 1u128);
+    Ok(StreamedPriceTwapResponse { twap, start, end })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::{Addr, Uint128, Uint256};
+
+    #[test]
+    fn test_price_history() {
+        let mut deps = mock_dependencies();
+        let mut stream = Stream::new(
+            "test".to_string(),
+            Addr::unchecked("treasury"),
+            None,
+            "out".to_string(),
+            Uint256::from(100u128),
+            "in".to_string(),
+            Timestamp::from_seconds(0),
+            Timestamp::from_seconds(1_000_000),
+            Timestamp::from_seconds(0),
+            "fee".to_string(),
+            Uint128::new(100),
+            Decimal256::percent(1),
+        );
+        // no price before distribution
+        save_price_snapshot(deps.as_mut().storage, 1, &stream).unwrap();
+        assert_eq!(PRICE_HISTORY_LEN.may_load(&deps.storage, 1).unwrap(), None);
+
+        for i in 1..=MAX_PRICE_HISTORY + 2 {
+            stream.last_updated = Timestamp::from_seconds(i as u64);
+            stream.current_streamed_price = Decimal256::from_ratio(i, 1u128);
+            save_price_snapshot(deps.as_mut().storage, 1, &stream).unwrap();
+        }
+        // same block overwrites
+        stream.current_streamed_price = Decimal256::percent(50);
+        save_price_snapshot(deps.as_mut().storage, 1, &stream).unwrap();
+        assert_eq!(
+            PRICE_HISTORY_LEN.load(&deps.storage, 1).unwrap(),
+            MAX_PRICE_HISTORY
+        );
+
+        // the two oldest snapshots are dropped
+        let res = query_price_history(deps.as_ref(), 1, None, None, Some(2)).unwrap();
+        assert_eq!(
+            res.prices,
+            vec![
+                PricePoint {
+                    time: Timestamp::from_seconds(3),
+                    price: Decimal256::from_ratio(3u128, 1u128),
+                },
+                PricePoint {
+                    time: Timestamp::from_seconds(4),
+                    price: Decimal256::from_ratio(4u128, 1u128),
+                },
+            ]
+        );
+        let res = query_price_history(
+            deps.as_ref(),
+            1,
+            Some(Timestamp::from_seconds(MAX_PRICE_HISTORY as u64 + 1)),
+            Some(Timestamp::from_seconds(MAX_PRICE_HISTORY as u64 + 2)),
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.prices.len(), 2);
+        assert_eq!(res.prices[1].price, Decimal256::percent(50));
+    }
+}
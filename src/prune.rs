@@ -0,0 +1,202 @@
+use crate::allocation_tiers::{remove_allocation_tiers, TIER_DEPOSITS};
+use crate::blocklist::BLOCKLISTS;
+use crate::crank::CRANK_RESERVES;
+use crate::creator_profiles::remove_stream_creator;
+use crate::escheat::ESCHEATED_POSITIONS;
+use crate::hard_cap::HARD_CAPS;
+use crate::keeper::{KEEPER_BOUNTIES, STREAM_KEEPER_BOUNTIES};
+use crate::killswitch::PAUSED_EXITS;
+use crate::names::remove_stream_name;
+use crate::operations::{PENDING_OPERATIONS, STREAM_OPERATIONS};
+use crate::partial_refund::{PARTIAL_FILLS, PARTIAL_REFUNDS};
+use crate::pool::STREAM_POOLS;
+use crate::price_history::{PRICE_CUMULATIVES, PRICE_HISTORY, PRICE_HISTORY_LEN};
+use crate::revenue_vesting::REVENUE_VESTINGS;
+use crate::state::{
+    remove_from_stream_count, Status, StreamId, CANCEL_CONFIRMATIONS, CONFIG, CREATOR_PAUSES,
+    FINALIZE_HOOKS, PENDING_TREASURIES, POSITIONS, REMOTE_TREASURIES, STREAMS, STREAM_CLOSED_AT,
+    STREAM_DONATIONS, STREAM_SAVED_AT, STREAM_SNAPSHOTS,
+};
+use crate::stats::STREAM_STATS;
+use crate::subscription_limit::{SUBSCRIPTION_LIMITS, SUBSCRIPTION_WINDOWS};
+use crate::tags::remove_stream_tags;
+use crate::threshold::ThresholdState;
+use crate::vesting::{FAILED_VESTINGS, STREAM_VESTINGS, VESTING};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, Uint256};
+use cw_storage_plus::Map;
+
+/// Summary kept for a stream after its state is pruned.
+#[cw_serde]
+pub struct PrunedStream {
+    pub name: String,
+    pub treasury: Addr,
+    /// `Finalized` or `Cancelled`.
+    pub status: Status,
+    pub out_denom: String,
+    pub out_supply: Uint256,
+    pub in_denom: String,
+    pub spent_in: Uint256,
+    /// Block height the stream was finalized or cancelled at.
+    pub closed_at_height: u64,
+}
+
+// Stream id -> summary of a pruned stream
+pub const PRUNED_STREAMS: Map<StreamId, PrunedStream> = Map::new("pruned_streams");
+
+/// Removes state of streams closed more than `prune_delay_blocks` ago whose positions are all
+/// exited or claimed from escrow, keeping a `PrunedStream` summary. Anyone can prune once the delay is configured.
+pub fn execute_prune_streams(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    stream_ids: Vec<StreamId>,
+) -> Result<Response, ContractError> {
+    let prune_delay = CONFIG
+        .load(deps.storage)?
+        .prune_delay_blocks
+        .ok_or(ContractError::PruneDisabled {})?;
+
+    for &stream_id in &stream_ids {
+        let stream = STREAMS.load(deps.storage, stream_id)?;
+        // streams closed before closing heights were recorded can not be pruned
+        let closed_at_height = STREAM_CLOSED_AT
+            .may_load(deps.storage, stream_id)?
+            .ok_or(ContractError::StreamNotPrunable { stream_id })?;
+        let has_positions = POSITIONS
+            .prefix(stream_id)
+            .keys_raw(deps.storage, None, None, Order::Ascending)
+            .next()
+            .is_some();
+        // claims of escheated positions need the vesting and blocklist of the stream
+        let has_escheated = ESCHEATED_POSITIONS
+            .prefix(stream_id)
+            .keys_raw(deps.storage, None, None, Order::Ascending)
+            .next()
+            .is_some();
+        let vesting_pending = REVENUE_VESTINGS
+            .may_load(deps.storage, stream_id)?
+            .is_some_and(|vesting| vesting.claimed < vesting.total);
+        // bounties of cancelled streams are forfeited
+        let bounties_pending = stream.status == Status::Finalized
+            && KEEPER_BOUNTIES
+                .prefix(stream_id)
+                .keys_raw(deps.storage, None, None, Order::Ascending)
+                .next()
+                .is_some();
+        // the rest of the reserve is released to the fee collector by `CrankExits`
+        let crank_reserve_pending = CRANK_RESERVES.has(deps.storage, stream_id);
+        let has_tier_deposits = TIER_DEPOSITS
+            .prefix(stream_id)
+            .keys_raw(deps.storage, None, None, Order::Ascending)
+            .next()
+            .is_some();
+        // retries of failed vestings need the vesting of the stream
+        let has_failed_vestings = FAILED_VESTINGS
+            .prefix(stream_id)
+            .keys_raw(deps.storage, None, None, Order::Ascending)
+            .next()
+            .is_some();
+        if env.block.height <= closed_at_height + prune_delay.u64()
+            || has_positions
+            || has_escheated
+            || has_tier_deposits
+            || has_failed_vestings
+            || vesting_pending
+            || bounties_pending
+            || crank_reserve_pending
+        {
+            return Err(ContractError::StreamNotPrunable { stream_id });
+        }
+
+        remove_stream_name(deps.storage, &stream.name, stream_id)?;
+        PRUNED_STREAMS.save(
+            deps.storage,
+            stream_id,
+            &PrunedStream {
+                name: stream.name,
+                treasury: stream.treasury,
+                status: stream.status,
+                out_denom: stream.out_denom,
+                out_supply: stream.out_supply,
+                in_denom: stream.in_denom,
+                spent_in: stream.spent_in,
+                closed_at_height,
+            },
+        )?;
+        STREAMS.remove(deps.storage, stream_id)?;
+        remove_from_stream_count(deps.storage)?;
+        STREAM_SNAPSHOTS.remove(deps.storage, stream_id, env.block.height)?;
+        STREAM_CLOSED_AT.remove(deps.storage, stream_id);
+        STREAM_SAVED_AT.remove(deps.storage, stream_id);
+        let prices = PRICE_HISTORY
+            .prefix(stream_id)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for time in prices {
+            PRICE_HISTORY.remove(deps.storage, (stream_id, time));
+            PRICE_CUMULATIVES.remove(deps.storage, (stream_id, time));
+        }
+        PRICE_HISTORY_LEN.remove(deps.storage, stream_id);
+        STREAM_STATS.remove(deps.storage, stream_id);
+        PENDING_TREASURIES.remove(deps.storage, stream_id);
+        STREAM_OPERATIONS.remove(deps.storage, stream_id);
+        PENDING_OPERATIONS.remove(deps.storage, stream_id);
+        FINALIZE_HOOKS.remove(deps.storage, stream_id);
+        REMOTE_TREASURIES.remove(deps.storage, stream_id);
+        STREAM_DONATIONS.remove(deps.storage, stream_id);
+        CREATOR_PAUSES.remove(deps.storage, stream_id);
+        CANCEL_CONFIRMATIONS.remove(deps.storage, stream_id);
+        PAUSED_EXITS.remove(deps.storage, stream_id);
+        REVENUE_VESTINGS.remove(deps.storage, stream_id);
+        let keepers = KEEPER_BOUNTIES
+            .prefix(stream_id)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for keeper in keepers {
+            KEEPER_BOUNTIES.remove(deps.storage, (stream_id, &keeper));
+        }
+        STREAM_KEEPER_BOUNTIES.remove(deps.storage, stream_id);
+        STREAM_VESTINGS.remove(deps.storage, stream_id);
+        STREAM_POOLS.remove(deps.storage, stream_id);
+        let vested = VESTING
+            .prefix(stream_id)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for owner in vested {
+            VESTING.remove(deps.storage, (stream_id, &owner));
+        }
+        let blocked = BLOCKLISTS
+            .prefix(stream_id)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for addr in blocked {
+            BLOCKLISTS.remove(deps.storage, (stream_id, &addr));
+        }
+        SUBSCRIPTION_LIMITS.remove(deps.storage, stream_id);
+        HARD_CAPS.remove(deps.storage, stream_id);
+        PARTIAL_REFUNDS.remove(deps.storage, stream_id);
+        PARTIAL_FILLS.remove(deps.storage, stream_id);
+        remove_allocation_tiers(deps.storage, stream_id)?;
+        SUBSCRIPTION_WINDOWS.remove(deps.storage, stream_id);
+        remove_stream_tags(deps.storage, stream_id)?;
+        remove_stream_creator(deps.storage, stream_id)?;
+        ThresholdState::new().remove_threshold(stream_id, deps.storage);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "prune_streams")
+        .add_attribute(
+            "stream_ids",
+            stream_ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        ))
+}
+
+pub fn query_pruned_stream(deps: Deps, stream_id: StreamId) -> StdResult<Option<PrunedStream>> {
+    PRUNED_STREAMS.may_load(deps.storage, stream_id)
+}
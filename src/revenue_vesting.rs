@@ -0,0 +1,168 @@
+use crate::ibc::treasury_payout_msg;
+use crate::state::{StreamId, CONFIG, STREAMS};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, Coin, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Timestamp, Uint128,
+    Uint256, Uint64,
+};
+use cw_storage_plus::Map;
+
+#[cw_serde]
+pub struct RevenueVesting {
+    /// Duration in seconds over which creator revenue is released after finalize.
+    pub duration: Uint64,
+    /// Finalize time, the release starts from here.
+    pub start_time: Option<Timestamp>,
+    /// Total creator revenue to be released.
+    pub total: Uint256,
+    /// Revenue claimed so far.
+    pub claimed: Uint256,
+}
+
+impl RevenueVesting {
+    /// Returns the amount of revenue that can be claimed at `now`.
+    pub fn claimable(&self, now: Timestamp) -> Uint256 {
+        let start_time = match self.start_time {
+            Some(start_time) => start_time,
+            None => return Uint256::zero(),
+        };
+        let elapsed = now.seconds().saturating_sub(start_time.seconds());
+        let vested = if elapsed >= self.duration.u64() {
+            self.total
+        } else {
+            self.total.multiply_ratio(elapsed, self.duration.u64())
+        };
+        vested.saturating_sub(self.claimed)
+    }
+}
+
+// Stream id -> revenue vesting, only set for streams releasing revenue over time
+pub const REVENUE_VESTINGS: Map<StreamId, RevenueVesting> = Map::new("revenue_vestings");
+
+pub fn set_revenue_vesting_if_any(
+    storage: &mut dyn Storage,
+    stream_id: StreamId,
+    duration: Option<Uint64>,
+) -> Result<(), ContractError> {
+    if let Some(duration) = duration {
+        if duration.is_zero() {
+            return Err(ContractError::InvalidRevenueVestingDuration {});
+        }
+        REVENUE_VESTINGS.save(
+            storage,
+            stream_id,
+            &RevenueVesting {
+                duration,
+                start_time: None,
+                total: Uint256::zero(),
+                claimed: Uint256::zero(),
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Starts releasing the creator revenue if the stream vests it. Returns false if the
+/// revenue should be paid out at once.
+pub fn start_revenue_vesting(
+    storage: &mut dyn Storage,
+    stream_id: StreamId,
+    now: Timestamp,
+    creator_revenue: Uint256,
+) -> StdResult<bool> {
+    match REVENUE_VESTINGS.may_load(storage, stream_id)? {
+        Some(mut vesting) => {
+            vesting.start_time = Some(now);
+            vesting.total = creator_revenue;
+            REVENUE_VESTINGS.save(storage, stream_id, &vesting)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Sends the vested part of the creator revenue to the treasury. Anyone can trigger the claim.
+pub fn execute_claim_vested_revenue(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    stream_id: StreamId,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    let mut vesting = REVENUE_VESTINGS.load(deps.storage, stream_id)?;
+
+    let claimable = vesting.claimable(env.block.time);
+    if claimable.is_zero() {
+        return Err(ContractError::NoVestedRevenue {});
+    }
+    vesting.claimed = vesting.claimed.checked_add(claimable)?;
+    REVENUE_VESTINGS.save(deps.storage, stream_id, &vesting)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let revenue = Coin {
+        denom: stream.in_denom,
+        amount: Uint128::try_from(claimable)?,
+    };
+    let payout_msg = treasury_payout_msg(
+        deps.storage,
+        &env,
+        &config,
+        stream_id,
+        &stream.treasury,
+        revenue,
+    )?;
+    Ok(Response::new()
+        .add_submessage(payout_msg)
+        .add_attributes(vec![
+            attr("action", "claim_vested_revenue"),
+            attr("stream_id", stream_id.to_string()),
+            attr("treasury", stream.treasury),
+            attr("claimed", claimable),
+            attr("total_claimed", vesting.claimed),
+        ]))
+}
+
+pub fn query_revenue_vesting(deps: Deps, stream_id: StreamId) -> StdResult<Option<RevenueVesting>> {
+    REVENUE_VESTINGS.may_load(deps.storage, stream_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claimable() {
+        let mut vesting = RevenueVesting {
+            duration: Uint64::new(100),
+            start_time: None,
+            total: Uint256::from(1_000u128),
+            claimed: Uint256::zero(),
+        };
+        // not finalized yet
+        assert_eq!(
+            vesting.claimable(Timestamp::from_seconds(50)),
+            Uint256::zero()
+        );
+
+        vesting.start_time = Some(Timestamp::from_seconds(1_000));
+        assert_eq!(
+            vesting.claimable(Timestamp::from_seconds(1_000)),
+            Uint256::zero()
+        );
+        assert_eq!(
+            vesting.claimable(Timestamp::from_seconds(1_025)),
+            Uint256::from(250u128)
+        );
+        vesting.claimed = Uint256::from(250u128);
+        assert_eq!(
+            vesting.claimable(Timestamp::from_seconds(1_050)),
+            Uint256::from(250u128)
+        );
+        // fully vested after duration
+        assert_eq!(
+            vesting.claimable(Timestamp::from_seconds(2_000)),
+            Uint256::from(750u128)
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::contract::{update_position, update_stream};
+use crate::hard_cap::hard_cap_refund;
+use crate::partial_refund::{apply_partial_fill, error_if_not_settled};
+use crate::state::{StreamId, POSITIONS, STREAMS};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal256, Deps, Env, StdError, StdResult, Timestamp, Uint256};
+
+#[cw_serde]
+pub struct SimulateSubscribeResponse {
+    /// Shares the subscription would mint.
+    pub shares: Uint256,
+    /// Out tokens the subscription would buy until the end if no further subscriptions occur.
+    pub expected_out: Uint256,
+    /// Price paid per out token for the expected purchase.
+    pub marginal_price: Decimal256,
+}
+
+#[cw_serde]
+pub struct SimulateExitResponse {
+    /// Out tokens sent to the owner.
+    pub purchased: Uint256,
+    /// Unspent in tokens refunded to the owner.
+    pub refund: Uint256,
+    /// In tokens spent by the position, swap fee included.
+    pub spent: Uint256,
+    /// Swap fee taken from the spent in tokens.
+    pub swap_fee: Uint256,
+}
+
+pub(crate) fn to_std_err(err: ContractError) -> StdError {
+    match err {
+        ContractError::Std(err) => err,
+        err => StdError::generic_err(err.to_string()),
+    }
+}
+
+/// Runs the stream distribution up to `at_time` on a copy of the stream and estimates the
+/// outcome of subscribing `amount` at that time.
+pub fn query_simulate_subscribe(
+    deps: Deps,
+    env: Env,
+    stream_id: StreamId,
+    amount: Uint256,
+    at_time: Option<Timestamp>,
+) -> StdResult<SimulateSubscribeResponse> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    let now = at_time.unwrap_or(env.block.time);
+    if stream.is_killswitch_active() {
+        return Err(to_std_err(ContractError::StreamKillswitchActive {}));
+    }
+    if now >= stream.end_time {
+        return Err(to_std_err(ContractError::StreamEnded {}));
+    }
+    // stream state can only be projected forward
+    if now < stream.last_updated {
+        return Err(StdError::generic_err(
+            "at_time is before last stream update",
+        ));
+    }
+
+    update_stream(now, &mut stream).map_err(to_std_err)?;
+    let shares = stream
+        .compute_shares_amount(amount, false)
+        .map_err(to_std_err)?;
+    let total_shares = stream.shares.checked_add(shares)?;
+
+    // remaining out tokens are distributed pro rata to shares until the end
+    let expected_out = if total_shares.is_zero() {
+        Uint256::zero()
+    } else {
+        stream.out_remaining.multiply_ratio(shares, total_shares)
+    };
+    let marginal_price = if expected_out.is_zero() {
+        Decimal256::zero()
+    } else {
+        Decimal256::from_ratio(amount, expected_out)
+    };
+
+    Ok(SimulateSubscribeResponse {
+        shares,
+        expected_out,
+        marginal_price,
+    })
+}
+
+/// Returns what exiting the position of `owner` would pay out at the current block time.
+pub fn query_simulate_exit(
+    deps: Deps,
+    env: Env,
+    stream_id: StreamId,
+    owner: String,
+) -> StdResult<SimulateExitResponse> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.is_killswitch_active() {
+        return Err(to_std_err(ContractError::StreamKillswitchActive {}));
+    }
+    if env.block.time <= stream.end_time {
+        return Err(to_std_err(ContractError::StreamNotEnded {}));
+    }
+    if stream.last_updated < stream.end_time {
+        update_stream(env.block.time, &mut stream).map_err(to_std_err)?;
+    }
+    error_if_not_settled(deps.storage, stream_id, &stream)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let owner = deps.api.addr_validate(&owner)?;
+    let mut position = POSITIONS.load(deps.storage, (stream_id, &owner))?;
+    update_position(
+        stream.dist_index,
+        stream.shares,
+        stream.last_updated,
+        stream.in_supply,
+        &mut position,
+    )
+    .map_err(to_std_err)?;
+    let excess_refund = hard_cap_refund(deps.storage, stream_id, stream.spent_in, position.spent)?;
+    position.spent = position.spent.checked_sub(excess_refund)?;
+    position.in_balance = position.in_balance.checked_add(excess_refund)?;
+    apply_partial_fill(deps.storage, stream_id, &mut position)?;
+    let swap_fee = Decimal256::from_ratio(position.spent, Uint256::one())
+        .checked_mul(stream.stream_exit_fee_percent)?
+        * Uint256::one();
+
+    Ok(SimulateExitResponse {
+        purchased: position.purchased,
+        refund: position.in_balance,
+        spent: position.spent,
+        swap_fee,
+    })
+}
@@ -1,8 +1,16 @@
+use crate::helpers::double_option;
 use crate::ContractError;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Decimal256, Storage, Timestamp, Uint128, Uint256, Uint64};
-use cw_storage_plus::{Item, Map};
-use std::ops::Mul;
+use cosmwasm_std::{
+    Addr, BlockInfo, Decimal256, Empty, StdResult, Storage, Timestamp, Uint128, Uint256, Uint64,
+};
+use cw_controllers::Hooks;
+use cw_storage_plus::{
+    Index, IndexList, IndexedMap, Item, KeyDeserialize, Map, MultiIndex, SnapshotMap, Strategy,
+};
+use cw_utils::Expiration;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use streamswap_math::{PositionState, StreamState};
 
 #[cw_serde]
 pub struct Config {
@@ -20,12 +28,321 @@ pub struct Config {
     pub exit_fee_percent: Decimal256,
     /// Address of the fee collector
     pub fee_collector: Addr,
-    /// protocol admin can pause streams in case of emergency.
-    pub protocol_admin: Addr,
+    /// protocol admin can pause streams in case of emergency. None once the admin is renounced.
+    pub protocol_admin: Option<Addr>,
+    /// If set, swap fees are swapped to a single denom before being sent to the fee collector.
+    pub fee_swap: Option<FeeSwap>,
+    /// Maximum pause duration in seconds, after which anyone can resume a paused stream.
+    pub max_pause_duration: Option<Uint64>,
+    /// Maximum duration in seconds a treasury can pause its own stream. Creator pause is disabled
+    /// if not set.
+    pub max_creator_pause_duration: Option<Uint64>,
+    /// Share of the creation fee kept as penalty when the treasury cancels a stream that missed
+    /// its threshold, one forfeits the whole fee. The rest of the creation fee is refunded to the
+    /// treasury, all of it if not set.
+    pub creator_cancel_penalty: Option<Decimal256>,
+    /// Restricts stream creation to allowlisted creators when set to true.
+    pub permissioned_creation: Option<bool>,
+    /// Delay in seconds between proposing and applying a config update. Config updates apply
+    /// immediately if not set or zero.
+    pub config_timelock: Option<Uint64>,
+    /// If set, exits can deliver purchased tokens to remote chain addresses over this channel.
+    pub ibc_transfer: Option<IbcTransfer>,
+    /// Local address of a remote chain governance, such as a Polytone proxy or an interchain
+    /// account. Accepted by the killswitch alongside protocol admin.
+    pub remote_controller: Option<Addr>,
+    /// Blocks after finalize or cancel from which stream state can be pruned. Pruning is
+    /// disabled if not set.
+    pub prune_delay_blocks: Option<Uint64>,
+    /// Blocks after finalize from which positions never exited can be moved into escrow by
+    /// protocol admin. Escheating is disabled if not set.
+    pub escheat_delay_blocks: Option<Uint64>,
+    /// If set, updates of streams not updated for a while earn a share of the swap fee.
+    pub keeper_bounty: Option<KeeperBounty>,
+    /// If set, exits of streams selling its denom can bond purchased tokens with this hub.
+    pub liquid_staking: Option<LiquidStaking>,
+    /// If set, stream names are unique until released after the stream ends.
+    pub name_registry: Option<NameRegistry>,
+    /// If set, subscriptions and exits of all streams are screened by this contract.
+    pub compliance_contract: Option<Addr>,
+    /// If set, positions not exited within the grace period can be exited by anyone for a
+    /// share of their swap fee.
+    pub exit_crank: Option<ExitCrank>,
+    /// Code id of the cw-vesting contract instantiated at exit for streams vesting purchases.
+    /// Streams can not vest if not set.
+    pub vesting_code_id: Option<u64>,
+    /// Bounds of the contracts notified when a stream is finalized. Defaults to 5 hooks with
+    /// 500,000 gas each if not set.
+    pub finalize_hook_limits: Option<FinalizeHookLimits>,
+}
+
+#[cw_serde]
+pub struct FeeSwap {
+    /// Denom the fee collector receives.
+    pub target_denom: String,
+    /// Poolmanager routes from the in_denom to `target_denom`.
+    pub routes: Vec<SwapAmountInRoute>,
+    /// Maximum accepted slippage against the output at the time weighted average price.
+    pub max_slippage: Decimal256,
+    /// Window in seconds of the pool TWAPs the swap output is bounded against.
+    pub twap_window: Uint64,
+}
+
+impl Config {
+    pub fn is_protocol_admin(&self, addr: &Addr) -> bool {
+        self.protocol_admin.as_ref() == Some(addr)
+    }
+
+    /// Returns true if `addr` is the protocol admin or the remote controller.
+    pub fn is_killswitch_controller(&self, addr: &Addr) -> bool {
+        self.is_protocol_admin(addr) || self.remote_controller.as_ref() == Some(addr)
+    }
+
+    /// Returns the finalize hook limits, or the defaults if not set.
+    pub fn finalize_hook_limits(&self) -> FinalizeHookLimits {
+        self.finalize_hook_limits.clone().unwrap_or_default()
+    }
+}
+
+/// Bounds of the finalize hooks, so a stream can not make finalize run out of gas.
+#[cw_serde]
+pub struct FinalizeHookLimits {
+    /// Maximum number of contracts notified when a stream is finalized.
+    pub max_hooks: u32,
+    /// Gas each hook can use, a hook running out of gas fails without reverting finalize.
+    pub gas_limit: Uint64,
+}
+
+impl Default for FinalizeHookLimits {
+    fn default() -> Self {
+        FinalizeHookLimits {
+            max_hooks: 5,
+            gas_limit: Uint64::new(500_000),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct NameRegistry {
+    /// Seconds after the end of a stream its name is released.
+    pub release_after: Uint64,
+}
+
+/// Liquid staking hub exits can bond purchased tokens with.
+#[cw_serde]
+pub struct LiquidStaking {
+    /// Hub contract, bonded with `{"bond": {"receiver": ...}}`.
+    pub hub: String,
+    /// Staking denom the hub accepts.
+    pub denom: String,
+}
+
+/// Bounty paid to callers of `UpdateStream` keeping stale streams up to date.
+#[cw_serde]
+pub struct KeeperBounty {
+    /// Share of the swap fee accrued by the update credited to the caller.
+    pub fee_share: Decimal256,
+    /// Blocks a stream has to go without updates for an update to earn the bounty.
+    pub min_blocks: Uint64,
+}
+
+/// Bounty paid to callers of `CrankExits` exiting positions left behind after a stream ends.
+#[cw_serde]
+pub struct ExitCrank {
+    /// Seconds after the end of a stream positions are left to exit by themselves.
+    pub grace_period: Uint64,
+    /// Share of the swap fee of a position paid to the caller exiting it.
+    pub fee_share: Decimal256,
+}
+
+#[cw_serde]
+pub struct IbcTransfer {
+    /// ICS-20 channel transfers are sent over.
+    pub channel_id: String,
+    /// Packet timeout in seconds, counted from the block the transfer is sent in.
+    pub timeout_seconds: Uint64,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/// Config fields to update, unset fields are kept as is. Optional config fields are cleared by
+/// `Some(None)`, sent as an explicit `null`.
+#[cw_serde]
+#[derive(Default)]
+pub struct ConfigUpdate {
+    pub min_stream_duration: Option<Uint64>,
+    pub min_duration_until_start_time: Option<Uint64>,
+    pub stream_creation_denom: Option<String>,
+    pub stream_creation_fee: Option<Uint128>,
+    pub fee_collector: Option<String>,
+    pub accepted_in_denom: Option<String>,
+    pub exit_fee_percent: Option<Decimal256>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub max_pause_duration: Option<Option<Uint64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub max_creator_pause_duration: Option<Option<Uint64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub creator_cancel_penalty: Option<Option<Decimal256>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub permissioned_creation: Option<Option<bool>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub config_timelock: Option<Option<Uint64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub prune_delay_blocks: Option<Option<Uint64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub escheat_delay_blocks: Option<Option<Uint64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub fee_swap: Option<Option<FeeSwap>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub ibc_transfer: Option<Option<IbcTransfer>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub remote_controller: Option<Option<String>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub keeper_bounty: Option<Option<KeeperBounty>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub liquid_staking: Option<Option<LiquidStaking>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub name_registry: Option<Option<NameRegistry>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub compliance_contract: Option<Option<String>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub exit_crank: Option<Option<ExitCrank>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub vesting_code_id: Option<Option<u64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "double_option"
+    )]
+    pub finalize_hook_limits: Option<Option<FinalizeHookLimits>>,
+}
+
+/// Config update waiting for the config timelock to pass.
+#[cw_serde]
+pub struct PendingConfigUpdate {
+    pub update: ConfigUpdate,
+    /// Update can be applied from this time on.
+    pub apply_after: Timestamp,
+}
+
+pub const PENDING_CONFIG_UPDATE: Item<PendingConfigUpdate> = Item::new("pending_config_update");
+
+/// Protocol admin transfer waiting to be accepted by the new admin.
+#[cw_serde]
+pub struct PendingProtocolAdmin {
+    pub new_protocol_admin: Addr,
+    /// Proposal can not be accepted after expiry.
+    pub expiry: Option<Expiration>,
+}
+
+pub const PENDING_PROTOCOL_ADMIN: Item<PendingProtocolAdmin> = Item::new("pending_protocol_admin");
+
+/// Killswitch guardians. Any guardian can pause a stream, cancelling needs `cancel_threshold`
+/// confirmations.
+#[cw_serde]
+pub struct GuardianSet {
+    pub guardians: Vec<Addr>,
+    pub cancel_threshold: u32,
+}
+
+impl GuardianSet {
+    pub fn is_guardian(&self, addr: &Addr) -> bool {
+        self.guardians.contains(addr)
+    }
+}
+
+// If set, replaces protocol admin for pausing and cancelling streams
+pub const GUARDIANS: Item<GuardianSet> = Item::new("guardians");
+// Stream id -> date the treasury paused its stream, a stream can be creator paused only once
+pub const CREATOR_PAUSES: Map<StreamId, Timestamp> = Map::new("creator_pauses");
+// Stream id -> guardians confirmed cancelling the paused stream
+pub const CANCEL_CONFIRMATIONS: Map<StreamId, Vec<Addr>> = Map::new("cancel_confirmations");
+
+// Creator address -> stream creation fee discount, `1` waives the fee entirely
+pub const FEE_WAIVERS: Map<&Addr, Decimal256> = Map::new("fee_waivers");
+
+// Creators allowed to create streams while creation is permissioned
+pub const CREATOR_ALLOWLIST: Map<&Addr, Empty> = Map::new("creator_allowlist");
+
+#[cw_serde]
+pub struct FrozenDenom {
+    /// Denom is unfrozen automatically after expiry. Frozen until unfrozen if not set.
+    pub expiry: Option<Expiration>,
+}
+
+impl FrozenDenom {
+    pub fn is_frozen(&self, block: &BlockInfo) -> bool {
+        !self.expiry.is_some_and(|e| e.is_expired(block))
+    }
+}
+
+// Denoms that can not be sold or accepted by new streams
+pub const FROZEN_DENOMS: Map<&str, FrozenDenom> = Map::new("frozen_denoms");
+
 #[cw_serde]
 pub struct Stream {
     /// Name of the stream.
@@ -58,6 +375,10 @@ pub struct Stream {
     pub end_time: Timestamp,
     /// price at when latest distribution is triggered.
     pub current_streamed_price: Decimal256,
+    /// Streamed price accumulated over the streamed seconds, zero for streams created before
+    /// the accumulator.
+    #[serde(default)]
+    pub price_cumulative: Decimal256,
     /// Status of the stream. Can be `Waiting`, `Active`, `Finalized`, `Paused` or `Canceled` for kill switch.
     pub status: Status,
     /// Date when the stream was paused.
@@ -79,6 +400,20 @@ pub enum Status {
     Paused,
     Cancelled,
 }
+
+impl Status {
+    /// Key of the status in the streams status index.
+    pub fn key(&self) -> String {
+        match self {
+            Status::Waiting => "waiting",
+            Status::Active => "active",
+            Status::Finalized => "finalized",
+            Status::Paused => "paused",
+            Status::Cancelled => "cancelled",
+        }
+        .to_string()
+    }
+}
 #[allow(clippy::too_many_arguments)]
 impl Stream {
     pub fn new(
@@ -111,6 +446,7 @@ impl Stream {
             start_time,
             end_time,
             current_streamed_price: Decimal256::zero(),
+            price_cumulative: Decimal256::zero(),
             status: Status::Waiting,
             pause_date: None,
             stream_creation_denom,
@@ -120,17 +456,46 @@ impl Stream {
     }
 
     // compute amount of shares that should be minted for a new subscription amount
-    pub fn compute_shares_amount(&self, amount_in: Uint256, round_up: bool) -> Uint256 {
-        if self.shares.is_zero() || amount_in.is_zero() {
-            return amount_in.into();
-        }
-        let mut shares = self.shares.mul(amount_in);
-        if round_up {
-            shares = (shares + self.in_supply - Uint256::one()) / self.in_supply;
-        } else {
-            shares /= self.in_supply
+    pub fn compute_shares_amount(
+        &self,
+        amount_in: Uint256,
+        round_up: bool,
+    ) -> Result<Uint256, ContractError> {
+        Ok(streamswap_math::compute_shares_amount(
+            self.shares,
+            self.in_supply,
+            amount_in,
+            round_up,
+        )?)
+    }
+
+    /// Returns the fields the stream distribution math works on.
+    pub fn math_state(&self) -> StreamState {
+        StreamState {
+            dist_index: self.dist_index,
+            last_updated: self.last_updated,
+            out_remaining: self.out_remaining,
+            in_supply: self.in_supply,
+            spent_in: self.spent_in,
+            shares: self.shares,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            current_streamed_price: self.current_streamed_price,
+            price_cumulative: self.price_cumulative,
         }
-        shares
+    }
+
+    pub fn apply_math_state(&mut self, state: StreamState) {
+        self.dist_index = state.dist_index;
+        self.last_updated = state.last_updated;
+        self.out_remaining = state.out_remaining;
+        self.in_supply = state.in_supply;
+        self.spent_in = state.spent_in;
+        self.shares = state.shares;
+        self.start_time = state.start_time;
+        self.end_time = state.end_time;
+        self.current_streamed_price = state.current_streamed_price;
+        self.price_cumulative = state.price_cumulative;
     }
 
     pub fn is_paused(&self) -> bool {
@@ -146,13 +511,144 @@ impl Stream {
     }
 }
 pub type StreamId = u64;
-pub const STREAMS: Map<StreamId, Stream> = Map::new("stream");
+
+pub struct StreamIndexes<'a> {
+    pub status: MultiIndex<'a, String, Stream, StreamId>,
+}
+
+impl<'a> IndexList<Stream> for StreamIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Stream>> + '_> {
+        let v: Vec<&dyn Index<Stream>> = vec![&self.status];
+        Box::new(v.into_iter())
+    }
+}
+
+pub const STREAMS: IndexedMap<StreamId, Stream, StreamIndexes> = IndexedMap::new(
+    "stream",
+    StreamIndexes {
+        status: MultiIndex::new(|_, s| s.status.key(), "stream", "stream__status"),
+    },
+);
+/// Fields of a stream changing after creation, the rest of a past state is read from the stream.
+#[cw_serde]
+pub struct StreamSnapshot {
+    pub treasury: Addr,
+    pub dist_index: Decimal256,
+    pub last_updated: Timestamp,
+    pub out_remaining: Uint256,
+    pub in_supply: Uint256,
+    pub spent_in: Uint256,
+    pub shares: Uint256,
+    pub end_time: Timestamp,
+    pub current_streamed_price: Decimal256,
+    pub status: Status,
+    pub pause_date: Option<Timestamp>,
+}
+
+impl StreamSnapshot {
+    /// Returns the stream with the snapshotted fields.
+    pub fn apply(self, stream: Stream) -> Stream {
+        Stream {
+            treasury: self.treasury,
+            dist_index: self.dist_index,
+            last_updated: self.last_updated,
+            out_remaining: self.out_remaining,
+            in_supply: self.in_supply,
+            spent_in: self.spent_in,
+            shares: self.shares,
+            end_time: self.end_time,
+            current_streamed_price: self.current_streamed_price,
+            status: self.status,
+            pause_date: self.pause_date,
+            ..stream
+        }
+    }
+}
+
+impl From<&Stream> for StreamSnapshot {
+    fn from(stream: &Stream) -> Self {
+        StreamSnapshot {
+            treasury: stream.treasury.clone(),
+            dist_index: stream.dist_index,
+            last_updated: stream.last_updated,
+            out_remaining: stream.out_remaining,
+            in_supply: stream.in_supply,
+            spent_in: stream.spent_in,
+            shares: stream.shares,
+            end_time: stream.end_time,
+            current_streamed_price: stream.current_streamed_price,
+            status: stream.status.clone(),
+            pause_date: stream.pause_date,
+        }
+    }
+}
+
+// Changing fields of streams are mirrored here with the state before every block changing them,
+// so past states can be queried by height
+pub const STREAM_SNAPSHOTS: SnapshotMap<StreamId, StreamSnapshot> = SnapshotMap::new(
+    "stream_snapshots",
+    "stream_snapshots__checkpoints",
+    "stream_snapshots__changelog",
+    Strategy::EveryBlock,
+);
+
+/// Saves the stream, keeping its previous state for height queries.
+pub fn save_stream(
+    store: &mut dyn Storage,
+    stream_id: StreamId,
+    stream: &Stream,
+    height: u64,
+) -> StdResult<()> {
+    STREAMS.save(store, stream_id, stream)?;
+    STREAM_SAVED_AT.save(store, stream_id, &height)?;
+    if matches!(stream.status, Status::Finalized | Status::Cancelled)
+        && !STREAM_CLOSED_AT.has(store, stream_id)
+    {
+        STREAM_CLOSED_AT.save(store, stream_id, &height)?;
+    }
+    STREAM_SNAPSHOTS.save(store, stream_id, &stream.into(), height)
+}
+// Block height the stream was last saved at
+pub const STREAM_SAVED_AT: Map<StreamId, u64> = Map::new("stream_saved_at");
+// Block height the stream was finalized or cancelled at
+pub const STREAM_CLOSED_AT: Map<StreamId, u64> = Map::new("stream_closed_at");
+// Treasury proposed by the current treasury, waiting to be accepted
+pub const PENDING_TREASURIES: Map<StreamId, Addr> = Map::new("pending_treasuries");
+
+// Contracts notified with `StreamHookMsg::StreamFinalized` when the stream is finalized
+pub const FINALIZE_HOOKS: Map<StreamId, Vec<Addr>> = Map::new("finalize_hooks");
+// Remote chain address receiving the stream revenue over the configured IBC channel
+pub const REMOTE_TREASURIES: Map<StreamId, String> = Map::new("remote_treasuries");
+// Contracts notified with `StreamHookMsg::StreamCreated` when any stream is created
+pub const CREATION_HOOKS: Hooks = Hooks::new("creation_hooks");
+// Share of creator revenue sent to the community pool at finalize
+pub const STREAM_DONATIONS: Map<StreamId, Decimal256> = Map::new("stream_donations");
 const STREAM_ID_COUNTER: Item<StreamId> = Item::new("stream_id_counter");
+// Number of streams in state, pruned streams are not counted
+const STREAM_COUNT: Item<u64> = Item::new("stream_count");
 pub fn next_stream_id(store: &mut dyn Storage) -> Result<u64, ContractError> {
+    let count = stream_count(store)?;
     let id: u64 = STREAM_ID_COUNTER.may_load(store)?.unwrap_or_default() + 1;
     STREAM_ID_COUNTER.save(store, &id)?;
+    STREAM_COUNT.save(store, &(count + 1))?;
     Ok(id)
 }
+/// The id counter is never decremented, so it is the number of created streams.
+pub fn created_stream_count(store: &dyn Storage) -> StdResult<u64> {
+    Ok(STREAM_ID_COUNTER.may_load(store)?.unwrap_or_default())
+}
+/// Returns the number of streams in state. Until the count is first saved no stream has been
+/// pruned, so it is the number of created streams.
+pub fn stream_count(store: &dyn Storage) -> StdResult<u64> {
+    match STREAM_COUNT.may_load(store)? {
+        Some(count) => Ok(count),
+        None => created_stream_count(store),
+    }
+}
+pub fn remove_from_stream_count(store: &mut dyn Storage) -> StdResult<()> {
+    let count = stream_count(store)?;
+    STREAM_COUNT.save(store, &count.saturating_sub(1))
+}
 
 #[cw_serde]
 pub struct Position {
@@ -195,14 +691,61 @@ impl Position {
             operator,
         }
     }
+
+    /// Returns the fields the position purchase math works on.
+    pub fn math_state(&self) -> PositionState {
+        PositionState {
+            index: self.index,
+            shares: self.shares,
+            in_balance: self.in_balance,
+            purchased: self.purchased,
+            pending_purchase: self.pending_purchase,
+            spent: self.spent,
+            last_updated: self.last_updated,
+        }
+    }
+
+    pub fn apply_math_state(&mut self, state: PositionState) {
+        self.index = state.index;
+        self.shares = state.shares;
+        self.in_balance = state.in_balance;
+        self.purchased = state.purchased;
+        self.pending_purchase = state.pending_purchase;
+        self.spent = state.spent;
+        self.last_updated = state.last_updated;
+    }
+}
+
+pub struct PositionIndexes<'a> {
+    pub owner: MultiIndex<'a, Addr, Position, (StreamId, Addr)>,
+    // (stream id, big endian shares) orders positions of a stream by size
+    pub shares: MultiIndex<'a, (StreamId, Vec<u8>), Position, (StreamId, Addr)>,
+}
+
+impl<'a> IndexList<Position> for PositionIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Position>> + '_> {
+        let v: Vec<&dyn Index<Position>> = vec![&self.owner, &self.shares];
+        Box::new(v.into_iter())
+    }
+}
+
+fn position_shares_key(pk: &[u8], position: &Position) -> (StreamId, Vec<u8>) {
+    // ok to use unwrap here, primary keys are always (stream_id, owner)
+    let (stream_id, _) = <(StreamId, Addr)>::from_slice(pk).unwrap();
+    (stream_id, position.shares.to_be_bytes().to_vec())
 }
 
 // Position (stream_id, owner_addr) -> Position
-pub const POSITIONS: Map<(StreamId, &Addr), Position> = Map::new("positions");
+pub const POSITIONS: IndexedMap<(StreamId, &Addr), Position, PositionIndexes> = IndexedMap::new(
+    "positions",
+    PositionIndexes {
+        owner: MultiIndex::new(|_, p| p.owner.clone(), "positions", "positions__owner"),
+        shares: MultiIndex::new(position_shares_key, "positions", "positions__shares"),
+    },
+);
 
 // Testing module
 #[cfg(test)]
-
 mod tests {
     use super::*;
     use cosmwasm_std::{Addr, Uint128};
@@ -226,6 +769,7 @@ mod tests {
             start_time: Timestamp::from_nanos(1722006000000000000),
             end_time: Timestamp::from_nanos(1722009600000000000),
             current_streamed_price: Decimal256::zero(),
+            price_cumulative: Decimal256::zero(),
             status: Status::Waiting,
             pause_date: None,
             stream_creation_denom: "fee_denom".to_string(),
@@ -234,7 +778,48 @@ mod tests {
         };
 
         // Test when shares is zero
-        let shares = stream.compute_shares_amount(Uint256::from(2000000000000000000u128), false);
+        let shares = stream
+            .compute_shares_amount(Uint256::from(2000000000000000000u128), false)
+            .unwrap();
         assert_eq!(shares, Uint256::from(2000000000000000000u128));
     }
+
+    #[test]
+    fn test_compute_shares_amount_extreme() {
+        let mut stream = Stream::new(
+            "test".to_string(),
+            Addr::unchecked("treasury"),
+            None,
+            "out_denom".to_string(),
+            Uint256::from(u128::MAX),
+            "in_denom".to_string(),
+            Timestamp::from_seconds(0),
+            Timestamp::from_seconds(100),
+            Timestamp::from_seconds(0),
+            "fee".to_string(),
+            Uint128::from(100u128),
+            Decimal256::percent(1),
+        );
+        // most of the in supply is spent, shares outgrow the remaining supply
+        stream.shares = Uint256::from(u128::MAX) * Uint256::from(u128::MAX);
+        stream.in_supply = Uint256::from(u128::MAX);
+        // shares * amount_in is far above 256 bits
+        let shares = stream
+            .compute_shares_amount(Uint256::from(u128::MAX - 1), false)
+            .unwrap();
+        assert_eq!(
+            shares,
+            Uint256::from(u128::MAX) * Uint256::from(u128::MAX - 1)
+        );
+        let shares = stream
+            .compute_shares_amount(Uint256::from(1u128), true)
+            .unwrap();
+        assert_eq!(shares, Uint256::from(u128::MAX));
+
+        // minted shares not fitting 256 bits error instead of panicking
+        stream.in_supply = Uint256::one();
+        stream
+            .compute_shares_amount(Uint256::from(u128::MAX), false)
+            .unwrap_err();
+    }
 }
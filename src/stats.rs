@@ -0,0 +1,184 @@
+use crate::state::{created_stream_count, StreamId};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Deps, Order, StdResult, Storage, Uint256};
+use cw_storage_plus::Map;
+
+#[cw_serde]
+#[derive(Default)]
+pub struct StreamStats {
+    /// Number of positions opened on the stream.
+    pub subscribers: u64,
+    /// Total in tokens subscribed to the stream.
+    pub subscription_volume: Uint256,
+    /// Total in tokens withdrawn from the stream.
+    pub withdrawn: Uint256,
+    /// Number of positions exited.
+    pub exits: u64,
+}
+
+#[cw_serde]
+pub struct StatisticsResponse {
+    pub stream_id: StreamId,
+    pub subscribers: u64,
+    pub subscription_volume: Uint256,
+    pub withdrawn: Uint256,
+    pub exits: u64,
+    /// Average subscription per subscriber.
+    pub average_position: Uint256,
+}
+
+/// Protocol wide totals of a denom, updated as streams are finalized.
+#[cw_serde]
+#[derive(Default)]
+pub struct DenomStats {
+    /// Total in tokens subscribed to finalized streams.
+    pub subscription_volume: Uint256,
+    /// Total revenue of finalized streams sent or vested to treasuries.
+    pub creators_revenue: Uint256,
+    /// Total swap fees, creation fees and cancel penalties sent to the fee collector. Swap fees
+    /// are counted in the in denom, before any fee swap.
+    pub fees_collected: Uint256,
+}
+
+#[cw_serde]
+pub struct DenomStatsResponse {
+    pub denom: String,
+    pub subscription_volume: Uint256,
+    pub creators_revenue: Uint256,
+    pub fees_collected: Uint256,
+}
+
+#[cw_serde]
+pub struct ProtocolStatsResponse {
+    /// Number of streams created.
+    pub streams: u64,
+    pub denoms: Vec<DenomStatsResponse>,
+}
+
+// Stream id -> usage statistics
+pub const STREAM_STATS: Map<StreamId, StreamStats> = Map::new("stream_stats");
+// Denom -> protocol wide totals
+pub const DENOM_STATS: Map<&str, DenomStats> = Map::new("denom_stats");
+
+pub fn update_stream_stats<F>(storage: &mut dyn Storage, stream_id: StreamId, f: F) -> StdResult<()>
+where
+    F: FnOnce(&mut StreamStats) -> StdResult<()>,
+{
+    let mut stats = STREAM_STATS
+        .may_load(storage, stream_id)?
+        .unwrap_or_default();
+    f(&mut stats)?;
+    STREAM_STATS.save(storage, stream_id, &stats)
+}
+
+pub fn record_subscription(
+    storage: &mut dyn Storage,
+    stream_id: StreamId,
+    amount: Uint256,
+    new_position: bool,
+) -> StdResult<()> {
+    update_stream_stats(storage, stream_id, |stats| {
+        if new_position {
+            stats.subscribers += 1;
+        }
+        stats.subscription_volume = stats.subscription_volume.checked_add(amount)?;
+        Ok(())
+    })
+}
+
+pub fn record_withdrawal(
+    storage: &mut dyn Storage,
+    stream_id: StreamId,
+    amount: Uint256,
+) -> StdResult<()> {
+    update_stream_stats(storage, stream_id, |stats| {
+        stats.withdrawn = stats.withdrawn.checked_add(amount)?;
+        Ok(())
+    })
+}
+
+pub fn record_exit(storage: &mut dyn Storage, stream_id: StreamId) -> StdResult<()> {
+    update_stream_stats(storage, stream_id, |stats| {
+        stats.exits += 1;
+        Ok(())
+    })
+}
+
+fn update_denom_stats<F>(storage: &mut dyn Storage, denom: &str, f: F) -> StdResult<()>
+where
+    F: FnOnce(&mut DenomStats) -> StdResult<()>,
+{
+    let mut stats = DENOM_STATS.may_load(storage, denom)?.unwrap_or_default();
+    f(&mut stats)?;
+    DENOM_STATS.save(storage, denom, &stats)
+}
+
+/// Adds a finalized stream's subscriptions, revenue and swap fee to the `in_denom` totals.
+pub fn record_finalize(
+    storage: &mut dyn Storage,
+    stream_id: StreamId,
+    in_denom: &str,
+    creators_revenue: Uint256,
+    swap_fee: Uint256,
+) -> StdResult<()> {
+    let subscription_volume = STREAM_STATS
+        .may_load(storage, stream_id)?
+        .unwrap_or_default()
+        .subscription_volume;
+    update_denom_stats(storage, in_denom, |stats| {
+        stats.subscription_volume = stats.subscription_volume.checked_add(subscription_volume)?;
+        stats.creators_revenue = stats.creators_revenue.checked_add(creators_revenue)?;
+        stats.fees_collected = stats.fees_collected.checked_add(swap_fee)?;
+        Ok(())
+    })
+}
+
+pub fn record_fee(storage: &mut dyn Storage, denom: &str, amount: Uint256) -> StdResult<()> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+    update_denom_stats(storage, denom, |stats| {
+        stats.fees_collected = stats.fees_collected.checked_add(amount)?;
+        Ok(())
+    })
+}
+
+pub fn query_protocol_stats(deps: Deps) -> StdResult<ProtocolStatsResponse> {
+    let denoms = DENOM_STATS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (denom, stats) = item?;
+            Ok(DenomStatsResponse {
+                denom,
+                subscription_volume: stats.subscription_volume,
+                creators_revenue: stats.creators_revenue,
+                fees_collected: stats.fees_collected,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ProtocolStatsResponse {
+        streams: created_stream_count(deps.storage)?,
+        denoms,
+    })
+}
+
+pub fn query_statistics(deps: Deps, stream_id: StreamId) -> StdResult<StatisticsResponse> {
+    let stats = STREAM_STATS
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default();
+    let average_position = if stats.subscribers == 0 {
+        Uint256::zero()
+    } else {
+        stats
+            .subscription_volume
+            .checked_div(Uint256::from(stats.subscribers))?
+    };
+    Ok(StatisticsResponse {
+        stream_id,
+        subscribers: stats.subscribers,
+        subscription_volume: stats.subscription_volume,
+        withdrawn: stats.withdrawn,
+        exits: stats.exits,
+        average_position,
+    })
+}
@@ -0,0 +1,135 @@
+use crate::operations::ensure_operations;
+use crate::state::{Status, StreamId, STREAMS};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Uint256, Uint64,
+};
+use cw_storage_plus::Map;
+
+/// Cap on in tokens a stream accepts from subscriptions within a window of blocks.
+#[cw_serde]
+pub struct SubscriptionLimit {
+    /// Maximum in tokens subscribed within a window.
+    pub max_in: Uint256,
+    /// Window length in blocks, `1` caps subscriptions per block.
+    pub window_blocks: Uint64,
+}
+
+/// In tokens subscribed in the current window.
+#[cw_serde]
+pub struct SubscriptionWindow {
+    pub start_height: u64,
+    pub subscribed: Uint256,
+}
+
+impl SubscriptionWindow {
+    pub fn contains(&self, limit: &SubscriptionLimit, height: u64) -> bool {
+        height < self.start_height + limit.window_blocks.u64()
+    }
+}
+
+#[cw_serde]
+pub struct SubscriptionLimitResponse {
+    pub limit: SubscriptionLimit,
+    /// In tokens the stream still accepts in the current window.
+    pub remaining: Uint256,
+}
+
+// Stream id -> subscription cap, only set for rate limited streams
+pub const SUBSCRIPTION_LIMITS: Map<StreamId, SubscriptionLimit> = Map::new("subscription_limits");
+// Stream id -> in tokens subscribed in the current window
+pub const SUBSCRIPTION_WINDOWS: Map<StreamId, SubscriptionWindow> =
+    Map::new("subscription_windows");
+
+/// Counts `amount` against the subscription limit of the stream, if any. Subscriptions over the
+/// remaining amount of the window are rejected.
+pub fn record_limited_subscription(
+    storage: &mut dyn Storage,
+    stream_id: StreamId,
+    height: u64,
+    amount: Uint256,
+) -> Result<(), ContractError> {
+    let limit = match SUBSCRIPTION_LIMITS.may_load(storage, stream_id)? {
+        Some(limit) => limit,
+        None => return Ok(()),
+    };
+    // a new window starts with the first subscription after the previous one expired
+    let window = SUBSCRIPTION_WINDOWS
+        .may_load(storage, stream_id)?
+        .filter(|window| window.contains(&limit, height))
+        .unwrap_or(SubscriptionWindow {
+            start_height: height,
+            subscribed: Uint256::zero(),
+        });
+    let subscribed = window.subscribed.checked_add(amount)?;
+    if subscribed > limit.max_in {
+        return Err(ContractError::SubscriptionLimitExceeded {
+            remaining: limit.max_in.saturating_sub(window.subscribed),
+        });
+    }
+    SUBSCRIPTION_WINDOWS.save(
+        storage,
+        stream_id,
+        &SubscriptionWindow {
+            start_height: window.start_height,
+            subscribed,
+        },
+    )?;
+    Ok(())
+}
+
+/// Sets the subscription limit of the stream, `None` removes it. Only the operations role can
+/// update, before the stream ends.
+pub fn execute_update_subscription_limit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    limit: Option<SubscriptionLimit>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if env.block.time >= stream.end_time
+        || matches!(stream.status, Status::Finalized | Status::Cancelled)
+    {
+        return Err(ContractError::StreamEnded {});
+    }
+    match &limit {
+        Some(limit) => {
+            if limit.max_in.is_zero() || limit.window_blocks.is_zero() {
+                return Err(ContractError::InvalidSubscriptionLimit {});
+            }
+            SUBSCRIPTION_LIMITS.save(deps.storage, stream_id, limit)?;
+        }
+        None => SUBSCRIPTION_LIMITS.remove(deps.storage, stream_id),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_subscription_limit")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute(
+            "max_in",
+            limit.map(|l| l.max_in.to_string()).unwrap_or_default(),
+        ))
+}
+
+pub fn query_subscription_limit(
+    deps: Deps,
+    env: Env,
+    stream_id: StreamId,
+) -> StdResult<Option<SubscriptionLimitResponse>> {
+    let limit = match SUBSCRIPTION_LIMITS.may_load(deps.storage, stream_id)? {
+        Some(limit) => limit,
+        None => return Ok(None),
+    };
+    let subscribed = SUBSCRIPTION_WINDOWS
+        .may_load(deps.storage, stream_id)?
+        .filter(|window| window.contains(&limit, env.block.height))
+        .map(|window| window.subscribed)
+        .unwrap_or_default();
+    Ok(Some(SubscriptionLimitResponse {
+        remaining: limit.max_in.saturating_sub(subscribed),
+        limit,
+    }))
+}
@@ -0,0 +1,120 @@
+use crate::contract::{execute_subscribe, execute_subscribe_pending};
+use crate::state::{StreamId, STREAMS};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, Addr, BankMsg, Coin, DepsMut, Env, MessageInfo, Reply, Response, SubMsg, SubMsgResult,
+    Uint128,
+};
+use cw_storage_plus::Item;
+use cw_utils::one_coin;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, SwapAmountInRoute,
+};
+use std::str::FromStr;
+
+pub const SWAP_SUBSCRIBE_REPLY_ID: u64 = 5;
+
+/// Subscription waiting for the swap of its funds to the in denom of the stream.
+#[cw_serde]
+pub struct PendingSwapSubscription {
+    pub stream_id: StreamId,
+    pub subscriber: Addr,
+    /// Attached funds, refunded as is if the swap fails.
+    pub funds: Coin,
+}
+
+pub const PENDING_SWAP_SUBSCRIPTION: Item<PendingSwapSubscription> =
+    Item::new("pending_swap_subscription");
+
+/// Swaps the attached funds to the in denom of the stream through poolmanager and subscribes
+/// with the swap output in the reply.
+pub fn execute_subscribe_with_swap(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    routes: Vec<SwapAmountInRoute>,
+    min_in_amount: Uint128,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.is_killswitch_active() {
+        return Err(ContractError::StreamKillswitchActive {});
+    }
+    if env.block.time >= stream.end_time {
+        return Err(ContractError::StreamEnded {});
+    }
+    let funds = one_coin(&info)?;
+    // funds in the in denom subscribe directly, routes must end in the in denom
+    if funds.denom == stream.in_denom || min_in_amount.is_zero() {
+        return Err(ContractError::InvalidSwapSubscription {});
+    }
+    match routes.last() {
+        Some(route) if route.token_out_denom == stream.in_denom => {}
+        _ => return Err(ContractError::InvalidSwapSubscription {}),
+    }
+
+    PENDING_SWAP_SUBSCRIPTION.save(
+        deps.storage,
+        &PendingSwapSubscription {
+            stream_id,
+            subscriber: info.sender.clone(),
+            funds: funds.clone(),
+        },
+    )?;
+    let swap_msg = MsgSwapExactAmountIn {
+        sender: env.contract.address.to_string(),
+        routes,
+        token_in: Some(funds.clone().into()),
+        token_out_min_amount: min_in_amount.to_string(),
+    };
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_always(swap_msg, SWAP_SUBSCRIBE_REPLY_ID))
+        .add_attribute("action", "subscribe_with_swap")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("subscriber", info.sender)
+        .add_attribute("token_in", funds.to_string()))
+}
+
+/// Subscribes with the swap output, failed swaps refund the attached funds. A failing
+/// subscription reverts the swap along with it.
+pub fn reply_swap_subscribe(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let pending = PENDING_SWAP_SUBSCRIPTION.load(deps.storage)?;
+    PENDING_SWAP_SUBSCRIPTION.remove(deps.storage);
+
+    let data = match msg.result {
+        SubMsgResult::Ok(res) => res
+            .data
+            .ok_or(ContractError::InvalidSwapSubscriptionReply {})?,
+        SubMsgResult::Err(_) => {
+            return Ok(Response::new()
+                .add_message(BankMsg::Send {
+                    to_address: pending.subscriber.to_string(),
+                    amount: vec![pending.funds.clone()],
+                })
+                .add_attributes(vec![
+                    attr("action", "subscribe_with_swap_failed"),
+                    attr("stream_id", pending.stream_id.to_string()),
+                    attr("refunded", pending.funds.to_string()),
+                ]));
+        }
+    };
+    let res: MsgSwapExactAmountInResponse = data.try_into()?;
+    let amount = Uint128::from_str(&res.token_out_amount)?;
+
+    let stream = STREAMS.load(deps.storage, pending.stream_id)?;
+    let info = MessageInfo {
+        sender: pending.subscriber,
+        funds: vec![Coin::new(amount.u128(), stream.in_denom.clone())],
+    };
+    if stream.start_time > env.block.time {
+        execute_subscribe_pending(deps, env, info, pending.stream_id, None, None, None, stream)
+    } else {
+        execute_subscribe(deps, env, info, pending.stream_id, None, None, None, stream)
+    }
+}
@@ -0,0 +1,78 @@
+use crate::operations::ensure_operations;
+use crate::state::{Status, StreamId, STREAMS};
+use crate::ContractError;
+use cosmwasm_std::{Deps, DepsMut, Empty, Env, MessageInfo, Response, StdResult, Storage};
+use cw_storage_plus::Map;
+
+pub const MAX_STREAM_TAGS: usize = 5;
+const MAX_TAG_LENGTH: usize = 32;
+
+// Stream id -> tags of the stream
+pub const STREAM_TAGS: Map<StreamId, Vec<String>> = Map::new("stream_tags");
+// (tag, stream id) -> (), lists the streams of a tag
+pub const TAGGED_STREAMS: Map<(&str, StreamId), Empty> = Map::new("tagged_streams");
+
+/// Tags are lowercase alphanumeric with dashes, such as `meme` or `real-world-assets`.
+pub fn validate_tags(tags: &[String]) -> Result<(), ContractError> {
+    if tags.len() > MAX_STREAM_TAGS {
+        return Err(ContractError::InvalidTags {});
+    }
+    for (i, tag) in tags.iter().enumerate() {
+        let valid = !tag.is_empty()
+            && tag.len() <= MAX_TAG_LENGTH
+            && tag
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid || tags[..i].contains(tag) {
+            return Err(ContractError::InvalidTags {});
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the tags of the stream. Only the operations role can update, until the stream is
+/// closed.
+pub fn execute_update_stream_tags(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    tags: Vec<String>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if matches!(stream.status, Status::Finalized | Status::Cancelled) {
+        return Err(ContractError::StreamEnded {});
+    }
+    validate_tags(&tags)?;
+    remove_stream_tags(deps.storage, stream_id)?;
+    for tag in &tags {
+        TAGGED_STREAMS.save(deps.storage, (tag, stream_id), &Empty {})?;
+    }
+    if !tags.is_empty() {
+        STREAM_TAGS.save(deps.storage, stream_id, &tags)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_stream_tags")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("tags", tags.join(",")))
+}
+
+/// Removes the tags of the stream and its entries in the tag listings.
+pub fn remove_stream_tags(storage: &mut dyn Storage, stream_id: StreamId) -> StdResult<()> {
+    let tags = STREAM_TAGS
+        .may_load(storage, stream_id)?
+        .unwrap_or_default();
+    for tag in tags {
+        TAGGED_STREAMS.remove(storage, (&tag, stream_id));
+    }
+    STREAM_TAGS.remove(storage, stream_id);
+    Ok(())
+}
+
+pub fn query_stream_tags(deps: Deps, stream_id: StreamId) -> StdResult<Vec<String>> {
+    Ok(STREAM_TAGS
+        .may_load(deps.storage, stream_id)?
+        .unwrap_or_default())
+}
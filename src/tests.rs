@@ -1,23 +1,54 @@
 #[cfg(test)]
 mod test_module {
-    use crate::contract::execute;
+    use crate::allocation_tiers::{
+        query_allocation_tiers, query_tier_deposit, AllocationTier, TierProof,
+    };
+    use crate::audit::{query_audit_balances, DenomAudit};
+    use crate::blocklist::query_blocklist;
+    use crate::contract::{execute, reply, sudo, CREATION_HOOK_REPLY_ID, FINALIZE_HOOK_REPLY_ID};
     use crate::contract::{
         execute_create_stream, execute_exit_stream, execute_finalize_stream,
         execute_update_operator, execute_update_position, execute_update_stream, instantiate,
-        query_average_price, query_config, query_last_streamed_price, query_position, query_stream,
+        query_average_price, query_config, query_creator_allowlist, query_fee_waiver,
+        query_finalize_hooks, query_frozen_denoms, query_last_streamed_price,
+        query_pending_config_update, query_pending_protocol_admin, query_pending_treasury,
+        query_position, query_stream, query_stream_at_height, update_position,
     };
+    use crate::escheat::{query_escheated_position, EscheatedPosition};
+    use crate::hard_cap::query_hard_cap;
+    use crate::keeper::query_keeper_bounty;
     use crate::killswitch::{execute_pause_stream, execute_withdraw_paused, sudo_resume_stream};
-    use crate::msg::ExecuteMsg::UpdateProtocolAdmin;
-    use crate::state::{Status, Stream};
+    use crate::lifecycle::{query_lifecycle, LifecycleResponse, Phase};
+    use crate::msg::ExecuteMsg::{
+        AcceptProtocolAdmin, ApplyConfigUpdate, CancelConfigUpdate, FreezeDenom,
+        ProposeProtocolAdmin, RenounceProtocolAdmin, UnfreezeDenom, UpdateCreatorAllowlist,
+        UpdateFeeWaiver,
+    };
+    use crate::msg::FrozenDenomResponse;
+    use crate::names::query_resolve_name;
+    use crate::partial_refund::{query_partial_refund, PartialRefundResponse};
+    use crate::price_history::{query_streamed_price_twap, StreamedPriceTwapResponse};
+    use crate::prune::{query_pruned_stream, PrunedStream};
+    use crate::revenue_vesting::query_revenue_vesting;
+    use crate::simulate::{query_simulate_exit, query_simulate_subscribe};
+    use crate::state::{
+        ExitCrank, KeeperBounty, LiquidStaking, NameRegistry, Position, Status, Stream,
+        CREATION_HOOKS,
+    };
+    use crate::stats::{query_protocol_stats, query_statistics, DenomStatsResponse};
+    use crate::subscription_limit::{query_subscription_limit, SubscriptionLimit};
+    use crate::tags::query_stream_tags;
     use crate::threshold::ThresholdError;
     use crate::ContractError;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
     use cosmwasm_std::StdError::{self};
     use cosmwasm_std::{
-        attr, coin, Addr, BankMsg, Coin, CosmosMsg, Decimal, Decimal256, Response, SubMsg,
-        Timestamp, Uint128, Uint256, Uint64,
+        attr, coin, to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal256, Event, Reply,
+        Response, SubMsg, SubMsgResult, Timestamp, Uint128, Uint256, Uint64, WasmMsg,
     };
-    use cw_utils::PaymentError;
+    use cw_controllers::HookError;
+    use cw_utils::{Expiration, PaymentError};
+    use osmosis_std::types::cosmos::distribution::v1beta1::MsgFundCommunityPool;
     use std::ops::Sub;
     use std::str::FromStr;
 
@@ -39,27 +70,61 @@ mod test_module {
         );
 
         // add new shares
-        let shares = stream.compute_shares_amount(Uint256::from(100u128), false);
+        let shares = stream
+            .compute_shares_amount(Uint256::from(100u128), false)
+            .unwrap();
         assert_eq!(shares, Uint256::from(100u128));
         stream.in_supply = Uint256::from(100u128);
         stream.shares = shares;
 
         // add new shares
-        stream.shares += stream.compute_shares_amount(Uint256::from(100u128), false);
+        stream.shares += stream
+            .compute_shares_amount(Uint256::from(100u128), false)
+            .unwrap();
         stream.in_supply += Uint256::from(100u128);
         assert_eq!(stream.shares, Uint256::from(200u128));
 
         // add new shares
-        stream.shares += stream.compute_shares_amount(Uint256::from(250u128), false);
+        stream.shares += stream
+            .compute_shares_amount(Uint256::from(250u128), false)
+            .unwrap();
         assert_eq!(stream.shares, Uint256::from(450u128));
         stream.in_supply += Uint256::from(250u128);
 
         // remove shares
-        stream.shares -= stream.compute_shares_amount(Uint256::from(100u128), true);
+        stream.shares -= stream
+            .compute_shares_amount(Uint256::from(100u128), true)
+            .unwrap();
         assert_eq!(stream.shares, Uint256::from(350u128));
         stream.in_supply -= Uint256::from(100u128);
     }
 
+    #[test]
+    fn test_update_position_extreme() {
+        // position shares above the Decimal256 range
+        let shares = Uint256::from(10u128).pow(60);
+        let mut position = Position::new(
+            Addr::unchecked("owner"),
+            Uint256::from(2u128) * Uint256::from(10u128).pow(37),
+            shares,
+            None,
+            Timestamp::from_seconds(0),
+            None,
+        );
+        let (purchased, spent) = update_position(
+            Decimal256::new(Uint256::from(1_000u128)),
+            Uint256::from(10u128).pow(61),
+            Timestamp::from_seconds(10),
+            Uint256::from(10u128).pow(38),
+            &mut position,
+        )
+        .unwrap();
+        assert_eq!(purchased, Uint256::from(10u128).pow(45));
+        assert_eq!(spent, Uint256::from(10u128).pow(37));
+        assert_eq!(position.in_balance, Uint256::from(10u128).pow(37));
+        assert_eq!(position.pending_purchase, Decimal256::zero());
+    }
+
     #[test]
     fn test_create_stream() {
         let mut deps = mock_dependencies();
@@ -73,6 +138,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         let res =
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
@@ -88,6 +158,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         let res =
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
@@ -102,6 +177,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -131,6 +211,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::InDenomIsNotAccepted {}));
         // end < start case
@@ -158,6 +241,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::StreamInvalidEndTime {}));
 
@@ -180,6 +266,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::StreamDurationTooShort {}));
 
@@ -202,6 +291,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::StreamInvalidStartTime {}));
 
@@ -224,6 +316,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::StreamStartsTooSoon {}));
 
@@ -246,6 +341,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::SameDenomOnEachSide {}));
 
@@ -268,6 +366,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::ZeroOutSupply {}));
 
@@ -302,6 +403,9 @@ mod test_module {
             start_time,
             end_time,
             Some(Uint256::zero()),
+            None,
+            None,
+            None,
         )
         .unwrap_err();
         assert_eq!(
@@ -328,6 +432,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::NoFundsSent {}));
 
@@ -348,6 +455,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::StreamOutSupplyFundsRequired {}));
 
@@ -374,6 +484,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::StreamCreationFeeRequired {}));
 
@@ -400,6 +513,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::NoFundsSent {}));
 
@@ -426,6 +542,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::NoFundsSent {}));
 
@@ -446,6 +565,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         );
         assert_eq!(res, Err(ContractError::StreamOutSupplyFundsRequired {}));
 
@@ -476,6 +598,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -509,6 +634,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap_err();
         assert_eq!(err, ContractError::InvalidFunds {});
@@ -537,6 +665,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap_err();
         assert_eq!(err, ContractError::InvalidFunds {});
@@ -564,6 +695,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap_err();
         assert_eq!(res, ContractError::StreamNameTooShort {});
@@ -581,6 +715,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap_err();
         assert_eq!(res, ContractError::StreamNameTooLong {});
@@ -598,6 +735,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap_err();
         assert_eq!(res, ContractError::InvalidStreamName {});
@@ -625,6 +765,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap_err();
         assert_eq!(res, ContractError::StreamUrlTooShort {});
@@ -642,6 +785,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None
         )
             .unwrap_err();
         assert_eq!(res, ContractError::StreamUrlTooLong {});
@@ -659,6 +805,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap_err();
 
@@ -687,6 +836,9 @@ mod test_module {
             start_time,
             end_time,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -717,6 +869,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -743,6 +900,9 @@ mod test_module {
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -754,6 +914,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
         assert_eq!(res, ContractError::StreamEnded {});
@@ -766,6 +927,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
         assert_eq!(res, PaymentError::NoFunds {}.into());
@@ -778,6 +940,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
         assert_eq!(res, PaymentError::MissingDenom("in".to_string()).into());
@@ -793,6 +956,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let _res = execute(deps.as_mut(), env, info, msg);
 
@@ -815,6 +979,7 @@ mod test_module {
             stream_id: 1,
             operator_target: Some("creator1".to_string()),
             operator: None,
+            min_shares_out: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
         assert_eq!(res, ContractError::Unauthorized {});
@@ -827,6 +992,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let _res = execute(deps.as_mut(), env.clone(), info, msg);
         // dist index updated
@@ -860,6 +1026,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -886,6 +1057,9 @@ mod test_module {
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -898,6 +1072,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap();
         assert_eq!(res.attributes[0].key, "action");
@@ -918,6 +1093,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap();
         assert_eq!(res.attributes[0].key, "action");
@@ -950,6 +1126,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap();
         assert_eq!(res.attributes[0].key, "action");
@@ -1037,6 +1214,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -1063,6 +1245,9 @@ mod test_module {
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -1074,6 +1259,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
@@ -1188,6 +1374,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -1213,6 +1404,9 @@ mod test_module {
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -1224,6 +1418,7 @@ mod test_module {
             stream_id: 1,
             operator_target: Some("creator1".to_string()),
             operator: None,
+            min_shares_out: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
         assert_eq!(res, ContractError::Unauthorized {});
@@ -1236,6 +1431,7 @@ mod test_module {
             stream_id: 1,
             operator_target: Some("creator1".to_string()),
             operator: Some("random".to_string()),
+            min_shares_out: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
         assert_eq!(res, ContractError::Unauthorized {});
@@ -1248,6 +1444,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let _res = execute(deps.as_mut(), env, info, msg);
 
@@ -1322,6 +1519,7 @@ mod test_module {
             stream_id: 1,
             operator_target: Some("creator1".to_string()),
             operator: None,
+            min_shares_out: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap();
         assert_eq!(
@@ -1332,6 +1530,13 @@ mod test_module {
                 .add_attribute("owner", "creator1")
                 .add_attribute("in_supply", "2000000")
                 .add_attribute("in_amount", "1000000")
+                .add_event(
+                    Event::new("stream_subscribed")
+                        .add_attribute("stream_id", "1")
+                        .add_attribute("owner", "creator1")
+                        .add_attribute("amount", "1000000")
+                        .add_attribute("in_supply", "2000000")
+                )
         );
 
         // random cannot update operator
@@ -1383,22 +1588,40 @@ mod test_module {
         let info = mock_info("random", &[]);
         let mut env = mock_env();
         env.block.time = end.plus_seconds(100);
-        execute_update_stream(deps.as_mut(), env.clone(), 1).unwrap();
-        let res = execute_exit_stream(deps.as_mut(), env, info, 1, Some("creator1".to_string()))
-            .unwrap_err();
+        execute_update_stream(deps.as_mut(), env.clone(), mock_info("keeper", &[]), 1).unwrap();
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env,
+            info,
+            1,
+            Some("creator1".to_string()),
+            None,
+            false,
+            None,
+        )
+        .unwrap_err();
         assert_eq!(res, ContractError::Unauthorized {});
 
         let mut env = mock_env();
         env.block.time = end.plus_seconds(100);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1).unwrap();
 
         // operator can exit
         let info = mock_info("operator1", &[]);
         let mut env = mock_env();
         env.block.time = end.plus_seconds(100);
-        let res =
-            execute_exit_stream(deps.as_mut(), env, info, 1, Some("creator1".to_string())).unwrap();
-        match res.messages.get(0).unwrap().msg.clone() {
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env,
+            info,
+            1,
+            Some("creator1".to_string()),
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+        match res.messages.first().unwrap().msg.clone() {
             CosmosMsg::Bank(BankMsg::Send {
                 to_address,
                 amount: _,
@@ -1430,6 +1653,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -1456,13 +1684,16 @@ mod test_module {
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
         //update stream without subscription this means no new  distribution so returned index should be 0
         let mut env = mock_env();
         env.block.time = start.plus_seconds(100);
-        let res = execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        let res = execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1).unwrap();
         assert_eq!(
             res,
             Response::default()
@@ -1470,6 +1701,7 @@ mod test_module {
                 .add_attribute("stream_id", "1")
                 .add_attribute("new_distribution_amount", "0")
                 .add_attribute("dist_index", "0")
+                .add_attribute("keeper_bounty", "0")
         );
         //first subscription
         //On first subscription index is not incresed because no distrubution prior to that(Execute_subscibe also includes update_stream)
@@ -1480,6 +1712,7 @@ mod test_module {
             stream_id: 1,
             operator_target: Some("creator1".to_string()),
             operator: None,
+            min_shares_out: None,
         };
         let _res = execute(deps.as_mut(), env, info, msg);
 
@@ -1492,7 +1725,7 @@ mod test_module {
         //Update stream again, this time with subscriber
         let mut env = mock_env();
         env.block.time = start.plus_seconds(300);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1).unwrap();
 
         //Query stream
         let mut env = mock_env();
@@ -1521,6 +1754,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -1547,6 +1785,9 @@ mod test_module {
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -1558,6 +1799,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let _res = execute(deps.as_mut(), env, info, msg);
 
@@ -1630,6 +1872,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -1656,6 +1903,9 @@ mod test_module {
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -1667,6 +1917,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         execute(deps.as_mut(), env, info, msg).unwrap();
 
@@ -1678,6 +1929,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         execute(deps.as_mut(), env, info, msg).unwrap();
 
@@ -1793,6 +2045,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -1819,6 +2076,9 @@ mod test_module {
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -1826,11 +2086,12 @@ mod test_module {
         let mut env = mock_env();
         env.block.time = start.plus_seconds(0);
         let funds = Coin::new(2_000_000_000_000, "in");
-        let info = mock_info("creator1", &[funds.clone()]);
+        let info = mock_info("creator1", std::slice::from_ref(&funds));
         let msg = crate::msg::ExecuteMsg::Subscribe {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
@@ -1893,7 +2154,7 @@ mod test_module {
         assert_eq!(position.spent, Uint256::from(499_993_773_466u128));
         assert_eq!(position.purchased, Uint256::from(249_999_999_998u128));
         assert_eq!(position.shares, Uint256::zero());
-        let msg = res.messages.get(0).unwrap();
+        let msg = res.messages.first().unwrap();
         assert_eq!(
             msg.msg,
             CosmosMsg::Bank(BankMsg::Send {
@@ -1936,6 +2197,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -1962,6 +2228,9 @@ mod test_module {
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -1974,6 +2243,7 @@ mod test_module {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
         let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
@@ -1981,23 +2251,23 @@ mod test_module {
         let mut env = mock_env();
         env.block.time = end.plus_seconds(1);
         let info = mock_info("random", &[]);
-        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
+        let res = execute_finalize_stream(deps.as_mut(), env, info, 1).unwrap_err();
         assert_eq!(res, ContractError::Unauthorized {});
 
         // can't finalize before stream ends
         let mut env = mock_env();
         env.block.time = start.plus_seconds(1);
         let info = mock_info(treasury.as_str(), &[]);
-        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
+        let res = execute_finalize_stream(deps.as_mut(), env, info, 1).unwrap_err();
         assert_eq!(res, ContractError::StreamNotEnded {});
 
         // happy path
         let mut env = mock_env();
         env.block.time = end.plus_seconds(1);
         let info = mock_info(treasury.as_str(), &[]);
-        execute_update_stream(deps.as_mut(), env.clone(), 1).unwrap();
+        execute_update_stream(deps.as_mut(), env.clone(), mock_info("keeper", &[]), 1).unwrap();
 
-        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap();
+        let res = execute_finalize_stream(deps.as_mut(), env, info, 1).unwrap();
         assert_eq!(
             res.attributes,
             vec![
@@ -2009,7 +2279,11 @@ mod test_module {
                 attr("refunded_out_remaining", "0"),
                 attr("total_sold", "1000000000000"),
                 attr("swap_fee", "20000000000"),
+                attr("keeper_bounties", "0"),
                 attr("creation_fee", "100"),
+                attr("revenue_vested", "false"),
+                attr("donated", "0"),
+                attr("in_amount_clp", "0"),
             ]
         );
         assert_eq!(
@@ -2041,33 +2315,36 @@ mod test_module {
     }
 
     #[test]
-    fn test_recurring_finalize_stream_calls() {
-        let malicious_treasury = Addr::unchecked("treasury");
-        let start = Timestamp::from_seconds(10);
-        let end = Timestamp::from_seconds(110);
-        let out_supply = Uint256::from(1000u128);
-        let out_denom = "myToken";
-        let in_denom = "uosmo";
+    fn test_treasury_transfer() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint256::from(1_000_000u128);
+        let out_denom = "out_denom";
+
         // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
-            min_stream_seconds: Uint64::new(100),
+            min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
             exit_fee_percent: Decimal256::percent(1),
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
-            accepted_in_denom: in_denom.to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
-        // Create stream
+
+        // create stream
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
-            malicious_treasury.as_str(),
+            "creator1",
             &[
                 Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
                 Coin::new(100, "fee"),
@@ -2077,82 +2354,101 @@ mod test_module {
             deps.as_mut(),
             env,
             info,
-            malicious_treasury.to_string(),
+            "treasury".to_string(),
             "test".to_string(),
-            Some("https://sample.url".to_string()),
-            in_denom.to_string(),
+            None,
+            "in".to_string(),
             out_denom.to_string(),
             out_supply,
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
-        // First subscription
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(1);
-        let funds = Coin::new(200, in_denom.to_string());
-        let info = mock_info("user1", &[funds]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
+
+        // accept without proposal fails
+        let msg = crate::msg::ExecuteMsg::AcceptTreasury { stream_id: 1 };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_treasury", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoPendingTreasury {});
+
+        // only treasury can propose
+        let propose_msg = crate::msg::ExecuteMsg::ProposeTreasury {
             stream_id: 1,
-            operator_target: None,
-            operator: None,
+            new_treasury: "new_treasury".to_string(),
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-        // Update
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            propose_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            propose_msg,
+        )
+        .unwrap();
+        let pending = query_pending_treasury(deps.as_ref(), 1).unwrap();
+        assert_eq!(pending, Some(Addr::unchecked("new_treasury")));
+
+        // treasury is not changed until accepted
+        let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+        assert_eq!(stream.treasury, "treasury".to_string());
+
+        // only proposed address can accept
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_treasury", &[]),
+            msg,
+        )
+        .unwrap();
+        let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+        assert_eq!(stream.treasury, "new_treasury".to_string());
+        let pending = query_pending_treasury(deps.as_ref(), 1).unwrap();
+        assert_eq!(pending, None);
+
+        // previous treasury can not finalize anymore
         let mut env = mock_env();
         env.block.time = end.plus_seconds(1);
-        let info = mock_info(malicious_treasury.as_str(), &[]);
-        execute_update_stream(deps.as_mut(), env.clone(), 1).unwrap();
-        // First call
+        let err =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1)
+                .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
         let res =
-            execute_finalize_stream(deps.as_mut(), env.clone(), info.clone(), 1, None).unwrap();
-        assert_eq!(
-            res.messages,
-            vec![
-                SubMsg::new(BankMsg::Send {
-                    to_address: malicious_treasury.to_string(),
-                    amount: vec![Coin {
-                        denom: in_denom.to_string(),
-                        amount: Uint128::new(198),
-                    }],
-                }),
-                SubMsg::new(BankMsg::Send {
-                    to_address: "collector".to_string(),
-                    amount: vec![Coin {
-                        denom: "fee".to_string(),
-                        amount: Uint128::new(100),
-                    }],
-                }),
-                SubMsg::new(BankMsg::Send {
-                    to_address: "collector".to_string(),
-                    amount: vec![Coin {
-                        denom: in_denom.to_string(),
-                        amount: Uint128::new(2),
-                    }],
-                }),
-            ],
-        );
-        // Check stream status
-        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
-        assert_eq!(stream.status, Status::Finalized);
-        // Sequential calls, anyone could force this sequential calls
-        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
-        assert_eq!(res, ContractError::StreamAlreadyFinalized {});
+            execute_finalize_stream(deps.as_mut(), env, mock_info("new_treasury", &[]), 1).unwrap();
+        assert!(res.attributes.contains(&attr("treasury", "new_treasury")));
     }
 
     #[test]
-    fn test_exit_stream() {
-        let treasury = Addr::unchecked("treasury");
+    fn test_stream_operations() {
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
-        let out_supply = Uint256::from(1_000_000_000_000u128);
+        let out_supply = Uint256::from(1_000_000u128);
         let out_denom = "out_denom";
 
         // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
@@ -2162,6 +2458,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -2179,84 +2480,206 @@ mod test_module {
             deps.as_mut(),
             env,
             info,
-            treasury.to_string(),
+            "treasury".to_string(),
             "test".to_string(),
-            Some("https://sample.url".to_string()),
+            None,
             "in".to_string(),
             out_denom.to_string(),
             out_supply,
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
-        // first subscription
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(1_000_000);
-        let funds = Coin::new(2_000_000_000_000, "in");
-        let info = mock_info("creator1", &[funds]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
-            stream_id: 1,
-            operator_target: None,
-            operator: None,
+        // treasury holds the operations role until handed over
+        let res = crate::operations::query_stream_operations(deps.as_ref(), 1).unwrap();
+        assert_eq!(res.operations, Addr::unchecked("treasury"));
+        assert_eq!(res.pending_operations, None);
+
+        // accept without proposal fails
+        let accept_msg = crate::msg::ExecuteMsg::AcceptOperations { stream_id: 1 };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("operations", &[]),
+            accept_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoPendingOperations {});
+
+        // only the current holder can propose
+        let propose_msg = crate::msg::ExecuteMsg::ProposeOperations {
+            stream_id: 1,
+            new_operations: "operations".to_string(),
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            propose_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            propose_msg,
+        )
+        .unwrap();
 
-        // can't exit before stream ends
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(2_000_000);
-        let info = mock_info("creator1", &[]);
-        let res = execute_exit_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
-        assert_eq!(res, ContractError::StreamNotEnded {});
+        // only proposed address can accept
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            accept_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("operations", &[]),
+            accept_msg,
+        )
+        .unwrap();
+        let res = crate::operations::query_stream_operations(deps.as_ref(), 1).unwrap();
+        assert_eq!(res.operations, Addr::unchecked("operations"));
+        assert_eq!(res.pending_operations, None);
 
-        //failed exit from random address
+        // treasury can not update tags or hooks anymore
+        let tags_msg = crate::msg::ExecuteMsg::UpdateStreamTags {
+            stream_id: 1,
+            tags: vec!["defi".to_string()],
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            tags_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("operations", &[]),
+            tags_msg,
+        )
+        .unwrap();
+        let hooks_msg = crate::msg::ExecuteMsg::UpdateFinalizeHooks {
+            stream_id: 1,
+            hooks: vec!["hook".to_string()],
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            hooks_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("operations", &[]),
+            hooks_msg,
+        )
+        .unwrap();
+
+        // sale terms and the blocklist are set by the operations role too
+        let blocklist_msg = crate::msg::ExecuteMsg::UpdateBlocklist {
+            stream_id: 1,
+            add: vec!["blocked".to_string()],
+            remove: vec![],
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            blocklist_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("operations", &[]),
+            blocklist_msg,
+        )
+        .unwrap();
         let mut env = mock_env();
-        env.block.time = end.plus_seconds(3_000_000);
-        let info = mock_info("random", &[]);
-        let res = execute_exit_stream(
+        env.block.time = Timestamp::from_seconds(0);
+        let hard_cap_msg = crate::msg::ExecuteMsg::UpdateHardCap {
+            stream_id: 1,
+            hard_cap: Some(Uint256::from(500_000u128)),
+        };
+        let err = execute(
             deps.as_mut(),
             env.clone(),
-            info,
-            1,
-            Some("creator1".to_string()),
+            mock_info("treasury", &[]),
+            hard_cap_msg.clone(),
         )
         .unwrap_err();
-        assert_eq!(res, ContractError::Unauthorized {});
-        // can exit
-        let info = mock_info("creator1", &[]);
-        let res = execute_exit_stream(deps.as_mut(), env, info, 1, None).unwrap();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("operations", &[]),
+            hard_cap_msg,
+        )
+        .unwrap();
         assert_eq!(
-            res.messages,
-            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "creator1".to_string(),
-                amount: vec![Coin::new(
-                    Uint128::new(1_000_000_000_000).u128(),
-                    "out_denom"
-                )]
-            }))]
+            query_hard_cap(deps.as_ref(), 1).unwrap(),
+            Some(Uint256::from(500_000u128))
         );
 
-        // position deleted
+        // the roles are transferred independently
+        let propose_msg = crate::msg::ExecuteMsg::ProposeTreasury {
+            stream_id: 1,
+            new_treasury: "new_treasury".to_string(),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            propose_msg,
+        )
+        .unwrap();
+        let accept_msg = crate::msg::ExecuteMsg::AcceptTreasury { stream_id: 1 };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_treasury", &[]),
+            accept_msg,
+        )
+        .unwrap();
+        let res = crate::operations::query_stream_operations(deps.as_ref(), 1).unwrap();
+        assert_eq!(res.operations, Addr::unchecked("operations"));
+
+        // operations can not finalize, treasury still receives the funds
         let mut env = mock_env();
-        env.block.time = end.plus_seconds(4_000_000);
-        let info = mock_info("creator1", &[]);
-        let res = execute_exit_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
-        assert!(matches!(res, ContractError::Std(StdError::NotFound { .. })));
+        env.block.time = end.plus_seconds(1);
+        let err =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("operations", &[]), 1)
+                .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
     }
 
     #[test]
-    fn test_withdraw_all_before_exit_case() {
-        let treasury = Addr::unchecked("treasury");
+    fn test_finalize_hooks() {
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
-        let out_supply = Uint256::from(1_000_000_000_000u128);
+        let out_supply = Uint256::from(1_000_000u128);
         let out_denom = "out_denom";
 
         // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
@@ -2266,6 +2689,11 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -2283,92 +2711,202 @@ mod test_module {
             deps.as_mut(),
             env,
             info,
-            treasury.to_string(),
+            "treasury".to_string(),
             "test".to_string(),
-            Some("https://sample.url".to_string()),
+            None,
             "in".to_string(),
             out_denom.to_string(),
             out_supply,
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
-        // first subscription
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(1_000_000);
-        let funds = Coin::new(2_000_000_000_000, "in");
-        let info = mock_info("creator1", &[funds]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
+        // only treasury can update hooks
+        let msg = crate::msg::ExecuteMsg::UpdateFinalizeHooks {
             stream_id: 1,
-            operator_target: None,
-            operator: None,
+            hooks: vec!["launchpad".to_string(), "indexer".to_string()],
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
 
-        // second subscription
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(1_000_000);
-        let funds = Coin::new(1_000_000_000_000, "in");
-        let info = mock_info("creator2", &[funds]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
+        // hooks are bounded
+        let too_many = crate::msg::ExecuteMsg::UpdateFinalizeHooks {
             stream_id: 1,
-            operator_target: None,
-            operator: None,
+            hooks: (0..6).map(|i| format!("hook{}", i)).collect(),
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            too_many,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::TooManyFinalizeHooks { max: 5 });
 
-        // first withdraw
-        let info = mock_info("creator1", &[]);
-        let mut env = mock_env();
-        env.block.time = end.minus_seconds(1_000_000);
-        let msg = crate::msg::ExecuteMsg::Withdraw {
-            stream_id: 1,
-            cap: None,
-            operator_target: None,
+        // only protocol admin can update hook limits, gas limit can not be zero
+        let limits = crate::state::FinalizeHookLimits {
+            max_hooks: 1,
+            gas_limit: Uint64::new(200_000),
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let update_limits = crate::msg::ExecuteMsg::UpdateFinalizeHookLimits {
+            limits: Some(limits.clone()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            update_limits.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            crate::msg::ExecuteMsg::UpdateFinalizeHookLimits {
+                limits: Some(crate::state::FinalizeHookLimits {
+                    gas_limit: Uint64::zero(),
+                    ..limits.clone()
+                }),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidFinalizeHookLimits {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            update_limits,
+        )
+        .unwrap();
+        assert_eq!(
+            query_config(deps.as_ref()).unwrap().finalize_hook_limits,
+            limits
+        );
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::TooManyFinalizeHooks { max: 1 });
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            crate::msg::ExecuteMsg::UpdateFinalizeHookLimits {
+                limits: Some(crate::state::FinalizeHookLimits {
+                    max_hooks: 2,
+                    ..limits
+                }),
+            },
+        )
+        .unwrap();
 
-        // second withdraw
-        let info = mock_info("creator2", &[]);
+        execute(deps.as_mut(), mock_env(), mock_info("treasury", &[]), msg).unwrap();
+        let hooks = query_finalize_hooks(deps.as_ref(), 1).unwrap();
+        assert_eq!(
+            hooks,
+            vec![Addr::unchecked("launchpad"), Addr::unchecked("indexer")]
+        );
+
+        // subscribe
         let mut env = mock_env();
-        env.block.time = end.minus_seconds(1_000_000);
-        let msg = crate::msg::ExecuteMsg::Withdraw {
+        env.block.time = start;
+        let msg = crate::msg::ExecuteMsg::Subscribe {
             stream_id: 1,
-            cap: None,
             operator_target: None,
+            operator: None,
+            min_shares_out: None,
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber", &[Coin::new(1_000_000, "in")]),
+            msg,
+        )
+        .unwrap();
 
-        // can exit
+        // finalize notifies hooks with sale stats
         let mut env = mock_env();
-        env.block.time = end.plus_seconds(1_000_000);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        env.block.time = end.plus_seconds(1);
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1)
+                .unwrap();
+        let hook_msg = to_json_binary(&crate::msg::StreamHookMsg::StreamFinalized {
+            stream_id: 1,
+            treasury: "treasury".to_string(),
+            in_denom: "in".to_string(),
+            out_denom: "out_denom".to_string(),
+            spent_in: Uint256::from(1_000_000u128),
+            total_sold: Uint256::from(1_000_000u128),
+            creators_revenue: Uint256::from(990_000u128),
+            swap_fee: Uint256::from(10_000u128),
+        })
+        .unwrap();
+        assert_eq!(
+            res.messages[3..],
+            vec![
+                SubMsg::reply_on_error(
+                    WasmMsg::Execute {
+                        contract_addr: "launchpad".to_string(),
+                        msg: hook_msg.clone(),
+                        funds: vec![],
+                    },
+                    FINALIZE_HOOK_REPLY_ID,
+                )
+                .with_gas_limit(200_000),
+                SubMsg::reply_on_error(
+                    WasmMsg::Execute {
+                        contract_addr: "indexer".to_string(),
+                        msg: hook_msg,
+                        funds: vec![],
+                    },
+                    FINALIZE_HOOK_REPLY_ID,
+                )
+                .with_gas_limit(200_000),
+            ]
+        );
 
-        let mut env = mock_env();
-        env.block.time = end.plus_seconds(1_000_001);
-        let info = mock_info("creator1", &[]);
-        execute_exit_stream(deps.as_mut(), env, info, 1, None).unwrap();
+        // failing hook does not revert finalize
+        let msg = Reply {
+            id: FINALIZE_HOOK_REPLY_ID,
+            result: SubMsgResult::Err("hook error".to_string()),
+        };
+        let res = reply(deps.as_mut(), env.clone(), msg).unwrap();
+        assert!(res
+            .attributes
+            .contains(&attr("action", "finalize_hook_failed")));
 
-        let mut env = mock_env();
-        env.block.time = end.plus_seconds(1_000_002);
-        let info = mock_info("creator2", &[]);
-        execute_exit_stream(deps.as_mut(), env, info, 1, None).unwrap();
+        // hooks can not be updated after finalize
+        let msg = crate::msg::ExecuteMsg::UpdateFinalizeHooks {
+            stream_id: 1,
+            hooks: vec![],
+        };
+        let err = execute(deps.as_mut(), env, mock_info("treasury", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::StreamAlreadyFinalized {});
     }
 
     #[test]
-    fn test_price_feed() {
-        let treasury = Addr::unchecked("treasury");
+    fn test_creation_hooks() {
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
         let out_supply = Uint256::from(1_000_000u128);
-        let out_denom = "out_denom";
 
         // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
@@ -2378,139 +2916,330 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-        // create stream
+        // only protocol admin can add hooks
+        let msg = crate::msg::ExecuteMsg::AddCreationHook {
+            addr: "registry".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let admin = mock_info("protocol_admin", &[]);
+        execute(deps.as_mut(), mock_env(), admin.clone(), msg.clone()).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Hook(HookError::HookAlreadyRegistered {})
+        );
+        let msg = crate::msg::ExecuteMsg::AddCreationHook {
+            addr: "indexer".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap();
+        assert_eq!(
+            CREATION_HOOKS.query_hooks(deps.as_ref()).unwrap().hooks,
+            vec!["registry".to_string(), "indexer".to_string()]
+        );
+
+        // create stream notifies hooks
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
             "creator1",
-            &[
-                Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
-                Coin::new(100, "fee"),
-            ],
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
         );
-        execute_create_stream(
+        let res = execute_create_stream(
             deps.as_mut(),
-            env,
+            env.clone(),
             info,
-            treasury.to_string(),
+            "treasury".to_string(),
             "test".to_string(),
-            Some("https://sample.url".to_string()),
+            None,
             "in".to_string(),
-            out_denom.to_string(),
+            "out_denom".to_string(),
             out_supply,
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
-
-        // first subscription
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(1_000_000);
-        let funds = Coin::new(3_000, "in");
-        let info = mock_info("creator1", &[funds]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
+        let hook_msg = to_json_binary(&crate::msg::StreamHookMsg::StreamCreated {
             stream_id: 1,
-            operator_target: None,
-            operator: None,
+            treasury: "treasury".to_string(),
+            in_denom: "in".to_string(),
+            out_denom: "out_denom".to_string(),
+            out_supply,
+            start_time: start,
+            end_time: end,
+        })
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::reply_on_error(
+                    WasmMsg::Execute {
+                        contract_addr: "registry".to_string(),
+                        msg: hook_msg.clone(),
+                        funds: vec![],
+                    },
+                    CREATION_HOOK_REPLY_ID,
+                ),
+                SubMsg::reply_on_error(
+                    WasmMsg::Execute {
+                        contract_addr: "indexer".to_string(),
+                        msg: hook_msg,
+                        funds: vec![],
+                    },
+                    CREATION_HOOK_REPLY_ID,
+                ),
+            ]
+        );
+
+        // failing hook does not revert creation
+        let msg = Reply {
+            id: CREATION_HOOK_REPLY_ID,
+            result: SubMsgResult::Err("hook error".to_string()),
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let res = reply(deps.as_mut(), env, msg).unwrap();
+        assert!(res
+            .attributes
+            .contains(&attr("action", "creation_hook_failed")));
 
-        //check current streamed price before update
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(2_000_000);
-        let res = query_last_streamed_price(deps.as_ref(), env, 1).unwrap();
-        assert_eq!(res.current_streamed_price, Decimal256::zero());
+        let msg = crate::msg::ExecuteMsg::RemoveCreationHook {
+            addr: "registry".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), admin, msg).unwrap();
+        assert_eq!(
+            CREATION_HOOKS.query_hooks(deps.as_ref()).unwrap().hooks,
+            vec!["indexer".to_string()]
+        );
+    }
 
-        //check current streamed price after update
+    #[test]
+    fn test_revenue_vesting() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint256::from(1_000_000u128);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // revenue is released over 1000 seconds
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(2_000_000);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
-        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
-        //approx 1000/333333
-        assert_eq!(
-            res.current_streamed_price,
-            Decimal256::from_str("0.002997002997002997").unwrap()
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                Coin::new(100, "fee"),
+            ],
         );
-        // second subscription
+        let msg = crate::msg::ExecuteMsg::CreateStream {
+            treasury: "treasury".to_string(),
+            name: "test".to_string(),
+            url: None,
+            in_denom: "in".to_string(),
+            out_denom: out_denom.to_string(),
+            out_supply,
+            start_time: start,
+            end_time: end,
+            threshold: None,
+            revenue_vesting_duration: Some(Uint64::new(1000)),
+            donation_percent: None,
+            refund_excess: None,
+        };
+        execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // subscribe
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(2_000_000);
-        let funds = Coin::new(1_000, "in");
-        let info = mock_info("creator2", &[funds]);
+        env.block.time = start;
         let msg = crate::msg::ExecuteMsg::Subscribe {
             stream_id: 1,
             operator_target: None,
             operator: None,
+            min_shares_out: None,
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber", &[Coin::new(1_000_000, "in")]),
+            msg,
+        )
+        .unwrap();
 
-        //check current streamed price before update
+        // finalize does not send revenue to treasury
+        let finalize_time = end.plus_seconds(1);
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_000_000);
-        let res = query_last_streamed_price(deps.as_ref(), env, 1).unwrap();
+        env.block.time = finalize_time;
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1)
+                .unwrap();
+        assert!(res.attributes.contains(&attr("revenue_vested", "true")));
         assert_eq!(
-            res.current_streamed_price,
-            Decimal256::from_str("0.002997002997002997").unwrap()
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin::new(100, "fee")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin::new(10_000, "in")],
+                }),
+            ]
         );
 
-        //check current streamed price after update
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_000_000);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
-        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
-        //approx 2000/333333
-        assert_eq!(
-            res.current_streamed_price,
-            Decimal256::from_str("0.0045000045000045").unwrap()
-        );
+        // nothing released yet
+        let msg = crate::msg::ExecuteMsg::ClaimVestedRevenue { stream_id: 1 };
+        let err = execute(deps.as_mut(), env, mock_info("random", &[]), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::NoVestedRevenue {});
 
-        //check average streamed price
+        // quarter of the revenue released
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_000_000);
-        let res = query_average_price(deps.as_ref(), env, 1).unwrap();
-        //approx 2500/333333
+        env.block.time = finalize_time.plus_seconds(250);
+        let res = execute(deps.as_mut(), env, mock_info("random", &[]), msg.clone()).unwrap();
         assert_eq!(
-            res.average_price,
-            Decimal256::from_str("0.003748503748503748").unwrap()
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: vec![Coin::new(247_500, "in")],
+            })]
         );
 
-        //withdraw creator 1
-        let info = mock_info("creator1", &[]);
+        // rest is released after duration
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_500_000);
-        let msg = crate::msg::ExecuteMsg::Withdraw {
-            stream_id: 1,
-            cap: None,
-            operator_target: None,
-        };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
+        env.block.time = finalize_time.plus_seconds(2000);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("random", &[]),
+            msg.clone(),
+        )
+        .unwrap();
         assert_eq!(
-            res.current_streamed_price,
-            Decimal256::from_str("0.004499991000017999").unwrap()
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: vec![Coin::new(742_500, "in")],
+            })]
         );
+        let vesting = query_revenue_vesting(deps.as_ref(), 1).unwrap().unwrap();
+        assert_eq!(vesting.claimed, Uint256::from(990_000u128));
+        let err = execute(deps.as_mut(), env, mock_info("random", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoVestedRevenue {});
 
-        //test price after withdraw
+        // zero duration is not accepted
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_750_000);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
-        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
-        //approx 2500/333333
-        assert_eq!(
-            res.current_streamed_price,
-            Decimal256::from_str("0.001500006000024000").unwrap()
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                Coin::new(100, "fee"),
+            ],
         );
+        let msg = crate::msg::ExecuteMsg::CreateStream {
+            treasury: "treasury".to_string(),
+            name: "test".to_string(),
+            url: None,
+            in_denom: "in".to_string(),
+            out_denom: out_denom.to_string(),
+            out_supply,
+            start_time: start,
+            end_time: end,
+            threshold: None,
+            revenue_vesting_duration: Some(Uint64::zero()),
+            donation_percent: None,
+            refund_excess: None,
+        };
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidRevenueVestingDuration {});
     }
 
     #[test]
-    fn test_update_protocol_admin() {
+    fn test_finalize_donation() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint256::from(1_000_000u128);
+
         // instantiate
         let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // whole revenue can not be donated
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        let err = execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+            None,
+            Some(Decimal256::one()),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidDonationPercent {});
+
+        let mut deps = mock_dependencies();
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
@@ -2520,144 +3249,228 @@ mod test_module {
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+            None,
+            Some(Decimal256::percent(10)),
+            None,
+        )
+        .unwrap();
 
-        // random cannot update
-        let env = mock_env();
-        let msg = UpdateProtocolAdmin {
-            new_protocol_admin: "new_protocol_admin".to_string(),
+        // subscribe
+        let mut env = mock_env();
+        env.block.time = start;
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
         };
-        let info = mock_info("random", &[]);
-        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber", &[Coin::new(1_000_000, "in")]),
+            msg,
+        )
+        .unwrap();
 
-        // protocol admin can update
-        let info = mock_info("protocol_admin", &[]);
-        execute(deps.as_mut(), env, info, msg).unwrap();
-        let query = query_config(deps.as_ref()).unwrap();
-        assert_eq!(query.protocol_admin, "new_protocol_admin".to_string());
+        // 10% of the creator revenue goes to the community pool
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1)
+                .unwrap();
+        assert!(res.attributes.contains(&attr("creators_revenue", "891000")));
+        assert!(res.attributes.contains(&attr("donated", "99000")));
+        let donation_msg: CosmosMsg = MsgFundCommunityPool {
+            amount: vec![Coin::new(99_000, "in").into()],
+            depositor: env.contract.address.to_string(),
+        }
+        .into();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![Coin::new(891_000, "in")],
+                }),
+                SubMsg::new(donation_msg),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin::new(100, "fee")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin::new(10_000, "in")],
+                }),
+            ]
+        );
     }
+
     #[test]
-    fn test_execute_update_config() {
+    fn test_recurring_finalize_stream_calls() {
+        let malicious_treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(10);
+        let end = Timestamp::from_seconds(110);
+        let out_supply = Uint256::from(1000u128);
+        let out_denom = "myToken";
+        let in_denom = "uosmo";
         // instantiate
         let mut deps = mock_dependencies();
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
-            min_stream_seconds: Uint64::new(1000),
+            min_stream_seconds: Uint64::new(100),
             min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
             exit_fee_percent: Decimal256::percent(1),
             fee_collector: "collector".to_string(),
             protocol_admin: "protocol_admin".to_string(),
-            accepted_in_denom: "in".to_string(),
+            accepted_in_denom: in_denom.to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
-
-        //query config
-        let config_response = query_config(deps.as_ref()).unwrap();
-        //check config
-        assert_eq!(config_response.min_stream_seconds, Uint64::new(1000));
-        assert_eq!(config_response.min_seconds_until_start_time, Uint64::new(0));
-        assert_eq!(config_response.stream_creation_denom, "fee".to_string());
-        assert_eq!(config_response.stream_creation_fee, Uint128::new(100));
-        assert_eq!(config_response.fee_collector, "collector".to_string());
-        assert_eq!(config_response.protocol_admin, "protocol_admin".to_string());
-        assert_eq!(config_response.accepted_in_denom, "in".to_string());
-
-        // random user cant update config
+        // Create stream
         let mut env = mock_env();
-        let info = mock_info("random", &[]);
         env.block.time = Timestamp::from_seconds(0);
-        let msg = crate::msg::ExecuteMsg::UpdateConfig {
-            min_stream_duration: Some(Uint64::new(2000)),
-            min_duration_until_start_time: Some(Uint64::new(2000)),
-            stream_creation_denom: Some("fee2".to_string()),
-            stream_creation_fee: Some(Uint128::new(200)),
-            fee_collector: Some("collector2".to_string()),
-            accepted_in_denom: Some("new_denom".to_string()),
-            exit_fee_percent: Some(Decimal256::percent(2)),
-        };
-        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(res, ContractError::Unauthorized {});
-
-        // wrong fee amount
-        let mut env = mock_env();
-        let info = mock_info("protocol_admin", &[]);
-        env.block.time = Timestamp::from_seconds(0);
-        let msg = crate::msg::ExecuteMsg::UpdateConfig {
-            min_stream_duration: Some(Uint64::new(2000)),
-            min_duration_until_start_time: Some(Uint64::new(2000)),
-            stream_creation_denom: Some("fee2".to_string()),
-            stream_creation_fee: Some(Uint128::new(0)),
-            fee_collector: Some("collector2".to_string()),
-            accepted_in_denom: Some("new_denom".to_string()),
-            exit_fee_percent: Some(Decimal256::percent(2)),
-        };
-        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(res, ContractError::InvalidStreamCreationFee {});
-
-        // wrong exit fee percent
+        let info = mock_info(
+            malicious_treasury.as_str(),
+            &[
+                Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            malicious_treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            in_denom.to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        // First subscription
         let mut env = mock_env();
-        let info = mock_info("protocol_admin", &[]);
-        env.block.time = Timestamp::from_seconds(0);
-        let msg = crate::msg::ExecuteMsg::UpdateConfig {
-            min_stream_duration: Some(Uint64::new(2000)),
-            min_duration_until_start_time: Some(Uint64::new(2000)),
-            stream_creation_denom: Some("fee2".to_string()),
-            stream_creation_fee: Some(Uint128::new(200)),
-            fee_collector: Some("collector2".to_string()),
-            accepted_in_denom: Some("new_denom".to_string()),
-            exit_fee_percent: Some(Decimal256::percent(101)),
+        env.block.time = start.plus_seconds(1);
+        let funds = Coin::new(200, in_denom.to_string());
+        let info = mock_info("user1", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
         };
-        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(res, ContractError::InvalidExitFeePercent {});
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        // Update
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let info = mock_info(malicious_treasury.as_str(), &[]);
+        execute_update_stream(deps.as_mut(), env.clone(), mock_info("keeper", &[]), 1).unwrap();
+        // First call
+        let res = execute_finalize_stream(deps.as_mut(), env.clone(), info.clone(), 1).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: malicious_treasury.to_string(),
+                    amount: vec![Coin {
+                        denom: in_denom.to_string(),
+                        amount: Uint128::new(198),
+                    }],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin {
+                        denom: "fee".to_string(),
+                        amount: Uint128::new(100),
+                    }],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin {
+                        denom: in_denom.to_string(),
+                        amount: Uint128::new(2),
+                    }],
+                }),
+            ],
+        );
+        // Check stream status
+        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(stream.status, Status::Finalized);
+        // Sequential calls, anyone could force this sequential calls
+        let res = execute_finalize_stream(deps.as_mut(), env, info, 1).unwrap_err();
+        assert_eq!(res, ContractError::StreamAlreadyFinalized {});
+    }
 
-        // protocol admin can update config
+    #[test]
+    fn test_exit_stream() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint256::from(1_000_000_000_000u128);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
         let mut env = mock_env();
-        let info = mock_info("protocol_admin", &[]);
         env.block.time = Timestamp::from_seconds(0);
-        let msg = crate::msg::ExecuteMsg::UpdateConfig {
-            min_stream_duration: Some(Uint64::new(2000)),
-            min_duration_until_start_time: Some(Uint64::new(2000)),
-            stream_creation_denom: Some("fee2".to_string()),
-            stream_creation_fee: Some(Uint128::new(200)),
-            fee_collector: Some("collector2".to_string()),
-            accepted_in_denom: Some("new_denom".to_string()),
-            exit_fee_percent: Some(Decimal256::percent(2)),
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
         };
-        execute(deps.as_mut(), env, info, msg).unwrap();
-
-        //query config
-        let config_response = query_config(deps.as_ref()).unwrap();
-        //check config
-        assert_eq!(config_response.min_stream_seconds, Uint64::new(2000));
-        assert_eq!(
-            config_response.min_seconds_until_start_time,
-            Uint64::new(2000)
-        );
-        assert_eq!(config_response.stream_creation_denom, "fee2".to_string());
-        assert_eq!(config_response.stream_creation_fee, Uint128::new(200));
-        assert_eq!(config_response.fee_collector, "collector2".to_string());
-        assert_eq!(config_response.protocol_admin, "protocol_admin".to_string());
-        assert_eq!(config_response.accepted_in_denom, "new_denom".to_string());
-        assert_eq!(config_response.exit_fee_percent, Decimal256::percent(2));
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
         // create stream
-        let out_supply = Uint256::from(1000u128);
-        let out_denom = "out";
-        let start = Timestamp::from_seconds(10000);
-        let end = Timestamp::from_seconds(1000000);
-        let treasury = "treasury";
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
             "creator1",
             &[
                 Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
-                Coin::new(200, "fee2"),
+                Coin::new(100, "fee"),
             ],
         );
         execute_create_stream(
@@ -2667,65 +3480,7876 @@ mod test_module {
             treasury.to_string(),
             "test".to_string(),
             Some("https://sample.url".to_string()),
-            "new_denom".to_string(),
+            "in".to_string(),
             out_denom.to_string(),
             out_supply,
             start,
             end,
             None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
-        // update config during stream
+        // first subscription
         let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(100000);
-        let info = mock_info("protocol_admin", &[]);
-        let msg = crate::msg::ExecuteMsg::UpdateConfig {
-            min_stream_duration: Some(Uint64::new(3000)),
-            min_duration_until_start_time: Some(Uint64::new(4000)),
-            stream_creation_denom: Some("fee3".to_string()),
-            stream_creation_fee: Some(Uint128::new(300)),
-            fee_collector: Some("collector3".to_string()),
-            accepted_in_denom: Some("new_denom2".to_string()),
-            exit_fee_percent: Some(Decimal256::percent(5)),
+        env.block.time = start.plus_seconds(1_000_000);
+        let funds = Coin::new(2_000_000_000_000, "in");
+        let info = mock_info("creator1", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
         };
-        execute(deps.as_mut(), env, info, msg).unwrap();
-        //query config
-        let config_response = query_config(deps.as_ref()).unwrap();
-        //check config
-        assert_eq!(config_response.min_stream_seconds, Uint64::new(3000));
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // can't exit before stream ends
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(2_000_000);
+        let info = mock_info("creator1", &[]);
+        let res =
+            execute_exit_stream(deps.as_mut(), env, info, 1, None, None, false, None).unwrap_err();
+        assert_eq!(res, ContractError::StreamNotEnded {});
+
+        //failed exit from random address
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(3_000_000);
+        let info = mock_info("random", &[]);
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            1,
+            Some("creator1".to_string()),
+            None,
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+        // can exit
+        let info = mock_info("creator1", &[]);
+        let res =
+            execute_exit_stream(deps.as_mut(), env, info, 1, None, None, false, None).unwrap();
         assert_eq!(
-            config_response.min_seconds_until_start_time,
-            Uint64::new(4000)
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator1".to_string(),
+                amount: vec![Coin::new(
+                    Uint128::new(1_000_000_000_000).u128(),
+                    "out_denom"
+                )]
+            }))]
         );
-        assert_eq!(config_response.stream_creation_denom, "fee3".to_string());
-        assert_eq!(config_response.stream_creation_fee, Uint128::new(300));
-        assert_eq!(config_response.fee_collector, "collector3".to_string());
-        assert_eq!(config_response.protocol_admin, "protocol_admin".to_string());
-        assert_eq!(config_response.accepted_in_denom, "new_denom2".to_string());
-        assert_eq!(config_response.exit_fee_percent, Decimal256::percent(5));
 
-        // check stream
+        // position deleted
         let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(100000);
-        let stream_response = query_stream(deps.as_ref(), env, 1).unwrap();
-        assert_eq!(stream_response.exit_fee_percent, Decimal256::percent(2));
-        assert_eq!(stream_response.stream_creation_fee, Uint128::new(200));
+        env.block.time = end.plus_seconds(4_000_000);
+        let info = mock_info("creator1", &[]);
+        let res =
+            execute_exit_stream(deps.as_mut(), env, info, 1, None, None, false, None).unwrap_err();
+        assert!(matches!(res, ContractError::Std(StdError::NotFound { .. })));
     }
 
-    #[cfg(test)]
-    mod killswitch {
-        use super::*;
-        use crate::contract::{list_positions, list_streams};
-        use crate::killswitch::{
-            execute_cancel_stream, execute_exit_cancelled, execute_resume_stream,
-            sudo_cancel_stream, sudo_pause_stream,
-        };
-        use cosmwasm_std::CosmosMsg::Bank;
-        use cosmwasm_std::{ReplyOn, SubMsg};
+    #[test]
+    fn test_withdraw_all_before_exit_case() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint256::from(1_000_000_000_000u128);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // create stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // first subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1_000_000);
+        let funds = Coin::new(2_000_000_000_000, "in");
+        let info = mock_info("creator1", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // second subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1_000_000);
+        let funds = Coin::new(1_000_000_000_000, "in");
+        let info = mock_info("creator2", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // first withdraw
+        let info = mock_info("creator1", &[]);
+        let mut env = mock_env();
+        env.block.time = end.minus_seconds(1_000_000);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // second withdraw
+        let info = mock_info("creator2", &[]);
+        let mut env = mock_env();
+        env.block.time = end.minus_seconds(1_000_000);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // can exit
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1_000_000);
+        execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1_000_001);
+        let info = mock_info("creator1", &[]);
+        execute_exit_stream(deps.as_mut(), env, info, 1, None, None, false, None).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1_000_002);
+        let info = mock_info("creator2", &[]);
+        execute_exit_stream(deps.as_mut(), env, info, 1, None, None, false, None).unwrap();
+    }
+
+    #[test]
+    fn test_price_feed() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint256::from(1_000_000u128);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // create stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // first subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1_000_000);
+        let funds = Coin::new(3_000, "in");
+        let info = mock_info("creator1", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        //check current streamed price before update
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(2_000_000);
+        let res = query_last_streamed_price(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(res.current_streamed_price, Decimal256::zero());
+
+        //check current streamed price after update
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(2_000_000);
+        execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1).unwrap();
+        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
+        //approx 1000/333333
+        assert_eq!(
+            res.current_streamed_price,
+            Decimal256::from_str("0.002997002997002997").unwrap()
+        );
+        // second subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(2_000_000);
+        let funds = Coin::new(1_000, "in");
+        let info = mock_info("creator2", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        //check current streamed price before update
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_000_000);
+        let res = query_last_streamed_price(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(
+            res.current_streamed_price,
+            Decimal256::from_str("0.002997002997002997").unwrap()
+        );
+
+        //check current streamed price after update
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_000_000);
+        execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1).unwrap();
+        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
+        //approx 2000/333333
+        assert_eq!(
+            res.current_streamed_price,
+            Decimal256::from_str("0.0045000045000045").unwrap()
+        );
+
+        //check average streamed price
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_000_000);
+        let res = query_average_price(deps.as_ref(), env, 1).unwrap();
+        //approx 2500/333333
+        assert_eq!(
+            res.average_price,
+            Decimal256::from_str("0.003748503748503748").unwrap()
+        );
+
+        //withdraw creator 1
+        let info = mock_info("creator1", &[]);
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_500_000);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
+        assert_eq!(
+            res.current_streamed_price,
+            Decimal256::from_str("0.004499991000017999").unwrap()
+        );
+
+        //test price after withdraw
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_750_000);
+        execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1).unwrap();
+        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
+        //approx 2500/333333
+        assert_eq!(
+            res.current_streamed_price,
+            Decimal256::from_str("0.001500006000024000").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_update_protocol_admin() {
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // random cannot propose
+        let env = mock_env();
+        let msg = ProposeProtocolAdmin {
+            new_protocol_admin: "new_protocol_admin".to_string(),
+            expiry: Some(Expiration::AtHeight(env.block.height + 10)),
+        };
+        let info = mock_info("random", &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // nothing to accept yet
+        let info = mock_info("new_protocol_admin", &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, AcceptProtocolAdmin {}).unwrap_err();
+        assert_eq!(err, ContractError::NoPendingProtocolAdmin {});
+
+        // protocol admin proposes, admin is not changed until accepted
+        let info = mock_info("protocol_admin", &[]);
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let query = query_config(deps.as_ref()).unwrap();
+        assert_eq!(query.protocol_admin, Some("protocol_admin".to_string()));
+        let pending = query_pending_protocol_admin(deps.as_ref())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            pending.new_protocol_admin,
+            Addr::unchecked("new_protocol_admin")
+        );
+
+        // only proposed admin can accept
+        let info = mock_info("random", &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, AcceptProtocolAdmin {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // proposal expires
+        let mut expired_env = env.clone();
+        expired_env.block.height += 10;
+        let info = mock_info("new_protocol_admin", &[]);
+        let err = execute(deps.as_mut(), expired_env, info, AcceptProtocolAdmin {}).unwrap_err();
+        assert_eq!(err, ContractError::ProtocolAdminProposalExpired {});
+
+        let info = mock_info("new_protocol_admin", &[]);
+        execute(deps.as_mut(), env.clone(), info, AcceptProtocolAdmin {}).unwrap();
+        let query = query_config(deps.as_ref()).unwrap();
+        assert_eq!(query.protocol_admin, Some("new_protocol_admin".to_string()));
+        assert_eq!(query_pending_protocol_admin(deps.as_ref()).unwrap(), None);
+
+        // old admin lost its rights
+        let msg = ProposeProtocolAdmin {
+            new_protocol_admin: "protocol_admin".to_string(),
+            expiry: None,
+        };
+        let info = mock_info("protocol_admin", &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // renounce drops the admin and the pending proposal
+        let info = mock_info("new_protocol_admin", &[]);
+        execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            RenounceProtocolAdmin {},
+        )
+        .unwrap();
+        let query = query_config(deps.as_ref()).unwrap();
+        assert_eq!(query.protocol_admin, None);
+        assert_eq!(query_pending_protocol_admin(deps.as_ref()).unwrap(), None);
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+    #[test]
+    fn test_fee_waiver() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint256::from(1_000_000u128);
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // random cannot update
+        let msg = UpdateFeeWaiver {
+            creator: "partner".to_string(),
+            discount: Some(Decimal256::percent(50)),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("random", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // discount must be in (0, 1]
+        for discount in [Decimal256::zero(), Decimal256::percent(101)] {
+            let msg = UpdateFeeWaiver {
+                creator: "partner".to_string(),
+                discount: Some(discount),
+            };
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("protocol_admin", &[]),
+                msg,
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::InvalidFeeWaiver {});
+        }
+
+        // half discount
+        let msg = UpdateFeeWaiver {
+            creator: "partner".to_string(),
+            discount: Some(Decimal256::percent(50)),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            query_fee_waiver(deps.as_ref(), "partner".to_string()).unwrap(),
+            Some(Decimal256::percent(50))
+        );
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let create_msg = crate::msg::ExecuteMsg::CreateStream {
+            treasury: "treasury".to_string(),
+            name: "test".to_string(),
+            url: None,
+            in_denom: "in".to_string(),
+            out_denom: "out_denom".to_string(),
+            out_supply,
+            start_time: start,
+            end_time: end,
+            threshold: None,
+            revenue_vesting_duration: None,
+            donation_percent: None,
+            refund_excess: None,
+        };
+        // full fee is not accepted
+        let info = mock_info(
+            "partner",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        let err = execute(deps.as_mut(), env.clone(), info, create_msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::StreamCreationFeeRequired {});
+
+        let info = mock_info(
+            "partner",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(50, "fee")],
+        );
+        execute(deps.as_mut(), env.clone(), info, create_msg.clone()).unwrap();
+        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(stream.stream_creation_fee, Uint128::new(50));
+
+        // fee waived entirely
+        let msg = UpdateFeeWaiver {
+            creator: "partner".to_string(),
+            discount: Some(Decimal256::one()),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        let info = mock_info("partner", &[Coin::new(1_000_000, "out_denom")]);
+        execute(deps.as_mut(), env.clone(), info, create_msg.clone()).unwrap();
+
+        // finalize does not send a creation fee
+        let mut finalize_env = mock_env();
+        finalize_env.block.time = end.plus_seconds(1);
+        let res =
+            execute_finalize_stream(deps.as_mut(), finalize_env, mock_info("treasury", &[]), 2)
+                .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: vec![Coin::new(1_000_000, "out_denom")],
+            })]
+        );
+
+        // waiver removed, full fee is charged again
+        let msg = UpdateFeeWaiver {
+            creator: "partner".to_string(),
+            discount: None,
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            query_fee_waiver(deps.as_ref(), "partner".to_string()).unwrap(),
+            None
+        );
+        let info = mock_info("partner", &[Coin::new(1_000_000, "out_denom")]);
+        let err = execute(deps.as_mut(), env, info, create_msg).unwrap_err();
+        assert_eq!(err, ContractError::NoFundsSent {});
+    }
+
+    #[test]
+    fn test_stream_at_height() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        env.block.height = 100;
+        let info = mock_info(
+            "creator",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.time = start;
+        env.block.height = 200;
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        let info = mock_info("creator1", &[Coin::new(1_000, "in")]);
+        execute(deps.as_mut(), env, info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(3_000_000);
+        env.block.height = 300;
+        execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1).unwrap();
+
+        // not created yet
+        let err = query_stream_at_height(deps.as_ref(), mock_env(), 1, 100).unwrap_err();
+        assert!(matches!(err, StdError::NotFound { .. }));
+
+        let stream = query_stream_at_height(deps.as_ref(), mock_env(), 1, 150).unwrap();
+        assert_eq!(stream.status, Status::Waiting);
+        assert_eq!(stream.in_supply, Uint256::zero());
+
+        let stream = query_stream_at_height(deps.as_ref(), mock_env(), 1, 300).unwrap();
+        assert_eq!(stream.status, Status::Active);
+        assert_eq!(stream.in_supply, Uint256::from(1_000u128));
+        assert_eq!(stream.dist_index, Decimal256::zero());
+
+        // latest state
+        let stream = query_stream_at_height(deps.as_ref(), mock_env(), 1, 301).unwrap();
+        let current = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+        assert_eq!(stream, current);
+        assert_eq!(stream.in_supply, Uint256::from(500u128));
+    }
+
+    #[test]
+    fn test_freeze_denom() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // random cannot freeze
+        let msg = FreezeDenom {
+            denom: "out_denom".to_string(),
+            expiry: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("random", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        for denom in ["out_denom", "in"] {
+            let msg = FreezeDenom {
+                denom: denom.to_string(),
+                expiry: None,
+            };
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("protocol_admin", &[]),
+                msg,
+            )
+            .unwrap();
+        }
+        let frozen = query_frozen_denoms(deps.as_ref(), mock_env(), None, None).unwrap();
+        assert_eq!(
+            frozen.into_iter().map(|f| f.denom).collect::<Vec<_>>(),
+            vec!["in".to_string(), "out_denom".to_string()]
+        );
+        let frozen =
+            query_frozen_denoms(deps.as_ref(), mock_env(), Some("in".to_string()), None).unwrap();
+        assert_eq!(
+            frozen,
+            vec![FrozenDenomResponse {
+                denom: "out_denom".to_string(),
+                expiry: None,
+            }]
+        );
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let create_msg = crate::msg::ExecuteMsg::CreateStream {
+            treasury: "treasury".to_string(),
+            name: "test".to_string(),
+            url: None,
+            in_denom: "in".to_string(),
+            out_denom: "out_denom".to_string(),
+            out_supply: Uint256::from(1_000_000u128),
+            start_time: start,
+            end_time: end,
+            threshold: None,
+            revenue_vesting_duration: None,
+            donation_percent: None,
+            refund_excess: None,
+        };
+        let info = mock_info(
+            "creator",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        let err =
+            execute(deps.as_mut(), env.clone(), info.clone(), create_msg.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DenomFrozen {
+                denom: "in".to_string()
+            }
+        );
+
+        let msg = UnfreezeDenom {
+            denom: "in".to_string(),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        let err =
+            execute(deps.as_mut(), env.clone(), info.clone(), create_msg.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DenomFrozen {
+                denom: "out_denom".to_string()
+            }
+        );
+
+        let msg = UnfreezeDenom {
+            denom: "out_denom".to_string(),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        execute(deps.as_mut(), env.clone(), info.clone(), create_msg.clone()).unwrap();
+        assert!(query_frozen_denoms(deps.as_ref(), mock_env(), None, None)
+            .unwrap()
+            .is_empty());
+
+        // expired freeze can not be set
+        let msg = FreezeDenom {
+            denom: "in".to_string(),
+            expiry: Some(Expiration::AtTime(env.block.time)),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidFreezeExpiry {});
+
+        // freeze unfreezes itself after expiry
+        let msg = FreezeDenom {
+            denom: "in".to_string(),
+            expiry: Some(Expiration::AtTime(env.block.time.plus_seconds(10))),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        let err =
+            execute(deps.as_mut(), env.clone(), info.clone(), create_msg.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DenomFrozen {
+                denom: "in".to_string()
+            }
+        );
+        assert_eq!(
+            query_frozen_denoms(deps.as_ref(), env.clone(), None, None)
+                .unwrap()
+                .len(),
+            1
+        );
+        env.block.time = env.block.time.plus_seconds(10);
+        assert!(query_frozen_denoms(deps.as_ref(), env.clone(), None, None)
+            .unwrap()
+            .is_empty());
+        execute(deps.as_mut(), env, info, create_msg).unwrap();
+    }
+
+    #[test]
+    fn test_creator_allowlist() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+
+        // instantiate with permissioned creation
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: Some(true),
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        assert!(query_config(deps.as_ref()).unwrap().permissioned_creation);
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let create_msg = |name: &str| crate::msg::ExecuteMsg::CreateStream {
+            treasury: "treasury".to_string(),
+            name: name.to_string(),
+            url: None,
+            in_denom: "in".to_string(),
+            out_denom: "out_denom".to_string(),
+            out_supply: Uint256::from(1_000_000u128),
+            start_time: start,
+            end_time: end,
+            threshold: None,
+            revenue_vesting_duration: None,
+            donation_percent: None,
+            refund_excess: None,
+        };
+        let info = mock_info(
+            "creator",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        let err =
+            execute(deps.as_mut(), env.clone(), info.clone(), create_msg("test")).unwrap_err();
+        assert_eq!(err, ContractError::CreatorNotAllowed {});
+
+        // random cannot update allowlist
+        let msg = UpdateCreatorAllowlist {
+            add: vec!["random".to_string()],
+            remove: vec![],
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("random", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let msg = UpdateCreatorAllowlist {
+            add: vec!["creator".to_string(), "creator2".to_string()],
+            remove: vec![],
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            query_creator_allowlist(deps.as_ref(), None, None).unwrap(),
+            vec![Addr::unchecked("creator"), Addr::unchecked("creator2")]
+        );
+        execute(deps.as_mut(), env.clone(), info.clone(), create_msg("test")).unwrap();
+
+        // removed creator cannot create
+        let msg = UpdateCreatorAllowlist {
+            add: vec![],
+            remove: vec!["creator".to_string()],
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            query_creator_allowlist(deps.as_ref(), None, None).unwrap(),
+            vec![Addr::unchecked("creator2")]
+        );
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            create_msg("test2"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::CreatorNotAllowed {});
+
+        // flip to permissionless
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            fee_collector: None,
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: Some(Some(false)),
+            config_timelock: None,
+            prune_delay_blocks: None,
+            escheat_delay_blocks: None,
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert!(!query_config(deps.as_ref()).unwrap().permissioned_creation);
+        execute(deps.as_mut(), env, info, create_msg("test2")).unwrap();
+    }
+
+    #[test]
+    fn test_statistics() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint256::from(1_000_000u128);
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // no activity yet
+        let res = query_statistics(deps.as_ref(), 1).unwrap();
+        assert_eq!(res.subscribers, 0);
+        assert_eq!(res.average_position, Uint256::zero());
+
+        // creator1 subscribes twice, creator2 once
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(100);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        for (sender, amount) in [
+            ("creator1", 1_000),
+            ("creator1", 2_000),
+            ("creator2", 3_000),
+        ] {
+            let info = mock_info(sender, &[Coin::new(amount, "in")]);
+            execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap();
+        }
+
+        // creator2 withdraws a part
+        env.block.time = start.plus_seconds(200);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: Some(Uint256::from(500u128)),
+            operator_target: None,
+        };
+        execute(deps.as_mut(), env, mock_info("creator2", &[]), msg).unwrap();
+
+        // creator1 exits after the stream ends
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let msg = crate::msg::ExecuteMsg::ExitStream {
+            stream_id: 1,
+            operator_target: None,
+            remote_recipient: None,
+            liquid_stake: false,
+            swap: None,
+        };
+        execute(deps.as_mut(), env, mock_info("creator1", &[]), msg).unwrap();
+
+        let res = query_statistics(deps.as_ref(), 1).unwrap();
+        assert_eq!(res.stream_id, 1);
+        assert_eq!(res.subscribers, 2);
+        assert_eq!(res.subscription_volume, Uint256::from(6_000u128));
+        assert_eq!(res.withdrawn, Uint256::from(500u128));
+        assert_eq!(res.exits, 1);
+        assert_eq!(res.average_position, Uint256::from(3_000u128));
+
+        // protocol totals are updated at finalize
+        let res = query_protocol_stats(deps.as_ref()).unwrap();
+        assert_eq!(res.streams, 1);
+        assert!(res.denoms.is_empty());
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        execute_finalize_stream(deps.as_mut(), env, mock_info("treasury", &[]), 1).unwrap();
+        let res = query_protocol_stats(deps.as_ref()).unwrap();
+        assert_eq!(
+            res.denoms,
+            vec![
+                DenomStatsResponse {
+                    denom: "fee".to_string(),
+                    subscription_volume: Uint256::zero(),
+                    creators_revenue: Uint256::zero(),
+                    fees_collected: Uint256::from(100u128),
+                },
+                DenomStatsResponse {
+                    denom: "in".to_string(),
+                    subscription_volume: Uint256::from(6_000u128),
+                    creators_revenue: Uint256::from(5_445u128),
+                    fees_collected: Uint256::from(55u128),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prune_streams() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut env = mock_env();
+        env.block.time = start;
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber", &[Coin::new(1_000_000, "in")]),
+            msg,
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1).unwrap();
+        let prune = crate::msg::ExecuteMsg::PruneStreams {
+            stream_ids: vec![1],
+        };
+
+        // disabled until a prune delay is configured
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("keeper", &[]),
+            prune.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::PruneDisabled {});
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            fee_collector: None,
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+            prune_delay_blocks: Some(Some(Uint64::new(100))),
+            escheat_delay_blocks: None,
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+
+        // delay not passed yet
+        env.block.height += 100;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("keeper", &[]),
+            prune.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StreamNotPrunable { stream_id: 1 });
+
+        // open positions keep the stream
+        env.block.height += 1;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("keeper", &[]),
+            prune.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StreamNotPrunable { stream_id: 1 });
+
+        execute_exit_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[]),
+            1,
+            None,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            crate::contract::query_count_streams(deps.as_ref())
+                .unwrap()
+                .count,
+            1
+        );
+
+        // escheated positions keep the stream until claimed
+        crate::escheat::ESCHEATED_POSITIONS
+            .save(
+                deps.as_mut().storage,
+                (1, &Addr::unchecked("absent")),
+                &EscheatedPosition {
+                    out_denom: "out_denom".to_string(),
+                    purchased: Uint256::from(1u128),
+                    in_denom: "in".to_string(),
+                    refunded: Uint256::zero(),
+                },
+            )
+            .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("keeper", &[]),
+            prune.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StreamNotPrunable { stream_id: 1 });
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("absent", &[]),
+            crate::msg::ExecuteMsg::ClaimEscheated { stream_id: 1 },
+        )
+        .unwrap();
+        assert_eq!(query_statistics(deps.as_ref(), 1).unwrap().subscribers, 1);
+        execute(deps.as_mut(), env.clone(), mock_info("keeper", &[]), prune).unwrap();
+        assert_eq!(
+            crate::contract::query_count_streams(deps.as_ref())
+                .unwrap()
+                .count,
+            0
+        );
+
+        // only the summary is left
+        let err = query_stream(deps.as_ref(), env, 1).unwrap_err();
+        assert_eq!(query_statistics(deps.as_ref(), 1).unwrap().subscribers, 0);
+        assert!(matches!(err, StdError::NotFound { .. }));
+        let pruned = query_pruned_stream(deps.as_ref(), 1).unwrap().unwrap();
+        assert_eq!(
+            pruned,
+            PrunedStream {
+                name: "test".to_string(),
+                treasury: Addr::unchecked("treasury"),
+                status: Status::Finalized,
+                out_denom: "out_denom".to_string(),
+                out_supply: Uint256::from(1_000_000u128),
+                in_denom: "in".to_string(),
+                spent_in: Uint256::from(1_000_000u128),
+                closed_at_height: mock_env().block.height,
+            }
+        );
+    }
+
+    #[test]
+    fn test_keeper_bounty() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(10),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // only protocol admin can set a valid bounty
+        let keeper_bounty = KeeperBounty {
+            fee_share: Decimal256::percent(50),
+            min_blocks: Uint64::new(10),
+        };
+        let msg = crate::msg::ExecuteMsg::UpdateKeeperBounty {
+            keeper_bounty: Some(keeper_bounty.clone()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("keeper", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let invalid = crate::msg::ExecuteMsg::UpdateKeeperBounty {
+            keeper_bounty: Some(KeeperBounty {
+                fee_share: Decimal256::percent(150),
+                min_blocks: Uint64::new(10),
+            }),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            invalid,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidKeeperBounty {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            query_config(deps.as_ref()).unwrap().keeper_bounty,
+            Some(keeper_bounty)
+        );
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut env = mock_env();
+        env.block.time = start;
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber", &[Coin::new(1_000_000, "in")]),
+            msg,
+        )
+        .unwrap();
+
+        // recently updated stream earns no bounty
+        let mut env = mock_env();
+        env.block.height += 5;
+        env.block.time = start.plus_seconds(1_000_000);
+        let res = execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1).unwrap();
+        assert!(res
+            .attributes
+            .contains(&attr("keeper_bounty", Uint256::zero())));
+
+        // about 250_000 in spent since the last update, half of its swap fee goes to the keeper
+        let mut env = mock_env();
+        env.block.height += 16;
+        env.block.time = start.plus_seconds(2_000_000);
+        let res = execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1).unwrap();
+        assert!(res
+            .attributes
+            .contains(&attr("keeper_bounty", Uint256::from(12_499u128))));
+        assert_eq!(
+            query_keeper_bounty(deps.as_ref(), 1, "keeper".to_string()).unwrap(),
+            Uint256::from(12_499u128)
+        );
+
+        // claimable only after finalize
+        let claim = crate::msg::ExecuteMsg::ClaimKeeperBounty { stream_id: 1 };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("keeper", &[]),
+            claim.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StreamNotFinalized {});
+
+        // bounties are deducted from the swap fee sent to the fee collector
+        let mut env = mock_env();
+        env.block.height += 100;
+        env.block.time = end.plus_seconds(1);
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1)
+                .unwrap();
+        assert!(res.attributes.contains(&attr("swap_fee", "87501")));
+        assert!(res.attributes.contains(&attr("keeper_bounties", "12499")));
+        assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+            to_address: "collector".to_string(),
+            amount: vec![Coin::new(87_501, "in")],
+        })));
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("keeper", &[]),
+            claim.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "keeper".to_string(),
+                amount: vec![Coin::new(12_499, "in")],
+            })]
+        );
+        let err = execute(deps.as_mut(), env, mock_info("keeper", &[]), claim).unwrap_err();
+        assert_eq!(err, ContractError::NoKeeperBounty {});
+    }
+
+    #[test]
+    fn test_sudo_clock() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // second stream has a threshold it never reaches, third one no subscription
+        for threshold in [None, Some(Uint256::from(2_000_000u128)), None] {
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                "treasury".to_string(),
+                "test".to_string(),
+                None,
+                "in".to_string(),
+                "out_denom".to_string(),
+                Uint256::from(1_000_000u128),
+                start,
+                end,
+                threshold,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+        for stream_id in [1, 2] {
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            execute(
+                deps.as_mut(),
+                env,
+                mock_info("subscriber", &[Coin::new(1_000_000, "in")]),
+                msg,
+            )
+            .unwrap();
+        }
+
+        // active streams are updated
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(2_000_000);
+        let res = sudo(deps.as_mut(), env.clone(), crate::msg::SudoMsg::Clock {}).unwrap();
+        assert!(res.attributes.contains(&attr("updated", "2")));
+        assert!(res.attributes.contains(&attr("finalized", "")));
+        for stream_id in [1, 2] {
+            let stream = query_stream(deps.as_ref(), env.clone(), stream_id).unwrap();
+            assert_eq!(stream.last_updated, env.block.time);
+        }
+        // already updated in this block
+        let res = sudo(deps.as_mut(), env, crate::msg::SudoMsg::Clock {}).unwrap();
+        assert!(res.attributes.contains(&attr("updated", "0")));
+
+        // ended stream is finalized, the one below its threshold is skipped
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let res = sudo(deps.as_mut(), env.clone(), crate::msg::SudoMsg::Clock {}).unwrap();
+        assert!(res.attributes.contains(&attr("action", "finalize_stream")));
+        assert!(res.attributes.contains(&attr("finalized", "1")));
+        assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin::new(990_000, "in")],
+        })));
+        assert_eq!(
+            query_stream(deps.as_ref(), env.clone(), 1).unwrap().status,
+            Status::Finalized
+        );
+        // stream ended without subscription is still waiting, it is finalized next
+        assert_eq!(
+            query_stream(deps.as_ref(), env.clone(), 3).unwrap().status,
+            Status::Waiting
+        );
+        let res = sudo(deps.as_mut(), env.clone(), crate::msg::SudoMsg::Clock {}).unwrap();
+        assert!(res.attributes.contains(&attr("finalized", "3")));
+        assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin::new(1_000_000, "out_denom")],
+        })));
+        assert_eq!(
+            query_stream(deps.as_ref(), env.clone(), 3).unwrap().status,
+            Status::Finalized
+        );
+        let res = sudo(deps.as_mut(), env.clone(), crate::msg::SudoMsg::Clock {}).unwrap();
+        assert!(res.attributes.contains(&attr("finalized", "")));
+        assert_eq!(
+            query_stream(deps.as_ref(), env, 2).unwrap().status,
+            Status::Active
+        );
+    }
+
+    #[test]
+    fn test_subscription_limit() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // only treasury can set a valid limit
+        let mut env = mock_env();
+        env.block.time = start.minus_seconds(1);
+        let limit = SubscriptionLimit {
+            max_in: Uint256::from(1_000u128),
+            window_blocks: Uint64::new(10),
+        };
+        let msg = crate::msg::ExecuteMsg::UpdateSubscriptionLimit {
+            stream_id: 1,
+            limit: Some(limit.clone()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let invalid = crate::msg::ExecuteMsg::UpdateSubscriptionLimit {
+            stream_id: 1,
+            limit: Some(SubscriptionLimit {
+                max_in: Uint256::from(1_000u128),
+                window_blocks: Uint64::zero(),
+            }),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            invalid,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidSubscriptionLimit {});
+        execute(deps.as_mut(), env.clone(), mock_info("treasury", &[]), msg).unwrap();
+
+        // pending subscriptions count against the window
+        let subscribe = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber1", &[Coin::new(600, "in")]),
+            subscribe.clone(),
+        )
+        .unwrap();
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1);
+        // excess over the window cap is rejected
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber2", &[Coin::new(500, "in")]),
+            subscribe.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::SubscriptionLimitExceeded {
+                remaining: Uint256::from(400u128)
+            }
+        );
+        env.block.height += 9;
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber2", &[Coin::new(400, "in")]),
+            subscribe.clone(),
+        )
+        .unwrap();
+        let res = query_subscription_limit(deps.as_ref(), env.clone(), 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.limit, limit);
+        assert_eq!(res.remaining, Uint256::zero());
+
+        // next window accepts up to the cap again
+        env.block.height += 1;
+        let res = query_subscription_limit(deps.as_ref(), env.clone(), 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.remaining, Uint256::from(1_000u128));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber2", &[Coin::new(1_000, "in")]),
+            subscribe,
+        )
+        .unwrap();
+
+        // removing the limit
+        let msg = crate::msg::ExecuteMsg::UpdateSubscriptionLimit {
+            stream_id: 1,
+            limit: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("treasury", &[]), msg).unwrap();
+        assert_eq!(
+            query_subscription_limit(deps.as_ref(), env, 1).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_hard_cap() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let msg = crate::msg::ExecuteMsg::UpdateHardCap {
+            stream_id: 1,
+            hard_cap: Some(Uint256::from(200_000u128)),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env, mock_info("treasury", &[]), msg.clone()).unwrap();
+        assert_eq!(
+            query_hard_cap(deps.as_ref(), 1).unwrap(),
+            Some(Uint256::from(200_000u128))
+        );
+
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1);
+        // can not be changed once started
+        let err = execute(deps.as_mut(), env.clone(), mock_info("treasury", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::StreamIsStarted {});
+        for (subscriber, amount) in [("subscriber1", 300_000), ("subscriber2", 100_000)] {
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(subscriber, &[Coin::new(amount, "in")]),
+                msg,
+            )
+            .unwrap();
+        }
+
+        // treasury receives the capped amount
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1)
+                .unwrap();
+        assert!(res.attributes.contains(&attr("creators_revenue", "198000")));
+        assert!(res.attributes.contains(&attr("swap_fee", "2000")));
+
+        // half of the spent tokens are refunded on exit
+        let res =
+            query_simulate_exit(deps.as_ref(), env.clone(), 1, "subscriber2".to_string()).unwrap();
+        assert_eq!(res.spent, Uint256::from(50_000u128));
+        assert_eq!(res.refund, Uint256::from(50_000u128));
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber1", &[]),
+            1,
+            None,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("swap_fee_paid", "1500")));
+        assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+            to_address: "subscriber1".to_string(),
+            amount: vec![Coin::new(150_000, "in")],
+        })));
+    }
+
+    #[test]
+    fn test_allocation_tiers() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let tiers = vec![
+            AllocationTier {
+                members: vec!["strategic1".to_string(), "strategic2".to_string()],
+                member_cap: Uint256::from(100_000u128),
+                out_allocation: Uint256::from(200_000u128),
+                merkle_root: None,
+            },
+            AllocationTier {
+                members: vec!["advisor".to_string()],
+                member_cap: Uint256::from(50_000u128),
+                out_allocation: Uint256::from(100_000u128),
+                merkle_root: None,
+            },
+        ];
+        // an address can only be in one tier
+        let mut invalid = tiers.clone();
+        invalid[1].members.push("strategic1".to_string());
+        let msg = crate::msg::ExecuteMsg::UpdateAllocationTiers {
+            stream_id: 1,
+            tiers: invalid,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("treasury", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidAllocationTiers {});
+        let msg = crate::msg::ExecuteMsg::UpdateAllocationTiers {
+            stream_id: 1,
+            tiers: tiers.clone(),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(query_allocation_tiers(deps.as_ref(), 1).unwrap(), tiers);
+        assert_eq!(
+            query_stream(deps.as_ref(), env.clone(), 1)
+                .unwrap()
+                .out_remaining,
+            Uint256::from(700_000u128)
+        );
+
+        let subscribe_tier = crate::msg::ExecuteMsg::SubscribeTier {
+            stream_id: 1,
+            proof: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("public", &[Coin::new(100, "in")]),
+            subscribe_tier.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NotTierMember {});
+        for (member, amount) in [("strategic1", 100_000), ("strategic2", 60_000)] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(member, &[Coin::new(amount, "in")]),
+                subscribe_tier.clone(),
+            )
+            .unwrap();
+        }
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("strategic2", &[Coin::new(50_000, "in")]),
+            subscribe_tier,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TierCapExceeded {
+                remaining: Uint256::from(40_000u128)
+            }
+        );
+        // tiers are locked once deposited in
+        let err = execute(deps.as_mut(), env, mock_info("treasury", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::AllocationTiersLocked {});
+
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("public", &[Coin::new(300_000, "in")]),
+            msg,
+        )
+        .unwrap();
+
+        // tier deposits are paid to the treasury, the allocation of the empty tier is refunded
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1)
+                .unwrap();
+        assert!(res.attributes.contains(&attr("creators_revenue", "455400")));
+        assert!(res.attributes.contains(&attr("swap_fee", "4600")));
+        assert!(res
+            .attributes
+            .contains(&attr("refunded_out_remaining", "100000")));
+        assert!(res.attributes.contains(&attr("total_sold", "900000")));
+
+        let res = query_tier_deposit(deps.as_ref(), 1, "strategic2".to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.allocation, Uint256::from(75_000u128));
+        let exit_tier = crate::msg::ExecuteMsg::ExitTier { stream_id: 1 };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("strategic1", &[]),
+            exit_tier.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "strategic1".to_string(),
+                amount: vec![Coin::new(125_000, "out_denom")],
+            })]
+        );
+        let err = execute(deps.as_mut(), env, mock_info("strategic1", &[]), exit_tier).unwrap_err();
+        assert_eq!(err, ContractError::NoTierDeposit {});
+    }
+
+    #[test]
+    fn test_merkle_allocation_tiers() {
+        use cosmwasm_std::HexBinary;
+        use sha2::{Digest, Sha256};
+
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // tree of two members, leaves are address hashes and pairs are hashed sorted
+        let leaf = |addr: &str| Sha256::digest(addr.as_bytes()).to_vec();
+        let (first, second) = (leaf("member1"), leaf("member2"));
+        let (low, high) = if first < second {
+            (&first, &second)
+        } else {
+            (&second, &first)
+        };
+        let root = HexBinary::from(
+            Sha256::new()
+                .chain_update(low)
+                .chain_update(high)
+                .finalize()
+                .to_vec(),
+        );
+        let tier = AllocationTier {
+            members: vec![],
+            member_cap: Uint256::from(100_000u128),
+            out_allocation: Uint256::from(200_000u128),
+            merkle_root: Some(root),
+        };
+        let update = |tiers: Vec<AllocationTier>| crate::msg::ExecuteMsg::UpdateAllocationTiers {
+            stream_id: 1,
+            tiers,
+        };
+
+        // listed tiers are capped, merkle tiers list no members
+        let listed = AllocationTier {
+            members: (0..101).map(|i| format!("member{i}")).collect(),
+            merkle_root: None,
+            ..tier.clone()
+        };
+        let both = AllocationTier {
+            members: vec!["member1".to_string()],
+            ..tier.clone()
+        };
+        let short_root = AllocationTier {
+            merkle_root: Some(HexBinary::from(vec![1u8; 31])),
+            ..tier.clone()
+        };
+        for invalid in [listed, both, short_root] {
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("treasury", &[]),
+                update(vec![invalid]),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::InvalidAllocationTiers {});
+        }
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(vec![tier]),
+        )
+        .unwrap();
+
+        let subscribe_tier = |proof: Option<Vec<Vec<u8>>>| crate::msg::ExecuteMsg::SubscribeTier {
+            stream_id: 1,
+            proof: proof.map(|proof| TierProof {
+                tier: 0,
+                proof: proof.into_iter().map(HexBinary::from).collect(),
+            }),
+        };
+        for (sender, proof) in [
+            ("member1", None),
+            ("member1", Some(vec![first.clone()])),
+            ("outsider", Some(vec![second.clone()])),
+        ] {
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(sender, &[Coin::new(100, "in")]),
+                subscribe_tier(proof),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::NotTierMember {});
+        }
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("member1", &[Coin::new(60_000, "in")]),
+            subscribe_tier(Some(vec![second])),
+        )
+        .unwrap();
+        // membership is kept after the first deposit
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("member1", &[Coin::new(40_000, "in")]),
+            subscribe_tier(None),
+        )
+        .unwrap();
+        let res = query_tier_deposit(deps.as_ref(), 1, "member1".to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.tier, 0);
+        assert_eq!(res.amount, Uint256::from(100_000u128));
+        assert_eq!(res.allocation, Uint256::from(200_000u128));
+    }
+
+    #[test]
+    fn test_liquid_stake_exit() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "ustake"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "ustake".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber1", &[Coin::new(100_000, "in")]),
+            msg,
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1).unwrap();
+        let exit = |liquid_stake, remote_recipient| crate::msg::ExecuteMsg::ExitStream {
+            stream_id: 1,
+            operator_target: None,
+            remote_recipient,
+            liquid_stake,
+            swap: None,
+        };
+
+        // liquid staking needs a configured hub
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            exit(true, None),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::LiquidStakingDisabled {});
+
+        let update = |denom: &str| crate::msg::ExecuteMsg::UpdateLiquidStaking {
+            liquid_staking: Some(LiquidStaking {
+                hub: "hub".to_string(),
+                denom: denom.to_string(),
+            }),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            update("ustake"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            update("uother"),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            exit(true, None),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::LiquidStakingDenomMismatch {
+                denom: "ustake".to_string()
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            update("ustake"),
+        )
+        .unwrap();
+        assert_eq!(
+            query_config(deps.as_ref()).unwrap().liquid_staking,
+            Some(LiquidStaking {
+                hub: "hub".to_string(),
+                denom: "ustake".to_string(),
+            })
+        );
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            exit(true, Some("cosmos1remote".to_string())),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::ConflictingExitDelivery {});
+
+        // purchased tokens are bonded for the position owner
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber1", &[]),
+            exit(true, None),
+        )
+        .unwrap();
+        assert!(res.messages.contains(&SubMsg::new(WasmMsg::Execute {
+            contract_addr: "hub".to_string(),
+            msg: to_json_binary(&crate::liquid_staking::HubExecuteMsg::Bond {
+                receiver: Some("subscriber1".to_string()),
+            })
+            .unwrap(),
+            funds: vec![Coin::new(1_000_000, "ustake")],
+        })));
+
+        // clients not aware of liquid staking exit with plain sends
+        let msg: crate::msg::ExecuteMsg =
+            cosmwasm_std::from_json(r#"{"exit_stream":{"stream_id":1}}"#).unwrap();
+        assert_eq!(msg, exit(false, None));
+    }
+
+    #[test]
+    fn test_vesting_exit() {
+        use crate::vesting::{
+            list_vestings, query_failed_vesting, query_stream_vesting, query_vesting, Schedule,
+            UncheckedDenom, VestingConfig, VestingInstantiateMsg, VESTING_REPLY_ID,
+        };
+        use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+        use cosmwasm_std::{
+            instantiate2_address, Api, CanonicalAddr, CodeInfoResponse, ContractResult, HexBinary,
+            OwnedDeps, RecoverPubkeyError, StdResult, SystemResult, VerificationError, WasmQuery,
+        };
+        use sha2::{Digest, Sha256};
+        use std::marker::PhantomData;
+
+        // mock api with derived contract addresses, hex encoded
+        #[derive(Default)]
+        struct VestingApi(MockApi);
+        impl Api for VestingApi {
+            fn addr_validate(&self, human: &str) -> StdResult<Addr> {
+                self.0.addr_validate(human)
+            }
+            fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+                self.0.addr_canonicalize(human)
+            }
+            fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+                match canonical.len() {
+                    32 => Ok(Addr::unchecked(canonical.to_string())),
+                    _ => self.0.addr_humanize(canonical),
+                }
+            }
+            fn secp256k1_verify(
+                &self,
+                message_hash: &[u8],
+                signature: &[u8],
+                public_key: &[u8],
+            ) -> Result<bool, VerificationError> {
+                self.0.secp256k1_verify(message_hash, signature, public_key)
+            }
+            fn secp256k1_recover_pubkey(
+                &self,
+                message_hash: &[u8],
+                signature: &[u8],
+                recovery_param: u8,
+            ) -> Result<Vec<u8>, RecoverPubkeyError> {
+                self.0
+                    .secp256k1_recover_pubkey(message_hash, signature, recovery_param)
+            }
+            fn ed25519_verify(
+                &self,
+                message: &[u8],
+                signature: &[u8],
+                public_key: &[u8],
+            ) -> Result<bool, VerificationError> {
+                self.0.ed25519_verify(message, signature, public_key)
+            }
+            fn ed25519_batch_verify(
+                &self,
+                messages: &[&[u8]],
+                signatures: &[&[u8]],
+                public_keys: &[&[u8]],
+            ) -> Result<bool, VerificationError> {
+                self.0
+                    .ed25519_batch_verify(messages, signatures, public_keys)
+            }
+            fn debug(&self, message: &str) {
+                self.0.debug(message)
+            }
+        }
+
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let checksum = HexBinary::from([7u8; 32]);
+        let mut deps = OwnedDeps {
+            storage: MockStorage::default(),
+            api: VestingApi::default(),
+            querier: MockQuerier::default(),
+            custom_query_type: PhantomData,
+        };
+        let code_checksum = checksum.clone();
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::CodeInfo { code_id } => {
+                let res =
+                    CodeInfoResponse::new(*code_id, "uploader".to_string(), code_checksum.clone());
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&res).unwrap()))
+            }
+            _ => panic!("unexpected query"),
+        });
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // vesting needs a configured code
+        let vesting = VestingConfig {
+            schedule: Schedule::SaturatingLinear,
+            vesting_duration_seconds: Uint64::new(1_000),
+            unbonding_duration_seconds: Uint64::new(100),
+        };
+        let update = |vesting: Option<VestingConfig>| crate::msg::ExecuteMsg::UpdateStreamVesting {
+            stream_id: 1,
+            vesting,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(Some(vesting.clone())),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::VestingDisabled {});
+
+        let update_code_id = crate::msg::ExecuteMsg::UpdateVestingCodeId {
+            vesting_code_id: Some(9),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update_code_id.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            update_code_id,
+        )
+        .unwrap();
+        assert_eq!(
+            query_config(deps.as_ref()).unwrap().vesting_code_id,
+            Some(9)
+        );
+
+        // only treasury can set the vesting, with a vesting duration
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("random", &[]),
+            update(Some(vesting.clone())),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(Some(VestingConfig {
+                vesting_duration_seconds: Uint64::zero(),
+                ..vesting.clone()
+            })),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidVestingConfig {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(Some(vesting.clone())),
+        )
+        .unwrap();
+        let stream_vesting = query_stream_vesting(deps.as_ref(), 1).unwrap().unwrap();
+        assert_eq!(stream_vesting.code_id, 9);
+        assert_eq!(stream_vesting.config, vesting);
+
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        for subscriber in ["subscriber1", "subscriber2"] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(subscriber, &[Coin::new(100_000, "in")]),
+                msg.clone(),
+            )
+            .unwrap();
+        }
+
+        // can't update after the stream starts
+        let mut env = mock_env();
+        env.block.time = start;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(None),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StreamIsStarted {});
+
+        // purchases vest with a single exit, paused streams can not be exited
+        execute_pause_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            1,
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            crate::msg::ExecuteMsg::ExitPaused {
+                stream_id: 1,
+                operator_target: None,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::VestingExitPaused {});
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("protocol_admin", &[]),
+            crate::msg::ExecuteMsg::ResumeStream { stream_id: 1 },
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1).unwrap();
+        let exit = |liquid_stake| crate::msg::ExecuteMsg::ExitStream {
+            stream_id: 1,
+            operator_target: None,
+            remote_recipient: None,
+            liquid_stake,
+            swap: None,
+        };
+
+        // vested purchases can not be delivered otherwise
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            exit(true),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::ConflictingExitDelivery {});
+
+        // purchased tokens fund a vesting contract of the position owner
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            exit(false),
+        )
+        .unwrap();
+        let subscriber = deps.api.addr_canonicalize("subscriber1").unwrap();
+        let salt = Sha256::new()
+            .chain_update(1u64.to_be_bytes())
+            .chain_update(subscriber.as_slice())
+            .finalize()
+            .to_vec();
+        let msg = VestingInstantiateMsg {
+            owner: None,
+            recipient: "subscriber1".to_string(),
+            title: "Stream 1 vesting".to_string(),
+            description: None,
+            total: Uint128::new(500_000),
+            denom: UncheckedDenom::Native("out_denom".to_string()),
+            schedule: Schedule::SaturatingLinear,
+            start_time: Some(env.block.time),
+            vesting_duration_seconds: 1_000,
+            unbonding_duration_seconds: 100,
+        };
+        assert_eq!(
+            res.messages[0],
+            SubMsg::reply_always(
+                WasmMsg::Instantiate2 {
+                    admin: None,
+                    code_id: 9,
+                    label: "streamswap-vesting-1-subscriber1".to_string(),
+                    msg: to_json_binary(&msg).unwrap(),
+                    funds: vec![Coin::new(500_000, "out_denom")],
+                    salt: salt.clone().into(),
+                },
+                VESTING_REPLY_ID
+            )
+        );
+        let creator = deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap();
+        let address = instantiate2_address(checksum.as_slice(), &creator, &salt).unwrap();
+        assert_eq!(
+            query_vesting(deps.as_ref(), 1, "subscriber1".to_string()).unwrap(),
+            Some(Addr::unchecked(address.to_string()))
+        );
+
+        // escheated purchases vest too
+        let update_config = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            fee_collector: None,
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+            prune_delay_blocks: None,
+            escheat_delay_blocks: Some(Some(Uint64::new(1_000))),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            update_config,
+        )
+        .unwrap();
+        env.block.height += 1_001;
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            crate::msg::ExecuteMsg::EscheatPositions {
+                stream_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber2", &[]),
+            crate::msg::ExecuteMsg::ClaimEscheated { stream_id: 1 },
+        )
+        .unwrap();
+        let subscriber = deps.api.addr_canonicalize("subscriber2").unwrap();
+        let salt = Sha256::new()
+            .chain_update(1u64.to_be_bytes())
+            .chain_update(subscriber.as_slice())
+            .finalize()
+            .to_vec();
+        let msg = VestingInstantiateMsg {
+            recipient: "subscriber2".to_string(),
+            start_time: Some(env.block.time),
+            ..msg
+        };
+        let vesting_submsg = SubMsg::reply_always(
+            WasmMsg::Instantiate2 {
+                admin: None,
+                code_id: 9,
+                label: "streamswap-vesting-1-subscriber2".to_string(),
+                msg: to_json_binary(&msg).unwrap(),
+                funds: vec![Coin::new(500_000, "out_denom")],
+                salt: salt.clone().into(),
+            },
+            VESTING_REPLY_ID,
+        );
+        assert_eq!(res.messages, vec![vesting_submsg.clone()]);
+        let address = instantiate2_address(checksum.as_slice(), &creator, &salt).unwrap();
+        assert_eq!(
+            query_vesting(deps.as_ref(), 1, "subscriber2".to_string()).unwrap(),
+            Some(Addr::unchecked(address.to_string()))
+        );
+
+        // vesting contracts are listed by owner
+        let vestings = list_vestings(deps.as_ref(), 1, None, None).unwrap();
+        assert_eq!(
+            vestings
+                .iter()
+                .map(|v| v.owner.to_string())
+                .collect::<Vec<_>>(),
+            vec!["subscriber1", "subscriber2"]
+        );
+        assert_eq!(vestings[1].vesting, Addr::unchecked(address.to_string()));
+        let vestings =
+            list_vestings(deps.as_ref(), 1, Some("subscriber1".to_string()), Some(1)).unwrap();
+        assert_eq!(vestings.len(), 1);
+        assert_eq!(vestings[0].owner, Addr::unchecked("subscriber2"));
+        assert!(list_vestings(deps.as_ref(), 2, None, None)
+            .unwrap()
+            .is_empty());
+
+        // a failed instantiation escrows the purchased tokens for a retry
+        let res = reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: VESTING_REPLY_ID,
+                result: SubMsgResult::Err("instantiate failed".to_string()),
+            },
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            query_vesting(deps.as_ref(), 1, "subscriber2".to_string()).unwrap(),
+            None
+        );
+        assert_eq!(
+            query_failed_vesting(deps.as_ref(), 1, "subscriber2".to_string()).unwrap(),
+            Some(Coin::new(500_000, "out_denom"))
+        );
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            crate::msg::ExecuteMsg::RetryVesting { stream_id: 1 },
+        );
+        assert_eq!(res, Err(ContractError::NoFailedVesting {}));
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber2", &[]),
+            crate::msg::ExecuteMsg::RetryVesting { stream_id: 1 },
+        )
+        .unwrap();
+        assert_eq!(res.messages, vec![vesting_submsg]);
+        assert_eq!(
+            query_vesting(deps.as_ref(), 1, "subscriber2".to_string()).unwrap(),
+            Some(Addr::unchecked(address.to_string()))
+        );
+        assert_eq!(
+            query_failed_vesting(deps.as_ref(), 1, "subscriber2".to_string()).unwrap(),
+            None
+        );
+        reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: VESTING_REPLY_ID,
+                result: SubMsgResult::Ok(cosmwasm_std::SubMsgResponse {
+                    events: vec![],
+                    data: None,
+                }),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            query_vesting(deps.as_ref(), 1, "subscriber2".to_string()).unwrap(),
+            Some(Addr::unchecked(address.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_create_pool() {
+        use crate::pool::{
+            query_stream_pool, CreatePool, CREATE_POOL_REPLY_ID, CREATE_POSITION_REPLY_ID,
+        };
+        use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
+        use cosmwasm_std::{
+            from_json, Binary, ContractResult, Empty, OwnedDeps, Querier, QuerierResult,
+            QueryRequest, SubMsgResponse, SystemResult,
+        };
+        use osmosis_std::types::osmosis::concentratedliquidity::poolmodel::concentrated::v1beta1::{
+            MsgCreateConcentratedPool, MsgCreateConcentratedPoolResponse,
+        };
+        use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+            MsgCreatePosition, MsgCreatePositionResponse, MsgTransferPositions,
+        };
+        use osmosis_std::types::osmosis::poolmanager::v1beta1::{Params, ParamsResponse};
+        use std::marker::PhantomData;
+
+        // Answers poolmanager params, everything else goes to the mock querier
+        struct PoolQuerier {
+            base: MockQuerier,
+            pool_creation_fee: u128,
+        }
+
+        impl Querier for PoolQuerier {
+            fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+                let request: QueryRequest<Empty> = from_json(bin_request).unwrap();
+                let res = match request {
+                    QueryRequest::Stargate { path, .. }
+                        if path == "/osmosis.poolmanager.v1beta1.Query/Params" =>
+                    {
+                        to_json_binary(&ParamsResponse {
+                            params: Some(Params {
+                                pool_creation_fee: vec![
+                                    Coin::new(self.pool_creation_fee, "uosmo").into()
+                                ],
+                                taker_fee_params: None,
+                                authorized_quote_denoms: vec!["in".to_string()],
+                            }),
+                        })
+                    }
+                    _ => return self.base.raw_query(bin_request),
+                };
+                SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+            }
+        }
+
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: PoolQuerier {
+                base: MockQuerier::default(),
+                pool_creation_fee: 1_000,
+            },
+            custom_query_type: PhantomData,
+        };
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        for _ in 0..5 {
+            let info = mock_info(
+                "creator1",
+                &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                "treasury".to_string(),
+                "test".to_string(),
+                None,
+                "in".to_string(),
+                "out_denom".to_string(),
+                Uint256::from(1_000_000u128),
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        let create_pool = CreatePool {
+            out_amount_clp: Uint256::from(100_000u128),
+            tick_spacing: 100,
+            spread_factor: Decimal256::permille(3),
+            lower_tick: None,
+            upper_tick: None,
+        };
+        // stream 1 seeds a ranged position
+        let ranged_pool = CreatePool {
+            lower_tick: Some(-1_000_000),
+            upper_tick: Some(1_000_000),
+            ..create_pool.clone()
+        };
+        let update = |stream_id, create_pool| crate::msg::ExecuteMsg::UpdateCreatePool {
+            stream_id,
+            create_pool,
+        };
+        let escrow = [Coin::new(100_000, "out_denom"), Coin::new(1_000, "uosmo")];
+
+        // only treasury can update
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("random", &escrow),
+            update(1, Some(create_pool.clone())),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // ticks have to be multiples of the tick spacing
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &escrow),
+            update(
+                1,
+                Some(CreatePool {
+                    tick_spacing: 7,
+                    ..create_pool.clone()
+                }),
+            ),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidCreatePool {});
+        for (lower_tick, upper_tick) in [(Some(1_000), Some(1_000)), (None, Some(150))] {
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("treasury", &escrow),
+                update(
+                    1,
+                    Some(CreatePool {
+                        lower_tick,
+                        upper_tick,
+                        ..create_pool.clone()
+                    }),
+                ),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::InvalidCreatePool {});
+        }
+
+        // out tokens and the pool creation fee are escrowed
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &escrow[..1]),
+            update(1, Some(create_pool.clone())),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidFunds {});
+        for stream_id in 1..=5 {
+            let create_pool = match stream_id {
+                1 => ranged_pool.clone(),
+                _ => create_pool.clone(),
+            };
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("treasury", &escrow),
+                update(stream_id, Some(create_pool)),
+            )
+            .unwrap();
+        }
+        let stream_pool = query_stream_pool(deps.as_ref(), 1).unwrap().unwrap();
+        assert_eq!(stream_pool.create_pool, ranged_pool);
+        assert_eq!(
+            stream_pool.pool_creation_fee,
+            vec![Coin::new(1_000, "uosmo")]
+        );
+        assert_eq!(stream_pool.pool_id, None);
+
+        env.block.time = start;
+        for stream_id in [1, 3, 4, 5] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber1", &[Coin::new(100_000, "in")]),
+                crate::msg::ExecuteMsg::Subscribe {
+                    stream_id,
+                    operator_target: None,
+                    operator: None,
+                    min_shares_out: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // cancelled streams refund the escrow
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1_000);
+        let info = mock_info("protocol_admin", &[]);
+        execute_pause_stream(deps.as_mut(), env.clone(), info.clone(), 2).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            env,
+            info,
+            crate::msg::ExecuteMsg::CancelStream { stream_id: 2 },
+        )
+        .unwrap();
+        for coin in &escrow {
+            assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: vec![coin.clone()],
+            })));
+        }
+        assert_eq!(query_stream_pool(deps.as_ref(), 2).unwrap(), None);
+
+        // the pool is seeded at the average price out of the creator revenue
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1)
+                .unwrap();
+        assert!(res.messages.contains(&SubMsg::reply_always(
+            MsgCreateConcentratedPool {
+                sender: env.contract.address.to_string(),
+                denom0: "out_denom".to_string(),
+                denom1: "in".to_string(),
+                tick_spacing: 100,
+                spread_factor: "3000000000000000".to_string(),
+            },
+            CREATE_POOL_REPLY_ID | 1
+        )));
+        // 100_000 spent, 1_000 swap fee and 10_000 provided as liquidity
+        assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin::new(89_000, "in")],
+        })));
+        assert!(res.attributes.contains(&attr("in_amount_clp", "10000")));
+        let stream_pool = query_stream_pool(deps.as_ref(), 1).unwrap().unwrap();
+        assert_eq!(stream_pool.pool_id, None);
+
+        // the position is provided to the pool id of the creation reply
+        let data: Binary = MsgCreateConcentratedPoolResponse { pool_id: 5 }.into();
+        let msg = Reply {
+            id: CREATE_POOL_REPLY_ID | 1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(data),
+            }),
+        };
+        let res = reply(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_always(
+                MsgCreatePosition {
+                    pool_id: 5,
+                    sender: env.contract.address.to_string(),
+                    lower_tick: -1_000_000,
+                    upper_tick: 1_000_000,
+                    tokens_provided: vec![
+                        Coin::new(10_000, "in").into(),
+                        Coin::new(100_000, "out_denom").into()
+                    ],
+                    token_min_amount0: "0".to_string(),
+                    token_min_amount1: "0".to_string(),
+                },
+                CREATE_POSITION_REPLY_ID | 1
+            )]
+        );
+        let stream_pool = query_stream_pool(deps.as_ref(), 1).unwrap().unwrap();
+        assert_eq!(stream_pool.pool_id, Some(5));
+
+        // the created position is transferred to the treasury, with the in tokens it did not take
+        let data: Binary = MsgCreatePositionResponse {
+            position_id: 11,
+            amount0: "100000".to_string(),
+            amount1: "9000".to_string(),
+            liquidity_created: "31622".to_string(),
+            lower_tick: -1_000_000,
+            upper_tick: 1_000_000,
+        }
+        .into();
+        let msg = Reply {
+            id: CREATE_POSITION_REPLY_ID | 1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(data),
+            }),
+        };
+        let res = reply(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(MsgTransferPositions {
+                    position_ids: vec![11],
+                    sender: env.contract.address.to_string(),
+                    new_owner: "treasury".to_string(),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![Coin::new(1_000, "in")],
+                })
+            ]
+        );
+        let stream_pool = query_stream_pool(deps.as_ref(), 1).unwrap().unwrap();
+        assert_eq!(stream_pool.position_id, Some(11));
+
+        // a lowered pool creation fee refunds the excess, failed positions refund their tokens
+        deps.querier.pool_creation_fee = 500;
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 3)
+                .unwrap();
+        assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin::new(500, "uosmo")],
+        })));
+        let data: Binary = MsgCreateConcentratedPoolResponse { pool_id: 6 }.into();
+        let msg = Reply {
+            id: CREATE_POOL_REPLY_ID | 3,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(data),
+            }),
+        };
+        reply(deps.as_mut(), env.clone(), msg).unwrap();
+        let msg = Reply {
+            id: CREATE_POSITION_REPLY_ID | 3,
+            result: SubMsgResult::Err("position error".to_string()),
+        };
+        let res = reply(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![Coin::new(10_000, "in")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![Coin::new(100_000, "out_denom")],
+                }),
+            ]
+        );
+        let stream_pool = query_stream_pool(deps.as_ref(), 3).unwrap().unwrap();
+        assert_eq!(stream_pool.pool_id, Some(6));
+        assert_eq!(stream_pool.position_id, None);
+
+        // failed pools refund the fee and the position tokens, the stream stays finalized
+        deps.querier.pool_creation_fee = 1_000;
+        execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 4).unwrap();
+        let msg = Reply {
+            id: CREATE_POOL_REPLY_ID | 4,
+            result: SubMsgResult::Err("pool error".to_string()),
+        };
+        let res = reply(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![Coin::new(1_000, "uosmo")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![Coin::new(10_000, "in")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![Coin::new(100_000, "out_denom")],
+                }),
+            ]
+        );
+        assert_eq!(query_stream_pool(deps.as_ref(), 4).unwrap(), None);
+        assert_eq!(
+            query_stream(deps.as_ref(), env.clone(), 4).unwrap().status,
+            Status::Finalized
+        );
+
+        // escrows short of a raised fee are refunded without creating the pool
+        deps.querier.pool_creation_fee = 2_000;
+        let res =
+            execute_finalize_stream(deps.as_mut(), env, mock_info("treasury", &[]), 5).unwrap();
+        for coin in &escrow {
+            assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: vec![coin.clone()],
+            })));
+        }
+        assert!(res.attributes.contains(&attr("in_amount_clp", "0")));
+        assert_eq!(query_stream_pool(deps.as_ref(), 5).unwrap(), None);
+    }
+
+    #[test]
+    fn test_audit_balances() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[Coin::new(100_000, "in")]),
+            msg,
+        )
+        .unwrap();
+
+        // spent tokens are owed to the treasury until finalize
+        env.block.time = Timestamp::from_seconds(3_000_000);
+        deps.querier.update_balance(
+            env.contract.address.clone(),
+            vec![
+                Coin::new(99_000, "in"),
+                Coin::new(1_000_000, "out_denom"),
+                Coin::new(100, "fee"),
+                Coin::new(5, "dust"),
+            ],
+        );
+        let res = query_audit_balances(deps.as_ref(), env).unwrap();
+        assert_eq!(
+            res.denoms,
+            vec![
+                DenomAudit {
+                    denom: "dust".to_string(),
+                    balance: Uint256::from(5u128),
+                    obligations: Uint256::zero(),
+                    surplus: Uint256::from(5u128),
+                    shortfall: Uint256::zero(),
+                },
+                DenomAudit {
+                    denom: "fee".to_string(),
+                    balance: Uint256::from(100u128),
+                    obligations: Uint256::from(100u128),
+                    surplus: Uint256::zero(),
+                    shortfall: Uint256::zero(),
+                },
+                DenomAudit {
+                    denom: "in".to_string(),
+                    balance: Uint256::from(99_000u128),
+                    obligations: Uint256::from(100_000u128),
+                    surplus: Uint256::zero(),
+                    shortfall: Uint256::from(1_000u128),
+                },
+                DenomAudit {
+                    denom: "out_denom".to_string(),
+                    balance: Uint256::from(1_000_000u128),
+                    obligations: Uint256::from(1_000_000u128),
+                    surplus: Uint256::zero(),
+                    shortfall: Uint256::zero(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sweep_dust() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        env.block.time = start;
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[Coin::new(100_000, "in")]),
+            msg,
+        )
+        .unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            crate::msg::ExecuteMsg::SweepDust {
+                denom: "out_denom".to_string(),
+                limit: None,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DenomInUse {
+                denom: "out_denom".to_string()
+            }
+        );
+
+        // rounding residue is left once every position exited
+        env.block.time = end.plus_seconds(1);
+        execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1).unwrap();
+        execute_exit_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            1,
+            None,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+        deps.querier.update_balance(
+            env.contract.address.clone(),
+            vec![Coin::new(3, "out_denom")],
+        );
+        let msg = crate::msg::ExecuteMsg::SweepDust {
+            denom: "out_denom".to_string(),
+            limit: Some(1),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        // obligations are tallied a stream per call, the call completing the tally sweeps
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            crate::audit::OBLIGATION_TALLIES
+                .load(&deps.storage, "out_denom")
+                .unwrap()
+                .start_after,
+            Some(1)
+        );
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "collector".to_string(),
+                amount: vec![Coin::new(3, "out_denom")],
+            })]
+        );
+        assert!(!crate::audit::OBLIGATION_TALLIES.has(&deps.storage, "out_denom"));
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info("protocol_admin", &[]),
+            crate::msg::ExecuteMsg::SweepDust {
+                denom: "in".to_string(),
+                limit: None,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoDust {});
+    }
+
+    #[test]
+    fn test_recover_funds() {
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            Timestamp::from_seconds(1_000_000),
+            Timestamp::from_seconds(5_000_000),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let recover = |denom: &str| crate::msg::ExecuteMsg::RecoverFunds {
+            denom: denom.to_string(),
+            amount: Uint128::new(40),
+            recipient: "sender".to_string(),
+            limit: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            recover("stray"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        // denoms of open streams are part of the accounting
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            recover("out_denom"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DenomInUse {
+                denom: "out_denom".to_string()
+            }
+        );
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("protocol_admin", &[]),
+            recover("stray"),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "sender".to_string(),
+                amount: vec![Coin::new(40, "stray")],
+            })]
+        );
+    }
+
+    #[test]
+    fn test_subscribe_min_shares() {
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            Timestamp::from_seconds(1_000_000),
+            Timestamp::from_seconds(5_000_000),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(2_000_000);
+        let subscribe = |min_shares_out| crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[Coin::new(100_000, "in")]),
+            subscribe(None),
+        )
+        .unwrap();
+
+        // the preview is the minimum the subscription accepts
+        env.block.time = Timestamp::from_seconds(3_000_000);
+        let preview = query_simulate_subscribe(
+            deps.as_ref(),
+            env.clone(),
+            1,
+            Uint256::from(50_000u128),
+            None,
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber2", &[Coin::new(50_000, "in")]),
+            subscribe(Some(preview.shares + Uint256::one())),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::SharesBelowMinimum {
+                shares: preview.shares
+            }
+        );
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber2", &[Coin::new(50_000, "in")]),
+            subscribe(Some(preview.shares)),
+        )
+        .unwrap();
+        let position = query_position(deps.as_ref(), env, 1, "subscriber2".to_string()).unwrap();
+        assert_eq!(position.shares, preview.shares);
+    }
+
+    #[test]
+    fn test_stream_tags() {
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            Timestamp::from_seconds(1_000_000),
+            Timestamp::from_seconds(5_000_000),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test2".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            Timestamp::from_seconds(1_000_000),
+            Timestamp::from_seconds(5_000_000),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let update = |stream_id, tags: &[&str]| crate::msg::ExecuteMsg::UpdateStreamTags {
+            stream_id,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            update(1, &["meme"]),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        for tags in [
+            &["Meme"][..],
+            &["meme", "meme"],
+            &[""],
+            &["a", "b", "c", "d", "e", "f"],
+        ] {
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("treasury", &[]),
+                update(1, tags),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::InvalidTags {});
+        }
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(1, &["meme"]),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(2, &["meme", "infra"]),
+        )
+        .unwrap();
+        assert_eq!(
+            query_stream_tags(deps.as_ref(), 2).unwrap(),
+            vec!["meme".to_string(), "infra".to_string()]
+        );
+
+        let ids =
+            |res: crate::msg::StreamsResponse| res.streams.iter().map(|s| s.id).collect::<Vec<_>>();
+        let res = crate::contract::list_streams(
+            deps.as_ref(),
+            mock_env(),
+            None,
+            None,
+            None,
+            None,
+            Some("meme".to_string()),
+        );
+        assert_eq!(ids(res.unwrap()), vec![1, 2]);
+        let res = crate::contract::list_streams(
+            deps.as_ref(),
+            mock_env(),
+            None,
+            None,
+            Some(Status::Waiting),
+            Some(crate::msg::OrderBy::Descending),
+            Some("meme".to_string()),
+        );
+        assert_eq!(ids(res.unwrap()), vec![2, 1]);
+        let res = crate::contract::list_streams(
+            deps.as_ref(),
+            mock_env(),
+            None,
+            None,
+            Some(Status::Active),
+            None,
+            Some("meme".to_string()),
+        );
+        assert_eq!(ids(res.unwrap()), Vec::<u64>::new());
+
+        // replaced tags leave the previous listings
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("treasury", &[]),
+            update(1, &[]),
+        )
+        .unwrap();
+        let res = crate::contract::list_streams(
+            deps.as_ref(),
+            mock_env(),
+            None,
+            None,
+            None,
+            None,
+            Some("meme".to_string()),
+        );
+        assert_eq!(ids(res.unwrap()), vec![2]);
+        assert!(query_stream_tags(deps.as_ref(), 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_creator_profiles() {
+        use crate::creator_profiles::{
+            query_creator_profile, query_creator_streams, query_stream_creator, CreatorProfile,
+            StreamCreatorResponse,
+        };
+
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        for name in ["test", "test2"] {
+            let info = mock_info(
+                "creator1",
+                &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                "treasury".to_string(),
+                name.to_string(),
+                None,
+                "in".to_string(),
+                "out_denom".to_string(),
+                Uint256::from(1_000_000u128),
+                Timestamp::from_seconds(1_000_000),
+                Timestamp::from_seconds(5_000_000),
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        // logo hash has to be a hex sha256 hash
+        let profile = CreatorProfile {
+            display_name: "Treasury Labs".to_string(),
+            url: Some("https://treasury.labs".to_string()),
+            logo_hash: Some("ab".repeat(32)),
+        };
+        let update = |profile| crate::msg::ExecuteMsg::UpdateCreatorProfile { profile };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(Some(CreatorProfile {
+                logo_hash: Some("AB".repeat(32)),
+                ..profile.clone()
+            })),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidCreatorProfile {});
+
+        // only the operations role can attach, with its own registered profile
+        let attach =
+            |stream_id, attach| crate::msg::ExecuteMsg::AttachCreatorProfile { stream_id, attach };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            attach(1, true),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoCreatorProfile {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("copycat", &[]),
+            update(Some(profile.clone())),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("copycat", &[]),
+            attach(1, true),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // display names are unique regardless of case and spaces, until released
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(Some(CreatorProfile {
+                display_name: " treasury LABS".to_string(),
+                ..profile.clone()
+            })),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DisplayNameTaken {
+                name: " treasury LABS".to_string()
+            }
+        );
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("copycat", &[]),
+            update(None),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(Some(profile.clone())),
+        )
+        .unwrap();
+        // updating keeps its own name
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(Some(profile.clone())),
+        )
+        .unwrap();
+        for stream_id in [1, 2] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("treasury", &[]),
+                attach(stream_id, true),
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            query_creator_profile(deps.as_ref(), "treasury".to_string()).unwrap(),
+            Some(profile.clone())
+        );
+        assert_eq!(
+            query_stream_creator(deps.as_ref(), 1).unwrap(),
+            Some(StreamCreatorResponse {
+                creator: Addr::unchecked("treasury"),
+                profile: profile.clone(),
+            })
+        );
+        assert_eq!(
+            query_creator_streams(deps.as_ref(), "treasury".to_string(), None, None).unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            query_creator_streams(deps.as_ref(), "treasury".to_string(), Some(1), None).unwrap(),
+            vec![2]
+        );
+
+        // detached streams leave the creator listing
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            attach(1, false),
+        )
+        .unwrap();
+        assert_eq!(query_stream_creator(deps.as_ref(), 1).unwrap(), None);
+        assert_eq!(
+            query_creator_streams(deps.as_ref(), "treasury".to_string(), None, None).unwrap(),
+            vec![2]
+        );
+
+        // removed profiles no longer resolve
+        execute(deps.as_mut(), env, mock_info("treasury", &[]), update(None)).unwrap();
+        assert_eq!(query_stream_creator(deps.as_ref(), 2).unwrap(), None);
+    }
+
+    #[test]
+    fn test_stream_name_registry() {
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+
+        let update = crate::msg::ExecuteMsg::UpdateNameRegistry {
+            name_registry: Some(NameRegistry {
+                release_after: Uint64::new(1_000_000),
+            }),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            update.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            update,
+        )
+        .unwrap();
+
+        let create = |deps: cosmwasm_std::DepsMut, env: cosmwasm_std::Env, name: &str| {
+            let info = mock_info(
+                "creator1",
+                &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+            );
+            execute_create_stream(
+                deps,
+                env.clone(),
+                info,
+                "treasury".to_string(),
+                name.to_string(),
+                None,
+                "in".to_string(),
+                "out_denom".to_string(),
+                Uint256::from(1_000_000u128),
+                env.block.time.plus_seconds(1_000_000),
+                env.block.time.plus_seconds(5_000_000),
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+        create(deps.as_mut(), env.clone(), "Test Stream").unwrap();
+        let resolved = query_resolve_name(deps.as_ref(), env.clone(), "test stream".to_string());
+        assert_eq!(resolved.unwrap(), Some(1));
+
+        // names are unique regardless of case
+        let err = create(deps.as_mut(), env.clone(), "TEST STREAM").unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::StreamNameTaken {
+                name: "TEST STREAM".to_string()
+            }
+        );
+
+        // the name is held until the release delay after the end of the stream passes
+        env.block.time = Timestamp::from_seconds(5_999_999);
+        let resolved = query_resolve_name(deps.as_ref(), env.clone(), "Test Stream".to_string());
+        assert_eq!(resolved.unwrap(), Some(1));
+        env.block.time = Timestamp::from_seconds(6_000_000);
+        let resolved = query_resolve_name(deps.as_ref(), env.clone(), "Test Stream".to_string());
+        assert_eq!(resolved.unwrap(), None);
+        create(deps.as_mut(), env.clone(), "Test Stream").unwrap();
+        let resolved = query_resolve_name(deps.as_ref(), env.clone(), "Test Stream".to_string());
+        assert_eq!(resolved.unwrap(), Some(2));
+    }
+
+    #[test]
+    fn test_blocklist() {
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            Timestamp::from_seconds(1_000_000),
+            Timestamp::from_seconds(5_000_000),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        env.block.time = Timestamp::from_seconds(2_000_000);
+        let subscribe = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        for subscriber in ["subscriber1", "subscriber2"] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(subscriber, &[Coin::new(100_000, "in")]),
+                subscribe.clone(),
+            )
+            .unwrap();
+        }
+
+        let block = crate::msg::ExecuteMsg::UpdateBlocklist {
+            stream_id: 1,
+            add: vec!["subscriber1".to_string()],
+            remove: vec![],
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            block.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            block,
+        )
+        .unwrap();
+        let blocked = query_blocklist(deps.as_ref(), 1, None, None).unwrap();
+        assert_eq!(blocked, vec![Addr::unchecked("subscriber1")]);
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[Coin::new(100_000, "in")]),
+            subscribe.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::AddressBlocked {
+                address: "subscriber1".to_string()
+            }
+        );
+
+        // only blocked positions can be refunded
+        env.block.time = Timestamp::from_seconds(3_000_000);
+        let refund = |owner: &str| crate::msg::ExecuteMsg::RefundBlocked {
+            stream_id: 1,
+            owner: owner.to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            refund("subscriber2"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::AddressNotBlocked {
+                address: "subscriber2".to_string()
+            }
+        );
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            refund("subscriber1"),
+        )
+        .unwrap();
+        let position =
+            query_position(deps.as_ref(), env.clone(), 1, "subscriber1".to_string()).unwrap();
+        assert_eq!(position.in_balance, Uint256::zero());
+        assert_eq!(position.shares, Uint256::zero());
+        assert!(!position.purchased.is_zero());
+        let refunded = Uint128::new(100_000) - Uint128::try_from(position.spent).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "subscriber1".to_string(),
+                amount: vec![Coin::new(refunded.u128(), "in")]
+            })
+        );
+
+        // blocked positions keep their purchased tokens
+        env.block.time = Timestamp::from_seconds(5_000_001);
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber1", &[]),
+            crate::msg::ExecuteMsg::ExitStream {
+                stream_id: 1,
+                operator_target: None,
+                remote_recipient: None,
+                liquid_stake: false,
+                swap: None,
+            },
+        )
+        .unwrap();
+        assert!(res.messages.iter().any(|m| matches!(
+            &m.msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount })
+                if to_address == "subscriber1" && amount[0].denom == "out_denom"
+        )));
+    }
+
+    #[test]
+    fn test_compliance_contract() {
+        use crate::compliance::{ComplianceQueryMsg, ComplianceResponse};
+        use cosmwasm_std::{from_json, ContractResult, SystemResult, WasmQuery};
+
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { msg, .. } => {
+                let ComplianceQueryMsg::CheckAddress { address, .. } = from_json(msg).unwrap();
+                let res = ComplianceResponse {
+                    allowed: address != "sanctioned",
+                    reason: Some("sanctioned address".to_string()),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&res).unwrap()))
+            }
+            _ => panic!("unexpected query"),
+        });
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            Timestamp::from_seconds(1_000_000),
+            Timestamp::from_seconds(5_000_000),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // positions opened before screening is enabled are screened on exit
+        env.block.time = Timestamp::from_seconds(2_000_000);
+        let subscribe = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sanctioned", &[Coin::new(100_000, "in")]),
+            subscribe.clone(),
+        )
+        .unwrap();
+
+        let update = crate::msg::ExecuteMsg::UpdateComplianceContract {
+            compliance_contract: Some("compliance".to_string()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            update.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            update,
+        )
+        .unwrap();
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.compliance_contract, Some("compliance".to_string()));
+
+        let rejected = ContractError::ComplianceRejected {
+            address: "sanctioned".to_string(),
+            reason: "sanctioned address".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sanctioned", &[Coin::new(100_000, "in")]),
+            subscribe.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, rejected);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[Coin::new(100_000, "in")]),
+            subscribe,
+        )
+        .unwrap();
+
+        env.block.time = Timestamp::from_seconds(5_000_001);
+        let exit = crate::msg::ExecuteMsg::ExitStream {
+            stream_id: 1,
+            operator_target: None,
+            remote_recipient: None,
+            liquid_stake: false,
+            swap: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sanctioned", &[]),
+            exit.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, rejected);
+        execute(deps.as_mut(), env, mock_info("subscriber", &[]), exit).unwrap();
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            Timestamp::from_seconds(1_000_000),
+            Timestamp::from_seconds(5_000_000),
+            Some(Uint256::from(50_000u128)),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let res = query_lifecycle(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(
+            res,
+            LifecycleResponse {
+                stream_id: 1,
+                phase: Phase::Waiting,
+                seconds_until_next_phase: Some(1_000_000),
+                effective_end_time: Timestamp::from_seconds(5_000_000),
+                finalize_callable: false,
+                exit_callable: false,
+            }
+        );
+
+        env.block.time = Timestamp::from_seconds(2_000_000);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[Coin::new(100_000, "in")]),
+            crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            },
+        )
+        .unwrap();
+        let res = query_lifecycle(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(res.phase, Phase::Active);
+        assert_eq!(res.seconds_until_next_phase, Some(3_000_000));
+
+        // paused streams end later by the pause duration
+        env.block.time = Timestamp::from_seconds(3_000_000);
+        execute_pause_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            1,
+        )
+        .unwrap();
+        env.block.time = Timestamp::from_seconds(3_500_000);
+        let res = query_lifecycle(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(res.phase, Phase::Paused);
+        assert_eq!(res.seconds_until_next_phase, None);
+        assert_eq!(res.effective_end_time, Timestamp::from_seconds(5_500_000));
+        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(stream.phase, Phase::Paused);
+        assert_eq!(
+            stream.effective_end_time,
+            Timestamp::from_seconds(5_500_000)
+        );
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            crate::msg::ExecuteMsg::ResumeStream { stream_id: 1 },
+        )
+        .unwrap();
+        let res = query_lifecycle(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(res.phase, Phase::Active);
+        assert_eq!(res.seconds_until_next_phase, Some(2_000_000));
+        assert_eq!(res.effective_end_time, Timestamp::from_seconds(5_500_000));
+
+        env.block.time = Timestamp::from_seconds(5_500_001);
+        let res = query_lifecycle(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(res.phase, Phase::Ended);
+        assert!(res.finalize_callable);
+        assert!(res.exit_callable);
+
+        execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1).unwrap();
+        let res = query_lifecycle(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(res.phase, Phase::Finalized);
+        assert!(!res.finalize_callable);
+        assert!(res.exit_callable);
+    }
+
+    #[test]
+    fn test_position_average_price() {
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            Timestamp::from_seconds(1_000_000),
+            Timestamp::from_seconds(5_000_000),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        env.block.time = Timestamp::from_seconds(1_000_000);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[Coin::new(400_000, "in")]),
+            crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            },
+        )
+        .unwrap();
+        let position =
+            query_position(deps.as_ref(), env.clone(), 1, "subscriber".to_string()).unwrap();
+        assert_eq!(position.average_price, None);
+        assert_eq!(position.projected_purchased, Uint256::zero());
+        assert_eq!(position.projected_average_price, None);
+
+        // a quarter of the stream is bought at 0.4 in per out
+        env.block.time = Timestamp::from_seconds(2_000_000);
+        execute_update_position(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[]),
+            1,
+            None,
+        )
+        .unwrap();
+        let position =
+            query_position(deps.as_ref(), env.clone(), 1, "subscriber".to_string()).unwrap();
+        assert_eq!(position.spent, Uint256::from(100_000u128));
+        assert_eq!(position.purchased, Uint256::from(250_000u128));
+        assert_eq!(position.average_price, Some(Decimal256::percent(40)));
+        assert_eq!(position.projected_purchased, Uint256::from(750_000u128));
+        assert_eq!(
+            position.projected_average_price,
+            Some(Decimal256::percent(40))
+        );
+        let positions = crate::contract::list_positions(deps.as_ref(), 1, None, None, None);
+        assert_eq!(positions.unwrap().positions, vec![position]);
+    }
+
+    #[test]
+    fn test_streamed_price_twap() {
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            Timestamp::from_seconds(1_000_000),
+            Timestamp::from_seconds(9_000_000),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // streamed price is 1 until 5M, 3 until 7M and 6 after
+        for (time, subscriber, amount) in [
+            (1_000_000, "subscriber1", 1_000_000),
+            (5_000_000, "subscriber2", 1_000_000),
+            (7_000_000, "subscriber3", 750_000),
+        ] {
+            env.block.time = Timestamp::from_seconds(time);
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(subscriber, &[Coin::new(amount, "in")]),
+                crate::msg::ExecuteMsg::Subscribe {
+                    stream_id: 1,
+                    operator_target: None,
+                    operator: None,
+                    min_shares_out: None,
+                },
+            )
+            .unwrap();
+        }
+
+        env.block.time = Timestamp::from_seconds(8_000_000);
+        let res = query_streamed_price_twap(deps.as_ref(), env.clone(), 1, 2_000_000).unwrap();
+        assert_eq!(
+            res,
+            StreamedPriceTwapResponse {
+                twap: Decimal256::from_ratio(9u128, 2u128),
+                start: Timestamp::from_seconds(6_000_000),
+                end: Timestamp::from_seconds(8_000_000),
+            }
+        );
+        let res = query_streamed_price_twap(deps.as_ref(), env.clone(), 1, 3_000_000).unwrap();
+        assert_eq!(res.twap, Decimal256::from_ratio(4u128, 1u128));
+
+        // no snapshot before the first distribution
+        query_streamed_price_twap(deps.as_ref(), env.clone(), 1, 4_000_000).unwrap_err();
+        query_streamed_price_twap(deps.as_ref(), env.clone(), 1, 0).unwrap_err();
+
+        // ended streams average up to their end
+        env.block.time = Timestamp::from_seconds(10_000_000);
+        let res = query_streamed_price_twap(deps.as_ref(), env, 1, 2_000_000).unwrap();
+        assert_eq!(res.end, Timestamp::from_seconds(9_000_000));
+        assert_eq!(res.twap, Decimal256::from_ratio(6u128, 1u128));
+    }
+
+    #[test]
+    fn test_crank_exits() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(10),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // only protocol admin can set a valid crank
+        let exit_crank = ExitCrank {
+            grace_period: Uint64::new(1_000_000),
+            fee_share: Decimal256::percent(10),
+        };
+        let msg = crate::msg::ExecuteMsg::UpdateExitCrank {
+            exit_crank: Some(exit_crank.clone()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("cranker", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let invalid = crate::msg::ExecuteMsg::UpdateExitCrank {
+            exit_crank: Some(ExitCrank {
+                grace_period: Uint64::new(1_000_000),
+                fee_share: Decimal256::zero(),
+            }),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            invalid,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidExitCrank {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            query_config(deps.as_ref()).unwrap().exit_crank,
+            Some(exit_crank)
+        );
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        env.block.time = start;
+        for subscriber in ["subscriber1", "subscriber2", "subscriber3"] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(subscriber, &[Coin::new(1_000_000, "in")]),
+                crate::msg::ExecuteMsg::Subscribe {
+                    stream_id: 1,
+                    operator_target: None,
+                    operator: None,
+                    min_shares_out: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // a tenth of the swap fee is held back at finalize
+        env.block.time = end.plus_seconds(1);
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1)
+                .unwrap();
+        assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+            to_address: "collector".to_string(),
+            amount: vec![Coin::new(270_000, "in")],
+        })));
+        execute_exit_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            1,
+            None,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+
+        // positions can exit by themselves during the grace period
+        let crank = |limit| crate::msg::ExecuteMsg::CrankExits {
+            stream_id: 1,
+            limit,
+        };
+        let grace_period_end = end.plus_seconds(1_000_000);
+        env.block.time = grace_period_end;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("cranker", &[]),
+            crank(None),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ExitGracePeriodActive {
+                ends_at: grace_period_end
+            }
+        );
+
+        env.block.time = grace_period_end.plus_seconds(1);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("cranker", &[]),
+            crank(Some(1)),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber2".to_string(),
+                    amount: vec![Coin::new(333_333, "out_denom")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "cranker".to_string(),
+                    amount: vec![Coin::new(10_000, "in")],
+                }),
+            ]
+        );
+        assert!(res.attributes.contains(&attr("exited", "1")));
+        query_position(deps.as_ref(), env.clone(), 1, "subscriber2".to_string()).unwrap_err();
+
+        // share of positions exited by themselves goes to the fee collector with the last exit
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("cranker", &[]),
+            crank(None),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber3".to_string(),
+                    amount: vec![Coin::new(333_333, "out_denom")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "cranker".to_string(),
+                    amount: vec![Coin::new(10_000, "in")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin::new(10_000, "in")],
+                }),
+            ]
+        );
+        assert!(res
+            .attributes
+            .contains(&attr("released_reserve", Uint256::from(10_000u128))));
+    }
+
+    #[test]
+    fn test_escheat_positions() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        env.block.time = start;
+        for subscriber in ["subscriber1", "subscriber2"] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(subscriber, &[Coin::new(1_000_000, "in")]),
+                crate::msg::ExecuteMsg::Subscribe {
+                    stream_id: 1,
+                    operator_target: None,
+                    operator: None,
+                    min_shares_out: None,
+                },
+            )
+            .unwrap();
+        }
+        env.block.time = end.plus_seconds(1);
+        execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1).unwrap();
+
+        let escheat = crate::msg::ExecuteMsg::EscheatPositions {
+            stream_id: 1,
+            limit: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            escheat.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::EscheatDisabled {});
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            fee_collector: None,
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+            prune_delay_blocks: None,
+            escheat_delay_blocks: Some(Some(Uint64::new(1_000))),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+
+        // delay not passed yet
+        env.block.height += 1_000;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            escheat.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::EscheatNotAvailable { stream_id: 1 });
+
+        // only protocol admin can escheat
+        env.block.height += 1;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            escheat.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            escheat,
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert!(res.attributes.contains(&attr("escheated", "2")));
+        query_position(deps.as_ref(), env.clone(), 1, "subscriber1".to_string()).unwrap_err();
+        let escheated =
+            query_escheated_position(deps.as_ref(), 1, "subscriber1".to_string()).unwrap();
+        assert_eq!(
+            escheated,
+            Some(EscheatedPosition {
+                out_denom: "out_denom".to_string(),
+                purchased: Uint256::from(500_000u128),
+                in_denom: "in".to_string(),
+                refunded: Uint256::zero(),
+            })
+        );
+
+        // tokens stay claimable by the owner
+        let claim = crate::msg::ExecuteMsg::ClaimEscheated { stream_id: 1 };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            claim.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "subscriber1".to_string(),
+                amount: vec![Coin::new(500_000, "out_denom")],
+            })]
+        );
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            claim.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoEscheatedPosition {});
+
+        // blocked owners can not claim
+        crate::blocklist::BLOCKLISTS
+            .save(
+                deps.as_mut().storage,
+                (1, &Addr::unchecked("subscriber2")),
+                &cosmwasm_std::Empty {},
+            )
+            .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber2", &[]),
+            claim.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::AddressBlocked {
+                address: "subscriber2".to_string()
+            }
+        );
+        assert!(
+            query_escheated_position(deps.as_ref(), 1, "subscriber2".to_string())
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_partial_refund() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(10),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        // first stream has no threshold, second misses its threshold by half
+        for threshold in [None, Some(Uint256::from(2_000_000u128))] {
+            let info = mock_info(
+                "creator1",
+                &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                "treasury".to_string(),
+                "test".to_string(),
+                None,
+                "in".to_string(),
+                "out_denom".to_string(),
+                Uint256::from(1_000_000u128),
+                start,
+                end,
+                threshold,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        let update = |stream_id| crate::msg::ExecuteMsg::UpdatePartialRefund {
+            stream_id,
+            enabled: true,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(1),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ThresholdError(ThresholdError::ThresholdNotSet {})
+        );
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            update(2),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            update(2),
+        )
+        .unwrap();
+
+        env.block.time = start;
+        for (subscriber, amount) in [("subscriber1", 750_000), ("subscriber2", 250_000)] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(subscriber, &[Coin::new(amount, "in")]),
+                crate::msg::ExecuteMsg::Subscribe {
+                    stream_id: 2,
+                    operator_target: None,
+                    operator: None,
+                    min_shares_out: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // missed thresholds are settled at finalize instead of cancelling
+        env.block.time = end.plus_seconds(1);
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            crate::msg::ExecuteMsg::CancelStreamWithThreshold { stream_id: 2 },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::PartialRefundEnabled {});
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            crate::msg::ExecuteMsg::ExitCancelled {
+                stream_id: 2,
+                operator_target: None,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StreamNotCancelled {});
+        let err = execute_exit_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            2,
+            None,
+            None,
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ThresholdError(ThresholdError::ThresholdNotReached {})
+        );
+
+        // half of the spent tokens and purchases are kept, the treasury gets half of the revenue
+        // and the unsold half of the out tokens
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 2)
+                .unwrap();
+        assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin::new(450_000, "in")],
+        })));
+        assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+            to_address: "collector".to_string(),
+            amount: vec![Coin::new(50_000, "in")],
+        })));
+        assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin::new(500_000, "out_denom")],
+        })));
+        assert_eq!(
+            query_partial_refund(deps.as_ref(), 2).unwrap(),
+            PartialRefundResponse {
+                enabled: true,
+                fill: Some(Decimal256::percent(50)),
+            }
+        );
+
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber1", &[]),
+            2,
+            None,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber1".to_string(),
+                    amount: vec![Coin::new(375_000, "out_denom")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber1".to_string(),
+                    amount: vec![Coin::new(375_000, "in")],
+                }),
+            ]
+        );
+        assert!(res
+            .attributes
+            .contains(&attr("swap_fee_paid", Uint256::from(37_500u128))));
+    }
+
+    #[test]
+    fn test_partial_refund_keeper_bounty() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(10),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            crate::msg::ExecuteMsg::UpdateKeeperBounty {
+                keeper_bounty: Some(KeeperBounty {
+                    fee_share: Decimal256::percent(50),
+                    min_blocks: Uint64::new(10),
+                }),
+            },
+        )
+        .unwrap();
+
+        // threshold is missed by 90%
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            Uint256::from(1_000_000u128),
+            start,
+            end,
+            Some(Uint256::from(10_000_000u128)),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            crate::msg::ExecuteMsg::UpdatePartialRefund {
+                stream_id: 1,
+                enabled: true,
+            },
+        )
+        .unwrap();
+        env.block.time = start;
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[Coin::new(1_000_000, "in")]),
+            crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            },
+        )
+        .unwrap();
+
+        // swap fee of partially refunding streams is only known once they end
+        env.block.height += 20;
+        env.block.time = start.plus_seconds(2_000_000);
+        let res =
+            execute_update_stream(deps.as_mut(), env.clone(), mock_info("keeper", &[]), 1).unwrap();
+        assert!(res
+            .attributes
+            .contains(&attr("keeper_bounty", Uint256::zero())));
+
+        // the filled tenth of the spent tokens pays the swap fee
+        env.block.height += 20;
+        env.block.time = end.plus_seconds(1);
+        let res =
+            execute_finalize_stream(deps.as_mut(), env, mock_info("treasury", &[]), 1).unwrap();
+        assert!(res.attributes.contains(&attr("swap_fee", "10000")));
+        assert!(res.attributes.contains(&attr("keeper_bounties", "0")));
+        assert_eq!(
+            query_keeper_bounty(deps.as_ref(), 1, "keeper".to_string()).unwrap(),
+            Uint256::zero()
+        );
+    }
+
+    #[test]
+    fn test_create_stream_funds() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let create = |refund_excess| crate::msg::ExecuteMsg::CreateStream {
+            treasury: "treasury".to_string(),
+            name: "test".to_string(),
+            url: None,
+            in_denom: "in".to_string(),
+            out_denom: "out_denom".to_string(),
+            out_supply: Uint256::from(1_000_000u128),
+            start_time: start,
+            end_time: end,
+            threshold: None,
+            revenue_vesting_duration: None,
+            donation_percent: None,
+            refund_excess,
+        };
+
+        // coins split over several entries are summed
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(600_000, "out_denom"),
+                Coin::new(100, "fee"),
+                Coin::new(400_000, "out_denom"),
+            ],
+        );
+        let res = execute(deps.as_mut(), env.clone(), info, create(None)).unwrap();
+        assert!(res.messages.is_empty());
+
+        // overpayment fails unless refunds are requested
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_500, "out_denom"), Coin::new(150, "fee")],
+        );
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), create(None)).unwrap_err();
+        assert_eq!(err, ContractError::StreamOutSupplyFundsRequired {});
+        let res = execute(deps.as_mut(), env.clone(), info, create(Some(true))).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "creator1".to_string(),
+                amount: vec![Coin::new(50, "fee"), Coin::new(500, "out_denom")],
+            })]
+        );
+
+        // underpayment is not accepted either way
+        let info = mock_info(
+            "creator1",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(50, "fee")],
+        );
+        let err = execute(deps.as_mut(), env, info, create(Some(true))).unwrap_err();
+        assert_eq!(err, ContractError::StreamCreationFeeRequired {});
+    }
+
+    #[test]
+    fn test_simulate_subscribe() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint256::from(1_000_000u128);
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator",
+            &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out_denom".to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // first subscriber gets the whole supply
+        let res = query_simulate_subscribe(
+            deps.as_ref(),
+            env.clone(),
+            1,
+            Uint256::from(1_000u128),
+            Some(start),
+        )
+        .unwrap();
+        assert_eq!(res.shares, Uint256::from(1_000u128));
+        assert_eq!(res.expected_out, out_supply);
+        assert_eq!(res.marginal_price, Decimal256::from_ratio(1u128, 1_000u128));
+
+        let mut env = mock_env();
+        env.block.time = start;
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+            min_shares_out: None,
+        };
+        let info = mock_info("creator1", &[Coin::new(1_000, "in")]);
+        execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap();
+
+        // subscribing at the middle gets a third of the remaining half
+        let at_time = Timestamp::from_seconds(3_000_000);
+        let res = query_simulate_subscribe(
+            deps.as_ref(),
+            env.clone(),
+            1,
+            Uint256::from(1_000u128),
+            Some(at_time),
+        )
+        .unwrap();
+        assert_eq!(res.shares, Uint256::from(2_000u128));
+        assert_eq!(res.expected_out, Uint256::from(333_333u128));
+
+        // simulation matches the actual purchase
+        env.block.time = at_time;
+        let info = mock_info("creator2", &[Coin::new(1_000, "in")]);
+        execute(deps.as_mut(), env, info, msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = end;
+        execute_update_position(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator2", &[]),
+            1,
+            None,
+        )
+        .unwrap();
+        let position =
+            query_position(deps.as_ref(), env.clone(), 1, "creator2".to_string()).unwrap();
+        assert_eq!(position.purchased, res.expected_out);
+
+        // ended
+        let err = query_simulate_subscribe(
+            deps.as_ref(),
+            env.clone(),
+            1,
+            Uint256::from(1_000u128),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(ContractError::StreamEnded {}.to_string())
+        );
+
+        // exit is simulated once the stream ends
+        let err = query_simulate_exit(deps.as_ref(), env, 1, "creator1".to_string()).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(ContractError::StreamNotEnded {}.to_string())
+        );
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let res =
+            query_simulate_exit(deps.as_ref(), env.clone(), 1, "creator1".to_string()).unwrap();
+        assert_eq!(res.refund, Uint256::zero());
+        assert_eq!(res.spent, Uint256::from(1_000u128));
+        assert_eq!(res.swap_fee, Uint256::from(10u128));
+        let msg = crate::msg::ExecuteMsg::ExitStream {
+            stream_id: 1,
+            operator_target: None,
+            remote_recipient: None,
+            liquid_stake: false,
+            swap: None,
+        };
+        let exit = execute(deps.as_mut(), env, mock_info("creator1", &[]), msg).unwrap();
+        assert_eq!(
+            exit.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator1".to_string(),
+                amount: vec![Coin::new(
+                    Uint128::try_from(res.purchased).unwrap().u128(),
+                    "out_denom"
+                )],
+            })
+        );
+        assert_eq!(
+            exit.events,
+            vec![Event::new("stream_exited").add_attributes(vec![
+                attr("stream_id", "1"),
+                attr("owner", "creator1"),
+                attr("purchased", res.purchased),
+                attr("refunded", "0"),
+                attr("swap_fee", "10"),
+            ])]
+        );
+    }
+    #[test]
+    fn test_execute_update_config() {
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        //query config
+        let config_response = query_config(deps.as_ref()).unwrap();
+        //check config
+        assert_eq!(config_response.min_stream_seconds, Uint64::new(1000));
+        assert_eq!(config_response.min_seconds_until_start_time, Uint64::new(0));
+        assert_eq!(config_response.stream_creation_denom, "fee".to_string());
+        assert_eq!(config_response.stream_creation_fee, Uint128::new(100));
+        assert_eq!(config_response.fee_collector, "collector".to_string());
+        assert_eq!(
+            config_response.protocol_admin,
+            Some("protocol_admin".to_string())
+        );
+        assert_eq!(config_response.accepted_in_denom, "in".to_string());
+
+        // random user cant update config
+        let mut env = mock_env();
+        let info = mock_info("random", &[]);
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: Some(Uint64::new(2000)),
+            min_duration_until_start_time: Some(Uint64::new(2000)),
+            stream_creation_denom: Some("fee2".to_string()),
+            stream_creation_fee: Some(Uint128::new(200)),
+            fee_collector: Some("collector2".to_string()),
+            accepted_in_denom: Some("new_denom".to_string()),
+            exit_fee_percent: Some(Decimal256::percent(2)),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+            prune_delay_blocks: None,
+            escheat_delay_blocks: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // wrong fee amount
+        let mut env = mock_env();
+        let info = mock_info("protocol_admin", &[]);
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: Some(Uint64::new(2000)),
+            min_duration_until_start_time: Some(Uint64::new(2000)),
+            stream_creation_denom: Some("fee2".to_string()),
+            stream_creation_fee: Some(Uint128::new(0)),
+            fee_collector: Some("collector2".to_string()),
+            accepted_in_denom: Some("new_denom".to_string()),
+            exit_fee_percent: Some(Decimal256::percent(2)),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+            prune_delay_blocks: None,
+            escheat_delay_blocks: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::InvalidStreamCreationFee {});
+
+        // wrong exit fee percent
+        let mut env = mock_env();
+        let info = mock_info("protocol_admin", &[]);
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: Some(Uint64::new(2000)),
+            min_duration_until_start_time: Some(Uint64::new(2000)),
+            stream_creation_denom: Some("fee2".to_string()),
+            stream_creation_fee: Some(Uint128::new(200)),
+            fee_collector: Some("collector2".to_string()),
+            accepted_in_denom: Some("new_denom".to_string()),
+            exit_fee_percent: Some(Decimal256::percent(101)),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+            prune_delay_blocks: None,
+            escheat_delay_blocks: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::InvalidExitFeePercent {});
+
+        // protocol admin can update config
+        let mut env = mock_env();
+        let info = mock_info("protocol_admin", &[]);
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: Some(Uint64::new(2000)),
+            min_duration_until_start_time: Some(Uint64::new(2000)),
+            stream_creation_denom: Some("fee2".to_string()),
+            stream_creation_fee: Some(Uint128::new(200)),
+            fee_collector: Some("collector2".to_string()),
+            accepted_in_denom: Some("new_denom".to_string()),
+            exit_fee_percent: Some(Decimal256::percent(2)),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+            prune_delay_blocks: None,
+            escheat_delay_blocks: None,
+        };
+        execute(deps.as_mut(), env, info, msg).unwrap();
+
+        //query config
+        let config_response = query_config(deps.as_ref()).unwrap();
+        //check config
+        assert_eq!(config_response.min_stream_seconds, Uint64::new(2000));
+        assert_eq!(
+            config_response.min_seconds_until_start_time,
+            Uint64::new(2000)
+        );
+        assert_eq!(config_response.stream_creation_denom, "fee2".to_string());
+        assert_eq!(config_response.stream_creation_fee, Uint128::new(200));
+        assert_eq!(config_response.fee_collector, "collector2".to_string());
+        assert_eq!(
+            config_response.protocol_admin,
+            Some("protocol_admin".to_string())
+        );
+        assert_eq!(config_response.accepted_in_denom, "new_denom".to_string());
+        assert_eq!(config_response.exit_fee_percent, Decimal256::percent(2));
+
+        // create stream
+        let out_supply = Uint256::from(1000u128);
+        let out_denom = "out";
+        let start = Timestamp::from_seconds(10000);
+        let end = Timestamp::from_seconds(1000000);
+        let treasury = "treasury";
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                Coin::new(200, "fee2"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "new_denom".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // update config during stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(100000);
+        let info = mock_info("protocol_admin", &[]);
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: Some(Uint64::new(3000)),
+            min_duration_until_start_time: Some(Uint64::new(4000)),
+            stream_creation_denom: Some("fee3".to_string()),
+            stream_creation_fee: Some(Uint128::new(300)),
+            fee_collector: Some("collector3".to_string()),
+            accepted_in_denom: Some("new_denom2".to_string()),
+            exit_fee_percent: Some(Decimal256::percent(5)),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+            prune_delay_blocks: None,
+            escheat_delay_blocks: None,
+        };
+        execute(deps.as_mut(), env, info, msg).unwrap();
+        //query config
+        let config_response = query_config(deps.as_ref()).unwrap();
+        //check config
+        assert_eq!(config_response.min_stream_seconds, Uint64::new(3000));
+        assert_eq!(
+            config_response.min_seconds_until_start_time,
+            Uint64::new(4000)
+        );
+        assert_eq!(config_response.stream_creation_denom, "fee3".to_string());
+        assert_eq!(config_response.stream_creation_fee, Uint128::new(300));
+        assert_eq!(config_response.fee_collector, "collector3".to_string());
+        assert_eq!(
+            config_response.protocol_admin,
+            Some("protocol_admin".to_string())
+        );
+        assert_eq!(config_response.accepted_in_denom, "new_denom2".to_string());
+        assert_eq!(config_response.exit_fee_percent, Decimal256::percent(5));
+
+        // check stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(100000);
+        let stream_response = query_stream(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(stream_response.exit_fee_percent, Decimal256::percent(2));
+        assert_eq!(stream_response.stream_creation_fee, Uint128::new(200));
+    }
+
+    #[test]
+    fn test_config_timelock() {
+        // instantiate with a day of config timelock
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            exit_fee_percent: Decimal256::percent(1),
+            fee_collector: "collector".to_string(),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: Some(Uint64::new(86_400)),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let update_msg = |fee: u128| crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: Some(Uint128::new(fee)),
+            fee_collector: None,
+            accepted_in_denom: None,
+            exit_fee_percent: Some(Decimal256::percent(2)),
+            max_pause_duration: None,
+            max_creator_pause_duration: None,
+            creator_cancel_penalty: None,
+            permissioned_creation: None,
+            config_timelock: None,
+            prune_delay_blocks: None,
+            escheat_delay_blocks: None,
+        };
+        let env = mock_env();
+        let info = mock_info("protocol_admin", &[]);
+
+        // nothing to apply or cancel
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ApplyConfigUpdate {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoPendingConfigUpdate {});
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            CancelConfigUpdate {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoPendingConfigUpdate {});
+
+        // invalid updates are rejected at proposal
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), update_msg(0)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidStreamCreationFee {});
+
+        // update is only proposed
+        execute(deps.as_mut(), env.clone(), info.clone(), update_msg(200)).unwrap();
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.stream_creation_fee, Uint128::new(100));
+        let pending = query_pending_config_update(deps.as_ref()).unwrap().unwrap();
+        assert_eq!(pending.apply_after, env.block.time.plus_seconds(86_400));
+        assert_eq!(pending.update.stream_creation_fee, Some(Uint128::new(200)));
+
+        // random cannot cancel
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("random", &[]),
+            CancelConfigUpdate {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // can not apply before timelock passes
+        let mut apply_env = env.clone();
+        apply_env.block.time = env.block.time.plus_seconds(86_399);
+        let err = execute(
+            deps.as_mut(),
+            apply_env.clone(),
+            mock_info("random", &[]),
+            ApplyConfigUpdate {},
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ConfigUpdateTimelocked {
+                apply_after: pending.apply_after
+            }
+        );
+
+        // protocol admin cancels and proposes again
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            CancelConfigUpdate {},
+        )
+        .unwrap();
+        assert_eq!(query_pending_config_update(deps.as_ref()).unwrap(), None);
+        execute(deps.as_mut(), env.clone(), info, update_msg(300)).unwrap();
+
+        // anyone can apply after timelock
+        apply_env.block.time = env.block.time.plus_seconds(86_400);
+        execute(
+            deps.as_mut(),
+            apply_env.clone(),
+            mock_info("random", &[]),
+            ApplyConfigUpdate {},
+        )
+        .unwrap();
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.stream_creation_fee, Uint128::new(300));
+        assert_eq!(config.exit_fee_percent, Decimal256::percent(2));
+        assert_eq!(config.config_timelock, Some(Uint64::new(86_400)));
+        assert_eq!(query_pending_config_update(deps.as_ref()).unwrap(), None);
+
+        // admin setters are timelocked too
+        let info = mock_info("protocol_admin", &[]);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            crate::msg::ExecuteMsg::UpdateVestingCodeId {
+                vesting_code_id: Some(7),
+            },
+        )
+        .unwrap();
+        assert!(res
+            .attributes
+            .contains(&attr("apply_after", pending.apply_after.to_string())));
+        assert_eq!(query_config(deps.as_ref()).unwrap().vesting_code_id, None);
+        execute(
+            deps.as_mut(),
+            apply_env.clone(),
+            mock_info("random", &[]),
+            ApplyConfigUpdate {},
+        )
+        .unwrap();
+        assert_eq!(
+            query_config(deps.as_ref()).unwrap().vesting_code_id,
+            Some(7)
+        );
+
+        // explicit null clears optional fields, missing fields are kept
+        let msg: crate::msg::ExecuteMsg =
+            cosmwasm_std::from_json(r#"{"update_config":{"config_timelock":null}}"#).unwrap();
+        let crate::msg::ExecuteMsg::UpdateConfig {
+            config_timelock,
+            escheat_delay_blocks,
+            ..
+        } = &msg
+        else {
+            panic!("unexpected msg");
+        };
+        assert_eq!(config_timelock, &Some(None));
+        assert_eq!(escheat_delay_blocks, &None);
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let pending = query_pending_config_update(deps.as_ref()).unwrap().unwrap();
+        assert_eq!(pending.update.config_timelock, Some(None));
+        execute(
+            deps.as_mut(),
+            apply_env,
+            mock_info("random", &[]),
+            ApplyConfigUpdate {},
+        )
+        .unwrap();
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(config.config_timelock, None);
+        assert_eq!(config.vesting_code_id, Some(7));
+
+        // without timelock setters apply right away
+        execute(
+            deps.as_mut(),
+            env,
+            info,
+            crate::msg::ExecuteMsg::UpdateVestingCodeId {
+                vesting_code_id: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(query_config(deps.as_ref()).unwrap().vesting_code_id, None);
+        assert_eq!(query_pending_config_update(deps.as_ref()).unwrap(), None);
+    }
+
+    #[cfg(test)]
+    mod killswitch {
+        use super::*;
+        use crate::contract::{
+            list_largest_positions, list_positions, list_positions_by_owner, list_streams,
+            query_count_positions, query_count_streams, query_threshold_state, sudo,
+        };
+        use crate::killswitch::{
+            execute_cancel_stream, execute_exit_cancelled, execute_resume_stream,
+            query_cancel_confirmations, sudo_cancel_stream, sudo_pause_stream,
+        };
+        use crate::msg::OrderBy;
+        use crate::msg::SudoMsg;
+        use cosmwasm_std::CosmosMsg::Bank;
+        use cosmwasm_std::{ReplyOn, SubMsg};
+
+        #[test]
+        fn test_pause_protocol_admin() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            // non protocol admin can't pause
+            let info = mock_info("non_protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(100);
+
+            let res = execute_pause_stream(deps.as_mut(), env, info, 1);
+            assert_eq!(res, Err(ContractError::Unauthorized {}));
+
+            // first subscription
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_000);
+            let funds = Coin::new(3_000, "in");
+            let info = mock_info("position1", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+            //can't pause before start time
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.minus_seconds(500_000);
+            let res = execute_pause_stream(deps.as_mut(), env, info, 1).unwrap_err();
+            assert_eq!(res, ContractError::StreamNotStarted {});
+
+            // can't pause after end time
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = end.plus_seconds(500_000);
+            let res = execute_pause_stream(deps.as_mut(), env, info, 1).unwrap_err();
+            assert_eq!(res, ContractError::StreamEnded {});
+
+            // protocol admin can pause
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_001);
+            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+
+            // can't paused if already paused
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_005);
+            let res = execute_pause_stream(deps.as_mut(), env, info, 1).unwrap_err();
+            assert_eq!(res, ContractError::StreamKillswitchActive {});
+
+            // can't subscribe new
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_002);
+            let funds = Coin::new(3_000, "in");
+            let info = mock_info("position2", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+            assert_eq!(res, ContractError::StreamKillswitchActive {});
+
+            // can't subscribe more
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_002);
+            let funds = Coin::new(3_000, "in");
+            let info = mock_info("position1", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+            assert_eq!(res, ContractError::StreamKillswitchActive {});
+
+            // can't withdraw
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_002);
+            let info = mock_info("position1", &[]);
+            let msg = crate::msg::ExecuteMsg::Withdraw {
+                stream_id: 1,
+                cap: None,
+                operator_target: None,
+            };
+            let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+            assert_eq!(res, ContractError::StreamKillswitchActive {});
+
+            // can't update stream
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_002);
+            let res = execute_update_stream(deps.as_mut(), env, mock_info("keeper", &[]), 1);
+            assert_eq!(res, Err(ContractError::StreamPaused {}));
+
+            // can't update position
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_002);
+            let info = mock_info("position1", &[]);
+            let res = execute_update_position(deps.as_mut(), env, info, 1, None);
+            assert_eq!(res, Err(ContractError::StreamPaused {}));
+
+            // can't finalize stream
+            let mut env = mock_env();
+            env.block.time = end.plus_seconds(1_000_002);
+            let info = mock_info("treasury", &[]);
+            let res = execute_finalize_stream(deps.as_mut(), env, info, 1);
+            assert_eq!(res, Err(ContractError::StreamKillswitchActive {}));
+
+            // can't exit
+            let mut env = mock_env();
+            env.block.time = end.plus_seconds(1_000_002);
+            let info = mock_info("position1", &[]);
+            let res = execute_exit_stream(deps.as_mut(), env, info, 1, None, None, false, None);
+            assert_eq!(res, Err(ContractError::StreamKillswitchActive {}));
+        }
+
+        #[test]
+        fn test_resume_protocol_admin() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            // first subscription
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_000);
+            let funds = Coin::new(3_000, "in");
+            let info = mock_info("position1", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+            // can't resume if not paused
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_003);
+            let res = execute_resume_stream(deps.as_mut(), env, info, 1).unwrap_err();
+            assert_eq!(res, ContractError::StreamNotPaused {});
+
+            // protocol admin can pause
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_001);
+            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+
+            // can't subscribe new
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_002);
+            let funds = Coin::new(3_000, "in");
+            let info = mock_info("position2", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+            assert_eq!(res, ContractError::StreamKillswitchActive {});
+
+            // non protocol admin can't resume
+            let info = mock_info("non_protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_003);
+            let res = execute_resume_stream(deps.as_mut(), env, info, 1).unwrap_err();
+            assert_eq!(res, ContractError::Unauthorized {});
+
+            // protocol admin can resume
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_003);
+            execute_resume_stream(deps.as_mut(), env, info, 1).unwrap();
+
+            // can subscribe new after resume
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_004);
+            let funds = Coin::new(3_000, "in");
+            let info = mock_info("position2", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            let res = execute(deps.as_mut(), env, info, msg).unwrap();
+            assert_eq!(res.attributes[0].key, "action");
+            assert_eq!(res.attributes[0].value, "subscribe");
+            assert_eq!(res.attributes[1].key, "stream_id");
+            assert_eq!(res.attributes[1].value, "1");
+            assert_eq!(res.attributes[2].key, "owner");
+            assert_eq!(res.attributes[2].value, "position2");
+            assert_eq!(res.attributes[3].key, "in_supply");
+            assert_eq!(res.attributes[3].value, "6000");
+            assert_eq!(res.attributes[4].key, "in_amount");
+            assert_eq!(res.attributes[4].value, "3000");
+
+            // protocol admin can pause
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_005);
+            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+
+            // cancel the stream
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_006);
+            execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap();
+
+            // can't resume if cancelled
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_007);
+            let res = execute_resume_stream(deps.as_mut(), env, info, 1).unwrap_err();
+            assert_eq!(res, ContractError::StreamIsCancelled {});
+        }
+
+        #[test]
+        fn test_cancel_protocol_admin() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            // subscription
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(0);
+            let funds = Coin::new(2_000_000_000_000, "in");
+            let info = mock_info("creator1", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: Some("operator".to_string()),
+                min_shares_out: None,
+            };
+            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+            // non protocol admin can't cancel
+            let info = mock_info("non_protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_000);
+            let err = execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+
+            // cant cancel without pause
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_000);
+            let err = execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap_err();
+            assert_eq!(err, ContractError::StreamNotPaused {});
+
+            // pause
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(2_000_000);
+            let info = mock_info("protocol_admin", &[]);
+            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+
+            //cancel
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(2_500_000);
+            let response = execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap();
+            //out_tokens and the creation fee are sent back to the treasury upon cancellation
+            assert_eq!(
+                response.messages,
+                [
+                    SubMsg {
+                        id: 0,
+                        msg: Bank(BankMsg::Send {
+                            to_address: "treasury".to_string(),
+                            amount: Vec::from([Coin {
+                                denom: "out_denom".to_string(),
+                                amount: Uint128::new(1000000000000)
+                            }])
+                        }),
+                        gas_limit: None,
+                        reply_on: ReplyOn::Never
+                    },
+                    SubMsg {
+                        id: 0,
+                        msg: Bank(BankMsg::Send {
+                            to_address: "treasury".to_string(),
+                            amount: Vec::from([Coin {
+                                denom: "fee".to_string(),
+                                amount: Uint128::new(100)
+                            }])
+                        }),
+                        gas_limit: None,
+                        reply_on: ReplyOn::Never
+                    }
+                ]
+            );
+
+            // can't cancel cancelled stream
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(2_500_000);
+            let response = execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap_err();
+            assert_eq!(response, ContractError::StreamIsCancelled {});
+        }
+
+        #[test]
+        fn test_remote_controller() {
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                "treasury".to_string(),
+                "test".to_string(),
+                None,
+                "in".to_string(),
+                "out_denom".to_string(),
+                Uint256::from(1_000_000u128),
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            // only protocol admin can set the remote controller
+            let msg = crate::msg::ExecuteMsg::UpdateRemoteController {
+                remote_controller: Some("polytone_proxy".to_string()),
+            };
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("polytone_proxy", &[]),
+                msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("protocol_admin", &[]),
+                msg,
+            )
+            .unwrap();
+            let config = query_config(deps.as_ref()).unwrap();
+            assert_eq!(config.remote_controller, Some("polytone_proxy".to_string()));
+
+            // remote controller can pause and resume
+            let info = mock_info("polytone_proxy", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(100);
+            execute_pause_stream(deps.as_mut(), env.clone(), info.clone(), 1).unwrap();
+            env.block.time = start.plus_seconds(200);
+            execute_resume_stream(deps.as_mut(), env.clone(), info.clone(), 1).unwrap();
+
+            // and cancel
+            env.block.time = start.plus_seconds(300);
+            execute_pause_stream(deps.as_mut(), env.clone(), info.clone(), 1).unwrap();
+            execute_cancel_stream(deps.as_mut(), env.clone(), info, 1).unwrap();
+            let stream = query_stream(deps.as_ref(), env, 1).unwrap();
+            assert_eq!(stream.status, Status::Cancelled);
+
+            // removed remote controller is rejected
+            let msg = crate::msg::ExecuteMsg::UpdateRemoteController {
+                remote_controller: None,
+            };
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("protocol_admin", &[]),
+                msg,
+            )
+            .unwrap();
+            let config = query_config(deps.as_ref()).unwrap();
+            assert_eq!(config.remote_controller, None);
+        }
+
+        #[test]
+        fn test_guardian_cancel() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            // only protocol admin can update guardians
+            let msg = crate::msg::ExecuteMsg::UpdateGuardians {
+                guardians: vec![
+                    "guardian1".to_string(),
+                    "guardian2".to_string(),
+                    "guardian3".to_string(),
+                ],
+                cancel_threshold: 2,
+            };
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("guardian1", &[]),
+                msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+
+            // threshold can not exceed guardian count
+            let invalid_msg = crate::msg::ExecuteMsg::UpdateGuardians {
+                guardians: vec!["guardian1".to_string(), "guardian1".to_string()],
+                cancel_threshold: 2,
+            };
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("protocol_admin", &[]),
+                invalid_msg,
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::InvalidGuardianSet {});
+
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("protocol_admin", &[]),
+                msg,
+            )
+            .unwrap();
+
+            // protocol admin is not a guardian
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(100);
+            let err = execute_pause_stream(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("protocol_admin", &[]),
+                1,
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+
+            // any guardian can pause
+            execute_pause_stream(deps.as_mut(), env.clone(), mock_info("guardian2", &[]), 1)
+                .unwrap();
+
+            // confirm needs an open proposal
+            let confirm_msg = crate::msg::ExecuteMsg::ConfirmCancelStream { stream_id: 1 };
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("guardian3", &[]),
+                confirm_msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::NoCancelProposal {});
+
+            // first confirmation does not cancel
+            let res =
+                execute_cancel_stream(deps.as_mut(), env.clone(), mock_info("guardian1", &[]), 1)
+                    .unwrap();
+            assert!(res.messages.is_empty());
+            assert!(res.attributes.contains(&attr("confirmations", "1")));
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("guardian1", &[]),
+                confirm_msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::CancelAlreadyConfirmed {});
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("random", &[]),
+                confirm_msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+
+            // second confirmation cancels
+            let res =
+                execute(deps.as_mut(), env, mock_info("guardian3", &[]), confirm_msg).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![
+                    SubMsg::new(BankMsg::Send {
+                        to_address: treasury.to_string(),
+                        amount: vec![Coin::new(1_000_000_000_000, out_denom)],
+                    }),
+                    SubMsg::new(BankMsg::Send {
+                        to_address: treasury.to_string(),
+                        amount: vec![Coin::new(100, "fee")],
+                    }),
+                ]
+            );
+            let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+            assert_eq!(stream.status, Status::Cancelled);
+            assert!(query_cancel_confirmations(deps.as_ref(), 1)
+                .unwrap()
+                .is_empty());
+        }
+
+        #[test]
+        fn test_withdraw_pause() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            // subscription
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(0);
+            let funds = Coin::new(2_000_000_000_000, "in");
+            let info = mock_info("creator1", std::slice::from_ref(&funds));
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: Some("operator".to_string()),
+                min_shares_out: None,
+            };
+            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+            // withdraw with cap
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(5000);
+            let info = mock_info("creator1", &[]);
+            let cap = Uint256::from(25_000_000u128);
+            let msg = crate::msg::ExecuteMsg::Withdraw {
+                stream_id: 1,
+                cap: Some(cap),
+                operator_target: None,
+            };
+            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+            let position =
+                query_position(deps.as_ref(), mock_env(), 1, "creator1".to_string()).unwrap();
+            assert_eq!(position.in_balance, Uint256::from(1_997_475_000_000u128));
+            assert_eq!(position.spent, Uint256::from(2_500_000_000u128));
+            assert_eq!(position.purchased, Uint256::from(1_250_000_000u128));
+            // first fund amount should be equal to in_balance + spent + cap
+            assert_eq!(
+                position.in_balance + position.spent + cap,
+                Uint256::from_str(funds.amount.to_string().as_str()).unwrap()
+            );
+
+            // can't withdraw pause
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(6000);
+            let info = mock_info("creator1", &[]);
+            let err = execute_withdraw_paused(deps.as_mut(), env, info, 1, None, None).unwrap_err();
+            assert_eq!(err, ContractError::StreamNotPaused {});
+
+            // pause
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(6000);
+            let info = mock_info("protocol_admin", &[]);
+            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(6500);
+            let stream1_old = query_stream(deps.as_ref(), env, 1).unwrap();
+            //Unauthorized check
+            let info = mock_info("random", &[]);
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(7000);
+            let res = execute_withdraw_paused(
+                deps.as_mut(),
+                env,
+                info,
+                1,
+                None,
+                Some("creator1".to_string()),
+            )
+            .unwrap_err();
+
+            assert_eq!(res, ContractError::Unauthorized {});
+            //Cap exceeds in balance check
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(7000);
+            let info = mock_info("creator1", &[]);
+            let res = execute_withdraw_paused(
+                deps.as_mut(),
+                env,
+                info,
+                1,
+                Some(Uint256::from(2_000_000_000_000u128 + 1u128)),
+                None,
+            )
+            .unwrap_err();
+            assert_eq!(
+                res,
+                ContractError::WithdrawAmountExceedsBalance(Uint256::from(2_000_000_000_001u128))
+            );
+            // Withdraw cap is zero
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(7000);
+            let info = mock_info("creator1", &[]);
+            let res =
+                execute_withdraw_paused(deps.as_mut(), env, info, 1, Some(Uint256::zero()), None)
+                    .unwrap_err();
+            assert_eq!(res, ContractError::InvalidWithdrawAmount {});
+
+            //withdraw with cap
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(7000);
+            let info = mock_info("creator1", &[]);
+            let cap = Uint256::from(25_000_000u128);
+            execute_withdraw_paused(deps.as_mut(), env, info, 1, Some(cap), None).unwrap();
+
+            // withdraw after pause
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(7000);
+            let info = mock_info("creator1", &[]);
+            let res = execute_withdraw_paused(deps.as_mut(), env, info, 1, None, None).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![SubMsg {
+                    id: 0,
+                    msg: BankMsg::Send {
+                        to_address: "creator1".to_string(),
+                        amount: vec![Coin {
+                            denom: "in".to_string(),
+                            amount: Uint128::new(1996950006258),
+                        }],
+                    }
+                    .into(),
+                    gas_limit: None,
+                    reply_on: ReplyOn::Never,
+                }]
+            );
+
+            // stream not updated
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(8000);
+            let stream1_new = query_stream(deps.as_ref(), env, 1).unwrap();
+            // dist_index not updated
+            assert_eq!(stream1_old.dist_index, stream1_new.dist_index);
+            assert_eq!(stream1_new.in_supply, Uint256::zero());
+            assert_eq!(stream1_new.shares, Uint256::zero());
+
+            // position updated
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(8001);
+            let position =
+                query_position(deps.as_ref(), mock_env(), 1, "creator1".to_string()).unwrap();
+            // in_balance updated
+            assert_eq!(position.in_balance, Uint256::zero());
+            assert_eq!(position.spent, Uint256::from(2_999_993_742u128));
+            assert_eq!(position.purchased, Uint256::from(1_499_999_998u128));
+            assert_eq!(position.shares, Uint256::zero());
+        }
+
+        #[test]
+        fn test_resume() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            // first subscription
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_000);
+            let funds = Coin::new(3_000, "in");
+            let info = mock_info("position1", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+            //cant resume if not paused
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_000);
+            let res = sudo_resume_stream(deps.as_mut(), env, 1).unwrap_err();
+            assert_eq!(res, ContractError::StreamNotPaused {});
+
+            // pause
+            let info = mock_info("protocol_admin", &[]);
+            let mut env = mock_env();
+            let pause_date = start.plus_seconds(2_000_000);
+            env.block.time = pause_date;
+            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+
+            // resume
+            let mut env = mock_env();
+            let resume_date = start.plus_seconds(3_000_000);
+            env.block.time = resume_date;
+            sudo_resume_stream(deps.as_mut(), env, 1).unwrap();
+
+            // new end date is correct
+            let new_end_date = end.plus_nanos(resume_date.nanos() - pause_date.nanos());
+            let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+            assert_eq!(stream.end_time, new_end_date);
+        }
+
+        #[test]
+        fn test_resume_expired_pause() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: Some(Uint64::new(500_000)),
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            // cant resume if not paused
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(1_000_000);
+            let msg = crate::msg::ExecuteMsg::ResumeExpiredPause { stream_id: 1 };
+            let res =
+                execute(deps.as_mut(), env, mock_info("random", &[]), msg.clone()).unwrap_err();
+            assert_eq!(res, ContractError::StreamNotPaused {});
+
+            // pause
+            let mut env = mock_env();
+            let pause_date = start.plus_seconds(2_000_000);
+            env.block.time = pause_date;
+            execute_pause_stream(deps.as_mut(), env, mock_info("protocol_admin", &[]), 1).unwrap();
+
+            // pause not expired yet
+            let mut env = mock_env();
+            env.block.time = pause_date.plus_seconds(499_999);
+            let res =
+                execute(deps.as_mut(), env, mock_info("random", &[]), msg.clone()).unwrap_err();
+            assert_eq!(res, ContractError::PauseNotExpired {});
+
+            // anyone can resume after max pause duration
+            let mut env = mock_env();
+            let resume_date = pause_date.plus_seconds(600_000);
+            env.block.time = resume_date;
+            execute(deps.as_mut(), env, mock_info("random", &[]), msg).unwrap();
+
+            // new end date is correct
+            let new_end_date = end.plus_nanos(resume_date.nanos() - pause_date.nanos());
+            let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+            assert_eq!(stream.end_time, new_end_date);
+            assert_eq!(stream.status, Status::Active);
+        }
+
+        #[test]
+        fn test_exit_paused() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: Some(Uint64::new(500_000)),
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            // subscribe
+            let mut env = mock_env();
+            env.block.time = start;
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            execute(
+                deps.as_mut(),
+                env,
+                mock_info("position1", &[Coin::new(1_000_000, "in")]),
+                msg,
+            )
+            .unwrap();
+
+            // pause at the half of the stream
+            let mut env = mock_env();
+            let pause_date = start.plus_seconds(2_000_000);
+            env.block.time = pause_date;
+            execute_pause_stream(deps.as_mut(), env, mock_info("protocol_admin", &[]), 1).unwrap();
+
+            // pause not expired yet
+            let mut env = mock_env();
+            env.block.time = pause_date.plus_seconds(1_000);
+            let msg = crate::msg::ExecuteMsg::ExitPaused {
+                stream_id: 1,
+                operator_target: None,
+            };
+            let err =
+                execute(deps.as_mut(), env, mock_info("position1", &[]), msg.clone()).unwrap_err();
+            assert_eq!(err, ContractError::PauseNotExpired {});
+
+            // exit with purchased until pause and unspent balance
+            let mut env = mock_env();
+            env.block.time = pause_date.plus_seconds(500_000);
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("random", &[]),
+                crate::msg::ExecuteMsg::ExitPaused {
+                    stream_id: 1,
+                    operator_target: Some("position1".to_string()),
+                },
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            let res = execute(deps.as_mut(), env, mock_info("position1", &[]), msg).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "position1".to_string(),
+                        amount: vec![Coin::new(500_000, out_denom)],
+                    }),
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "position1".to_string(),
+                        amount: vec![Coin::new(500_000, "in")],
+                    }),
+                ]
+            );
+            let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+            assert_eq!(stream.in_supply, Uint256::zero());
+            assert!(query_position(deps.as_ref(), mock_env(), 1, "position1".to_string()).is_err());
+            assert!(crate::killswitch::PAUSED_EXITS.has(&deps.storage, 1));
+
+            // paused exits are settled at finalize
+            let mut env = mock_env();
+            env.block.time = pause_date.plus_seconds(500_000);
+            execute_resume_stream(deps.as_mut(), env, mock_info("protocol_admin", &[]), 1).unwrap();
+            let end_time = query_stream(deps.as_ref(), mock_env(), 1).unwrap().end_time;
+            let mut env = mock_env();
+            env.block.time = end_time.plus_seconds(1);
+            execute_finalize_stream(deps.as_mut(), env, mock_info("treasury", &[]), 1).unwrap();
+            assert!(!crate::killswitch::PAUSED_EXITS.has(&deps.storage, 1));
+        }
+
+        #[test]
+        fn test_exit_paused_cancel() {
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_denom = "out_denom";
+
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: Some(Uint64::new(500_000)),
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            for threshold in [None, Some(Uint256::from(5_000_000u128))] {
+                let info = mock_info(
+                    "creator1",
+                    &[Coin::new(1_000_000, out_denom), Coin::new(100, "fee")],
+                );
+                execute_create_stream(
+                    deps.as_mut(),
+                    env.clone(),
+                    info,
+                    "treasury".to_string(),
+                    "test".to_string(),
+                    None,
+                    "in".to_string(),
+                    out_denom.to_string(),
+                    Uint256::from(1_000_000u128),
+                    start,
+                    end,
+                    threshold,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap();
+            }
+            let mut env = mock_env();
+            env.block.time = start;
+            for (stream_id, subscriber) in [(1, "position1"), (1, "position2"), (2, "position1")] {
+                let msg = crate::msg::ExecuteMsg::Subscribe {
+                    stream_id,
+                    operator_target: None,
+                    operator: None,
+                    min_shares_out: None,
+                };
+                execute(
+                    deps.as_mut(),
+                    env.clone(),
+                    mock_info(subscriber, &[Coin::new(1_000_000, "in")]),
+                    msg,
+                )
+                .unwrap();
+            }
+
+            // pause at the half of the streams
+            let mut env = mock_env();
+            let pause_date = start.plus_seconds(2_000_000);
+            env.block.time = pause_date;
+            for stream_id in [1, 2] {
+                execute_pause_stream(
+                    deps.as_mut(),
+                    env.clone(),
+                    mock_info("protocol_admin", &[]),
+                    stream_id,
+                )
+                .unwrap();
+            }
+
+            // contract balances, debited with every payout
+            let mut balances = [(out_denom, 1_000_000u128), ("in", 2_000_000u128)];
+            let mut pay_out = |res: &Response| {
+                for msg in &res.messages {
+                    if let CosmosMsg::Bank(BankMsg::Send { amount, .. }) = &msg.msg {
+                        for coin in amount {
+                            if let Some((_, balance)) =
+                                balances.iter_mut().find(|(d, _)| *d == coin.denom)
+                            {
+                                *balance -= coin.amount.u128();
+                            }
+                        }
+                    }
+                }
+            };
+
+            // threshold streams can not be exited while paused
+            let mut env = mock_env();
+            env.block.time = pause_date.plus_seconds(500_000);
+            let msg = |stream_id| crate::msg::ExecuteMsg::ExitPaused {
+                stream_id,
+                operator_target: None,
+            };
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("position1", &[]),
+                msg(2),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::ThresholdExitPaused {});
+
+            // swap fee of the exited position is reported, charged once the stream settles
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("position1", &[]),
+                msg(1),
+            )
+            .unwrap();
+            assert!(res.attributes.contains(&attr("purchased", "250000")));
+            assert!(res.attributes.contains(&attr("swap_fee", "5000")));
+            pay_out(&res);
+
+            // cancel refunds the out tokens not purchased yet and pays out the exited spent tokens
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("protocol_admin", &[]),
+                crate::msg::ExecuteMsg::CancelStream { stream_id: 1 },
+            )
+            .unwrap();
+            for (to_address, coin) in [
+                ("treasury", Coin::new(750_000, out_denom)),
+                ("treasury", Coin::new(495_000, "in")),
+                ("collector", Coin::new(5_000, "in")),
+            ] {
+                assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+                    to_address: to_address.to_string(),
+                    amount: vec![coin],
+                })));
+            }
+            pay_out(&res);
+
+            let res =
+                execute_exit_cancelled(deps.as_mut(), env, mock_info("position2", &[]), 1, None)
+                    .unwrap();
+            pay_out(&res);
+            assert_eq!(balances, [(out_denom, 0), ("in", 0)]);
+        }
+
+        #[test]
+        fn test_creator_pause() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: Some(Uint64::new(3600)),
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            let pause_msg = crate::msg::ExecuteMsg::CreatorPauseStream { stream_id: 1 };
+            let resume_msg = crate::msg::ExecuteMsg::CreatorResumeStream { stream_id: 1 };
+
+            // only treasury can creator pause
+            let mut env = mock_env();
+            let pause_date = start.plus_seconds(100);
+            env.block.time = pause_date;
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("random", &[]),
+                pause_msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            execute(
+                deps.as_mut(),
+                env,
+                mock_info(treasury.as_str(), &[]),
+                pause_msg.clone(),
+            )
+            .unwrap();
+            let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+            assert_eq!(stream.status, Status::Paused);
+
+            // anyone can resume once the creator pause expires
+            let mut env = mock_env();
+            env.block.time = pause_date.plus_seconds(3599);
+            let err = execute(
+                deps.as_mut(),
+                env,
+                mock_info("random", &[]),
+                resume_msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            let mut env = mock_env();
+            let resume_date = pause_date.plus_seconds(3600);
+            env.block.time = resume_date;
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("random", &[]),
+                resume_msg.clone(),
+            )
+            .unwrap();
+            let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+            assert_eq!(stream.status, Status::Active);
+            assert_eq!(stream.end_time, end.plus_seconds(3600));
+
+            // creator pause can be used only once
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(treasury.as_str(), &[]),
+                pause_msg,
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::CreatorPauseUsed {});
+
+            // treasury can not resume protocol admin pause
+            execute_pause_stream(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("protocol_admin", &[]),
+                1,
+            )
+            .unwrap();
+            let err = execute(
+                deps.as_mut(),
+                env,
+                mock_info(treasury.as_str(), &[]),
+                resume_msg,
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::NotCreatorPaused {});
+        }
+
+        #[test]
+        fn test_sudo_update_stream_params() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+            // subscribe
+            let mut env = mock_env();
+            env.block.time = start;
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            execute(
+                deps.as_mut(),
+                env,
+                mock_info("position1", &[Coin::new(1_000_000, "in")]),
+                msg,
+            )
+            .unwrap();
+
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(2_000_000);
+
+            // empty update
+            let err = sudo(
+                deps.as_mut(),
+                env.clone(),
+                SudoMsg::UpdateStreamParams {
+                    stream_id: 1,
+                    end_time: None,
+                    threshold: None,
+                },
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::EmptyStreamParamsUpdate {});
+
+            // end time can only be extended
+            let err = sudo(
+                deps.as_mut(),
+                env.clone(),
+                SudoMsg::UpdateStreamParams {
+                    stream_id: 1,
+                    end_time: Some(end.minus_seconds(1)),
+                    threshold: None,
+                },
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::StreamInvalidEndTime {});
+
+            let new_end = end.plus_seconds(2_000_000);
+            let res = sudo(
+                deps.as_mut(),
+                env,
+                SudoMsg::UpdateStreamParams {
+                    stream_id: 1,
+                    end_time: Some(new_end),
+                    threshold: Some(Uint256::from(100u128)),
+                },
+            )
+            .unwrap();
+            assert!(res
+                .attributes
+                .contains(&attr("new_end_time", new_end.to_string())));
+
+            // half of the stream is distributed with the old end time
+            let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+            assert_eq!(stream.end_time, new_end);
+            assert_eq!(stream.out_remaining, Uint256::from(500_000u128));
+            assert_eq!(
+                query_threshold_state(deps.as_ref(), mock_env(), 1).unwrap(),
+                Some(Uint256::from(100u128))
+            );
+
+            // ended stream can not be updated
+            let mut env = mock_env();
+            env.block.time = new_end;
+            let err = sudo(
+                deps.as_mut(),
+                env,
+                SudoMsg::UpdateStreamParams {
+                    stream_id: 1,
+                    end_time: Some(new_end.plus_seconds(1)),
+                    threshold: None,
+                },
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::StreamEnded {});
+        }
+
+        #[test]
+        fn test_sudo_pause_all_streams() {
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(1_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create streams, the last one starts later
+            for stream_start in [start, start, start, end.minus_seconds(1000)] {
+                let mut env = mock_env();
+                env.block.time = Timestamp::from_seconds(0);
+                let info = mock_info(
+                    "creator1",
+                    &[
+                        Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                        Coin::new(100, "fee"),
+                    ],
+                );
+                execute_create_stream(
+                    deps.as_mut(),
+                    env,
+                    info,
+                    "treasury".to_string(),
+                    "test".to_string(),
+                    None,
+                    "in".to_string(),
+                    out_denom.to_string(),
+                    out_supply,
+                    stream_start,
+                    end,
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap();
+            }
+            let mut env = mock_env();
+            env.block.time = start;
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            execute(
+                deps.as_mut(),
+                env,
+                mock_info("subscriber", &[Coin::new(1_000_000, "in")]),
+                msg,
+            )
+            .unwrap();
+
+            // first batch
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(100);
+            let res = sudo(
+                deps.as_mut(),
+                env.clone(),
+                SudoMsg::PauseAllStreams {
+                    start_after: None,
+                    limit: Some(2),
+                },
+            )
+            .unwrap();
+            assert!(res.attributes.contains(&attr("paused", "2")));
+            assert!(res.attributes.contains(&attr("last_stream_id", "2")));
+            let stream = query_stream(deps.as_ref(), mock_env(), 3).unwrap();
+            assert_eq!(stream.status, Status::Waiting);
+            // streamed price at the pause is recorded
+            let history =
+                crate::price_history::query_price_history(deps.as_ref(), 1, None, None, None)
+                    .unwrap();
+            assert_eq!(history.prices.len(), 1);
+            assert_eq!(history.prices[0].time, env.block.time);
+
+            // second batch skips the stream not started yet
+            let res = sudo(
+                deps.as_mut(),
+                env.clone(),
+                SudoMsg::PauseAllStreams {
+                    start_after: Some(2),
+                    limit: Some(2),
+                },
+            )
+            .unwrap();
+            assert!(res.attributes.contains(&attr("paused", "1")));
+            assert!(res.attributes.contains(&attr("last_stream_id", "4")));
+            let stream = query_stream(deps.as_ref(), mock_env(), 4).unwrap();
+            assert_eq!(stream.status, Status::Waiting);
+
+            // resume all
+            env.block.time = env.block.time.plus_seconds(100);
+            let res = sudo(
+                deps.as_mut(),
+                env,
+                SudoMsg::ResumeAllStreams {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+            assert!(res.attributes.contains(&attr("resumed", "3")));
+            for stream_id in 1..=3 {
+                let stream = query_stream(deps.as_ref(), mock_env(), stream_id).unwrap();
+                assert_eq!(stream.status, Status::Active);
+                assert_eq!(stream.end_time, end.plus_seconds(100));
+            }
+        }
 
         #[test]
-        fn test_pause_protocol_admin() {
+        fn test_sudo_pause_stream() {
             let treasury = Addr::unchecked("treasury");
             let start = Timestamp::from_seconds(1_000_000);
             let end = Timestamp::from_seconds(5_000_000);
@@ -2745,6 +11369,11 @@ mod test_module {
                 fee_collector: "collector".to_string(),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
             };
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -2771,124 +11400,320 @@ mod test_module {
                 start,
                 end,
                 None,
+                None,
+                None,
+                None,
             )
             .unwrap();
 
-            // non protocol admin can't pause
-            let info = mock_info("non_protocol_admin", &[]);
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(100);
-
-            let res = execute_pause_stream(deps.as_mut(), env, info, 1);
-            assert_eq!(res, Err(ContractError::Unauthorized {}));
+            env.block.time = Timestamp::from_seconds(500_000);
+            let res = sudo_pause_stream(deps.as_mut(), env, 1).unwrap_err();
+            assert_eq!(res, ContractError::StreamNotStarted {});
 
-            // first subscription
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_000);
-            let funds = Coin::new(3_000, "in");
-            let info = mock_info("position1", &[funds]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
-                stream_id: 1,
-                operator_target: None,
-                operator: None,
-            };
-            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+            env.block.time = Timestamp::from_seconds(6_000_000);
+            let res = sudo_pause_stream(deps.as_mut(), env, 1).unwrap_err();
+            assert_eq!(res, ContractError::StreamEnded {});
 
-            //can't pause before start time
-            let info = mock_info("protocol_admin", &[]);
             let mut env = mock_env();
-            env.block.time = start.minus_seconds(500_000);
-            let res = execute_pause_stream(deps.as_mut(), env, info, 1).unwrap_err();
-            assert_eq!(res, ContractError::StreamNotStarted {});
+            env.block.time = Timestamp::from_seconds(3_000_000);
+            let res = sudo_pause_stream(deps.as_mut(), env, 1).unwrap();
+            assert_eq!(
+                res,
+                Response::new()
+                    .add_attribute("action", "sudo_pause_stream")
+                    .add_attribute("stream_id", "1")
+                    .add_attribute("is_paused", "true")
+                    .add_attribute("pause_date", "3000000.000000000")
+                    .add_event(
+                        Event::new("stream_paused")
+                            .add_attribute("stream_id", "1")
+                            .add_attribute("pause_date", "3000000.000000000")
+                    )
+            );
 
-            // can't pause after end time
-            let info = mock_info("protocol_admin", &[]);
             let mut env = mock_env();
-            env.block.time = end.plus_seconds(500_000);
-            let res = execute_pause_stream(deps.as_mut(), env, info, 1).unwrap_err();
-            assert_eq!(res, ContractError::StreamEnded {});
+            env.block.time = Timestamp::from_seconds(4_000_000);
+            let res = sudo_pause_stream(deps.as_mut(), env, 1).unwrap_err();
+            assert_eq!(res, ContractError::StreamKillswitchActive {});
+        }
 
-            // protocol admin can pause
-            let info = mock_info("protocol_admin", &[]);
+        #[test]
+        fn test_range_queries() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(2000);
+            let end = Timestamp::from_seconds(1_000_000);
+            let out_supply = Uint256::from(1_000_000u128);
+            let out_denom = "out_denom";
+
+            // instantiate
+            let mut deps = mock_dependencies();
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_001);
-            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+            env.block.time = Timestamp::from_seconds(100);
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(1000),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-            // can't paused if already paused
-            let info = mock_info("protocol_admin", &[]);
+            // create stream
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_005);
-            let res = execute_pause_stream(deps.as_mut(), env, info, 1).unwrap_err();
-            assert_eq!(res, ContractError::StreamKillswitchActive {});
+            env.block.time = Timestamp::from_seconds(1);
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            //first stream
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info.clone(),
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+            //second stream
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
 
-            // can't subscribe new
+            let res =
+                list_streams(deps.as_ref(), mock_env(), None, None, None, None, None).unwrap();
+            assert_eq!(res.streams.len(), 2);
+
+            // first subscription to first stream
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_002);
-            let funds = Coin::new(3_000, "in");
-            let info = mock_info("position2", &[funds]);
+            env.block.time = start.plus_seconds(100);
+            let info = mock_info("creator1", &[Coin::new(1_000_000, "in")]);
             let msg = crate::msg::ExecuteMsg::Subscribe {
                 stream_id: 1,
                 operator_target: None,
                 operator: None,
+                min_shares_out: None,
             };
-            let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-            assert_eq!(res, ContractError::StreamKillswitchActive {});
+            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
-            // can't subscribe more
+            // second subscription to first stream
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_002);
-            let funds = Coin::new(3_000, "in");
-            let info = mock_info("position1", &[funds]);
+            env.block.time = start.plus_seconds(100);
+            let info = mock_info("creator2", &[Coin::new(1_000_000, "in")]);
             let msg = crate::msg::ExecuteMsg::Subscribe {
                 stream_id: 1,
                 operator_target: None,
                 operator: None,
+                min_shares_out: None,
             };
-            let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-            assert_eq!(res, ContractError::StreamKillswitchActive {});
+            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
-            // can't withdraw
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_002);
-            let info = mock_info("position1", &[]);
-            let msg = crate::msg::ExecuteMsg::Withdraw {
-                stream_id: 1,
-                cap: None,
-                operator_target: None,
-            };
-            let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-            assert_eq!(res, ContractError::StreamKillswitchActive {});
+            let res = list_positions(deps.as_ref(), 1, None, None, None).unwrap();
+            assert_eq!(res.positions.len(), 2);
 
-            // can't update stream
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_002);
-            let res = execute_update_stream(deps.as_mut(), env, 1);
-            assert_eq!(res, Err(ContractError::StreamPaused {}));
+            // filter by status
+            let res = list_streams(
+                deps.as_ref(),
+                mock_env(),
+                None,
+                None,
+                Some(Status::Active),
+                None,
+                None,
+            )
+            .unwrap();
+            assert_eq!(res.streams.len(), 1);
+            assert_eq!(res.streams[0].id, 1);
+            let res = list_streams(
+                deps.as_ref(),
+                mock_env(),
+                None,
+                None,
+                Some(Status::Waiting),
+                None,
+                None,
+            )
+            .unwrap();
+            assert_eq!(res.streams.len(), 1);
+            assert_eq!(res.streams[0].id, 2);
+            let res = list_streams(
+                deps.as_ref(),
+                mock_env(),
+                Some(1),
+                None,
+                Some(Status::Active),
+                None,
+                None,
+            )
+            .unwrap();
+            assert!(res.streams.is_empty());
 
-            // can't update position
+            // status index follows updates
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_002);
-            let info = mock_info("position1", &[]);
-            let res = execute_update_position(deps.as_mut(), env, info, 1, None);
-            assert_eq!(res, Err(ContractError::StreamPaused {}));
+            env.block.time = start.plus_seconds(200);
+            execute_pause_stream(deps.as_mut(), env, mock_info("protocol_admin", &[]), 1).unwrap();
+            let res = list_streams(
+                deps.as_ref(),
+                mock_env(),
+                None,
+                None,
+                Some(Status::Active),
+                None,
+                None,
+            )
+            .unwrap();
+            assert!(res.streams.is_empty());
+            let res = list_streams(
+                deps.as_ref(),
+                mock_env(),
+                None,
+                None,
+                Some(Status::Paused),
+                None,
+                None,
+            )
+            .unwrap();
+            assert_eq!(res.streams.len(), 1);
+            assert_eq!(res.streams[0].id, 1);
 
-            // can't finalize stream
+            // second stream positions
             let mut env = mock_env();
-            env.block.time = end.plus_seconds(1_000_002);
-            let info = mock_info("treasury", &[]);
-            let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None);
-            assert_eq!(res, Err(ContractError::StreamKillswitchActive {}));
+            env.block.time = start.plus_seconds(100);
+            let info = mock_info("creator1", &[Coin::new(1_000_000, "in")]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 2,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            execute(deps.as_mut(), env, info, msg).unwrap();
 
-            // can't exit
-            let mut env = mock_env();
-            env.block.time = end.plus_seconds(1_000_002);
-            let info = mock_info("position1", &[]);
-            let res = execute_exit_stream(deps.as_mut(), env, info, 1, None);
-            assert_eq!(res, Err(ContractError::StreamKillswitchActive {}));
+            let res =
+                list_positions_by_owner(deps.as_ref(), "creator1".to_string(), None, None, None)
+                    .unwrap();
+            assert_eq!(res.positions.len(), 2);
+            assert_eq!(res.positions[0].stream_id, 1);
+            assert_eq!(res.positions[1].stream_id, 2);
+            let res =
+                list_positions_by_owner(deps.as_ref(), "creator1".to_string(), Some(1), None, None)
+                    .unwrap();
+            assert_eq!(res.positions.len(), 1);
+            assert_eq!(res.positions[0].stream_id, 2);
+            let res =
+                list_positions_by_owner(deps.as_ref(), "creator2".to_string(), None, None, None)
+                    .unwrap();
+            assert_eq!(res.positions.len(), 1);
+            assert_eq!(res.positions[0].owner, "creator2".to_string());
+
+            // largest positions by shares
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(100);
+            let info = mock_info("creator2", &[Coin::new(3_000_000, "in")]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 2,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
+            };
+            execute(deps.as_mut(), env, info, msg).unwrap();
+            let res = list_largest_positions(deps.as_ref(), 2, None).unwrap();
+            assert_eq!(res.positions.len(), 2);
+            assert_eq!(res.positions[0].owner, "creator2".to_string());
+            assert_eq!(res.positions[1].owner, "creator1".to_string());
+            assert!(res.positions[0].shares > res.positions[1].shares);
+            let res = list_largest_positions(deps.as_ref(), 2, Some(1)).unwrap();
+            assert_eq!(res.positions.len(), 1);
+            assert_eq!(res.positions[0].owner, "creator2".to_string());
+            // other streams are not mixed in
+            let res = list_largest_positions(deps.as_ref(), 3, None).unwrap();
+            assert!(res.positions.is_empty());
+
+            // newest first
+            let res = list_streams(
+                deps.as_ref(),
+                mock_env(),
+                None,
+                None,
+                None,
+                Some(OrderBy::Descending),
+                None,
+            )
+            .unwrap();
+            assert_eq!(res.streams[0].id, 2);
+            assert_eq!(res.streams[1].id, 1);
+            let res = list_streams(
+                deps.as_ref(),
+                mock_env(),
+                Some(2),
+                None,
+                None,
+                Some(OrderBy::Descending),
+                None,
+            )
+            .unwrap();
+            assert_eq!(res.streams.len(), 1);
+            assert_eq!(res.streams[0].id, 1);
+            let res =
+                list_positions(deps.as_ref(), 1, None, None, Some(OrderBy::Descending)).unwrap();
+            assert_eq!(res.positions[0].owner, "creator2".to_string());
+            assert_eq!(res.positions[1].owner, "creator1".to_string());
+            let res = list_positions_by_owner(
+                deps.as_ref(),
+                "creator1".to_string(),
+                Some(2),
+                None,
+                Some(OrderBy::Descending),
+            )
+            .unwrap();
+            assert_eq!(res.positions.len(), 1);
+            assert_eq!(res.positions[0].stream_id, 1);
+
+            // counts
+            assert_eq!(query_count_streams(deps.as_ref()).unwrap().count, 2);
+            assert_eq!(query_count_positions(deps.as_ref(), 1).unwrap().count, 2);
+            assert_eq!(query_count_positions(deps.as_ref(), 2).unwrap().count, 2);
+            assert_eq!(query_count_positions(deps.as_ref(), 3).unwrap().count, 0);
         }
 
         #[test]
-        fn test_resume_protocol_admin() {
+        fn test_exit_cancel() {
             let treasury = Addr::unchecked("treasury");
             let start = Timestamp::from_seconds(1_000_000);
             let end = Timestamp::from_seconds(5_000_000);
@@ -2908,6 +11733,11 @@ mod test_module {
                 fee_collector: "collector".to_string(),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
             };
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -2934,108 +11764,109 @@ mod test_module {
                 start,
                 end,
                 None,
+                None,
+                None,
+                None,
             )
             .unwrap();
 
-            // first subscription
+            // subscription
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_000);
-            let funds = Coin::new(3_000, "in");
-            let info = mock_info("position1", &[funds]);
+            env.block.time = start.plus_seconds(0);
+            let funds = Coin::new(2_000_000_000_000, "in");
+            let info = mock_info("creator1", &[funds]);
             let msg = crate::msg::ExecuteMsg::Subscribe {
                 stream_id: 1,
                 operator_target: None,
-                operator: None,
+                operator: Some("operator".to_string()),
+                min_shares_out: None,
             };
             let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
-            // can't resume if not paused
-            let info = mock_info("protocol_admin", &[]);
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_003);
-            let res = execute_resume_stream(deps.as_mut(), env, info, 1).unwrap_err();
-            assert_eq!(res, ContractError::StreamNotPaused {});
-
-            // protocol admin can pause
-            let info = mock_info("protocol_admin", &[]);
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_001);
-            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
-
-            // can't subscribe new
+            // cant cancel without pause
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_002);
-            let funds = Coin::new(3_000, "in");
-            let info = mock_info("position2", &[funds]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
-                stream_id: 1,
-                operator_target: None,
-                operator: None,
-            };
-            let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-            assert_eq!(res, ContractError::StreamKillswitchActive {});
+            env.block.time = start.plus_seconds(1_000_000);
+            let err = sudo_cancel_stream(deps.as_mut(), env, 1).unwrap_err();
+            assert_eq!(err, ContractError::StreamNotPaused {});
 
-            // non protocol admin can't resume
-            let info = mock_info("non_protocol_admin", &[]);
+            // pause
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_003);
-            let res = execute_resume_stream(deps.as_mut(), env, info, 1).unwrap_err();
-            assert_eq!(res, ContractError::Unauthorized {});
-
-            // protocol admin can resume
+            env.block.time = start.plus_seconds(2_000_000);
             let info = mock_info("protocol_admin", &[]);
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_003);
-            execute_resume_stream(deps.as_mut(), env, info, 1).unwrap();
+            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
 
-            // can subscribe new after resume
+            //can't exit before cancel
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_004);
-            let funds = Coin::new(3_000, "in");
-            let info = mock_info("position2", &[funds]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
-                stream_id: 1,
-                operator_target: None,
-                operator: None,
-            };
-            let res = execute(deps.as_mut(), env, info, msg).unwrap();
-            assert_eq!(res.attributes[0].key, "action");
-            assert_eq!(res.attributes[0].value, "subscribe");
-            assert_eq!(res.attributes[1].key, "stream_id");
-            assert_eq!(res.attributes[1].value, "1");
-            assert_eq!(res.attributes[2].key, "owner");
-            assert_eq!(res.attributes[2].value, "position2");
-            assert_eq!(res.attributes[3].key, "in_supply");
-            assert_eq!(res.attributes[3].value, "6000");
-            assert_eq!(res.attributes[4].key, "in_amount");
-            assert_eq!(res.attributes[4].value, "3000");
+            env.block.time = start.plus_seconds(2_250_000);
+            let info = mock_info("creator1", &[]);
+            let res = execute_exit_cancelled(deps.as_mut(), env, info, 1, None).unwrap_err();
+            assert_eq!(res, ContractError::StreamNotCancelled {});
 
-            // protocol admin can pause
-            let info = mock_info("protocol_admin", &[]);
+            //cancel
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_005);
-            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+            env.block.time = start.plus_seconds(2_500_000);
+            let response = sudo_cancel_stream(deps.as_mut(), env, 1).unwrap();
+            //out_tokens and the creation fee are sent back to the treasury upon cancellation
+            assert_eq!(
+                response.messages,
+                [
+                    SubMsg {
+                        id: 0,
+                        msg: Bank(BankMsg::Send {
+                            to_address: "treasury".to_string(),
+                            amount: Vec::from([Coin {
+                                denom: "out_denom".to_string(),
+                                amount: Uint128::new(1000000000000)
+                            }])
+                        }),
+                        gas_limit: None,
+                        reply_on: ReplyOn::Never
+                    },
+                    SubMsg {
+                        id: 0,
+                        msg: Bank(BankMsg::Send {
+                            to_address: "treasury".to_string(),
+                            amount: Vec::from([Coin {
+                                denom: "fee".to_string(),
+                                amount: Uint128::new(100)
+                            }])
+                        }),
+                        gas_limit: None,
+                        reply_on: ReplyOn::Never
+                    }
+                ]
+            );
 
-            // cancel the stream
-            let info = mock_info("protocol_admin", &[]);
+            //random operator can't exit
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_006);
-            execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap();
+            env.block.time = start.plus_seconds(2_250_000);
+            let info = mock_info("random", &[]);
+            let res =
+                execute_exit_cancelled(deps.as_mut(), env, info, 1, Some("creator1".to_string()))
+                    .unwrap_err();
+            assert_eq!(res, ContractError::Unauthorized {});
 
-            // can't resume if cancelled
-            let info = mock_info("protocol_admin", &[]);
+            // exit
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_007);
-            let res = execute_resume_stream(deps.as_mut(), env, info, 1).unwrap_err();
-            assert_eq!(res, ContractError::StreamIsCancelled {});
+            env.block.time = start.plus_seconds(3_000_000);
+            let info = mock_info("creator1", &[]);
+            let res = execute_exit_cancelled(deps.as_mut(), env, info, 1, None).unwrap();
+            let msg = res.messages.first().unwrap();
+            assert_eq!(
+                msg.msg,
+                Bank(BankMsg::Send {
+                    to_address: "creator1".to_string(),
+                    amount: vec![Coin::new(2000000000000, "in")]
+                })
+            );
         }
 
         #[test]
-        fn test_cancel_protocol_admin() {
+        fn test_batch_exit_cancelled() {
             let treasury = Addr::unchecked("treasury");
             let start = Timestamp::from_seconds(1_000_000);
             let end = Timestamp::from_seconds(5_000_000);
-            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_supply = Uint256::from(1_000_000u128);
             let out_denom = "out_denom";
 
             // instantiate
@@ -3051,6 +11882,11 @@ mod test_module {
                 fee_collector: "collector".to_string(),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
             };
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -3077,92 +11913,118 @@ mod test_module {
                 start,
                 end,
                 None,
+                None,
+                None,
+                None,
             )
             .unwrap();
 
-            // subscription
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(0);
-            let funds = Coin::new(2_000_000_000_000, "in");
-            let info = mock_info("creator1", &[funds]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
-                stream_id: 1,
-                operator_target: None,
-                operator: Some("operator".to_string()),
-            };
-            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-
-            // non protocol admin can't cancel
-            let info = mock_info("non_protocol_admin", &[]);
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_000);
-            let err = execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap_err();
-            assert_eq!(err, ContractError::Unauthorized {});
+            // three subscriptions
+            for (subscriber, amount) in [("sub1", 1_000), ("sub2", 2_000), ("sub3", 3_000)] {
+                let mut env = mock_env();
+                env.block.time = start;
+                let info = mock_info(subscriber, &[Coin::new(amount, "in")]);
+                let msg = crate::msg::ExecuteMsg::Subscribe {
+                    stream_id: 1,
+                    operator_target: None,
+                    operator: None,
+                    min_shares_out: None,
+                };
+                execute(deps.as_mut(), env, info, msg).unwrap();
+            }
 
-            // cant cancel without pause
-            let info = mock_info("protocol_admin", &[]);
+            // can't batch exit an active stream
             let mut env = mock_env();
             env.block.time = start.plus_seconds(1_000_000);
-            let err = execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap_err();
-            assert_eq!(err, ContractError::StreamNotPaused {});
+            let msg = crate::msg::ExecuteMsg::BatchExitCancelled {
+                stream_id: 1,
+                limit: None,
+            };
+            let err = execute(deps.as_mut(), env, mock_info("anyone", &[]), msg).unwrap_err();
+            assert_eq!(err, ContractError::StreamNotCancelled {});
 
-            // pause
+            // pause and cancel
             let mut env = mock_env();
             env.block.time = start.plus_seconds(2_000_000);
             let info = mock_info("protocol_admin", &[]);
-            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+            execute_pause_stream(deps.as_mut(), env.clone(), info.clone(), 1).unwrap();
+            execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap();
 
-            //cancel
-            let info = mock_info("protocol_admin", &[]);
+            // anyone can refund positions in batches
             let mut env = mock_env();
             env.block.time = start.plus_seconds(2_500_000);
-            let response = execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap();
-            //out_tokens and the creation fee are sent back to the treasury upon cancellation
+            let msg = crate::msg::ExecuteMsg::BatchExitCancelled {
+                stream_id: 1,
+                limit: Some(2),
+            };
+            let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap();
             assert_eq!(
-                response.messages,
-                [
-                    SubMsg {
-                        id: 0,
-                        msg: Bank(BankMsg::Send {
-                            to_address: "treasury".to_string(),
-                            amount: Vec::from([Coin {
-                                denom: "out_denom".to_string(),
-                                amount: Uint128::new(1000000000000)
-                            }])
-                        }),
-                        gas_limit: None,
-                        reply_on: ReplyOn::Never
-                    },
-                    SubMsg {
-                        id: 0,
-                        msg: Bank(BankMsg::Send {
-                            to_address: "treasury".to_string(),
-                            amount: Vec::from([Coin {
-                                denom: "fee".to_string(),
-                                amount: Uint128::new(100)
-                            }])
-                        }),
-                        gas_limit: None,
-                        reply_on: ReplyOn::Never
-                    }
+                res.messages,
+                [("sub1", 1_000u128), ("sub2", 2_000)].map(|(owner, amount)| {
+                    SubMsg::new(BankMsg::Send {
+                        to_address: owner.to_string(),
+                        amount: vec![Coin::new(amount, "in")],
+                    })
+                })
+            );
+            assert_eq!(res.events.len(), 2);
+            assert_eq!(
+                res.attributes,
+                vec![
+                    attr("action", "batch_exit_cancelled"),
+                    attr("stream_id", "1"),
+                    attr("exited", "2"),
+                    attr("refunded", "3000"),
                 ]
             );
+            assert!(crate::state::POSITIONS
+                .may_load(&deps.storage, (1, &Addr::unchecked("sub1")))
+                .unwrap()
+                .is_none());
 
-            // can't cancel cancelled stream
-            let info = mock_info("protocol_admin", &[]);
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(2_500_000);
-            let response = execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap_err();
-            assert_eq!(response, ContractError::StreamIsCancelled {});
+            let msg = crate::msg::ExecuteMsg::BatchExitCancelled {
+                stream_id: 1,
+                limit: Some(2),
+            };
+            let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap();
+            assert_eq!(
+                res.messages,
+                [SubMsg::new(BankMsg::Send {
+                    to_address: "sub3".to_string(),
+                    amount: vec![Coin::new(3_000, "in")],
+                })]
+            );
+
+            // nothing left to refund
+            let msg = crate::msg::ExecuteMsg::BatchExitCancelled {
+                stream_id: 1,
+                limit: None,
+            };
+            let res = execute(deps.as_mut(), env, mock_info("anyone", &[]), msg).unwrap();
+            assert!(res.messages.is_empty());
+            assert_eq!(res.attributes[2], attr("exited", "0"));
         }
+    }
+    mod threshold {
+        use crate::{
+            killswitch::{execute_cancel_stream_with_threshold, execute_exit_cancelled},
+            threshold::ThresholdError,
+        };
+
+        // Create a stream with a threshold
+        // Subscribe to the stream
+        use super::*;
 
         #[test]
-        fn test_withdraw_pause() {
+        fn test_threshold_reached() {
             let treasury = Addr::unchecked("treasury");
             let start = Timestamp::from_seconds(1_000_000);
             let end = Timestamp::from_seconds(5_000_000);
-            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_supply = Uint256::from(500u128);
             let out_denom = "out_denom";
+            let in_denom = "in_denom";
+
+            // threshold = 500*0.5 / 1-0.01 =252.5
 
             // instantiate
             let mut deps = mock_dependencies();
@@ -3176,7 +12038,12 @@ mod test_module {
                 exit_fee_percent: Decimal256::percent(1),
                 fee_collector: "collector".to_string(),
                 protocol_admin: "protocol_admin".to_string(),
-                accepted_in_denom: "in".to_string(),
+                accepted_in_denom: in_denom.to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
             };
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -3184,7 +12051,7 @@ mod test_module {
             let mut env = mock_env();
             env.block.time = Timestamp::from_seconds(0);
             let info = mock_info(
-                "creator1",
+                "creator",
                 &[
                     Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
                     Coin::new(100, "fee"),
@@ -3197,164 +12064,249 @@ mod test_module {
                 treasury.to_string(),
                 "test".to_string(),
                 Some("https://sample.url".to_string()),
-                "in".to_string(),
+                in_denom.to_string(),
                 out_denom.to_string(),
                 out_supply,
                 start,
                 end,
+                Some(Uint256::from(250u128)),
+                None,
+                None,
                 None,
             )
             .unwrap();
 
             // subscription
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(0);
-            let funds = Coin::new(2_000_000_000_000, "in");
-            let info = mock_info("creator1", &[funds.clone()]);
+            env.block.time = start;
+            let funds = Coin::new(252, "in_denom");
+            let info = mock_info("subscriber", &[funds]);
             let msg = crate::msg::ExecuteMsg::Subscribe {
                 stream_id: 1,
                 operator_target: None,
                 operator: Some("operator".to_string()),
+                min_shares_out: None,
             };
             let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
-            // withdraw with cap
+            // Threshold should be reached
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(5000);
-            let info = mock_info("creator1", &[]);
-            let cap = Uint256::from(25_000_000u128);
-            let msg = crate::msg::ExecuteMsg::Withdraw {
-                stream_id: 1,
-                cap: Some(cap),
-                operator_target: None,
-            };
-            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+            env.block.time = end.plus_seconds(1);
 
-            let position =
-                query_position(deps.as_ref(), mock_env(), 1, "creator1".to_string()).unwrap();
-            assert_eq!(position.in_balance, Uint256::from(1_997_475_000_000u128));
-            assert_eq!(position.spent, Uint256::from(2_500_000_000u128));
-            assert_eq!(position.purchased, Uint256::from(1_250_000_000u128));
-            // first fund amount should be equal to in_balance + spent + cap
+            // Exit should be possible
+            // Since there is only one subscriber all out denom should be sent to subscriber
+            // In calculations we are always rounding down that one token will be left in the stream
+            // Asuming token is 6 decimals
+            // This amount could be considered as insignificant
+            let info = mock_info("subscriber", &[]);
+            let res =
+                execute_exit_stream(deps.as_mut(), env.clone(), info, 1, None, None, false, None)
+                    .unwrap();
             assert_eq!(
-                position.in_balance + position.spent + cap,
-                Uint256::from_str(funds.amount.to_string().as_str()).unwrap()
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber".to_string(),
+                    amount: vec![Coin::new(499, "out_denom")],
+                })],
             );
 
-            // can't withdraw pause
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(6000);
-            let info = mock_info("creator1", &[]);
-            let err = execute_withdraw_paused(deps.as_mut(), env, info, 1, None, None).unwrap_err();
-            assert_eq!(err, ContractError::StreamNotPaused {});
+            // Creator finalizes the stream
+            let info = mock_info("treasury", &[]);
+            let res = execute_finalize_stream(deps.as_mut(), env.clone(), info, 1).unwrap();
+            // Creator's revenue
+            assert_eq!(
+                res.messages[0].msg,
+                cosmwasm_std::CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![Coin::new(250, "in_denom")],
+                })
+            );
+            assert_eq!(
+                res.messages[1].msg,
+                cosmwasm_std::CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin::new(100, "fee")],
+                })
+            );
+            assert_eq!(
+                res.messages[2].msg,
+                cosmwasm_std::CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin::new(2, "in_denom")],
+                })
+            )
+        }
 
-            // pause
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(6000);
-            let info = mock_info("protocol_admin", &[]);
-            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+        #[test]
+        fn test_threshold_not_reached() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint256::from(500u128);
+            let out_denom = "out_denom";
+            let in_denom = "in_denom";
+
+            // threshold = 500*0.5 / 1-0.01 =252.5
 
+            // instantiate
+            let mut deps = mock_dependencies();
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(6500);
-            let stream1_old = query_stream(deps.as_ref(), env, 1).unwrap();
-            //Unauthorized check
-            let info = mock_info("random", &[]);
+            env.block.height = 0;
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: in_denom.to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(7000);
-            let res = execute_withdraw_paused(
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator",
+                &[
+                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
                 deps.as_mut(),
                 env,
                 info,
-                1,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                in_denom.to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                Some(500u128.into()),
+                None,
+                None,
                 None,
-                Some("creator1".to_string()),
             )
-            .unwrap_err();
+            .unwrap();
 
-            assert_eq!(res, ContractError::Unauthorized {});
-            //Cap exceeds in balance check
+            // Subscription 1
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(7000);
-            let info = mock_info("creator1", &[]);
-            let res = execute_withdraw_paused(
-                deps.as_mut(),
-                env,
-                info,
-                1,
-                Some(Uint256::from(2_000_000_000_000u128 + 1u128)),
-                None,
-            )
-            .unwrap_err();
-            assert_eq!(
-                res,
-                ContractError::WithdrawAmountExceedsBalance(Uint256::from(2_000_000_000_001u128))
-            );
-            // Withdraw cap is zero
+            env.block.time = start;
+            let funds = Coin::new(250, "in_denom");
+            let info = mock_info("subscriber", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: Some("operator".to_string()),
+                min_shares_out: None,
+            };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+            // Subscription 2
+            let funds = Coin::new(1, "in_denom");
+            let info = mock_info("subscriber2", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: Some("operator".to_string()),
+                min_shares_out: None,
+            };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+            // Set time to the end of the stream
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(7000);
-            let info = mock_info("creator1", &[]);
+            env.block.time = end.plus_seconds(1);
+
+            // Exit should not be possible
+            let info = mock_info("subscriber", &[]);
             let res =
-                execute_withdraw_paused(deps.as_mut(), env, info, 1, Some(Uint256::zero()), None)
+                execute_exit_stream(deps.as_mut(), env.clone(), info, 1, None, None, false, None)
                     .unwrap_err();
-            assert_eq!(res, ContractError::InvalidWithdrawAmount {});
+            assert_eq!(
+                res,
+                ContractError::ThresholdError(ThresholdError::ThresholdNotReached {})
+            );
 
-            //withdraw with cap
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(7000);
-            let info = mock_info("creator1", &[]);
-            let cap = Uint256::from(25_000_000u128);
-            execute_withdraw_paused(deps.as_mut(), env, info, 1, Some(cap), None).unwrap();
+            // Finalize should not be possible
+            let info = mock_info("treasury", &[]);
+            let res = execute_finalize_stream(deps.as_mut(), env.clone(), info, 1).unwrap_err();
+            assert_eq!(
+                res,
+                ContractError::ThresholdError(ThresholdError::ThresholdNotReached {})
+            );
 
-            // withdraw after pause
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(7000);
-            let info = mock_info("creator1", &[]);
-            let res = execute_withdraw_paused(deps.as_mut(), env, info, 1, None, None).unwrap();
+            // Subscriber one executes exit cancelled before creator cancels stream
+            let info = mock_info("subscriber", &[]);
+            let res = execute_exit_cancelled(deps.as_mut(), env.clone(), info, 1, None).unwrap();
             assert_eq!(
                 res.messages,
-                vec![SubMsg {
-                    id: 0,
-                    msg: BankMsg::Send {
-                        to_address: "creator1".to_string(),
-                        amount: vec![Coin {
-                            denom: "in".to_string(),
-                            amount: Uint128::new(1996950006258),
-                        }],
-                    }
-                    .into(),
-                    gas_limit: None,
-                    reply_on: ReplyOn::Never,
-                }]
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber".to_string(),
+                    amount: vec![Coin::new(250, "in_denom")],
+                })]
+            );
+            // Creator threshold cancels the stream
+            let info = mock_info("treasury", &[]);
+            let res =
+                execute_cancel_stream_with_threshold(deps.as_mut(), env.clone(), info, 1).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![
+                    // Out denom refunded
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "treasury".to_string(),
+                        amount: vec![Coin::new(500, "out_denom")],
+                    }),
+                    // Creation fee refunded without a penalty
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "treasury".to_string(),
+                        amount: vec![Coin::new(100, "fee")],
+                    }),
+                ]
             );
+            // Creator can not finalize the stream
+            let info = mock_info("treasury", &[]);
+            let res = execute_finalize_stream(deps.as_mut(), env.clone(), info, 1).unwrap_err();
+            assert_eq!(res, ContractError::StreamKillswitchActive {});
 
-            // stream not updated
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(8000);
-            let stream1_new = query_stream(deps.as_ref(), env, 1).unwrap();
-            // dist_index not updated
-            assert_eq!(stream1_old.dist_index, stream1_new.dist_index);
-            assert_eq!(stream1_new.in_supply, Uint256::zero());
-            assert_eq!(stream1_new.shares, Uint256::zero());
+            // Creator can not cancel the stream again
+            let info = mock_info("treasury", &[]);
+            let res = execute_cancel_stream_with_threshold(deps.as_mut(), env.clone(), info, 1)
+                .unwrap_err();
+            assert_eq!(res, ContractError::StreamKillswitchActive {});
 
-            // position updated
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(8001);
-            let position =
-                query_position(deps.as_ref(), mock_env(), 1, "creator1".to_string()).unwrap();
-            // in_balance updated
-            assert_eq!(position.in_balance, Uint256::zero());
-            assert_eq!(position.spent, Uint256::from(2_999_993_742u128));
-            assert_eq!(position.purchased, Uint256::from(1_499_999_998u128));
-            assert_eq!(position.shares, Uint256::zero());
+            // Subscriber 2 executes exit cancelled after creator cancels stream
+            let info = mock_info("subscriber2", &[]);
+            let res = execute_exit_cancelled(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+            assert_eq!(
+                // In denom refunded
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber2".to_string(),
+                    amount: vec![Coin::new(1, "in_denom")],
+                })]
+            );
         }
 
         #[test]
-        fn test_resume() {
+        fn test_threshold_cancel() {
             let treasury = Addr::unchecked("treasury");
             let start = Timestamp::from_seconds(1_000_000);
             let end = Timestamp::from_seconds(5_000_000);
-            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_supply = Uint256::from(500u128);
             let out_denom = "out_denom";
+            let in_denom = "in_denom";
+
+            // threshold = 500*0.5 / 1-0.01 =252.5
 
             // instantiate
             let mut deps = mock_dependencies();
@@ -3368,7 +12320,12 @@ mod test_module {
                 exit_fee_percent: Decimal256::percent(1),
                 fee_collector: "collector".to_string(),
                 protocol_admin: "protocol_admin".to_string(),
-                accepted_in_denom: "in".to_string(),
+                accepted_in_denom: in_denom.to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
             };
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -3376,7 +12333,7 @@ mod test_module {
             let mut env = mock_env();
             env.block.time = Timestamp::from_seconds(0);
             let info = mock_info(
-                "creator1",
+                "creator",
                 &[
                     Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
                     Coin::new(100, "fee"),
@@ -3389,64 +12346,107 @@ mod test_module {
                 treasury.to_string(),
                 "test".to_string(),
                 Some("https://sample.url".to_string()),
-                "in".to_string(),
+                in_denom.to_string(),
                 out_denom.to_string(),
                 out_supply,
                 start,
                 end,
+                Some(1_000u128.into()),
+                None,
+                None,
                 None,
             )
             .unwrap();
 
-            // first subscription
+            // Subscription 1
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_000);
-            let funds = Coin::new(3_000, "in");
-            let info = mock_info("position1", &[funds]);
+            env.block.time = start;
+            let funds = Coin::new(250, "in_denom");
+            let info = mock_info("subscriber", &[funds]);
             let msg = crate::msg::ExecuteMsg::Subscribe {
                 stream_id: 1,
                 operator_target: None,
-                operator: None,
+                operator: Some("operator".to_string()),
+                min_shares_out: None,
             };
-            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-            //cant resume if not paused
+            // Subscription 2
+            let funds = Coin::new(500, "in_denom");
+            let info = mock_info("subscriber2", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: Some("operator".to_string()),
+                min_shares_out: None,
+            };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+            // Can not cancel stream before it ends
             let mut env = mock_env();
             env.block.time = start.plus_seconds(1_000_000);
-            let res = sudo_resume_stream(deps.as_mut(), env, 1).unwrap_err();
-            assert_eq!(res, ContractError::StreamNotPaused {});
+            let res = execute_cancel_stream_with_threshold(
+                deps.as_mut(),
+                env,
+                mock_info("treasury", &[]),
+                1,
+            )
+            .unwrap_err();
+            assert_eq!(res, ContractError::StreamNotEnded {});
 
-            // pause
-            let info = mock_info("protocol_admin", &[]);
+            // Set block to the end of the stream
             let mut env = mock_env();
-            let pause_date = start.plus_seconds(2_000_000);
-            env.block.time = pause_date;
-            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+            env.block.time = end.plus_seconds(1);
 
-            // resume
-            let mut env = mock_env();
-            let resume_date = start.plus_seconds(3_000_000);
-            env.block.time = resume_date;
-            sudo_resume_stream(deps.as_mut(), env, 1).unwrap();
+            // Non creator can't cancel stream
+            let res = execute_cancel_stream_with_threshold(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("random", &[]),
+                1,
+            )
+            .unwrap_err();
+            assert_eq!(res, ContractError::Unauthorized {});
 
-            // new end date is correct
-            let new_end_date = end.plus_nanos(resume_date.nanos() - pause_date.nanos());
-            let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
-            assert_eq!(stream.end_time, new_end_date);
+            // Creator can cancel stream, the creation fee is refunded without a penalty
+            let res = execute_cancel_stream_with_threshold(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("treasury", &[]),
+                1,
+            )
+            .unwrap();
+            assert_eq!(
+                res.messages,
+                vec![
+                    SubMsg::new(BankMsg::Send {
+                        to_address: treasury.to_string(),
+                        amount: vec![Coin::new(500, out_denom)],
+                    }),
+                    SubMsg::new(BankMsg::Send {
+                        to_address: treasury.to_string(),
+                        amount: vec![Coin::new(100, "fee")],
+                    }),
+                ]
+            );
+            assert!(res
+                .attributes
+                .contains(&attr("creation_fee_refunded", "100")));
+            // Query stream should return stream with is_cancelled = true
+            let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+            assert_eq!(stream.status, Status::Cancelled);
         }
 
         #[test]
-        fn test_sudo_pause_stream() {
+        fn test_threshold_cancel_penalty() {
             let treasury = Addr::unchecked("treasury");
             let start = Timestamp::from_seconds(1_000_000);
             let end = Timestamp::from_seconds(5_000_000);
-            let out_supply = Uint256::from(1_000_000_000_000u128);
+            let out_supply = Uint256::from(500u128);
             let out_denom = "out_denom";
+            let in_denom = "in_denom";
 
             // instantiate
             let mut deps = mock_dependencies();
-            let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(0);
             let msg = crate::msg::InstantiateMsg {
                 min_stream_seconds: Uint64::new(1000),
                 min_seconds_until_start_time: Uint64::new(0),
@@ -3455,7 +12455,12 @@ mod test_module {
                 exit_fee_percent: Decimal256::percent(1),
                 fee_collector: "collector".to_string(),
                 protocol_admin: "protocol_admin".to_string(),
-                accepted_in_denom: "in".to_string(),
+                accepted_in_denom: in_denom.to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: Some(Decimal256::percent(25)),
+                permissioned_creation: None,
+                config_timelock: None,
             };
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
@@ -3463,7 +12468,7 @@ mod test_module {
             let mut env = mock_env();
             env.block.time = Timestamp::from_seconds(0);
             let info = mock_info(
-                "creator1",
+                "creator",
                 &[
                     Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
                     Coin::new(100, "fee"),
@@ -3476,151 +12481,132 @@ mod test_module {
                 treasury.to_string(),
                 "test".to_string(),
                 Some("https://sample.url".to_string()),
-                "in".to_string(),
+                in_denom.to_string(),
                 out_denom.to_string(),
                 out_supply,
                 start,
                 end,
+                Some(1_000u128.into()),
+                None,
+                None,
                 None,
             )
             .unwrap();
 
+            // Subscription below threshold
             let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(500_000);
-            let res = sudo_pause_stream(deps.as_mut(), env, 1).unwrap_err();
-            assert_eq!(res, ContractError::StreamNotStarted {});
-
-            let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(6_000_000);
-            let res = sudo_pause_stream(deps.as_mut(), env, 1).unwrap_err();
-            assert_eq!(res, ContractError::StreamEnded {});
-
-            let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(3_000_000);
-            let res = sudo_pause_stream(deps.as_mut(), env, 1).unwrap();
-            assert_eq!(
-                res,
-                Response::new()
-                    .add_attribute("action", "sudo_pause_stream")
-                    .add_attribute("stream_id", "1")
-                    .add_attribute("is_paused", "true")
-                    .add_attribute("pause_date", "3000000.000000000")
-            );
-
-            let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(4_000_000);
-            let res = sudo_pause_stream(deps.as_mut(), env, 1).unwrap_err();
-            assert_eq!(res, ContractError::StreamKillswitchActive {});
-        }
-
-        #[test]
-        fn test_range_queries() {
-            let treasury = Addr::unchecked("treasury");
-            let start = Timestamp::from_seconds(2000);
-            let end = Timestamp::from_seconds(1_000_000);
-            let out_supply = Uint256::from(1_000_000u128);
-            let out_denom = "out_denom";
-
-            // instantiate
-            let mut deps = mock_dependencies();
-            let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(100);
-            let msg = crate::msg::InstantiateMsg {
-                min_stream_seconds: Uint64::new(1000),
-                min_seconds_until_start_time: Uint64::new(1000),
-                stream_creation_denom: "fee".to_string(),
-                stream_creation_fee: Uint128::new(100),
-                exit_fee_percent: Decimal256::percent(1),
-                fee_collector: "collector".to_string(),
-                protocol_admin: "protocol_admin".to_string(),
-                accepted_in_denom: "in".to_string(),
+            env.block.time = start;
+            let info = mock_info("subscriber", &[Coin::new(250, "in_denom")]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+                min_shares_out: None,
             };
-            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+            execute(deps.as_mut(), env, info, msg).unwrap();
 
-            // create stream
+            // Penalty is kept from the creation fee
             let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(1);
-            let info = mock_info(
-                "creator1",
-                &[
-                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
-                    Coin::new(100, "fee"),
-                ],
-            );
-            //first stream
-            execute_create_stream(
-                deps.as_mut(),
-                env.clone(),
-                info.clone(),
-                treasury.to_string(),
-                "test".to_string(),
-                Some("https://sample.url".to_string()),
-                "in".to_string(),
-                out_denom.to_string(),
-                out_supply,
-                start,
-                end,
-                None,
-            )
-            .unwrap();
-            //second stream
-            execute_create_stream(
+            env.block.time = end.plus_seconds(1);
+            let res = execute_cancel_stream_with_threshold(
                 deps.as_mut(),
                 env,
-                info,
-                treasury.to_string(),
-                "test".to_string(),
-                Some("https://sample.url".to_string()),
-                "in".to_string(),
-                out_denom.to_string(),
-                out_supply,
-                start,
-                end,
-                None,
+                mock_info("treasury", &[]),
+                1,
             )
             .unwrap();
+            assert_eq!(
+                res.messages,
+                vec![
+                    SubMsg::new(BankMsg::Send {
+                        to_address: treasury.to_string(),
+                        amount: vec![Coin::new(500, out_denom)],
+                    }),
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "collector".to_string(),
+                        amount: vec![Coin::new(25, "fee")],
+                    }),
+                    SubMsg::new(BankMsg::Send {
+                        to_address: treasury.to_string(),
+                        amount: vec![Coin::new(75, "fee")],
+                    }),
+                ]
+            );
+            assert!(res.attributes.contains(&attr("penalty", "25")));
+            assert!(res
+                .attributes
+                .contains(&attr("creation_fee_refunded", "75")));
+        }
+    }
 
-            let res = list_streams(deps.as_ref(), None, None).unwrap();
-            assert_eq!(res.streams.len(), 2);
-
-            // first subscription to first stream
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(100);
-            let info = mock_info("creator1", &[Coin::new(1_000_000, "in")]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
-                stream_id: 1,
-                operator_target: None,
-                operator: None,
-            };
-            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+    mod fee_swap {
+        use super::*;
+        use crate::contract::reply;
+        use crate::exit_swap::{
+            ExitSwap, PendingExitSwap, SwapRoute, EXIT_SWAP_PURCHASED_REPLY_ID,
+            EXIT_SWAP_REFUNDED_REPLY_ID, PENDING_EXIT_SWAPS,
+        };
+        use crate::fee_swap::{PendingFeeSwap, FEE_SWAP_REPLY_ID, PENDING_FEE_SWAP};
+        use crate::state::FeeSwap;
+        use crate::swap_subscribe::{PENDING_SWAP_SUBSCRIPTION, SWAP_SUBSCRIBE_REPLY_ID};
+        use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
+        use cosmwasm_std::{
+            from_json, to_json_binary, Binary, ContractResult, Empty, OwnedDeps, Querier,
+            QuerierResult, QueryRequest, Reply, SubMsgResponse, SubMsgResult, SystemResult,
+        };
+        use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+            MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, SwapAmountInRoute,
+        };
+        use osmosis_std::types::osmosis::twap::v1beta1::{
+            ArithmeticTwapToNowRequest, ArithmeticTwapToNowResponse,
+        };
+        use std::marker::PhantomData;
 
-            // second subscription to first stream
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(100);
-            let info = mock_info("creator2", &[Coin::new(1_000_000, "in")]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
-                stream_id: 1,
-                operator_target: None,
-                operator: None,
-            };
-            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        // Answers pool TWAPs over the expected window, everything else goes to the mock querier
+        struct TwapQuerier {
+            base: MockQuerier,
+            arithmetic_twap: String,
+            start_time: Timestamp,
+        }
 
-            let res = list_positions(deps.as_ref(), 1, None, None).unwrap();
-            assert_eq!(res.positions.len(), 2);
+        impl Querier for TwapQuerier {
+            fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+                let request: QueryRequest<Empty> = from_json(bin_request).unwrap();
+                match request {
+                    QueryRequest::Stargate { path, data }
+                        if path == "/osmosis.twap.v1beta1.Query/ArithmeticTwapToNow" =>
+                    {
+                        let request = ArithmeticTwapToNowRequest::try_from(data).unwrap();
+                        assert_eq!(
+                            request.start_time.unwrap().seconds as u64,
+                            self.start_time.seconds()
+                        );
+                        SystemResult::Ok(ContractResult::Ok(
+                            to_json_binary(&ArithmeticTwapToNowResponse {
+                                arithmetic_twap: self.arithmetic_twap.clone(),
+                            })
+                            .unwrap(),
+                        ))
+                    }
+                    _ => self.base.raw_query(bin_request),
+                }
+            }
         }
 
-        #[test]
-        fn test_exit_cancel() {
-            let treasury = Addr::unchecked("treasury");
-            let start = Timestamp::from_seconds(1_000_000);
-            let end = Timestamp::from_seconds(5_000_000);
-            let out_supply = Uint256::from(1_000_000_000_000u128);
-            let out_denom = "out_denom";
+        fn fee_swap() -> FeeSwap {
+            FeeSwap {
+                target_denom: "usdc".to_string(),
+                routes: vec![SwapAmountInRoute {
+                    pool_id: 7,
+                    token_out_denom: "usdc".to_string(),
+                }],
+                max_slippage: Decimal256::percent(5),
+                twap_window: Uint64::new(3_600),
+            }
+        }
 
-            // instantiate
-            let mut deps = mock_dependencies();
-            let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(0);
+        // instantiates, enables fee swap and runs a stream until after its end
+        fn setup<Q: Querier>(deps: &mut OwnedDeps<MockStorage, MockApi, Q>) {
             let msg = crate::msg::InstantiateMsg {
                 min_stream_seconds: Uint64::new(1000),
                 min_seconds_until_start_time: Uint64::new(0),
@@ -3630,150 +12616,500 @@ mod test_module {
                 fee_collector: "collector".to_string(),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
             };
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+            let msg = crate::msg::ExecuteMsg::UpdateFeeSwap {
+                fee_swap: Some(fee_swap()),
+            };
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("protocol_admin", &[]),
+                msg,
+            )
+            .unwrap();
 
-            // create stream
             let mut env = mock_env();
             env.block.time = Timestamp::from_seconds(0);
             let info = mock_info(
                 "creator1",
-                &[
-                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
-                    Coin::new(100, "fee"),
-                ],
+                &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
             );
             execute_create_stream(
                 deps.as_mut(),
                 env,
                 info,
-                treasury.to_string(),
+                "treasury".to_string(),
                 "test".to_string(),
-                Some("https://sample.url".to_string()),
+                None,
                 "in".to_string(),
-                out_denom.to_string(),
-                out_supply,
-                start,
-                end,
+                "out_denom".to_string(),
+                Uint256::from(1_000_000u128),
+                Timestamp::from_seconds(1_000),
+                Timestamp::from_seconds(5_000),
+                None,
+                None,
+                None,
                 None,
             )
             .unwrap();
 
-            // subscription
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(0);
-            let funds = Coin::new(2_000_000_000_000, "in");
-            let info = mock_info("creator1", &[funds]);
+            env.block.time = Timestamp::from_seconds(1_000);
             let msg = crate::msg::ExecuteMsg::Subscribe {
                 stream_id: 1,
                 operator_target: None,
-                operator: Some("operator".to_string()),
+                operator: None,
+                min_shares_out: None,
             };
-            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+            execute(
+                deps.as_mut(),
+                env,
+                mock_info("subscriber", &[Coin::new(1_000_000, "in")]),
+                msg,
+            )
+            .unwrap();
+        }
+
+        #[test]
+        fn test_update_fee_swap() {
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // random cannot update
+            let msg = crate::msg::ExecuteMsg::UpdateFeeSwap {
+                fee_swap: Some(fee_swap()),
+            };
+            let err =
+                execute(deps.as_mut(), mock_env(), mock_info("random", &[]), msg).unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+
+            // slippage must be smaller than 1
+            let msg = crate::msg::ExecuteMsg::UpdateFeeSwap {
+                fee_swap: Some(FeeSwap {
+                    max_slippage: Decimal256::one(),
+                    ..fee_swap()
+                }),
+            };
+            let info = mock_info("protocol_admin", &[]);
+            let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+            assert_eq!(err, ContractError::InvalidFeeSwap {});
+
+            // routes must end in target denom
+            let msg = crate::msg::ExecuteMsg::UpdateFeeSwap {
+                fee_swap: Some(FeeSwap {
+                    target_denom: "atom".to_string(),
+                    ..fee_swap()
+                }),
+            };
+            let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+            assert_eq!(err, ContractError::InvalidFeeSwap {});
+
+            // TWAP window can not be zero
+            let msg = crate::msg::ExecuteMsg::UpdateFeeSwap {
+                fee_swap: Some(FeeSwap {
+                    twap_window: Uint64::zero(),
+                    ..fee_swap()
+                }),
+            };
+            let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+            assert_eq!(err, ContractError::InvalidFeeSwap {});
+
+            let msg = crate::msg::ExecuteMsg::UpdateFeeSwap {
+                fee_swap: Some(fee_swap()),
+            };
+            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+            let config = query_config(deps.as_ref()).unwrap();
+            assert_eq!(config.fee_swap, Some(fee_swap()));
+
+            // disable
+            let msg = crate::msg::ExecuteMsg::UpdateFeeSwap { fee_swap: None };
+            execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+            let config = query_config(deps.as_ref()).unwrap();
+            assert_eq!(config.fee_swap, None);
+        }
+
+        #[test]
+        fn test_finalize_swaps_fee() {
+            let mut deps = OwnedDeps {
+                storage: MockStorage::default(),
+                api: MockApi::default(),
+                querier: TwapQuerier {
+                    base: MockQuerier::default(),
+                    arithmetic_twap: "0.1".to_string(),
+                    start_time: Timestamp::from_seconds(5_001 - 3_600),
+                },
+                custom_query_type: PhantomData,
+            };
+            setup(&mut deps);
 
-            // cant cancel without pause
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_000);
-            let err = sudo_cancel_stream(deps.as_mut(), env, 1).unwrap_err();
-            assert_eq!(err, ContractError::StreamNotPaused {});
+            env.block.time = Timestamp::from_seconds(5_001);
+            let res =
+                execute_finalize_stream(deps.as_mut(), env.clone(), mock_info("treasury", &[]), 1)
+                    .unwrap();
+            // swap fee is 1% of 1_000_000, min out is the output at the TWAP minus 5% slippage
+            assert_eq!(
+                res.messages[2],
+                SubMsg::reply_always(
+                    MsgSwapExactAmountIn {
+                        sender: env.contract.address.to_string(),
+                        routes: fee_swap().routes,
+                        token_in: Some(Coin::new(10_000, "in").into()),
+                        token_out_min_amount: "950".to_string(),
+                    },
+                    FEE_SWAP_REPLY_ID
+                )
+            );
+            assert_eq!(
+                PENDING_FEE_SWAP.load(deps.as_ref().storage).unwrap(),
+                PendingFeeSwap {
+                    fee: Coin::new(10_000, "in"),
+                    target_denom: "usdc".to_string(),
+                }
+            );
+        }
 
-            // pause
+        #[test]
+        fn test_finalize_without_twap() {
+            let mut deps = mock_dependencies();
+            setup(&mut deps);
+
+            // TWAP is not available, fee is sent without swapping
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(2_000_000);
-            let info = mock_info("protocol_admin", &[]);
-            execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
+            env.block.time = Timestamp::from_seconds(5_001);
+            let res =
+                execute_finalize_stream(deps.as_mut(), env, mock_info("treasury", &[]), 1).unwrap();
+            assert_eq!(
+                res.messages[2],
+                SubMsg::new(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin::new(10_000, "in")],
+                })
+            );
+            assert!(PENDING_FEE_SWAP
+                .may_load(deps.as_ref().storage)
+                .unwrap()
+                .is_none());
+        }
 
-            //can't exit before cancel
+        #[test]
+        fn test_fee_swap_reply() {
+            let mut deps = mock_dependencies();
+            setup(&mut deps);
+            let pending = PendingFeeSwap {
+                fee: Coin::new(10_000, "in"),
+                target_denom: "usdc".to_string(),
+            };
+
+            // successful swap forwards the swapped amount
+            PENDING_FEE_SWAP
+                .save(deps.as_mut().storage, &pending)
+                .unwrap();
+            let data: Binary = MsgSwapExactAmountInResponse {
+                token_out_amount: "990".to_string(),
+            }
+            .into();
+            let msg = Reply {
+                id: FEE_SWAP_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(data),
+                }),
+            };
+            let res = reply(deps.as_mut(), mock_env(), msg).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin::new(990, "usdc")],
+                })]
+            );
+            assert!(PENDING_FEE_SWAP
+                .may_load(deps.as_ref().storage)
+                .unwrap()
+                .is_none());
+
+            // failed swap falls back to the raw fee
+            PENDING_FEE_SWAP
+                .save(deps.as_mut().storage, &pending)
+                .unwrap();
+            let msg = Reply {
+                id: FEE_SWAP_REPLY_ID,
+                result: SubMsgResult::Err("slippage".to_string()),
+            };
+            let res = reply(deps.as_mut(), mock_env(), msg).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![Coin::new(10_000, "in")],
+                })]
+            );
+        }
+
+        #[test]
+        fn test_subscribe_with_swap() {
+            let mut deps = mock_dependencies();
+            setup(&mut deps);
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(2_250_000);
-            let info = mock_info("creator1", &[]);
-            let res = execute_exit_cancelled(deps.as_mut(), env, info, 1, None).unwrap_err();
-            assert_eq!(res, ContractError::StreamNotCancelled {});
+            env.block.time = Timestamp::from_seconds(2_000);
+            let routes = vec![SwapAmountInRoute {
+                pool_id: 3,
+                token_out_denom: "in".to_string(),
+            }];
+            let msg = |routes| crate::msg::ExecuteMsg::SubscribeWithSwap {
+                stream_id: 1,
+                routes,
+                min_in_amount: Uint128::new(400),
+            };
 
-            //cancel
+            // routes must end in the in denom
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber2", &[Coin::new(500, "atom")]),
+                msg(fee_swap().routes),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::InvalidSwapSubscription {});
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber2", &[Coin::new(500, "in")]),
+                msg(routes.clone()),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::InvalidSwapSubscription {});
+
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber2", &[Coin::new(500, "atom")]),
+                msg(routes.clone()),
+            )
+            .unwrap();
+            assert_eq!(
+                res.messages,
+                vec![SubMsg::reply_always(
+                    MsgSwapExactAmountIn {
+                        sender: env.contract.address.to_string(),
+                        routes: routes.clone(),
+                        token_in: Some(Coin::new(500, "atom").into()),
+                        token_out_min_amount: "400".to_string(),
+                    },
+                    SWAP_SUBSCRIBE_REPLY_ID
+                )]
+            );
+
+            // failed swap refunds the attached funds
+            let msg_err = Reply {
+                id: SWAP_SUBSCRIBE_REPLY_ID,
+                result: SubMsgResult::Err("slippage".to_string()),
+            };
+            let res = reply(deps.as_mut(), env.clone(), msg_err).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber2".to_string(),
+                    amount: vec![Coin::new(500, "atom")],
+                })]
+            );
+            assert!(PENDING_SWAP_SUBSCRIPTION
+                .may_load(deps.as_ref().storage)
+                .unwrap()
+                .is_none());
+
+            // successful swap subscribes with the output
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber2", &[Coin::new(500, "atom")]),
+                msg(routes),
+            )
+            .unwrap();
+            let data: Binary = MsgSwapExactAmountInResponse {
+                token_out_amount: "450".to_string(),
+            }
+            .into();
+            let msg_ok = Reply {
+                id: SWAP_SUBSCRIBE_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(data),
+                }),
+            };
+            reply(deps.as_mut(), env.clone(), msg_ok).unwrap();
+            let position =
+                query_position(deps.as_ref(), env, 1, "subscriber2".to_string()).unwrap();
+            assert_eq!(position.in_balance, Uint256::from(450u128));
+        }
+
+        #[test]
+        fn test_exit_swap() {
+            let mut deps = mock_dependencies();
+            setup(&mut deps);
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(2_500_000);
-            let response = sudo_cancel_stream(deps.as_mut(), env, 1).unwrap();
-            //out_tokens and the creation fee are sent back to the treasury upon cancellation
+            env.block.time = Timestamp::from_seconds(6_000);
+            let exit = |min_out_amount: u128, liquid_stake| crate::msg::ExecuteMsg::ExitStream {
+                stream_id: 1,
+                operator_target: None,
+                remote_recipient: None,
+                liquid_stake,
+                swap: Some(ExitSwap {
+                    purchased: Some(SwapRoute {
+                        routes: vec![SwapAmountInRoute {
+                            pool_id: 9,
+                            token_out_denom: "usdc".to_string(),
+                        }],
+                        min_out_amount: Uint128::new(min_out_amount),
+                    }),
+                    refunded: None,
+                }),
+            };
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber", &[]),
+                exit(0, false),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::InvalidExitSwap {});
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber", &[]),
+                exit(100, true),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::ConflictingExitDelivery {});
+
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber", &[]),
+                exit(100, false),
+            )
+            .unwrap();
+            assert!(res.attributes.contains(&attr("purchased", "1000000")));
             assert_eq!(
-                response.messages,
-                [
-                    SubMsg {
-                        id: 0,
-                        msg: Bank(BankMsg::Send {
-                            to_address: "treasury".to_string(),
-                            amount: Vec::from([Coin {
-                                denom: "out_denom".to_string(),
-                                amount: Uint128::new(1000000000000)
-                            }])
-                        }),
-                        gas_limit: None,
-                        reply_on: ReplyOn::Never
+                res.messages,
+                vec![SubMsg::reply_always(
+                    MsgSwapExactAmountIn {
+                        sender: env.contract.address.to_string(),
+                        routes: vec![SwapAmountInRoute {
+                            pool_id: 9,
+                            token_out_denom: "usdc".to_string(),
+                        }],
+                        token_in: Some(Coin::new(1_000_000, "out_denom").into()),
+                        token_out_min_amount: "100".to_string(),
                     },
-                    SubMsg {
-                        id: 0,
-                        msg: Bank(BankMsg::Send {
-                            to_address: "treasury".to_string(),
-                            amount: Vec::from([Coin {
-                                denom: "fee".to_string(),
-                                amount: Uint128::new(100)
-                            }])
-                        }),
-                        gas_limit: None,
-                        reply_on: ReplyOn::Never
-                    }
-                ]
+                    EXIT_SWAP_PURCHASED_REPLY_ID
+                )]
             );
 
-            //random operator can't exit
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(2_250_000);
-            let info = mock_info("random", &[]);
-            let res =
-                execute_exit_cancelled(deps.as_mut(), env, info, 1, Some("creator1".to_string()))
-                    .unwrap_err();
-            assert_eq!(res, ContractError::Unauthorized {});
-
-            // exit
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(3_000_000);
-            let info = mock_info("creator1", &[]);
-            let res = execute_exit_cancelled(deps.as_mut(), env, info, 1, None).unwrap();
-            let msg = res.messages.get(0).unwrap();
+            // swap output is sent to the position owner
+            let data: Binary = MsgSwapExactAmountInResponse {
+                token_out_amount: "2000".to_string(),
+            }
+            .into();
+            let msg = Reply {
+                id: EXIT_SWAP_PURCHASED_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(data),
+                }),
+            };
+            let res = reply(deps.as_mut(), env.clone(), msg).unwrap();
             assert_eq!(
-                msg.msg,
-                Bank(BankMsg::Send {
-                    to_address: "creator1".to_string(),
-                    amount: vec![Coin::new(2000000000000, "in")]
-                })
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber".to_string(),
+                    amount: vec![Coin::new(2_000, "usdc")],
+                })]
+            );
+            assert!(PENDING_EXIT_SWAPS
+                .may_load(deps.as_ref().storage, EXIT_SWAP_PURCHASED_REPLY_ID)
+                .unwrap()
+                .is_none());
+
+            // failed swap falls back to the raw tokens
+            PENDING_EXIT_SWAPS
+                .save(
+                    deps.as_mut().storage,
+                    EXIT_SWAP_REFUNDED_REPLY_ID,
+                    &PendingExitSwap {
+                        recipient: Addr::unchecked("subscriber"),
+                        token_in: Coin::new(500, "in"),
+                        token_out_denom: "usdc".to_string(),
+                    },
+                )
+                .unwrap();
+            let msg = Reply {
+                id: EXIT_SWAP_REFUNDED_REPLY_ID,
+                result: SubMsgResult::Err("slippage".to_string()),
+            };
+            let res = reply(deps.as_mut(), env, msg).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber".to_string(),
+                    amount: vec![Coin::new(500, "in")],
+                })]
             );
         }
     }
-    mod threshold {
-        use crate::{
-            killswitch::{execute_cancel_stream_with_threshold, execute_exit_cancelled},
-            threshold::ThresholdError,
-        };
 
-        // Create a stream with a threshold
-        // Subscribe to the stream
+    #[cfg(test)]
+    mod ibc {
         use super::*;
+        use crate::contract::query_validate_denom;
+        use crate::ibc::{
+            query_ibc_refunds, IbcLifecycleComplete, IBC_TRANSFERS, IBC_TRANSFER_REPLY_ID,
+        };
+        use crate::msg::SudoMsg;
+        use crate::msg::ValidateDenomResponse;
+        use crate::state::IbcTransfer;
+        use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
+        use cosmwasm_std::{
+            from_json, to_json_binary, Binary, ContractResult, Empty, OwnedDeps, Querier,
+            QuerierResult, QueryRequest, SubMsgResponse, SystemResult,
+        };
+        use osmosis_std::types::cosmos::bank::v1beta1::QuerySupplyOfResponse;
+        use osmosis_std::types::cosmos::base::v1beta1::Coin as OsmosisCoin;
+        use osmosis_std::types::ibc::applications::transfer::v1::{
+            DenomTrace, MsgTransfer, MsgTransferResponse, QueryDenomTraceResponse,
+        };
+        use std::marker::PhantomData;
 
-        #[test]
-        fn test_threshold_reached() {
-            let treasury = Addr::unchecked("treasury");
-            let start = Timestamp::from_seconds(1_000_000);
-            let end = Timestamp::from_seconds(5_000_000);
-            let out_supply = Uint256::from(500u128);
-            let out_denom = "out_denom";
-            let in_denom = "in_denom";
-
-            // threshold = 500*0.5 / 1-0.01 =252.5
+        fn ibc_transfer() -> IbcTransfer {
+            IbcTransfer {
+                channel_id: "channel-0".to_string(),
+                timeout_seconds: Uint64::new(600),
+            }
+        }
 
-            // instantiate
-            let mut deps = mock_dependencies();
-            let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(0);
+        fn setup(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>) {
             let msg = crate::msg::InstantiateMsg {
                 min_stream_seconds: Uint64::new(1000),
                 min_seconds_until_start_time: Uint64::new(0),
@@ -3782,254 +13118,392 @@ mod test_module {
                 exit_fee_percent: Decimal256::percent(1),
                 fee_collector: "collector".to_string(),
                 protocol_admin: "protocol_admin".to_string(),
-                accepted_in_denom: in_denom.to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
             };
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-            // create stream
             let mut env = mock_env();
             env.block.time = Timestamp::from_seconds(0);
             let info = mock_info(
-                "creator",
-                &[
-                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
-                    Coin::new(100, "fee"),
-                ],
+                "creator1",
+                &[Coin::new(1_000_000, "out_denom"), Coin::new(100, "fee")],
             );
             execute_create_stream(
                 deps.as_mut(),
                 env,
                 info,
-                treasury.to_string(),
+                "treasury".to_string(),
                 "test".to_string(),
-                Some("https://sample.url".to_string()),
-                in_denom.to_string(),
-                out_denom.to_string(),
-                out_supply,
-                start,
-                end,
-                Some(Uint256::from(250u128)),
+                None,
+                "in".to_string(),
+                "out_denom".to_string(),
+                Uint256::from(1_000_000u128),
+                Timestamp::from_seconds(1_000),
+                Timestamp::from_seconds(5_000),
+                None,
+                None,
+                None,
+                None,
             )
             .unwrap();
 
-            // subscription
             let mut env = mock_env();
-            env.block.time = start;
-            let funds = Coin::new(252, "in_denom");
-            let info = mock_info("subscriber", &[funds]);
+            env.block.time = Timestamp::from_seconds(1_000);
             let msg = crate::msg::ExecuteMsg::Subscribe {
                 stream_id: 1,
                 operator_target: None,
-                operator: Some("operator".to_string()),
+                operator: None,
+                min_shares_out: None,
             };
-            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+            execute(
+                deps.as_mut(),
+                env,
+                mock_info("subscriber", &[Coin::new(1_000_000, "in")]),
+                msg,
+            )
+            .unwrap();
+        }
 
-            // Threshold should be reached
+        #[test]
+        fn test_update_ibc_transfer() {
+            let mut deps = mock_dependencies();
+            setup(&mut deps);
+
+            // random cannot update
+            let msg = crate::msg::ExecuteMsg::UpdateIbcTransfer {
+                ibc_transfer: Some(ibc_transfer()),
+            };
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("random", &[]),
+                msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+
+            // timeout can not be zero
+            let invalid = crate::msg::ExecuteMsg::UpdateIbcTransfer {
+                ibc_transfer: Some(IbcTransfer {
+                    timeout_seconds: Uint64::zero(),
+                    ..ibc_transfer()
+                }),
+            };
+            let info = mock_info("protocol_admin", &[]);
+            let err = execute(deps.as_mut(), mock_env(), info.clone(), invalid).unwrap_err();
+            assert_eq!(err, ContractError::InvalidIbcTransfer {});
+
+            execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+            let config = query_config(deps.as_ref()).unwrap();
+            assert_eq!(config.ibc_transfer, Some(ibc_transfer()));
+        }
+
+        #[test]
+        fn test_ibc_exit() {
+            let mut deps = mock_dependencies();
+            setup(&mut deps);
             let mut env = mock_env();
-            env.block.time = end.plus_seconds(1);
+            env.block.time = Timestamp::from_seconds(5_001);
+            let msg = crate::msg::ExecuteMsg::ExitStream {
+                stream_id: 1,
+                operator_target: None,
+                remote_recipient: Some("cosmos1remote".to_string()),
+                liquid_stake: false,
+                swap: None,
+            };
 
-            // Exit should be possible
-            // Since there is only one subscriber all out denom should be sent to subscriber
-            // In calculations we are always rounding down that one token will be left in the stream
-            // Asuming token is 6 decimals
-            // This amount could be considered as insignificant
-            let info = mock_info("subscriber", &[]);
-            let res = execute_exit_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+            // cross-chain exits need a configured channel
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber", &[]),
+                msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::IbcTransferDisabled {});
+
+            let update = crate::msg::ExecuteMsg::UpdateIbcTransfer {
+                ibc_transfer: Some(ibc_transfer()),
+            };
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("protocol_admin", &[]),
+                update,
+            )
+            .unwrap();
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber", &[]),
+                msg,
+            )
+            .unwrap();
+            let transfer_msg = MsgTransfer {
+                source_port: "transfer".to_string(),
+                source_channel: "channel-0".to_string(),
+                token: Some(Coin::new(1_000_000, "out_denom").into()),
+                sender: env.contract.address.to_string(),
+                receiver: "cosmos1remote".to_string(),
+                timeout_height: None,
+                timeout_timestamp: env.block.time.plus_seconds(600).nanos(),
+                memo: format!(r#"{{"ibc_callback":"{}"}}"#, env.contract.address),
+            };
             assert_eq!(
                 res.messages,
-                vec![SubMsg::new(BankMsg::Send {
-                    to_address: "subscriber".to_string(),
-                    amount: vec![Coin::new(499, "out_denom")],
-                })],
+                vec![SubMsg::reply_on_success(
+                    transfer_msg,
+                    IBC_TRANSFER_REPLY_ID
+                )]
             );
 
-            // Creator finalizes the stream
-            let info = mock_info("treasury", &[]);
-            let res = execute_finalize_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap();
-            // Creator's revenue
+            // reply records the packet sequence
+            let data: Binary = MsgTransferResponse { sequence: 7 }.into();
+            let reply_msg = Reply {
+                id: IBC_TRANSFER_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(data),
+                }),
+            };
+            reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+            assert!(IBC_TRANSFERS.has(&deps.storage, ("channel-0", 7)));
+
+            // unknown packets are ignored
+            let timeout = SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+                channel: "channel-0".to_string(),
+                sequence: 8,
+            });
+            let res = sudo(deps.as_mut(), env.clone(), timeout).unwrap();
+            assert!(res.messages.is_empty());
+
+            // timed out transfer is claimable by the position owner
+            let timeout = SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+                channel: "channel-0".to_string(),
+                sequence: 7,
+            });
+            let res = sudo(deps.as_mut(), env.clone(), timeout).unwrap();
+            assert!(res.messages.is_empty());
+            assert!(!IBC_TRANSFERS.has(&deps.storage, ("channel-0", 7)));
             assert_eq!(
-                res.messages[0].msg,
-                cosmwasm_std::CosmosMsg::Bank(BankMsg::Send {
-                    to_address: "treasury".to_string(),
-                    amount: vec![Coin::new(250, "in_denom")],
-                })
+                query_ibc_refunds(deps.as_ref(), "subscriber".to_string()).unwrap(),
+                vec![Coin::new(1_000_000, "out_denom")]
             );
+
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("random", &[]),
+                crate::msg::ExecuteMsg::ClaimIbcRefunds {},
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::NoIbcRefunds {});
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber", &[]),
+                crate::msg::ExecuteMsg::ClaimIbcRefunds {},
+            )
+            .unwrap();
             assert_eq!(
-                res.messages[1].msg,
-                cosmwasm_std::CosmosMsg::Bank(BankMsg::Send {
-                    to_address: "collector".to_string(),
-                    amount: vec![Coin::new(100, "fee")],
-                })
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber".to_string(),
+                    amount: vec![Coin::new(1_000_000, "out_denom")],
+                })]
             );
-            assert_eq!(
-                res.messages[2].msg,
-                cosmwasm_std::CosmosMsg::Bank(BankMsg::Send {
-                    to_address: "collector".to_string(),
-                    amount: vec![Coin::new(2, "in_denom")],
-                })
+            // claimed only once
+            let err = execute(
+                deps.as_mut(),
+                env,
+                mock_info("subscriber", &[]),
+                crate::msg::ExecuteMsg::ClaimIbcRefunds {},
             )
+            .unwrap_err();
+            assert_eq!(err, ContractError::NoIbcRefunds {});
         }
 
         #[test]
-        fn test_threshold_not_reached() {
-            let treasury = Addr::unchecked("treasury");
-            let start = Timestamp::from_seconds(1_000_000);
-            let end = Timestamp::from_seconds(5_000_000);
-            let out_supply = Uint256::from(500u128);
-            let out_denom = "out_denom";
-            let in_denom = "in_denom";
-
-            // threshold = 500*0.5 / 1-0.01 =252.5
-
-            // instantiate
+        fn test_remote_treasury() {
             let mut deps = mock_dependencies();
-            let mut env = mock_env();
-            env.block.height = 0;
-            let msg = crate::msg::InstantiateMsg {
-                min_stream_seconds: Uint64::new(1000),
-                min_seconds_until_start_time: Uint64::new(0),
-                stream_creation_denom: "fee".to_string(),
-                stream_creation_fee: Uint128::new(100),
-                exit_fee_percent: Decimal256::percent(1),
-                fee_collector: "collector".to_string(),
-                protocol_admin: "protocol_admin".to_string(),
-                accepted_in_denom: in_denom.to_string(),
+            setup(&mut deps);
+            let msg = crate::msg::ExecuteMsg::UpdateRemoteTreasury {
+                stream_id: 1,
+                remote_treasury: Some("cosmos1dao".to_string()),
             };
-            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-            // create stream
-            let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(0);
-            let info = mock_info(
-                "creator",
-                &[
-                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
-                    Coin::new(100, "fee"),
-                ],
-            );
-            execute_create_stream(
+            // only treasury can set, and only with a configured channel
+            let err = execute(
                 deps.as_mut(),
-                env,
-                info,
-                treasury.to_string(),
-                "test".to_string(),
-                Some("https://sample.url".to_string()),
-                in_denom.to_string(),
-                out_denom.to_string(),
-                out_supply,
-                start,
-                end,
-                Some(500u128.into()),
+                mock_env(),
+                mock_info("random", &[]),
+                msg.clone(),
             )
-            .unwrap();
-
-            // Subscription 1
-            let mut env = mock_env();
-            env.block.time = start;
-            let funds = Coin::new(250, "in_denom");
-            let info = mock_info("subscriber", &[funds]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
-                stream_id: 1,
-                operator_target: None,
-                operator: Some("operator".to_string()),
-            };
-            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            let treasury = mock_info("treasury", &[]);
+            let err =
+                execute(deps.as_mut(), mock_env(), treasury.clone(), msg.clone()).unwrap_err();
+            assert_eq!(err, ContractError::IbcTransferDisabled {});
 
-            // Subscription 2
-            let funds = Coin::new(1, "in_denom");
-            let info = mock_info("subscriber2", &[funds]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
-                stream_id: 1,
-                operator_target: None,
-                operator: Some("operator".to_string()),
+            let update = crate::msg::ExecuteMsg::UpdateIbcTransfer {
+                ibc_transfer: Some(ibc_transfer()),
             };
-            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("protocol_admin", &[]),
+                update,
+            )
+            .unwrap();
+            execute(deps.as_mut(), mock_env(), treasury.clone(), msg).unwrap();
 
-            // Set time to the end of the stream
+            // finalize sends revenue over IBC
             let mut env = mock_env();
-            env.block.time = end.plus_seconds(1);
-
-            // Exit should not be possible
-            let info = mock_info("subscriber", &[]);
-            let res = execute_exit_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap_err();
+            env.block.time = Timestamp::from_seconds(5_001);
+            let res = execute_finalize_stream(deps.as_mut(), env.clone(), treasury, 1).unwrap();
+            let transfer_msg = MsgTransfer {
+                source_port: "transfer".to_string(),
+                source_channel: "channel-0".to_string(),
+                token: Some(Coin::new(990_000, "in").into()),
+                sender: env.contract.address.to_string(),
+                receiver: "cosmos1dao".to_string(),
+                timeout_height: None,
+                timeout_timestamp: env.block.time.plus_seconds(600).nanos(),
+                memo: format!(r#"{{"ibc_callback":"{}"}}"#, env.contract.address),
+            };
             assert_eq!(
-                res,
-                ContractError::ThresholdError(ThresholdError::ThresholdNotReached {})
+                res.messages[0],
+                SubMsg::reply_on_success(transfer_msg, IBC_TRANSFER_REPLY_ID)
             );
 
-            // Finalize should not be possible
-            let info = mock_info("treasury", &[]);
-            let res =
-                execute_finalize_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap_err();
+            // failed ack refunds the local treasury
+            let data: Binary = MsgTransferResponse { sequence: 1 }.into();
+            let reply_msg = Reply {
+                id: IBC_TRANSFER_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(data),
+                }),
+            };
+            reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+            let ack = SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcAck {
+                channel: "channel-0".to_string(),
+                sequence: 1,
+                ack: "error".to_string(),
+                success: false,
+            });
+            let res = sudo(deps.as_mut(), env, ack).unwrap();
+            assert!(res.messages.is_empty());
             assert_eq!(
-                res,
-                ContractError::ThresholdError(ThresholdError::ThresholdNotReached {})
+                query_ibc_refunds(deps.as_ref(), "treasury".to_string()).unwrap(),
+                vec![Coin::new(990_000, "in")]
             );
+        }
 
-            // Subscriber one executes exit cancelled before creator cancels stream
-            let info = mock_info("subscriber", &[]);
-            let res = execute_exit_cancelled(deps.as_mut(), env.clone(), info, 1, None).unwrap();
-            assert_eq!(
-                res.messages,
-                vec![SubMsg::new(BankMsg::Send {
-                    to_address: "subscriber".to_string(),
-                    amount: vec![Coin::new(250, "in_denom")],
-                })]
-            );
-            // Creator threshold cancels the stream
-            let info = mock_info("treasury", &[]);
-            let res =
-                execute_cancel_stream_with_threshold(deps.as_mut(), env.clone(), info, 1).unwrap();
+        // Answers denom trace and supply queries, everything else goes to the mock querier
+        struct DenomQuerier {
+            base: MockQuerier,
+            trace: Option<DenomTrace>,
+            supply: String,
+        }
+
+        impl Querier for DenomQuerier {
+            fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+                let request: QueryRequest<Empty> = from_json(bin_request).unwrap();
+                match request {
+                    QueryRequest::Stargate { path, .. }
+                        if path == "/ibc.applications.transfer.v1.Query/DenomTrace" =>
+                    {
+                        match &self.trace {
+                            Some(trace) => SystemResult::Ok(ContractResult::Ok(
+                                to_json_binary(&QueryDenomTraceResponse {
+                                    denom_trace: Some(trace.clone()),
+                                })
+                                .unwrap(),
+                            )),
+                            None => SystemResult::Ok(ContractResult::Err(
+                                "denomination trace not found".to_string(),
+                            )),
+                        }
+                    }
+                    QueryRequest::Stargate { path, .. }
+                        if path == "/cosmos.bank.v1beta1.Query/SupplyOf" =>
+                    {
+                        SystemResult::Ok(ContractResult::Ok(
+                            to_json_binary(&QuerySupplyOfResponse {
+                                amount: Some(OsmosisCoin {
+                                    denom: IBC_DENOM.to_string(),
+                                    amount: self.supply.clone(),
+                                }),
+                            })
+                            .unwrap(),
+                        ))
+                    }
+                    _ => self.base.raw_query(bin_request),
+                }
+            }
+        }
+
+        const IBC_DENOM: &str =
+            "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2";
+
+        fn denom_trace() -> DenomTrace {
+            DenomTrace {
+                path: "transfer/channel-0".to_string(),
+                base_denom: "uatom".to_string(),
+            }
+        }
+
+        #[test]
+        fn test_validate_ibc_denom() {
+            let mut deps = OwnedDeps {
+                storage: MockStorage::default(),
+                api: MockApi::default(),
+                querier: DenomQuerier {
+                    base: MockQuerier::default(),
+                    trace: Some(denom_trace()),
+                    supply: "1000000".to_string(),
+                },
+                custom_query_type: PhantomData,
+            };
+
+            // native denoms have no trace
+            let res = query_validate_denom(deps.as_ref(), "out_denom".to_string()).unwrap();
             assert_eq!(
-                res.messages,
-                vec![
-                    // Out denom refunded
-                    SubMsg::new(BankMsg::Send {
-                        to_address: "treasury".to_string(),
-                        amount: vec![Coin::new(500, "out_denom")],
-                    }),
-                ]
+                res,
+                ValidateDenomResponse {
+                    denom: "out_denom".to_string(),
+                    path: None,
+                    base_denom: None,
+                }
             );
-            // Creator can not finalize the stream
-            let info = mock_info("treasury", &[]);
-            let res =
-                execute_finalize_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap_err();
-            assert_eq!(res, ContractError::StreamKillswitchActive {});
-
-            // Creator can not cancel the stream again
-            let info = mock_info("treasury", &[]);
-            let res = execute_cancel_stream_with_threshold(deps.as_mut(), env.clone(), info, 1)
-                .unwrap_err();
-            assert_eq!(res, ContractError::StreamKillswitchActive {});
 
-            // Subscriber 2 executes exit cancelled after creator cancels stream
-            let info = mock_info("subscriber2", &[]);
-            let res = execute_exit_cancelled(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+            let res = query_validate_denom(deps.as_ref(), IBC_DENOM.to_string()).unwrap();
             assert_eq!(
-                // In denom refunded
-                res.messages,
-                vec![SubMsg::new(BankMsg::Send {
-                    to_address: "subscriber2".to_string(),
-                    amount: vec![Coin::new(1, "in_denom")],
-                })]
+                res,
+                ValidateDenomResponse {
+                    denom: IBC_DENOM.to_string(),
+                    path: Some("transfer/channel-0".to_string()),
+                    base_denom: Some("uatom".to_string()),
+                }
             );
-        }
 
-        #[test]
-        fn test_threshold_cancel() {
-            let treasury = Addr::unchecked("treasury");
-            let start = Timestamp::from_seconds(1_000_000);
-            let end = Timestamp::from_seconds(5_000_000);
-            let out_supply = Uint256::from(500u128);
-            let out_denom = "out_denom";
-            let in_denom = "in_denom";
+            // no supply on chain
+            deps.querier.supply = "0".to_string();
+            query_validate_denom(deps.as_ref(), IBC_DENOM.to_string()).unwrap_err();
 
-            // threshold = 500*0.5 / 1-0.01 =252.5
+            // unknown trace
+            deps.querier.supply = "1000000".to_string();
+            deps.querier.trace = None;
+            query_validate_denom(deps.as_ref(), IBC_DENOM.to_string()).unwrap_err();
 
-            // instantiate
-            let mut deps = mock_dependencies();
-            let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(0);
+            // stream creation rejects unknown ibc denoms
             let msg = crate::msg::InstantiateMsg {
                 min_stream_seconds: Uint64::new(1000),
                 min_seconds_until_start_time: Uint64::new(0),
@@ -4038,94 +13512,481 @@ mod test_module {
                 exit_fee_percent: Decimal256::percent(1),
                 fee_collector: "collector".to_string(),
                 protocol_admin: "protocol_admin".to_string(),
-                accepted_in_denom: in_denom.to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
             };
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
-
-            // create stream
             let mut env = mock_env();
             env.block.time = Timestamp::from_seconds(0);
             let info = mock_info(
-                "creator",
-                &[
-                    Coin::new(out_supply.to_string().parse().unwrap(), out_denom),
-                    Coin::new(100, "fee"),
-                ],
+                "creator1",
+                &[Coin::new(1_000_000, IBC_DENOM), Coin::new(100, "fee")],
+            );
+            let err = execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info.clone(),
+                "treasury".to_string(),
+                "test".to_string(),
+                None,
+                "in".to_string(),
+                IBC_DENOM.to_string(),
+                Uint256::from(1_000_000u128),
+                Timestamp::from_seconds(1_000),
+                Timestamp::from_seconds(5_000),
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap_err();
+            assert_eq!(
+                err,
+                ContractError::InvalidIbcDenom {
+                    denom: IBC_DENOM.to_string()
+                }
             );
+
+            deps.querier.trace = Some(denom_trace());
             execute_create_stream(
                 deps.as_mut(),
                 env,
                 info,
-                treasury.to_string(),
+                "treasury".to_string(),
                 "test".to_string(),
-                Some("https://sample.url".to_string()),
-                in_denom.to_string(),
-                out_denom.to_string(),
-                out_supply,
-                start,
-                end,
-                Some(1_000u128.into()),
+                None,
+                "in".to_string(),
+                IBC_DENOM.to_string(),
+                Uint256::from(1_000_000u128),
+                Timestamp::from_seconds(1_000),
+                Timestamp::from_seconds(5_000),
+                None,
+                None,
+                None,
+                None,
             )
             .unwrap();
+        }
+    }
 
-            // Subscription 1
-            let mut env = mock_env();
-            env.block.time = start;
-            let funds = Coin::new(250, "in_denom");
-            let info = mock_info("subscriber", &[funds]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
-                stream_id: 1,
-                operator_target: None,
-                operator: Some("operator".to_string()),
-            };
-            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    mod stream_contract {
+        use super::*;
+        use crate::contract::query;
+        use crate::helpers::StreamContract;
+        use crate::pool::{CreatePool, StreamPool};
+        use cosmwasm_std::{
+            Api, Binary, BlockInfo, CustomMsg, CustomQuery, Empty, Order, Querier, Storage,
+        };
+        use cw_multi_test::error::{bail, AnyResult};
+        use cw_multi_test::{
+            App, AppBuilder, AppResponse, ContractWrapper, CosmosRouter, Executor, Module,
+            Stargate, StargateMsg, StargateQuery,
+        };
+        use cw_storage_plus::Map;
+        use osmosis_std::types::osmosis::concentratedliquidity::poolmodel::concentrated::v1beta1::{
+            MsgCreateConcentratedPool, MsgCreateConcentratedPoolResponse,
+        };
+        use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+            MsgCreatePosition, MsgCreatePositionResponse, MsgTransferPositions,
+        };
+        use osmosis_std::types::osmosis::poolmanager::v1beta1::{Params, ParamsResponse};
+        use serde::de::DeserializeOwned;
 
-            // Subscription 2
-            let funds = Coin::new(500, "in_denom");
-            let info = mock_info("subscriber2", &[funds]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
-                stream_id: 1,
-                operator_target: None,
-                operator: Some("operator".to_string()),
+        #[test]
+        fn test_stream_contract_helpers() {
+            let mut app = App::new(|router, _, storage| {
+                for (addr, funds) in [
+                    (
+                        "creator1",
+                        vec![coin(1_000_000, "out_denom"), coin(100, "fee")],
+                    ),
+                    ("subscriber", vec![coin(1_000_000, "in")]),
+                ] {
+                    router
+                        .bank
+                        .init_balance(storage, &Addr::unchecked(addr), funds)
+                        .unwrap();
+                }
+            });
+            let code_id = app.store_code(Box::new(
+                ContractWrapper::new(execute, instantiate, query)
+                    .with_sudo(sudo)
+                    .with_reply(reply),
+            ));
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
             };
-            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
-            // Can not cancel stream before it ends
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(1_000_000);
-            let res = execute_cancel_stream_with_threshold(
-                deps.as_mut(),
-                env,
-                mock_info("treasury", &[]),
-                1,
-            )
-            .unwrap_err();
-            assert_eq!(res, ContractError::StreamNotEnded {});
+            let addr = app
+                .instantiate_contract(
+                    code_id,
+                    Addr::unchecked("creator"),
+                    &msg,
+                    &[],
+                    "streamswap",
+                    None,
+                )
+                .unwrap();
+            let stream_contract = StreamContract(addr);
+
+            let start = app.block_info().time.plus_seconds(100);
+            let end = start.plus_seconds(1_000);
+            let msg = stream_contract
+                .call(
+                    crate::msg::ExecuteMsg::CreateStream {
+                        treasury: "treasury".to_string(),
+                        name: "test".to_string(),
+                        url: Some("https://sample.url".to_string()),
+                        in_denom: "in".to_string(),
+                        out_denom: "out_denom".to_string(),
+                        out_supply: Uint256::from(1_000_000u128),
+                        start_time: start,
+                        end_time: end,
+                        threshold: None,
+                        revenue_vesting_duration: None,
+                        donation_percent: None,
+                        refund_excess: None,
+                    },
+                    vec![coin(1_000_000, "out_denom"), coin(100, "fee")],
+                )
+                .unwrap();
+            app.execute(Addr::unchecked("creator1"), msg).unwrap();
 
-            // Set block to the end of the stream
-            let mut env = mock_env();
-            env.block.time = end.plus_seconds(1);
+            app.update_block(|block| block.time = start);
+            let subscriber = Addr::unchecked("subscriber");
+            let msg = stream_contract
+                .subscribe(1, vec![coin(1_000_000, "in")])
+                .unwrap();
+            app.execute(subscriber.clone(), msg).unwrap();
+            let position = stream_contract
+                .query_position(&app.wrap(), 1, "subscriber")
+                .unwrap();
+            assert_eq!(position.in_balance, Uint256::from(1_000_000u128));
 
-            // Non creator can't cancel stream
-            let res = execute_cancel_stream_with_threshold(
-                deps.as_mut(),
-                env.clone(),
-                mock_info("random", &[]),
-                1,
+            app.update_block(|block| block.time = end.plus_seconds(1));
+            app.execute(subscriber.clone(), stream_contract.exit(1).unwrap())
+                .unwrap();
+            app.execute(
+                Addr::unchecked("treasury"),
+                stream_contract.finalize(1).unwrap(),
             )
-            .unwrap_err();
-            assert_eq!(res, ContractError::Unauthorized {});
+            .unwrap();
 
-            // Creator can cancel stream
-            let _res = execute_cancel_stream_with_threshold(
-                deps.as_mut(),
-                env.clone(),
-                mock_info("treasury", &[]),
-                1,
+            let stream = stream_contract.query_stream(&app.wrap(), 1).unwrap();
+            assert_eq!(stream.status, Status::Finalized);
+            let config = stream_contract.query_config(&app.wrap()).unwrap();
+            assert_eq!(config.accepted_in_denom, "in".to_string());
+            assert_eq!(
+                app.wrap().query_balance(&subscriber, "out_denom").unwrap(),
+                coin(1_000_000, "out_denom")
+            );
+            // exit fee is 1% of the spent in tokens
+            assert_eq!(
+                app.wrap().query_balance("treasury", "in").unwrap(),
+                coin(990_000, "in")
+            );
+        }
+
+        // Osmosis concentrated liquidity module, creates pools and positions with sequential ids
+        struct ConcentratedLiquidity {
+            pool_creation_fee: Coin,
+        }
+
+        const POOL_DENOMS: Map<u64, (String, String)> = Map::new("cl_pool_denoms");
+        const POSITION_OWNERS: Map<u64, Addr> = Map::new("cl_position_owners");
+        // holds the pool creation fees and the liquidity of the positions
+        const POOL_MODULE: &str = "poolmanager";
+
+        impl Module for ConcentratedLiquidity {
+            type ExecT = StargateMsg;
+            type QueryT = StargateQuery;
+            type SudoT = Empty;
+
+            fn execute<ExecC, QueryC>(
+                &self,
+                api: &dyn Api,
+                storage: &mut dyn Storage,
+                router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+                block: &BlockInfo,
+                sender: Addr,
+                msg: StargateMsg,
+            ) -> AnyResult<AppResponse>
+            where
+                ExecC: CustomMsg + DeserializeOwned + 'static,
+                QueryC: CustomQuery + DeserializeOwned + 'static,
+            {
+                let send = |storage: &mut dyn Storage, amount: Vec<Coin>| {
+                    router.execute(
+                        api,
+                        storage,
+                        block,
+                        sender.clone(),
+                        CosmosMsg::Bank(BankMsg::Send {
+                            to_address: POOL_MODULE.to_string(),
+                            amount,
+                        }),
+                    )
+                };
+                let data: Binary = match msg.type_url.as_str() {
+                    MsgCreateConcentratedPool::TYPE_URL => {
+                        let msg = MsgCreateConcentratedPool::try_from(msg.value)?;
+                        send(storage, vec![self.pool_creation_fee.clone()])?;
+                        let pool_id = POOL_DENOMS
+                            .keys(storage, None, None, Order::Descending)
+                            .next()
+                            .transpose()?
+                            .unwrap_or_default()
+                            + 1;
+                        POOL_DENOMS.save(storage, pool_id, &(msg.denom0, msg.denom1))?;
+                        MsgCreateConcentratedPoolResponse { pool_id }.into()
+                    }
+                    MsgCreatePosition::TYPE_URL => {
+                        let msg = MsgCreatePosition::try_from(msg.value)?;
+                        let (denom0, denom1) = POOL_DENOMS.load(storage, msg.pool_id)?;
+                        let tokens = msg
+                            .tokens_provided
+                            .into_iter()
+                            .map(|coin| Coin::new(coin.amount.parse().unwrap(), coin.denom))
+                            .collect::<Vec<_>>();
+                        let amount = |denom: &str| {
+                            tokens
+                                .iter()
+                                .find(|coin| coin.denom == denom)
+                                .map(|coin| coin.amount.to_string())
+                                .unwrap_or_default()
+                        };
+                        let (amount0, amount1) = (amount(&denom0), amount(&denom1));
+                        send(storage, tokens)?;
+                        let position_id = POSITION_OWNERS
+                            .keys(storage, None, None, Order::Descending)
+                            .next()
+                            .transpose()?
+                            .unwrap_or_default()
+                            + 1;
+                        POSITION_OWNERS.save(storage, position_id, &sender)?;
+                        MsgCreatePositionResponse {
+                            position_id,
+                            amount0,
+                            amount1,
+                            liquidity_created: "1".to_string(),
+                            lower_tick: msg.lower_tick,
+                            upper_tick: msg.upper_tick,
+                        }
+                        .into()
+                    }
+                    MsgTransferPositions::TYPE_URL => {
+                        let msg = MsgTransferPositions::try_from(msg.value)?;
+                        for position_id in msg.position_ids {
+                            if POSITION_OWNERS.load(storage, position_id)? != sender {
+                                bail!("sender does not own position {position_id}");
+                            }
+                            POSITION_OWNERS.save(
+                                storage,
+                                position_id,
+                                &Addr::unchecked(&msg.new_owner),
+                            )?;
+                        }
+                        Binary::default()
+                    }
+                    type_url => bail!("unexpected stargate message {type_url}"),
+                };
+                Ok(AppResponse {
+                    events: vec![],
+                    data: Some(data),
+                })
+            }
+
+            fn query(
+                &self,
+                _api: &dyn Api,
+                _storage: &dyn Storage,
+                _querier: &dyn Querier,
+                _block: &BlockInfo,
+                request: StargateQuery,
+            ) -> AnyResult<Binary> {
+                if request.path != "/osmosis.poolmanager.v1beta1.Query/Params" {
+                    bail!("unexpected stargate query {}", request.path);
+                }
+                Ok(to_json_binary(&ParamsResponse {
+                    params: Some(Params {
+                        pool_creation_fee: vec![self.pool_creation_fee.clone().into()],
+                        taker_fee_params: None,
+                        authorized_quote_denoms: vec!["in".to_string()],
+                    }),
+                })?)
+            }
+
+            fn sudo<ExecC, QueryC>(
+                &self,
+                _api: &dyn Api,
+                _storage: &mut dyn Storage,
+                _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+                _block: &BlockInfo,
+                _msg: Empty,
+            ) -> AnyResult<AppResponse> {
+                bail!("unexpected sudo")
+            }
+        }
+
+        impl Stargate for ConcentratedLiquidity {}
+
+        #[test]
+        fn test_create_pool_at_finalize() {
+            let mut app = AppBuilder::new()
+                .with_stargate(ConcentratedLiquidity {
+                    pool_creation_fee: coin(1_000, "uosmo"),
+                })
+                .build(|router, _, storage| {
+                    for (addr, funds) in [
+                        (
+                            "creator1",
+                            vec![coin(1_000_000, "out_denom"), coin(100, "fee")],
+                        ),
+                        (
+                            "treasury",
+                            vec![coin(100_000, "out_denom"), coin(1_000, "uosmo")],
+                        ),
+                        ("subscriber", vec![coin(1_000_000, "in")]),
+                    ] {
+                        router
+                            .bank
+                            .init_balance(storage, &Addr::unchecked(addr), funds)
+                            .unwrap();
+                    }
+                });
+            let code_id = app.store_code(Box::new(
+                ContractWrapper::new(execute, instantiate, query)
+                    .with_sudo(sudo)
+                    .with_reply(reply),
+            ));
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                exit_fee_percent: Decimal256::percent(1),
+                fee_collector: "collector".to_string(),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+                max_pause_duration: None,
+                max_creator_pause_duration: None,
+                creator_cancel_penalty: None,
+                permissioned_creation: None,
+                config_timelock: None,
+            };
+            let addr = app
+                .instantiate_contract(
+                    code_id,
+                    Addr::unchecked("creator"),
+                    &msg,
+                    &[],
+                    "streamswap",
+                    None,
+                )
+                .unwrap();
+            let stream_contract = StreamContract(addr);
+
+            let start = app.block_info().time.plus_seconds(100);
+            let end = start.plus_seconds(1_000);
+            let msg = stream_contract
+                .call(
+                    crate::msg::ExecuteMsg::CreateStream {
+                        treasury: "treasury".to_string(),
+                        name: "test".to_string(),
+                        url: Some("https://sample.url".to_string()),
+                        in_denom: "in".to_string(),
+                        out_denom: "out_denom".to_string(),
+                        out_supply: Uint256::from(1_000_000u128),
+                        start_time: start,
+                        end_time: end,
+                        threshold: None,
+                        revenue_vesting_duration: None,
+                        donation_percent: None,
+                        refund_excess: None,
+                    },
+                    vec![coin(1_000_000, "out_denom"), coin(100, "fee")],
+                )
+                .unwrap();
+            app.execute(Addr::unchecked("creator1"), msg).unwrap();
+            let msg = stream_contract
+                .call(
+                    crate::msg::ExecuteMsg::UpdateCreatePool {
+                        stream_id: 1,
+                        create_pool: Some(CreatePool {
+                            out_amount_clp: Uint256::from(100_000u128),
+                            tick_spacing: 100,
+                            spread_factor: Decimal256::permille(3),
+                            lower_tick: None,
+                            upper_tick: None,
+                        }),
+                    },
+                    vec![coin(100_000, "out_denom"), coin(1_000, "uosmo")],
+                )
+                .unwrap();
+            app.execute(Addr::unchecked("treasury"), msg).unwrap();
+
+            app.update_block(|block| block.time = start);
+            let subscriber = Addr::unchecked("subscriber");
+            let msg = stream_contract
+                .subscribe(1, vec![coin(1_000_000, "in")])
+                .unwrap();
+            app.execute(subscriber.clone(), msg).unwrap();
+
+            app.update_block(|block| block.time = end.plus_seconds(1));
+            app.execute(
+                Addr::unchecked("treasury"),
+                stream_contract.finalize(1).unwrap(),
             )
             .unwrap();
-            // Query stream should return stream with is_cancelled = true
-            let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
-            assert_eq!(stream.status, Status::Cancelled);
+
+            let stream_pool: Option<StreamPool> = app
+                .wrap()
+                .query_wasm_smart(
+                    stream_contract.addr(),
+                    &crate::msg::QueryMsg::StreamPool { stream_id: 1 },
+                )
+                .unwrap();
+            let stream_pool = stream_pool.unwrap();
+            assert_eq!(stream_pool.pool_id, Some(1));
+            assert_eq!(stream_pool.position_id, Some(1));
+            // the position is owned by the treasury
+            assert_eq!(
+                POSITION_OWNERS.load(app.storage(), 1).unwrap(),
+                Addr::unchecked("treasury")
+            );
+            assert_eq!(
+                POOL_DENOMS.load(app.storage(), 1).unwrap(),
+                ("out_denom".to_string(), "in".to_string())
+            );
+            // the position is seeded at the average price, 1 in per out token
+            assert_eq!(
+                app.wrap().query_all_balances(POOL_MODULE).unwrap(),
+                vec![
+                    coin(100_000, "in"),
+                    coin(100_000, "out_denom"),
+                    coin(1_000, "uosmo")
+                ]
+            );
+            // the revenue left after the exit fee and the position goes to the treasury
+            assert_eq!(
+                app.wrap().query_balance("treasury", "in").unwrap(),
+                coin(890_000, "in")
+            );
         }
     }
 }
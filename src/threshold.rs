@@ -27,6 +27,12 @@ pub const THRESHOLDS_STATE_KEY: &str = "thresholds";
 
 pub struct ThresholdState<'a>(Map<'a, u64, Threshold>);
 
+impl<'a> Default for ThresholdState<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> ThresholdState<'a> {
     pub fn new() -> Self {
         ThresholdState(Map::new(THRESHOLDS_STATE_KEY))
@@ -101,6 +107,9 @@ impl<'a> ThresholdState<'a> {
         let threshold = self.0.may_load(storage, stream_id)?;
         Ok(threshold)
     }
+    pub fn remove_threshold(&self, stream_id: u64, storage: &mut dyn Storage) {
+        self.0.remove(storage, stream_id);
+    }
 }
 
 #[cfg(test)]
@@ -110,7 +119,7 @@ mod tests {
     use super::*;
     use crate::state::Stream;
     use cosmwasm_std::testing::MockStorage;
-    use cosmwasm_std::{Addr, Decimal, Decimal256, Timestamp, Uint128};
+    use cosmwasm_std::{Addr, Decimal256, Timestamp, Uint128};
 
     #[test]
     fn test_thresholds_state() {
@@ -122,6 +131,7 @@ mod tests {
             start_time: Timestamp::from_seconds(0),
             end_time: Timestamp::from_seconds(100),
             current_streamed_price: Decimal256::percent(100),
+            price_cumulative: Decimal256::zero(),
             dist_index: Decimal256::one(),
             in_denom: "uusd".to_string(),
             last_updated: Timestamp::from_seconds(0),
@@ -147,9 +157,9 @@ mod tests {
 
         stream.spent_in = Uint256::from(1_500_000_000_000u128 - 1);
         let result = thresholds.error_if_not_reached(stream_id, &storage, &stream.clone());
-        assert_eq!(result.is_err(), true);
+        assert!(result.is_err());
         stream.spent_in = Uint256::from(1_500_000_000_000u128);
         let result = thresholds.error_if_not_reached(stream_id, &storage, &stream.clone());
-        assert_eq!(result.is_err(), false);
+        assert!(result.is_ok());
     }
 }
@@ -0,0 +1,307 @@
+use crate::contract::save_config_update;
+use crate::operations::ensure_operations;
+use crate::state::{ConfigUpdate, StreamId, CONFIG, STREAMS};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, instantiate2_address, to_json_binary, Addr, Coin, Deps, DepsMut, Env, MessageInfo, Order,
+    Reply, Response, StdResult, SubMsg, SubMsgResult, Timestamp, Uint128, Uint64, WasmMsg,
+};
+use cw_storage_plus::{Bound, Item, Map};
+use sha2::{Digest, Sha256};
+
+pub const VESTING_REPLY_ID: u64 = 8;
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Vesting curve of the purchased tokens, as in the cw-vesting contract.
+#[cw_serde]
+pub enum Schedule {
+    /// Vests linearly from the exit until the end of the vesting duration.
+    SaturatingLinear,
+    /// Vests along (seconds after the exit, vested amount) points, the last amount has to be
+    /// the total.
+    PiecewiseLinear(Vec<(u64, Uint128)>),
+}
+
+#[cw_serde]
+pub enum UncheckedDenom {
+    Native(String),
+}
+
+/// Instantiate message of the cw-vesting contract.
+#[cw_serde]
+pub struct VestingInstantiateMsg {
+    pub owner: Option<String>,
+    pub recipient: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub total: Uint128,
+    pub denom: UncheckedDenom,
+    pub schedule: Schedule,
+    pub start_time: Option<Timestamp>,
+    pub vesting_duration_seconds: u64,
+    pub unbonding_duration_seconds: u64,
+}
+
+#[cw_serde]
+pub struct VestingConfig {
+    pub schedule: Schedule,
+    /// Duration in seconds over which purchased tokens vest after the exit.
+    pub vesting_duration_seconds: Uint64,
+    /// Unbonding duration of the chain, vesting tokens can be staked.
+    pub unbonding_duration_seconds: Uint64,
+}
+
+#[cw_serde]
+pub struct StreamVesting {
+    /// Vesting code id at the time vesting was set, config updates do not affect the stream.
+    pub code_id: u64,
+    pub config: VestingConfig,
+}
+
+// Stream id -> vesting of the purchased tokens, only set for streams vesting at exit
+pub const STREAM_VESTINGS: Map<StreamId, StreamVesting> = Map::new("stream_vestings");
+// (stream id, owner) -> vesting contract instantiated at exit
+pub const VESTING: Map<(StreamId, &Addr), Addr> = Map::new("vesting");
+
+/// Exit waiting for the result of its vesting contract instantiation.
+#[cw_serde]
+pub struct PendingExit {
+    pub stream_id: StreamId,
+    pub owner: Addr,
+    pub purchased: Coin,
+}
+
+// Vesting instantiation in flight, replied to before the next one is sent
+pub const PENDING_EXITS: Item<PendingExit> = Item::new("pending_exits");
+// (stream id, owner) -> purchased tokens of a failed vesting instantiation, escrowed for
+// `RetryVesting`
+pub const FAILED_VESTINGS: Map<(StreamId, &Addr), Coin> = Map::new("failed_vestings");
+
+pub fn execute_update_vesting_code_id(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vesting_code_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_protocol_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let apply_after = save_config_update(
+        deps,
+        &env,
+        config,
+        ConfigUpdate {
+            vesting_code_id: Some(vesting_code_id),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_vesting_code_id")
+        .add_attribute(
+            "vesting_code_id",
+            vesting_code_id.map(|id| id.to_string()).unwrap_or_default(),
+        )
+        .add_attributes(apply_after.map(|t| ("apply_after", t.to_string()))))
+}
+
+/// Sets the vesting of tokens purchased in the stream, `None` sends them at exit. Only the
+/// operations role can update, before the stream starts.
+pub fn execute_update_stream_vesting(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+    vesting: Option<VestingConfig>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    ensure_operations(deps.storage, stream_id, &stream, &info.sender)?;
+    if env.block.time >= stream.start_time {
+        return Err(ContractError::StreamIsStarted {});
+    }
+    let mut res = Response::new()
+        .add_attribute("action", "update_stream_vesting")
+        .add_attribute("stream_id", stream_id.to_string());
+    match vesting {
+        Some(config) => {
+            if config.vesting_duration_seconds.is_zero() {
+                return Err(ContractError::InvalidVestingConfig {});
+            }
+            let code_id = CONFIG
+                .load(deps.storage)?
+                .vesting_code_id
+                .ok_or(ContractError::VestingDisabled {})?;
+            res = res
+                .add_attribute("code_id", code_id.to_string())
+                .add_attribute("vesting_duration_seconds", config.vesting_duration_seconds);
+            STREAM_VESTINGS.save(deps.storage, stream_id, &StreamVesting { code_id, config })?;
+        }
+        None => STREAM_VESTINGS.remove(deps.storage, stream_id),
+    }
+
+    Ok(res)
+}
+
+/// Instantiates a vesting contract for the owner funded with the purchased tokens. The contract
+/// address is derived from a hash of the stream and the owner, and saved for queries. A failed
+/// instantiation does not revert the exit, the reply removes the saved address and escrows the
+/// purchased tokens for `RetryVesting`.
+pub fn vesting_msg(
+    deps: DepsMut,
+    env: &Env,
+    stream_id: StreamId,
+    owner: &Addr,
+    purchased: Coin,
+) -> Result<SubMsg, ContractError> {
+    let vesting = STREAM_VESTINGS.load(deps.storage, stream_id)?;
+    let checksum = deps.querier.query_wasm_code_info(vesting.code_id)?.checksum;
+    let salt = Sha256::new()
+        .chain_update(stream_id.to_be_bytes())
+        .chain_update(deps.api.addr_canonicalize(owner.as_str())?.as_slice())
+        .finalize()
+        .to_vec();
+    let creator = deps.api.addr_canonicalize(env.contract.address.as_str())?;
+    let address = deps
+        .api
+        .addr_humanize(&instantiate2_address(&checksum, &creator, &salt)?)?;
+    VESTING.save(deps.storage, (stream_id, owner), &address)?;
+    PENDING_EXITS.save(
+        deps.storage,
+        &PendingExit {
+            stream_id,
+            owner: owner.clone(),
+            purchased: purchased.clone(),
+        },
+    )?;
+
+    let msg = VestingInstantiateMsg {
+        owner: None,
+        recipient: owner.to_string(),
+        title: format!("Stream {stream_id} vesting"),
+        description: None,
+        total: purchased.amount,
+        denom: UncheckedDenom::Native(purchased.denom.clone()),
+        schedule: vesting.config.schedule,
+        start_time: Some(env.block.time),
+        vesting_duration_seconds: vesting.config.vesting_duration_seconds.u64(),
+        unbonding_duration_seconds: vesting.config.unbonding_duration_seconds.u64(),
+    };
+    Ok(SubMsg::reply_always(
+        WasmMsg::Instantiate2 {
+            admin: None,
+            code_id: vesting.code_id,
+            label: format!("streamswap-vesting-{stream_id}-{owner}"),
+            msg: to_json_binary(&msg)?,
+            funds: vec![purchased],
+            salt: salt.into(),
+        },
+        VESTING_REPLY_ID,
+    ))
+}
+
+pub fn reply_vesting(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let pending = PENDING_EXITS.load(deps.storage)?;
+    PENDING_EXITS.remove(deps.storage);
+
+    let attrs = vec![
+        attr("action", "vesting"),
+        attr("stream_id", pending.stream_id.to_string()),
+        attr("owner", pending.owner.to_string()),
+    ];
+    let SubMsgResult::Err(error) = msg.result else {
+        return Ok(Response::new()
+            .add_attributes(attrs)
+            .add_attribute("success", "true"));
+    };
+    let key = (pending.stream_id, &pending.owner);
+    VESTING.remove(deps.storage, key);
+    FAILED_VESTINGS.update(deps.storage, key, |escrow| -> StdResult<_> {
+        Ok(match escrow {
+            Some(mut escrow) => {
+                escrow.amount = escrow.amount.checked_add(pending.purchased.amount)?;
+                escrow
+            }
+            None => pending.purchased.clone(),
+        })
+    })?;
+
+    Ok(Response::new()
+        .add_attributes(attrs)
+        .add_attribute("success", "false")
+        .add_attribute("escrowed", pending.purchased.to_string())
+        .add_attribute("error", error))
+}
+
+/// Instantiates the vesting contract of the sender again with the tokens escrowed by a failed
+/// instantiation.
+pub fn execute_retry_vesting(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: StreamId,
+) -> Result<Response, ContractError> {
+    let purchased = FAILED_VESTINGS
+        .may_load(deps.storage, (stream_id, &info.sender))?
+        .ok_or(ContractError::NoFailedVesting {})?;
+    FAILED_VESTINGS.remove(deps.storage, (stream_id, &info.sender));
+
+    Ok(Response::new()
+        .add_attribute("action", "retry_vesting")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("purchased", purchased.to_string())
+        .add_submessage(vesting_msg(deps, &env, stream_id, &info.sender, purchased)?))
+}
+
+pub fn query_stream_vesting(deps: Deps, stream_id: StreamId) -> StdResult<Option<StreamVesting>> {
+    STREAM_VESTINGS.may_load(deps.storage, stream_id)
+}
+
+pub fn query_vesting(deps: Deps, stream_id: StreamId, owner: String) -> StdResult<Option<Addr>> {
+    let owner = deps.api.addr_validate(&owner)?;
+    VESTING.may_load(deps.storage, (stream_id, &owner))
+}
+
+/// Returns the tokens escrowed for the owner by a failed vesting instantiation.
+pub fn query_failed_vesting(
+    deps: Deps,
+    stream_id: StreamId,
+    owner: String,
+) -> StdResult<Option<Coin>> {
+    let owner = deps.api.addr_validate(&owner)?;
+    FAILED_VESTINGS.may_load(deps.storage, (stream_id, &owner))
+}
+
+#[cw_serde]
+pub struct VestingResponse {
+    pub owner: Addr,
+    pub vesting: Addr,
+}
+
+/// Lists the vesting contracts instantiated at exits of the stream, ordered by owner.
+pub fn list_vestings(
+    deps: Deps,
+    stream_id: StreamId,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<VestingResponse>> {
+    let start_after = start_after
+        .map(|owner| deps.api.addr_validate(&owner))
+        .transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    VESTING
+        .prefix(stream_id)
+        .range(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(owner, vesting)| VestingResponse { owner, vesting }))
+        .collect()
+}